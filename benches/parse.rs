@@ -0,0 +1,50 @@
+use std::collections::HashMap;
+use std::error::Error;
+
+use chrono::NaiveDate;
+use criterion::{criterion_group, criterion_main, Criterion};
+use csv::{ReaderBuilder, StringRecord};
+use sales_chart::{date_to_key, process_sales_data, validate_csv_structure, ParseOptions, SalesTotals};
+
+// The pre-ByteRecord implementation, kept here as the baseline the hot path is measured against.
+fn process_string_records(data: &[u8]) -> Result<SalesTotals, Box<dyn Error>> {
+    let mut rdr = ReaderBuilder::new().has_headers(true).from_reader(data);
+    let headers = rdr.headers()?.clone();
+    validate_csv_structure(&headers)?;
+
+    let month_index = headers.iter().position(|h| h.to_lowercase() == "month").unwrap();
+    let product_index = headers.iter().position(|h| h.to_lowercase() == "product").unwrap();
+    let sales_index = headers.iter().position(|h| h.to_lowercase() == "sales_amount").unwrap();
+
+    let mut sales_by_month = HashMap::new();
+    let mut sales_by_product = HashMap::new();
+    for record in rdr.records() {
+        let record: StringRecord = record?;
+        let date_str = &record[month_index];
+        let month = NaiveDate::parse_from_str(&format!("{}-01", date_str), "%Y-%m-%d")?;
+        let product = record[product_index].to_string();
+        let sales: f64 = record[sales_index].parse()?;
+
+        *sales_by_month.entry(date_to_key(&month)).or_insert(0.0) += sales;
+        *sales_by_product.entry(product).or_insert(0.0) += sales;
+    }
+
+    Ok((sales_by_month, sales_by_product))
+}
+
+fn bench_parse(c: &mut Criterion) {
+    let data = std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/large_sales_data.csv")).unwrap();
+
+    let mut group = c.benchmark_group("process_sales_data");
+    group.bench_function("string_record", |b| b.iter(|| process_string_records(&data).unwrap()));
+    group.bench_function("byte_record", |b| {
+        b.iter(|| {
+            let mut rdr = ReaderBuilder::new().has_headers(true).from_reader(data.as_slice());
+            process_sales_data(&mut rdr, &ParseOptions::default()).unwrap()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_parse);
+criterion_main!(benches);
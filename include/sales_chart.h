@@ -0,0 +1,43 @@
+#ifndef SALES_CHART_H
+#define SALES_CHART_H
+
+/* Generated by cbindgen from src/ffi.rs; do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Parses the sales CSV at `csv_path` and, unless `output_dir` is NULL, writes
+// `line_chart.png`, `bar_chart.png`, and `pie_chart.png` into it. Returns the totals as JSON
+// (`{"monthly": [{"month", "sales"}], "products": [{"product", "sales"}]}`), to be released with
+// `sales_chart_free_string`, or NULL on failure; `sales_chart_last_error` then says why.
+// Malformed rows fail the call unless `lenient` is set, in which case they are skipped.
+//
+// # Safety
+//
+// `csv_path` must point to a NUL-terminated string, and so must `output_dir` when not NULL.
+char *sales_chart_process(const char *csv_path, const char *output_dir, bool lenient);
+
+// The message of the last failed call on this thread, or NULL if none has failed. The
+// string belongs to the library and stays valid until the next failing call on this thread.
+const char *sales_chart_last_error(void);
+
+// Releases a string returned by `sales_chart_process`. NULL is ignored.
+//
+// # Safety
+//
+// `value` must be NULL or a pointer returned by this library that has not been freed yet.
+void sales_chart_free_string(char *value);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* SALES_CHART_H */
@@ -0,0 +1,160 @@
+use std::error::Error;
+
+use crate::fx::currency_symbol;
+use crate::number::MoneyFormat;
+use crate::summary::RunSummary;
+
+// Months of the trailing average when a rule does not say.
+const AVERAGE_MONTHS: usize = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Subject {
+    /// The latest month's sales
+    Latest,
+    /// The latest month's sales in percent of the average of the months before it
+    LatestOfAverage { months: usize },
+    /// The latest month's change in percent on the month before
+    Change,
+    /// How far each product's latest month fell in percent below its month before
+    ProductDrop,
+    /// The total sales
+    Total,
+}
+
+/// A condition on the totals of a run that raises an alert, such as `latest < 80% of
+/// average`. The rules are:
+///
+/// - `latest < 50000` or `latest > 50000`: the latest month's sales against an amount;
+/// - `latest < 80% of average`: against the average of the three months before it, or of
+///   `6-month average` and the like;
+/// - `change < -10%`: the latest month's change on the month before;
+/// - `product drop > 30%`: any product whose latest month fell by more than that on the
+///   month before, which needs each product's months;
+/// - `total < 1000000`: the total sales.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AlertRule {
+    text: String,
+    subject: Subject,
+    below: bool,
+    threshold: f64,
+}
+
+impl AlertRule {
+    pub fn parse(text: &str) -> Result<Self, Box<dyn Error>> {
+        let invalid = |reason: &str| format!("Invalid alert \"{}\": {}", text, reason);
+        let spaced = text.replace('<', " < ").replace('>', " > ");
+        let words: Vec<&str> = spaced.split_whitespace().collect();
+        let split = words.iter().position(|word| *word == "<" || *word == ">").ok_or_else(|| invalid("needs < or > between what is checked and the limit"))?;
+        let (subject, limit) = (&words[..split], &words[split + 1..]);
+        let below = words[split] == "<";
+        let percent = |word: &str| word.strip_suffix('%').and_then(|number| number.parse::<f64>().ok()).filter(|percent| percent.is_finite());
+        let amount = |words: &[&str]| match words {
+            [word] => word.parse::<f64>().ok().filter(|amount| amount.is_finite()),
+            _ => None,
+        };
+        let (subject, threshold) = match (subject, limit) {
+            (["latest"], [share, "of", "average"]) => (Subject::LatestOfAverage { months: AVERAGE_MONTHS }, percent(share).ok_or_else(|| invalid("needs a share such as 80% of the average"))?),
+            (["latest"], [share, "of", months, "average"]) => {
+                let months = months.strip_suffix("-month").and_then(|months| months.parse::<usize>().ok()).filter(|months| *months > 0);
+                let months = months.ok_or_else(|| invalid("needs the months of the average as in 6-month average"))?;
+                (Subject::LatestOfAverage { months }, percent(share).ok_or_else(|| invalid("needs a share such as 80% of the average"))?)
+            }
+            (["latest"], limit) => (Subject::Latest, amount(limit).ok_or_else(|| invalid("needs an amount, or a share of the average as in 80% of average"))?),
+            (["change"], [change]) => (Subject::Change, percent(change).ok_or_else(|| invalid("needs a change in percent such as -10%"))?),
+            (["product", "drop"], [drop]) => {
+                if below {
+                    return Err(invalid("only checks for drops larger than a percentage, as in product drop > 30%").into());
+                }
+                (Subject::ProductDrop, percent(drop).ok_or_else(|| invalid("needs a drop in percent such as 30%"))?)
+            }
+            (["total"], limit) => (Subject::Total, amount(limit).ok_or_else(|| invalid("needs an amount"))?),
+            _ => return Err(invalid("checks latest, change, product drop, or total").into()),
+        };
+        Ok(AlertRule { text: text.trim().to_string(), subject, below, threshold })
+    }
+
+    /// Whether the rule looks at each product's months, which a run only has with the month
+    /// by product cube.
+    pub fn needs_products(&self) -> bool {
+        self.subject == Subject::ProductDrop
+    }
+
+    fn crosses(&self, value: f64) -> bool {
+        if self.below {
+            value < self.threshold
+        } else {
+            value > self.threshold
+        }
+    }
+
+    // What the rule found in one summary, a message each time it is crossed.
+    fn check(&self, summary: &RunSummary, money: &MoneyFormat) -> Vec<String> {
+        let monthly = &summary.aggregates.monthly;
+        let side = if self.below { "under" } else { "over" };
+        let Some(latest) = monthly.last() else {
+            return Vec::new();
+        };
+        match self.subject {
+            Subject::Latest if self.crosses(latest.sales) => {
+                vec![format!("{} sales of {} are {} {} ({})", latest.month, money.format(latest.sales), side, money.format(self.threshold), self.text)]
+            }
+            Subject::LatestOfAverage { months } => {
+                let before = &monthly[monthly.len().saturating_sub(months + 1)..monthly.len() - 1];
+                if before.is_empty() {
+                    return Vec::new();
+                }
+                let average = before.iter().fold(0.0, |sum, month| sum + month.sales) / before.len() as f64;
+                if average == 0.0 {
+                    return Vec::new();
+                }
+                let share = latest.sales / average * 100.0;
+                if !self.crosses(share) {
+                    return Vec::new();
+                }
+                let months = if before.len() == 1 { "the month".to_string() } else { format!("the {} months", before.len()) };
+                vec![format!("{} sales of {} are {} of the {} average of {} before ({})", latest.month, money.format(latest.sales), money.percent(share, false), money.format(average), months, self.text)]
+            }
+            Subject::Change => match (monthly.len().checked_sub(2).map(|i| &monthly[i]), summary.latest_change) {
+                (Some(previous), Some(change)) if self.crosses(change) => {
+                    vec![format!("{} sales changed {} on {} ({})", latest.month, money.percent(change, true), previous.month, self.text)]
+                }
+                _ => Vec::new(),
+            },
+            Subject::ProductDrop => summary
+                .product_trends
+                .iter()
+                .filter_map(|trend| match trend.monthly.as_slice() {
+                    [.., (_, previous), (_, last)] if *previous > 0.0 => {
+                        let drop = (previous - last) / previous * 100.0;
+                        self.crosses(drop).then(|| format!("{} fell {} in {}, from {} to {} ({})", trend.product, money.percent(drop, false), latest.month, money.format(*previous), money.format(*last), self.text))
+                    }
+                    _ => None,
+                })
+                .collect(),
+            Subject::Total if self.crosses(summary.total_sales) => {
+                vec![format!("Total sales of {} are {} {} ({})", money.format(summary.total_sales), side, money.format(self.threshold), self.text)]
+            }
+            Subject::Latest | Subject::Total => Vec::new(),
+        }
+    }
+}
+
+/// The alerts `rules` raise on the totals of `summary`, one message each. A run split by
+/// currency is checked a currency at a time, with its messages starting with the currency.
+pub fn check_alerts(rules: &[AlertRule], summary: &RunSummary, money: &MoneyFormat) -> Vec<String> {
+    if !summary.currencies.is_empty() {
+        return summary
+            .currencies
+            .iter()
+            .flat_map(|section| {
+                let currency = section.currency.as_deref().unwrap_or_default();
+                check_alerts(rules, section, money).into_iter().map(move |alert| format!("{}: {}", currency, alert))
+            })
+            .collect();
+    }
+    let money = &match &summary.currency {
+        Some(currency) => MoneyFormat { symbol: currency_symbol(currency), ..money.clone() },
+        None => money.clone(),
+    };
+    rules.iter().flat_map(|rule| rule.check(summary, money)).collect()
+}
@@ -0,0 +1,30 @@
+use std::borrow::Cow;
+
+use unicode_bidi::{bidi_class, BidiClass, BidiInfo, Level};
+
+// Languages written right to left, as the first part of a locale name such as `ar_EG`.
+const RTL_LANGUAGES: &[&str] = &["ar", "dv", "fa", "he", "ps", "sd", "ug", "ur", "yi"];
+
+/// Whether `language`, a code such as `he` or a locale name such as `ar_EG`, is written right to left.
+pub fn is_rtl_language(language: &str) -> bool {
+    let code = language.split(['-', '_']).next().unwrap_or_default().to_lowercase();
+    RTL_LANGUAGES.contains(&code.as_str())
+}
+
+fn is_rtl_char(c: char) -> bool {
+    matches!(bidi_class(c), BidiClass::R | BidiClass::AL)
+}
+
+/// `text` in the order its glyphs are drawn from left to right, for chart backends that do
+/// not lay text out themselves: Arabic letters take their joined forms and right-to-left runs
+/// are reversed by the Unicode bidirectional algorithm. `rtl` reads the text as a right-to-left
+/// paragraph, so a name's trailing amount ends up on its left; otherwise the first letter decides.
+pub fn visual_text(text: &str, rtl: bool) -> Cow<'_, str> {
+    if !text.chars().any(is_rtl_char) {
+        return Cow::Borrowed(text);
+    }
+    let shaped = ar_reshaper::reshape_line(text);
+    let info = BidiInfo::new(&shaped, rtl.then(Level::rtl));
+    let visual: String = info.paragraphs.iter().map(|paragraph| info.reorder_line(paragraph, paragraph.range.clone())).collect();
+    Cow::Owned(visual)
+}
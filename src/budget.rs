@@ -0,0 +1,96 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::Path;
+
+use chrono::NaiveDate;
+use serde::Serialize;
+
+use crate::{date_to_key, key_to_date, parse_month, DateKey};
+
+/// Sales targets by month.
+#[derive(Clone, Debug, Default)]
+pub struct Budget {
+    targets: BTreeMap<DateKey, f64>,
+}
+
+/// One month's sales against its target.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct BudgetVariance {
+    pub month: String,
+    pub sales: f64,
+    pub target: f64,
+    /// Sales above the target, negative below it
+    pub variance: f64,
+    /// The variance in percent of the target, or None with a zero target
+    pub variance_percent: Option<f64>,
+}
+
+impl BudgetVariance {
+    pub fn on_plan(&self) -> bool {
+        self.variance >= 0.0
+    }
+}
+
+/// Monthly costs that sales have to cover: fixed costs, and with a unit cost and price, what
+/// each unit sold costs to make or buy.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BreakEven {
+    pub fixed_costs: f64,
+    pub unit_cost: f64,
+    pub unit_price: f64,
+}
+
+impl BreakEven {
+    /// The monthly sales at which what the units sold earn over their cost covers the fixed costs.
+    pub fn sales(&self) -> Result<f64, Box<dyn Error>> {
+        if self.unit_price <= 0.0 || self.unit_cost >= self.unit_price {
+            return Err(format!("A unit cost of {} leaves nothing of a unit price of {} to cover the fixed costs", self.unit_cost, self.unit_price).into());
+        }
+        Ok(self.fixed_costs * self.unit_price / (self.unit_price - self.unit_cost))
+    }
+}
+
+impl Budget {
+    /// Reads a `month,target` CSV file with `YYYY-MM` months.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let path = path.as_ref();
+        let mut rdr = csv::ReaderBuilder::new().trim(csv::Trim::All).from_path(path)?;
+        let mut budget = Budget::default();
+        for (i, record) in rdr.records().enumerate() {
+            let record = record?;
+            let invalid = |reason: &str| format!("Invalid budget file {}: row {} {}", path.display(), i + 2, reason);
+            let (Some(month), Some(target)) = (record.get(0), record.get(1)) else {
+                return Err(invalid("needs a month and a target column").into());
+            };
+            let target = target.parse::<f64>().ok().filter(|target| target.is_finite()).ok_or_else(|| invalid("needs a numeric target"))?;
+            let month = parse_month(month.as_bytes()).map_err(|e| invalid(&e))?;
+            if budget.targets.insert(month, target).is_some() {
+                return Err(invalid(&format!("repeats {}", key_to_date(month).format("%Y-%m"))).into());
+            }
+        }
+        Ok(budget)
+    }
+
+    /// The targets of the months of `monthly_data` that have one.
+    pub fn targets(&self, monthly_data: &[(NaiveDate, f64)]) -> Vec<(NaiveDate, f64)> {
+        monthly_data.iter().filter_map(|(month, _)| self.targets.get(&date_to_key(month)).map(|target| (*month, *target))).collect()
+    }
+
+    /// Each month of `monthly_data` with a target against it.
+    pub fn variance(&self, monthly_data: &[(NaiveDate, f64)]) -> Vec<BudgetVariance> {
+        monthly_data
+            .iter()
+            .filter_map(|(month, sales)| {
+                let target = *self.targets.get(&date_to_key(month))?;
+                let variance = sales - target;
+                Some(BudgetVariance {
+                    month: month.format("%Y-%m").to_string(),
+                    sales: *sales,
+                    target,
+                    variance,
+                    variance_percent: (target != 0.0).then(|| variance / target.abs() * 100.0),
+                })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,102 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::Debug;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use serde::{Deserialize, Serialize};
+
+use crate::charts::ChartLabels;
+
+// What a chart was drawn from, and the file as it was drawn.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+struct Drawn {
+    inputs: u64,
+    size: u64,
+    modified_ms: u64,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct Saved {
+    version: String,
+    charts: BTreeMap<PathBuf, Drawn>,
+}
+
+/// A hash of the data and labels each chart was last drawn from, by file, kept in a JSON file
+/// across runs so a chart whose inputs have not changed is not drawn again. A chart file that
+/// was changed or removed since is drawn again all the same. Inputs are hashed the way this
+/// build hashes them, so the charts recorded by another version are all drawn again once.
+#[derive(Debug)]
+pub struct ChartCache {
+    path: PathBuf,
+    charts: BTreeMap<PathBuf, Drawn>,
+    changed: bool,
+    /// Charts drawn and charts kept since the cache was last saved
+    pub drawn: usize,
+    pub kept: usize,
+}
+
+fn version() -> String {
+    env!("CARGO_PKG_VERSION").to_string()
+}
+
+// The file at `path` as it is now, or None when there is none.
+fn on_disk(path: &Path, inputs: u64) -> Option<Drawn> {
+    let metadata = fs::metadata(path).ok()?;
+    let modified_ms = metadata.modified().ok()?.duration_since(UNIX_EPOCH).map_or(0, |age| age.as_millis() as u64);
+    Some(Drawn { inputs, size: metadata.len(), modified_ms })
+}
+
+impl ChartCache {
+    /// The cache saved at `path`, or an empty one when there is none yet.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let path = path.as_ref();
+        let saved: Saved = match fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents).map_err(|e| format!("Invalid chart cache {}: {}", path.display(), e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Saved::default(),
+            Err(e) => return Err(format!("Cannot read chart cache {}: {}", path.display(), e).into()),
+        };
+        let charts = if saved.version == version() { saved.charts } else { BTreeMap::new() };
+        Ok(ChartCache { path: path.to_path_buf(), charts, changed: false, drawn: 0, kept: 0 })
+    }
+
+    /// The hash of what a chart is drawn from: its data, anything else it shows, and `labels`.
+    /// The time of the run is left out unless a translated caption shows it.
+    pub fn inputs(data: &dyn Debug, labels: &ChartLabels) -> u64 {
+        let labels = ChartLabels { translations: labels.translations.timeless(), ..labels.clone() };
+        let mut hasher = DefaultHasher::new();
+        format!("{:?} {:?}", data, labels).hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Whether the chart at `path` was drawn from `inputs` and is still as it was drawn.
+    pub fn is_current(&self, path: &Path, inputs: u64) -> bool {
+        self.charts.get(path).is_some_and(|drawn| on_disk(path, inputs).as_ref() == Some(drawn))
+    }
+
+    /// Records the chart just drawn at `path` from `inputs`.
+    pub fn record(&mut self, path: &Path, inputs: u64) {
+        match on_disk(path, inputs) {
+            Some(drawn) => self.charts.insert(path.to_path_buf(), drawn),
+            None => self.charts.remove(path),
+        };
+        self.changed = true;
+    }
+
+    /// Writes the cache back to its file when a chart was recorded since it was opened or last
+    /// saved, and starts counting the charts drawn and kept again.
+    pub fn save(&mut self) -> Result<(), Box<dyn Error>> {
+        self.drawn = 0;
+        self.kept = 0;
+        if !self.changed {
+            return Ok(());
+        }
+        let saved = Saved { version: version(), charts: self.charts.clone() };
+        fs::write(&self.path, serde_json::to_string_pretty(&saved)?).map_err(|e| format!("Cannot write chart cache {}: {}", self.path.display(), e))?;
+        self.changed = false;
+        Ok(())
+    }
+}
@@ -0,0 +1,1690 @@
+#[cfg(all(feature = "bitmap", not(target_arch = "wasm32")))]
+use std::borrow::Cow;
+use std::error::Error;
+#[cfg(all(feature = "bitmap", not(target_arch = "wasm32")))]
+use std::path::{Path, PathBuf};
+
+use chrono::{Days, Months, NaiveDate};
+use plotters::coord::ranged1d::{AsRangedCoord, ValueFormatter};
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use plotters::style::text_anchor::{HPos, Pos, VPos};
+#[cfg(all(feature = "bitmap", not(target_arch = "wasm32")))]
+use tracing::debug_span;
+
+use crate::bidi::visual_text;
+use crate::choropleth::RegionMap;
+use crate::compare::Comparison;
+use crate::holidays::Holiday;
+use crate::layout::{CellChart, Layout, Metric};
+use crate::locale::DateLocale;
+use crate::number::{parse_decimals, DigitGrouping, MoneyFormat};
+use crate::summary::{Facet, FunnelStage, ProductTrend};
+use crate::translations::{CaptionValues, Translations};
+use crate::PlotData;
+
+/// How chart text names months and writes amounts, the language of its captions, the font it
+/// is drawn in, and which way it reads.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChartLabels {
+    /// Language of the month names on time axes, which show ISO dates without one
+    pub locale: Option<DateLocale>,
+    pub money: MoneyFormat,
+    pub translations: Translations,
+    /// Font family of all chart text. Natively, text this font has no glyphs for, such as
+    /// Japanese product names, is drawn in an installed CJK font instead.
+    pub font: String,
+    /// Lay labels out right to left, with the legend on the left and bar labels ending at
+    /// the bar's right edge, for Arabic, Hebrew, and other right-to-left reports
+    pub rtl: bool,
+    /// Shape Arabic and reorder right-to-left text before drawing it, which bitmaps need. SVG
+    /// shown in a browser is laid out by the browser and wants this off.
+    pub visual_order: bool,
+    /// Holidays and promotions shaded on the line charts; periods of several days are named
+    pub holidays: Vec<Holiday>,
+    /// Draw the monthly trend's months a year earlier behind it as a grey dashed line
+    pub prior_year: bool,
+    /// Monthly sales that cover the costs, drawn across the monthly trend with its months
+    /// below marked
+    pub break_even: Option<f64>,
+    /// Decimal places of the amounts of single charts written to files, by their name in
+    /// `CHART_NAMES`, instead of those of `money`
+    pub chart_decimals: Vec<(String, usize)>,
+    /// Width and height in pixels of the charts written to files, other than the metric cards,
+    /// the executive summary, and layouts, which have sizes of their own
+    pub size: (u32, u32),
+}
+
+/// The charts `chart_decimals` can round on their own.
+pub const CHART_NAMES: &[&str] = &[
+    "line", "bar", "pie", "product", "map", "scenario", "bump", "streamgraph", "stacked_bar", "funnel", "kpi", "executive_summary", "layout", "history",
+    "compare_line", "compare_bar", "inflation", "budget", "variance", "facet",
+];
+
+/// Reads a `--chart-decimals` setting such as `pie=0`.
+pub fn parse_chart_decimals(value: &str) -> Result<(String, usize), String> {
+    let (chart, decimals) = value.split_once('=').ok_or_else(|| format!("expected CHART=N, such as pie=0, got \"{}\"", value))?;
+    let chart = chart.trim();
+    if !CHART_NAMES.contains(&chart) {
+        return Err(format!("unknown chart \"{}\", expected one of {}", chart, CHART_NAMES.join(", ")));
+    }
+    Ok((chart.to_string(), parse_decimals(decimals.trim())?))
+}
+
+impl Default for ChartLabels {
+    fn default() -> Self {
+        ChartLabels { locale: None, money: MoneyFormat::default(),
+            translations: Translations::default(),
+            font: "sans-serif".to_string(), rtl: false, visual_order: true, holidays: Vec::new(), prior_year: false, break_even: None,
+            chart_decimals: Vec::new(), size: SIZE }
+    }
+}
+
+type AmountTicks<'a> = Box<dyn Fn(&f64) -> String + 'a>;
+
+impl ChartLabels {
+    // These labels with the decimal places `chart_decimals` gives `chart`, if any.
+    #[cfg(all(feature = "bitmap", not(target_arch = "wasm32")))]
+    fn for_chart(&self, chart: &str) -> Cow<'_, ChartLabels> {
+        match self.chart_decimals.iter().rev().find(|(name, _)| name == chart) {
+            Some(&(_, decimals)) => Cow::Owned(ChartLabels { money: MoneyFormat { decimals, ..self.money.clone() }, ..self.clone() }),
+            None => Cow::Borrowed(self),
+        }
+    }
+
+    /// These labels with the placeholders of their captions, such as `{total_sales}`, standing
+    /// for `values`.
+    pub fn with_values(&self, values: CaptionValues) -> ChartLabels {
+        ChartLabels { translations: self.translations.clone().with_values(values), ..self.clone() }
+    }
+
+    /// These labels with caption placeholders filled in from `data`, read from `rows` rows.
+    pub fn for_data(&self, data: &PlotData, rows: u64) -> ChartLabels {
+        let months: Vec<String> = data.0.iter().map(|(month, _)| month.format("%Y-%m").to_string()).collect();
+        let total: f64 = data.0.iter().map(|(_, sales)| sales).sum();
+        self.with_values(CaptionValues::new(months.iter().map(String::as_str), self.money.format(total), rows))
+    }
+
+    // `text` without what `font` cannot draw, such as emoji no installed font has.
+    fn fit(&self, font: &str, text: String) -> String {
+        #[cfg(not(target_arch = "wasm32"))]
+        return crate::fonts::drawable(font, &text).into_owned();
+        // Browsers fall back to emoji fonts on their own.
+        #[cfg(target_arch = "wasm32")]
+        {
+            let _ = font;
+            text
+        }
+    }
+
+    // Label text as it is handed to the backend.
+    fn text(&self, text: &str) -> String {
+        if self.visual_order {
+            visual_text(text, self.rtl).into_owned()
+        } else {
+            text.to_string()
+        }
+    }
+
+    // A caption in the reader's language, ready to draw.
+    fn caption(&self, english: &str) -> String {
+        self.text(&self.translations.get(english))
+    }
+
+    // Amount axis labels in lakh and crore with Indian grouping; plotters' plain numbers otherwise.
+    fn amount_ticks(&self) -> Option<AmountTicks<'_>> {
+        if self.money.digit_grouping != DigitGrouping::Indian {
+            return None;
+        }
+        Some(Box::new(|value: &f64| self.money.compact(*value)))
+    }
+
+    fn legend(&self, framed: bool) -> Legend {
+        let position = if self.rtl { SeriesLabelPosition::MiddleLeft } else { SeriesLabelPosition::MiddleRight };
+        Legend { position, framed }
+    }
+
+    fn font_for<'a>(&self, texts: impl IntoIterator<Item = &'a str>) -> String {
+        #[cfg(not(target_arch = "wasm32"))]
+        return crate::fonts::font_for(&self.font, texts);
+        // Browsers fall back to other fonts on their own.
+        #[cfg(target_arch = "wasm32")]
+        {
+            let _ = texts;
+            self.font.clone()
+        }
+    }
+}
+
+pub fn draw_line_chart<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    monthly_data: &[(NaiveDate, f64)],
+    labels: &ChartLabels,
+) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    draw_trend(root, "Monthly Sales Trend", monthly_data, labels.break_even, labels)
+}
+
+/// The line chart of one product's `monthly_data`, captioned with its name, for a chart of
+/// its own outside the report.
+pub fn draw_product_chart<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    product: &str,
+    monthly_data: &[(NaiveDate, f64)],
+    labels: &ChartLabels,
+) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    draw_trend(root, &labels.translations.fill("Monthly Sales of {}", product), monthly_data, None, labels)
+}
+
+// The break-even line covers the costs of the whole business, so one product's trend goes without it.
+fn draw_trend<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    caption: &str,
+    monthly_data: &[(NaiveDate, f64)],
+    break_even: Option<f64>,
+    labels: &ChartLabels,
+) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let level: Vec<(NaiveDate, f64)> = break_even.map_or(Vec::new(), |level| monthly_data.iter().map(|(month, _)| (*month, level)).collect());
+    let mut lines = vec![(monthly_data, labels.translations.get("Total Sales").to_string(), RED)];
+    if !level.is_empty() {
+        lines.push((level.as_slice(), labels.translations.get("Break-even").to_string(), BREAK_EVEN));
+    }
+    let below: Vec<Dot> = break_even
+        .map_or(Vec::new(), |level| monthly_data.iter().filter(|(_, sales)| *sales < level).map(|(month, sales)| (*month, *sales, BELOW_PLAN)).collect());
+    let prior = if labels.prior_year { prior_year(monthly_data) } else { Vec::new() };
+    let dashed = (!prior.is_empty()).then(|| (prior.as_slice(), labels.translations.get("Prior Year").to_string(), PRIOR_YEAR));
+    draw_monthly_lines(root, caption, &lines, false, Overlay { dots: &below, dashed }, labels)
+}
+
+/// Each month's sales a year earlier, on the month of `monthly_data` they correspond to, for the
+/// months whose prior year is in the data.
+pub fn prior_year(monthly_data: &[(NaiveDate, f64)]) -> Vec<(NaiveDate, f64)> {
+    monthly_data
+        .iter()
+        .filter_map(|(month, _)| {
+            let earlier = *month - Months::new(12);
+            monthly_data.iter().find(|(other, _)| *other == earlier).map(|(_, sales)| (*month, *sales))
+        })
+        .collect()
+}
+
+/// The monthly totals of both datasets of `comparison` on one chart, the old in red and the new
+/// in blue, each named in the legend with the months it covers.
+pub fn draw_comparison_line_chart<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    comparison: &Comparison,
+    labels: &ChartLabels,
+) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let legend = |side: usize| format!("{} ({})", comparison.names[side], comparison.ranges[side]);
+    let new = comparison.charted_new();
+    let lines = [(comparison.monthly[0].as_slice(), legend(0), RED), (new.as_slice(), legend(1), BLUE)];
+    draw_monthly_lines(root, "Monthly Sales Comparison", &lines, true, Overlay::default(), labels)
+}
+
+/// Monthly totals against `targets`, with a dot on every month that has a target: green at or
+/// above plan, red below it.
+pub fn draw_budget_chart<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    monthly_data: &[(NaiveDate, f64)],
+    targets: &[(NaiveDate, f64)],
+    labels: &ChartLabels,
+) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let translations = &labels.translations;
+    let lines = [
+        (monthly_data, translations.get("Total Sales").to_string(), BLUE),
+        (targets, translations.get("Budget").to_string(), RGBColor(128, 128, 128)),
+    ];
+    let dots: Vec<Dot> = targets
+        .iter()
+        .filter_map(|(month, target)| {
+            let (_, sales) = monthly_data.iter().find(|(actual, _)| actual == month)?;
+            Some((*month, *sales, if sales >= target { ON_PLAN } else { BELOW_PLAN }))
+        })
+        .collect();
+    draw_monthly_lines(root, "Actual vs Budget", &lines, true, Overlay { dots: &dots, ..Overlay::default() }, labels)
+}
+
+/// The monthly totals as they were in red and as a what-if scenario has them in blue, for
+/// planning discussions.
+pub fn draw_scenario_chart<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    monthly_data: &[(NaiveDate, f64)],
+    scenario: &[(NaiveDate, f64)],
+    labels: &ChartLabels,
+) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let translations = &labels.translations;
+    let lines = [(monthly_data, translations.get("Total Sales").to_string(), RED), (scenario, translations.get("Scenario").to_string(), BLUE)];
+    draw_monthly_lines(root, "Scenario vs Actual", &lines, true, Overlay::default(), labels)
+}
+
+/// Each month's difference from its target as a bar from zero, green above plan and red below.
+pub fn draw_variance_chart<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    variance: &[(NaiveDate, f64)],
+    labels: &ChartLabels,
+) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    root.fill(&WHITE)?;
+
+    let months: Vec<String> = variance
+        .iter()
+        .map(|(month, _)| labels.text(&labels.locale.map_or_else(|| month.format("%Y-%m").to_string(), |locale| locale.month_label(*month))))
+        .collect();
+    let caption = labels.caption("Variance from Budget");
+    let font = labels.font_for(std::iter::once(&caption).chain(&months).map(String::as_str));
+    let caption = labels.fit(&font, caption);
+    let months: Vec<String> = months.into_iter().map(|month| labels.fit(&font, month)).collect();
+    let low = variance.iter().map(|(_, v)| *v).fold(0f64, f64::min);
+    let high = variance.iter().map(|(_, v)| *v).fold(0f64, f64::max);
+    // Room on both sides of zero even when every month is on the same side
+    let pad = (high - low).max(1.0) * 0.05;
+    let mut chart = ChartBuilder::on(root)
+        .caption(caption, (font.as_str(), 30).into_font())
+        .margin(10)
+        .x_label_area_size(40)
+        .y_label_area_size(60)
+        .build_cartesian_2d((0..variance.len().saturating_sub(1)).into_segmented(), (low - pad)..(high + pad))?;
+
+    let month_ticks = |value: &SegmentValue<usize>| match value {
+        SegmentValue::CenterOf(i) | SegmentValue::Exact(i) => months.get(*i).cloned().unwrap_or_default(),
+        SegmentValue::Last => String::new(),
+    };
+    let mut mesh = chart.configure_mesh();
+    mesh.label_style((font.as_str(), 12)).disable_x_mesh().x_label_formatter(&month_ticks);
+    let amounts = labels.amount_ticks();
+    if let Some(amounts) = &amounts {
+        mesh.y_label_formatter(amounts);
+    }
+    mesh.draw()?;
+
+    chart.draw_series(variance.iter().enumerate().map(|(i, (_, difference))| {
+        let color = if *difference >= 0.0 { ON_PLAN } else { BELOW_PLAN };
+        // The last month's segment ends where the axis does.
+        let end = if i + 1 < variance.len() { SegmentValue::Exact(i + 1) } else { SegmentValue::Last };
+        let mut bar = Rectangle::new([(SegmentValue::Exact(i), 0.0), (end, *difference)], color.mix(0.9).filled());
+        bar.set_margin(0, 0, BAR_MARGIN as u32, BAR_MARGIN as u32);
+        bar
+    }))?;
+    chart.draw_series(std::iter::once(PathElement::new(vec![(SegmentValue::Exact(0), 0.0), (SegmentValue::Last, 0.0)], BLACK)))?;
+
+    root.present()?;
+    Ok(())
+}
+
+/// The total sales of each run in `runs`, named by when it ran, as a line with a dot per run
+/// spaced evenly however far apart the runs were.
+pub fn draw_history_chart<DB: DrawingBackend>(root: &DrawingArea<DB, Shift>, runs: &[(String, f64)], labels: &ChartLabels) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    root.fill(&WHITE)?;
+
+    let caption = labels.caption("Total Sales per Run");
+    let font = labels.font_for([caption.as_str()]);
+    let caption = labels.fit(&font, caption);
+    let high = runs.iter().map(|(_, total)| *total).fold(0f64, f64::max);
+    let low = runs.iter().map(|(_, total)| *total).fold(0f64, f64::min);
+    let last = runs.len().saturating_sub(1).max(1) as f64;
+    let mut chart = ChartBuilder::on(root)
+        .caption(caption, (font.as_str(), 30).into_font())
+        .margin(20)
+        .x_label_area_size(40)
+        .y_label_area_size(60)
+        .build_cartesian_2d(-0.25..last + 0.25, low..if high > low { high * 1.05 } else { low + 1.0 })?;
+
+    let run_ticks = |x: &f64| if x.fract() == 0.0 { runs.get(*x as usize).map(|(name, _)| name.clone()).unwrap_or_default() } else { String::new() };
+    let mut mesh = chart.configure_mesh();
+    mesh.label_style((font.as_str(), 12)).disable_x_mesh().x_labels(runs.len().min(8)).x_label_formatter(&run_ticks);
+    let amounts = labels.amount_ticks();
+    if let Some(amounts) = &amounts {
+        mesh.y_label_formatter(amounts);
+    }
+    mesh.draw()?;
+
+    let points = runs.iter().enumerate().map(|(i, (_, total))| (i as f64, *total));
+    chart.draw_series(LineSeries::new(points.clone(), BLUE.stroke_width(2)))?;
+    chart.draw_series(points.map(|point| Circle::new(point, 4, BLUE.filled())))?;
+
+    root.present()?;
+    Ok(())
+}
+
+/// A grid of small monthly charts, one per facet, the best-selling first, under one caption
+/// naming the column they split the data `by`. The panels share both axes, so their lines
+/// compare at a glance.
+pub fn draw_facet_chart<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    facets: &[Facet],
+    by: &str,
+    labels: &ChartLabels,
+) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    root.fill(&WHITE)?;
+
+    let caption = labels.text(&labels.translations.fill("Monthly Sales by {}", by));
+    let names: Vec<String> = facets.iter().map(|facet| labels.text(&facet.name)).collect();
+    let font = labels.font_for(std::iter::once(&caption).chain(&names).map(String::as_str));
+    let caption = labels.fit(&font, caption);
+    let names: Vec<String> = names.into_iter().map(|name| labels.fit(&font, name)).collect();
+    let lines: Vec<Line> = facets.iter().map(|facet| (facet.monthly.as_slice(), String::new(), RED)).collect();
+    if facets.iter().all(|facet| facet.monthly.is_empty()) {
+        return Ok(());
+    }
+    let (first, last) = span(&lines)?;
+    let max = facets.iter().flat_map(|facet| facet.monthly.iter()).map(|(_, v)| *v).fold(0f64, f64::max);
+    let label = |month: &NaiveDate| match labels.locale {
+        Some(locale) => labels.fit(&font, labels.text(&locale.month_label(*month))),
+        None => month.format("%Y-%m").to_string(),
+    };
+    let amounts = labels.amount_ticks();
+
+    let root = root.titled(&caption, (font.as_str(), 30).into_font())?;
+    let columns = facets_per_row(facets.len());
+    let panels = root.split_evenly((facets.len().div_ceil(columns), columns));
+    for ((panel, facet), name) in panels.iter().zip(facets).zip(&names) {
+        let mut chart = ChartBuilder::on(panel)
+            .caption(name, (font.as_str(), 16).into_font())
+            .margin(8)
+            .x_label_area_size(30)
+            .y_label_area_size(60)
+            .build_cartesian_2d((first..last).monthly(), 0f64..max)?;
+        let mut mesh = chart.configure_mesh();
+        mesh.label_style((font.as_str(), 10)).x_labels(4).y_labels(5).x_label_formatter(&label);
+        if let Some(amounts) = &amounts {
+            mesh.y_label_formatter(amounts);
+        }
+        mesh.draw()?;
+        chart.draw_series(LineSeries::new(facet.monthly.iter().copied(), RED))?;
+    }
+
+    root.present()?;
+    Ok(())
+}
+
+/// Each product's rank by sales in every month as connected lines, rank 1 at the top and the
+/// product named at its line's end, so changes in the lead stand out. Products of `trends`
+/// beyond the best-selling `MAX_BUMP` are left out, and the rest are ranked among themselves.
+pub fn draw_bump_chart<DB: DrawingBackend>(root: &DrawingArea<DB, Shift>, trends: &[ProductTrend], labels: &ChartLabels) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    root.fill(&WHITE)?;
+
+    let trends = &trends[..trends.len().min(MAX_BUMP)];
+    let caption = labels.caption("Product Rank by Month");
+    let names: Vec<String> = trends.iter().map(|trend| labels.text(&trend.product)).collect();
+    let font = labels.font_for(std::iter::once(&caption).chain(&names).map(String::as_str));
+    let caption = labels.fit(&font, caption);
+    let names: Vec<String> = names.into_iter().map(|name| labels.fit(&font, name)).collect();
+    let (Some(first), Some(last)) = (trends.first().and_then(|trend| trend.monthly.first()), trends.first().and_then(|trend| trend.monthly.last())) else {
+        root.present()?;
+        return Ok(());
+    };
+    let (first, last) = (first.0, last.0);
+
+    // Ties keep the order of the totals, so the best seller overall ranks first.
+    let months = trends[0].monthly.len();
+    let mut ranks = vec![Vec::with_capacity(months); trends.len()];
+    for month in 0..months {
+        let mut order: Vec<usize> = (0..trends.len()).collect();
+        order.sort_by(|a, b| trends[*b].monthly[month].1.total_cmp(&trends[*a].monthly[month].1));
+        for (rank, product) in order.into_iter().enumerate() {
+            ranks[product].push((trends[product].monthly[month].0, rank as f64 + 1.0));
+        }
+    }
+
+    let label = |month: &NaiveDate| match labels.locale {
+        Some(locale) => labels.fit(&font, labels.text(&locale.month_label(*month))),
+        None => month.format("%Y-%m").to_string(),
+    };
+    let rank = |value: &f64| if value.fract() == 0.0 { format!("#{}", value) } else { String::new() };
+    let count = trends.len() as f64;
+    let mut chart = ChartBuilder::on(root)
+        .caption(&caption, (font.as_str(), 30).into_font())
+        .margin(10)
+        .margin_right(150)
+        .x_label_area_size(40)
+        .y_label_area_size(50)
+        .build_cartesian_2d((first..last).monthly(), (count + 0.5)..0.5)?;
+    chart
+        .configure_mesh()
+        .label_style((font.as_str(), 12))
+        .x_label_formatter(&label)
+        .y_labels(trends.len())
+        .y_label_formatter(&rank)
+        .disable_y_mesh()
+        .draw()?;
+
+    for (i, (points, name)) in ranks.iter().zip(&names).enumerate() {
+        let color = Palette99::pick(i).to_rgba();
+        chart.draw_series(LineSeries::new(points.iter().copied(), color.stroke_width(3)))?;
+        chart.draw_series(points.iter().map(|point| Circle::new(*point, 5, color.filled())))?;
+        if let Some(end) = points.last() {
+            let style = (font.as_str(), 13).into_font().color(&color).pos(Pos::new(HPos::Left, VPos::Center));
+            chart.draw_series(std::iter::once(EmptyElement::at(*end) + Text::new(name.clone(), (10, 0), style)))?;
+        }
+    }
+
+    root.present()?;
+    Ok(())
+}
+
+/// Products a bump chart ranks at most; more lines cross too often to follow.
+pub const MAX_BUMP: usize = 10;
+
+/// Each product's monthly sales as a layer of a streamgraph: stacked, and centered on the axis
+/// so the stack's thickness is the month's total and no product sits on a flat baseline. The
+/// best-selling `MAX_STREAMS` products get a layer each, in chart order from the top, and the
+/// rest share one.
+pub fn draw_streamgraph<DB: DrawingBackend>(root: &DrawingArea<DB, Shift>, trends: &[ProductTrend], labels: &ChartLabels) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    root.fill(&WHITE)?;
+
+    let layers = labelled_layers(trends, labels);
+    let caption = labels.caption("Product Mix by Month");
+    let font = labels.font_for(std::iter::once(&caption).chain(layers.iter().map(|(name, _)| name)).map(String::as_str));
+    let caption = labels.fit(&font, caption);
+    let (Some(first), Some(last)) = (layers.first().and_then(|(_, monthly)| monthly.first()), layers.first().and_then(|(_, monthly)| monthly.last())) else {
+        root.present()?;
+        return Ok(());
+    };
+    let (first, last) = (first.0, last.0);
+
+    // Each month's stack runs from minus half its total to plus half.
+    let totals: Vec<f64> = (0..layers[0].1.len()).map(|month| layers.iter().map(|(_, monthly)| monthly[month].1.max(0.0)).sum()).collect();
+    let half = totals.iter().fold(0f64, |max, total| max.max(*total)) / 2.0;
+    let half = if half > 0.0 { half } else { 1.0 };
+    let label = |month: &NaiveDate| match labels.locale {
+        Some(locale) => labels.fit(&font, labels.text(&locale.month_label(*month))),
+        None => month.format("%Y-%m").to_string(),
+    };
+    let mut chart = ChartBuilder::on(root)
+        .caption(&caption, (font.as_str(), 30).into_font())
+        .margin(10)
+        .margin_left(if labels.rtl { SIDE_LEGEND_WIDTH } else { 40 })
+        .margin_right(if labels.rtl { 40 } else { SIDE_LEGEND_WIDTH })
+        .x_label_area_size(40)
+        .build_cartesian_2d((first..last).monthly(), -half * 1.05..half * 1.05)?;
+    chart.configure_mesh().label_style((font.as_str(), 12)).x_label_formatter(&label).disable_y_mesh().disable_y_axis().draw()?;
+
+    let mut top: Vec<f64> = totals.iter().map(|total| total / 2.0).collect();
+    for (i, (_, monthly)) in layers.iter().enumerate() {
+        let bottom: Vec<f64> = top.iter().zip(monthly).map(|(top, (_, sales))| top - sales.max(0.0)).collect();
+        let upper = monthly.iter().zip(&top).map(|((month, _), top)| (*month, *top));
+        let lower = monthly.iter().zip(&bottom).rev().map(|((month, _), bottom)| (*month, *bottom));
+        let color = Palette99::pick(i).to_rgba();
+        chart.draw_series(std::iter::once(Polygon::new(upper.chain(lower).collect::<Vec<_>>(), color.mix(0.85).filled())))?;
+        top = bottom;
+    }
+    // The legend goes in the margin beside the stack, which fills the whole plot, best-selling
+    // product first as in the stack.
+    let names: Vec<&String> = layers.iter().map(|(name, _)| name).collect();
+    draw_side_legend(root, &names, |i| i, &font, labels)?;
+
+    root.present()?;
+    Ok(())
+}
+
+/// Products a streamgraph or stacked bar chart gives a layer of their own; the rest are drawn
+/// as one.
+pub const MAX_STREAMS: usize = 12;
+
+// Room beside a streamgraph or stacked bar chart for its legend, in pixels.
+const SIDE_LEGEND_WIDTH: u32 = 150;
+
+// A product's layer of a streamgraph or stacked bar chart: its name, None for the rest added
+// up, and its sales by month.
+pub(crate) type Layer<'a> = (Option<&'a str>, Vec<(NaiveDate, f64)>);
+
+/// The monthly sales of the best-selling `MAX_STREAMS` products of `trends`, in chart order,
+/// and of the rest added up as one layer, named None.
+pub(crate) fn product_layers(trends: &[ProductTrend]) -> Vec<Layer<'_>> {
+    let mut layers: Vec<Layer> = trends.iter().take(MAX_STREAMS).map(|trend| (Some(trend.product.as_str()), trend.monthly.clone())).collect();
+    if trends.len() > MAX_STREAMS {
+        let mut other = trends[MAX_STREAMS].monthly.clone();
+        for trend in &trends[MAX_STREAMS + 1..] {
+            for ((_, total), (_, sales)) in other.iter_mut().zip(&trend.monthly) {
+                *total += sales;
+            }
+        }
+        layers.push((None, other));
+    }
+    layers
+}
+
+fn labelled_layers(trends: &[ProductTrend], labels: &ChartLabels) -> Vec<(String, Vec<(NaiveDate, f64)>)> {
+    product_layers(trends).into_iter().map(|(product, monthly)| (product.map_or_else(|| labels.caption("Other"), |product| labels.text(product)), monthly)).collect()
+}
+
+// Swatches and names of `names` down the margin beside a chart, each in the color of the layer
+// whose index `layer` gives for it.
+fn draw_side_legend<DB: DrawingBackend>(root: &DrawingArea<DB, Shift>, names: &[&String], layer: impl Fn(usize) -> usize, font: &str, labels: &ChartLabels) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let (width, _) = root.dim_in_pixel();
+    let x = if labels.rtl { 10 } else { width as i32 - SIDE_LEGEND_WIDTH as i32 + 10 };
+    for (i, name) in names.iter().enumerate() {
+        let y = 60 + 20 * i as i32;
+        root.draw(&Rectangle::new([(x, y - 5), (x + 10, y + 5)], Palette99::pick(layer(i)).filled()))?;
+        let style = (font, 12).into_font().color(&BLACK).pos(Pos::new(HPos::Left, VPos::Center));
+        root.draw(&Text::new(labels.fit(font, name.to_string()), (x + 16, y), style))?;
+    }
+    Ok(())
+}
+
+/// A bar a month of each product's sales stacked on the others, the best-selling product at
+/// the bottom, so the bars' heights are the monthly totals and their layers the product mix.
+/// The best-selling `MAX_STREAMS` products get a layer each and the rest share one, named in a
+/// legend beside the bars in the order they are stacked from the top.
+pub fn draw_stacked_bar_chart<DB: DrawingBackend>(root: &DrawingArea<DB, Shift>, trends: &[ProductTrend], labels: &ChartLabels) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    root.fill(&WHITE)?;
+
+    let layers = labelled_layers(trends, labels);
+    let months: Vec<String> = layers
+        .first()
+        .map(|(_, monthly)| monthly.iter().map(|(month, _)| labels.text(&labels.locale.map_or_else(|| month.format("%Y-%m").to_string(), |locale| locale.month_label(*month)))).collect())
+        .unwrap_or_default();
+    let caption = labels.caption("Product Sales by Month");
+    let font = labels.font_for(std::iter::once(&caption).chain(&months).chain(layers.iter().map(|(name, _)| name)).map(String::as_str));
+    let caption = labels.fit(&font, caption);
+    let months: Vec<String> = months.into_iter().map(|month| labels.fit(&font, month)).collect();
+    if months.is_empty() {
+        root.present()?;
+        return Ok(());
+    }
+
+    // Months with returns count what was sold, as in the streamgraph.
+    let totals: Vec<f64> = (0..months.len()).map(|month| layers.iter().map(|(_, monthly)| monthly[month].1.max(0.0)).sum()).collect();
+    let high = totals.iter().fold(0f64, |max, total| max.max(*total));
+    let high = if high > 0.0 { high * 1.05 } else { 1.0 };
+    let mut chart = ChartBuilder::on(root)
+        .caption(&caption, (font.as_str(), 30).into_font())
+        .margin(10)
+        .margin_left(if labels.rtl { SIDE_LEGEND_WIDTH } else { 10 })
+        .margin_right(if labels.rtl { 10 } else { SIDE_LEGEND_WIDTH })
+        .x_label_area_size(40)
+        .y_label_area_size(60)
+        .build_cartesian_2d((0..months.len() - 1).into_segmented(), 0.0..high)?;
+
+    let month_ticks = |value: &SegmentValue<usize>| match value {
+        SegmentValue::CenterOf(i) | SegmentValue::Exact(i) => months.get(*i).cloned().unwrap_or_default(),
+        SegmentValue::Last => String::new(),
+    };
+    let mut mesh = chart.configure_mesh();
+    mesh.label_style((font.as_str(), 12)).disable_x_mesh().x_label_formatter(&month_ticks);
+    let amounts = labels.amount_ticks();
+    if let Some(amounts) = &amounts {
+        mesh.y_label_formatter(amounts);
+    }
+    mesh.draw()?;
+
+    let mut bottom = vec![0.0; months.len()];
+    for (layer, (_, monthly)) in layers.iter().enumerate() {
+        let color = Palette99::pick(layer).to_rgba();
+        chart.draw_series(monthly.iter().zip(&mut bottom).enumerate().map(|(i, ((_, sales), bottom))| {
+            let top = *bottom + sales.max(0.0);
+            // The last month's segment ends where the axis does.
+            let end = if i + 1 < months.len() { SegmentValue::Exact(i + 1) } else { SegmentValue::Last };
+            let mut bar = Rectangle::new([(SegmentValue::Exact(i), *bottom), (end, top)], color.mix(0.9).filled());
+            bar.set_margin(0, 0, BAR_MARGIN as u32, BAR_MARGIN as u32);
+            *bottom = top;
+            bar
+        }))?;
+    }
+    let names: Vec<&String> = layers.iter().rev().map(|(name, _)| name).collect();
+    draw_side_legend(root, &names, |i| layers.len() - 1 - i, &font, labels)?;
+
+    root.present()?;
+    Ok(())
+}
+
+/// A funnel of `stages` from the top down, each a bar centered under the one before and as wide
+/// as its sales against the largest stage's, with each stage's conversion from the one before
+/// between them.
+pub fn draw_funnel_chart<DB: DrawingBackend>(root: &DrawingArea<DB, Shift>, stages: &[FunnelStage], labels: &ChartLabels) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    root.fill(&WHITE)?;
+
+    let caption = labels.caption("Sales Funnel");
+    let names: Vec<String> = stages.iter().map(|stage| labels.text(&stage.stage)).collect();
+    let font = labels.font_for(std::iter::once(&caption).chain(&names).map(String::as_str));
+    let caption = labels.fit(&font, caption);
+    let root = root.titled(&caption, (font.as_str(), 30).into_font())?;
+    if stages.is_empty() {
+        root.present()?;
+        return Ok(());
+    }
+
+    let (width, height) = root.dim_in_pixel();
+    let row = (f64::from(height) - 2.0 * FUNNEL_MARGIN) / stages.len() as f64;
+    let room = f64::from(width) - 2.0 * FUNNEL_MARGIN;
+    let max = stages.iter().fold(0f64, |max, stage| max.max(stage.sales));
+    let center = width as i32 / 2;
+    for (i, (stage, name)) in stages.iter().zip(&names).enumerate() {
+        let top = FUNNEL_MARGIN + row * i as f64;
+        // Bars keep a sliver of width so a stage without sales still shows where it is.
+        let half = if max > 0.0 { (stage.sales.max(0.0) / max * room / 2.0).max(2.0) } else { 2.0 };
+        let (bar_top, bar_bottom) = ((top + row * 0.25) as i32, (top + row) as i32);
+        let color = Palette99::pick(i).to_rgba();
+        root.draw(&Rectangle::new([(center - half as i32, bar_top), (center + half as i32, bar_bottom)], color.mix(0.85).filled()))?;
+        let style = (font.as_str(), 14).into_font().color(&BLACK).pos(Pos::new(HPos::Center, VPos::Center));
+        let text = labels.fit(&font, format!("{}: {}", name, labels.money.format(stage.sales)));
+        root.draw(&Text::new(text, (center, (bar_top + bar_bottom) / 2), style))?;
+        if let Some(conversion) = stage.conversion_percent {
+            let style = (font.as_str(), 12).into_font().color(&BLACK).pos(Pos::new(HPos::Center, VPos::Center));
+            let text = labels.text(&labels.translations.fill("{} converted", &labels.money.percent(conversion, false)));
+            root.draw(&Text::new(text, (center, (top + row * 0.125) as i32), style))?;
+        }
+    }
+
+    root.present()?;
+    Ok(())
+}
+
+// Room around a funnel chart's bars, in pixels.
+const FUNNEL_MARGIN: f64 = 20.0;
+
+/// One headline figure of a metric card, such as the total revenue.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KpiCard {
+    /// File stem of the card, such as `kpi_total_revenue`
+    pub name: &'static str,
+    /// The metric, as an English caption
+    pub title: &'static str,
+    /// The figure in big type
+    pub value: String,
+    /// A line of context under it, such as the months it covers
+    pub detail: String,
+    /// Whether the figure went up (true) or down (false) from the month before, for its arrow
+    pub rising: Option<bool>,
+}
+
+/// The metric cards of `data`: total revenue, the latest month's growth on the one before, the
+/// best month, and the top product. None without any months.
+pub fn kpi_cards((monthly_data, product_data): &PlotData, labels: &ChartLabels) -> Option<[KpiCard; 4]> {
+    let month = |month: &NaiveDate| match labels.locale {
+        Some(locale) => locale.month_label(*month),
+        None => month.format("%Y-%m").to_string(),
+    };
+    let (first, last) = (monthly_data.first()?, monthly_data.last()?);
+    let total: f64 = monthly_data.iter().map(|(_, sales)| sales).sum();
+    let change = match monthly_data.as_slice() {
+        [.., (_, previous), (_, last)] if *previous != 0.0 => Some((last - previous) / previous.abs() * 100.0),
+        _ => None,
+    };
+    let rising = change.filter(|change| *change != 0.0).map(|change| change > 0.0);
+    let range = if first.0 == last.0 { month(&first.0) } else { format!("{} – {}", month(&first.0), month(&last.0)) };
+    let growth_detail = match monthly_data.as_slice() {
+        [.., (previous, _), (last, _)] => format!("{} / {}", month(last), month(previous)),
+        _ => month(&last.0),
+    };
+    let best = monthly_data.iter().fold(first, |best, month| if month.1 > best.1 { month } else { best });
+    let products: f64 = product_data.iter().map(|(_, sales)| sales).sum();
+    let (top, top_detail) = match product_data.first() {
+        Some((product, sales)) if products != 0.0 => {
+            (product.clone(), format!("{} ({})", labels.money.format(*sales), labels.money.percent(sales / products * 100.0, false)))
+        }
+        Some((product, sales)) => (product.clone(), labels.money.format(*sales)),
+        None => ("–".to_string(), String::new()),
+    };
+    Some([
+        KpiCard { name: "kpi_total_revenue", title: "Total Revenue", value: labels.money.format(total), detail: range, rising },
+        KpiCard {
+            name: "kpi_growth",
+            title: "Month over Month",
+            value: change.map_or("–".to_string(), |change| labels.money.percent(change, true)),
+            detail: growth_detail,
+            rising,
+        },
+        KpiCard { name: "kpi_best_month", title: "Best Month", value: month(&best.0), detail: labels.money.format(best.1), rising: None },
+        KpiCard { name: "kpi_top_product", title: "Top Product", value: top, detail: top_detail, rising: None },
+    ])
+}
+
+/// A metric card: `card`'s title, its figure in big type made smaller until it fits, with a
+/// green arrow up or red arrow down after it when it rose or fell, and its detail line.
+pub fn draw_kpi_card<DB: DrawingBackend>(root: &DrawingArea<DB, Shift>, card: &KpiCard, labels: &ChartLabels) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    root.fill(&WHITE)?;
+
+    let (title, value, detail) = (labels.caption(card.title), labels.text(&card.value), labels.text(&card.detail));
+    let font = labels.font_for([title.as_str(), value.as_str(), detail.as_str()]);
+    let (title, value, detail) = (labels.fit(&font, title), labels.fit(&font, value), labels.fit(&font, detail));
+    let (width, height) = root.dim_in_pixel();
+    let (width, height) = (width as i32, height as i32);
+    root.draw(&Rectangle::new([(0, 0), (width - 1, height - 1)], NO_SALES))?;
+
+    let (anchor, x) = if labels.rtl { (HPos::Right, width - KPI_PADDING) } else { (HPos::Left, KPI_PADDING) };
+    let style = |size: u32, color: &RGBColor| (font.as_str(), size).into_font().color(color).pos(Pos::new(anchor, VPos::Top));
+    root.draw(&Text::new(title, (x, KPI_PADDING), style(16, &KPI_GREY)))?;
+
+    // Arrows take a square as high as the figure beside it.
+    let room = width - 2 * KPI_PADDING;
+    let text_width = |size: u32| -> Result<i32, Box<dyn Error>> { Ok(root.estimate_text_size(&value, &style(size, &BLACK))?.0 as i32) };
+    let arrow = |size: u32| if card.rising.is_some() { size as i32 } else { 0 };
+    let mut size = 44;
+    while size > 12 && text_width(size)? + arrow(size) > room {
+        size -= 2;
+    }
+    let top = height / 2 - size as i32 / 2;
+    root.draw(&Text::new(value.clone(), (x, top), style(size, &BLACK)))?;
+    if let Some(rising) = card.rising {
+        let text_width = text_width(size)?;
+        let side = size as i32 * 2 / 3;
+        let left = if labels.rtl { x - text_width - side - side / 4 } else { x + text_width + side / 4 };
+        let (bottom, top) = (top + (size as i32 + side) / 2, top + (size as i32 - side) / 2);
+        let (points, color) = if rising {
+            (vec![(left, bottom), (left + side, bottom), (left + side / 2, top)], ON_PLAN)
+        } else {
+            (vec![(left, top), (left + side, top), (left + side / 2, bottom)], BELOW_PLAN)
+        };
+        root.draw(&Polygon::new(points, color.filled()))?;
+    }
+    let style = (font.as_str(), 14).into_font().color(&KPI_GREY).pos(Pos::new(anchor, VPos::Bottom));
+    root.draw(&Text::new(detail, (x, height - KPI_PADDING), style))?;
+
+    root.present()?;
+    Ok(())
+}
+
+/// A one-page overview of `data` to share: the metric cards in a row, the monthly trend as a
+/// sparkline, and a table of the `TOP_PRODUCTS` best-selling products.
+pub fn draw_executive_summary<DB: DrawingBackend>(root: &DrawingArea<DB, Shift>, data: &PlotData, labels: &ChartLabels) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    root.fill(&WHITE)?;
+
+    let (monthly_data, product_data) = data;
+    let caption = labels.caption("Executive Summary");
+    let products: Vec<String> = product_data.iter().take(TOP_PRODUCTS).map(|(product, _)| labels.text(product)).collect();
+    let font = labels.font_for(std::iter::once(&caption).chain(&products).map(String::as_str));
+    let caption = labels.fit(&font, caption);
+    let root = root.titled(&caption, (font.as_str(), 30).into_font())?;
+    let Some(cards) = kpi_cards(data, labels) else {
+        root.present()?;
+        return Ok(());
+    };
+    let root = root.margin(0, 10, 10, 10);
+    let (row, rest) = root.split_vertically(KPI_SIZE.1);
+    for (area, card) in row.split_evenly((1, cards.len())).iter().zip(&cards) {
+        draw_kpi_card(&area.margin(0, 0, 5, 5), card, labels)?;
+    }
+    let (left, right) = rest.split_horizontally(rest.dim_in_pixel().0 / 2);
+    let (trend, table) = if labels.rtl { (right, left) } else { (left, right) };
+
+    // The sparkline leaves out the axes; the first and last month under its ends date it.
+    let (first, last) = (monthly_data[0], monthly_data[monthly_data.len() - 1]);
+    let max = monthly_data.iter().fold(0f64, |max, (_, sales)| max.max(*sales));
+    let min = monthly_data.iter().fold(0f64, |min, (_, sales)| min.min(*sales));
+    let (first_day, last_day) = if first.0 == last.0 { (first.0 - Days::new(1), last.0 + Days::new(1)) } else { (first.0, last.0) };
+    let sparkline = trend.margin(20, 30, 20, 20);
+    let mut chart = ChartBuilder::on(&sparkline)
+        .caption(labels.fit(&font, labels.caption("Monthly Sales Trend")), (font.as_str(), 18).into_font())
+        .build_cartesian_2d(first_day..last_day, min..if max > min { max * 1.05 } else { min + 1.0 })?;
+    chart.draw_series(AreaSeries::new(monthly_data.iter().copied(), min, BLUE.mix(0.15)).border_style(BLUE.stroke_width(2)))?;
+    chart.draw_series(std::iter::once(Circle::new(last, 4, BLUE.filled())))?;
+    let month = |month: &NaiveDate| match labels.locale {
+        Some(locale) => labels.fit(&font, labels.text(&locale.month_label(*month))),
+        None => month.format("%Y-%m").to_string(),
+    };
+    let (width, height) = trend.dim_in_pixel();
+    let style = |anchor| (font.as_str(), 13).into_font().color(&KPI_GREY).pos(Pos::new(anchor, VPos::Bottom));
+    trend.draw(&Text::new(month(&first.0), (20, height as i32 - 5), style(HPos::Left)))?;
+    trend.draw(&Text::new(month(&last.0), (width as i32 - 30, height as i32 - 5), style(HPos::Right)))?;
+
+    // The table: rank, product, sales, and share of all sales, in columns from the reading side.
+    let total: f64 = product_data.iter().map(|(_, sales)| sales).sum();
+    let (width, _) = table.dim_in_pixel();
+    let width = width as i32;
+    let column = |offset: i32| if labels.rtl { width - offset } else { offset };
+    let (near, far) = if labels.rtl { (HPos::Right, HPos::Left) } else { (HPos::Left, HPos::Right) };
+    let text = |size: u32, color: &RGBColor, anchor| (font.as_str(), size).into_font().color(color).pos(Pos::new(anchor, VPos::Center));
+    table.draw(&Text::new(labels.fit(&font, labels.caption("Top Products")), (width / 2, 30), text(18, &BLACK, HPos::Center)))?;
+    let headers = [(labels.caption("Product"), column(50), near), (labels.caption("Sales"), column(width - 130), far), (labels.caption("Share"), column(width - 30), far)];
+    for (header, x, anchor) in headers {
+        table.draw(&Text::new(labels.fit(&font, header), (x, 75), text(14, &KPI_GREY, anchor)))?;
+    }
+    for (i, ((_, sales), product)) in product_data.iter().zip(&products).enumerate() {
+        let y = 110 + 36 * i as i32;
+        table.draw(&Text::new(format!("{}", i + 1), (column(20), y), text(16, &KPI_GREY, near)))?;
+        table.draw(&Text::new(labels.fit(&font, product.clone()), (column(50), y), text(16, &BLACK, near)))?;
+        table.draw(&Text::new(labels.money.format(*sales), (column(width - 130), y), text(16, &BLACK, far)))?;
+        let share = if total != 0.0 { labels.money.percent(sales / total * 100.0, false) } else { String::new() };
+        table.draw(&Text::new(share, (column(width - 30), y), text(16, &BLACK, far)))?;
+        table.draw(&PathElement::new(vec![(20, y + 18), (width - 20, y + 18)], NO_SALES))?;
+    }
+
+    root.present()?;
+    Ok(())
+}
+
+/// Each chart of `layout` in its cells, with a gap between them. Cells for products take their
+/// trends from `trends`, and the funnel its stages from `funnel`.
+pub fn draw_layout<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    layout: &Layout,
+    data: &PlotData,
+    trends: &[ProductTrend],
+    funnel: &[FunnelStage],
+    labels: &ChartLabels,
+) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    root.fill(&WHITE)?;
+
+    let cards = kpi_cards(data, labels);
+    for (cell, (corner, size)) in layout.cells.iter().zip(layout.areas(root.dim_in_pixel())) {
+        let area = root.clone().shrink(corner, size).margin(LAYOUT_GAP, LAYOUT_GAP, LAYOUT_GAP, LAYOUT_GAP);
+        match cell.chart {
+            CellChart::Line => {
+                let labels = ChartLabels { prior_year: cell.prior_year.unwrap_or(labels.prior_year), ..labels.clone() };
+                draw_line_chart(&area, &data.0, &labels)?
+            }
+            CellChart::Bar => draw_bar_chart(&area, &data.1, labels)?,
+            CellChart::Pie => draw_pie_chart(&area, &data.1, labels)?,
+            CellChart::Streamgraph => draw_streamgraph(&area, trends, labels)?,
+            CellChart::StackedBar => draw_stacked_bar_chart(&area, trends, labels)?,
+            CellChart::Bump => draw_bump_chart(&area, trends, labels)?,
+            CellChart::Funnel => draw_funnel_chart(&area, funnel, labels)?,
+            CellChart::Kpi => {
+                let index = match cell.metric {
+                    Some(Metric::TotalRevenue) | None => 0,
+                    Some(Metric::Growth) => 1,
+                    Some(Metric::BestMonth) => 2,
+                    Some(Metric::TopProduct) => 3,
+                };
+                if let Some(cards) = &cards {
+                    draw_kpi_card(&area, &cards[index], labels)?;
+                }
+            }
+            CellChart::Product => {
+                let product = cell.product.as_deref().unwrap_or_default();
+                let trend = trends.iter().find(|trend| trend.product == product).ok_or_else(|| format!("The layout charts {}, which has no sales", product))?;
+                draw_product_chart(&area, product, &trend.monthly, labels)?
+            }
+        }
+    }
+
+    root.present()?;
+    Ok(())
+}
+
+// Room around each chart of a layout, in pixels.
+const LAYOUT_GAP: u32 = 5;
+
+/// Products the executive summary lists.
+pub const TOP_PRODUCTS: usize = 5;
+
+// A metric card's title and detail line, and the room inside its border, in pixels.
+const KPI_GREY: RGBColor = RGBColor(100, 100, 100);
+const KPI_PADDING: i32 = 14;
+
+// Metric cards are small enough to sit in a row in a README or a dashboard tile.
+const KPI_SIZE: (u32, u32) = (320, 160);
+
+// Panels per row of a faceted chart: as many as rows, or one more.
+pub(crate) fn facets_per_row(count: usize) -> usize {
+    (1..).find(|columns| columns * columns >= count).unwrap_or(1)
+}
+
+/// A choropleth map of `map`, each area shaded by its `sales` from light blue at none to dark
+/// blue at the best-selling area's, and grey without any, above a scale of the shades.
+pub fn draw_choropleth<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    map: &RegionMap,
+    sales: &[Option<f64>],
+    labels: &ChartLabels,
+) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    root.fill(&WHITE)?;
+
+    let caption = labels.caption("Sales by Region");
+    let font = labels.font_for([caption.as_str()]);
+    let caption = labels.fit(&font, caption);
+    let root = root.titled(&caption, (font.as_str(), 30).into_font())?;
+
+    let points = map.areas.iter().flat_map(|area| &area.polygons).flatten();
+    let (west, east, south, north) =
+        points.fold((f64::MAX, f64::MIN, f64::MAX, f64::MIN), |(w, e, s, n), (x, y)| (w.min(*x), e.max(*x), s.min(*y), n.max(*y)));
+    if west >= east || south >= north {
+        root.present()?;
+        return Ok(());
+    }
+    let (width, height) = root.dim_in_pixel();
+    let (room_x, room_y) = (f64::from(width) - 2.0 * MAP_MARGIN, f64::from(height) - 2.0 * MAP_MARGIN - MAP_SCALE_HEIGHT);
+    let scale = (room_x / ((east - west) * map.aspect)).min(room_y / (north - south));
+    let left = MAP_MARGIN + (room_x - (east - west) * map.aspect * scale) / 2.0;
+    let top = MAP_MARGIN + (room_y - (north - south) * scale) / 2.0;
+    let pixel = |(x, y): &(f64, f64)| ((left + (x - west) * map.aspect * scale) as i32, (top + (north - y) * scale) as i32);
+
+    let max = sales.iter().flatten().fold(0f64, |max, sales| max.max(*sales));
+    let share = |sales: f64| if max > 0.0 { (sales / max).clamp(0.0, 1.0) } else { 0.0 };
+    for (area, sales) in map.areas.iter().zip(sales) {
+        let color = sales.map_or(NO_SALES, |sales| map_shade(share(sales)));
+        for polygon in &area.polygons {
+            let points: Vec<(i32, i32)> = polygon.iter().map(pixel).collect();
+            root.draw(&Polygon::new(points.clone(), color.filled()))?;
+            root.draw(&PathElement::new(points.iter().chain(points.first()).copied().collect::<Vec<_>>(), WHITE))?;
+        }
+        let (Some(label), Some(polygon)) = (&area.label, area.polygons.first()) else {
+            continue;
+        };
+        let (x, y) = polygon.iter().fold((0.0, 0.0), |(x, y), point| (x + point.0, y + point.1));
+        let center = pixel(&(x / polygon.len() as f64, y / polygon.len() as f64));
+        let text = if sales.is_some_and(|sales| share(sales) > 0.5) { WHITE } else { BLACK };
+        let style = (font.as_str(), 13).into_font().color(&text).pos(Pos::new(HPos::Center, VPos::Center));
+        root.draw(&Text::new(label.clone(), center, style))?;
+    }
+
+    // The scale: a bar shading from none to the top seller, with both amounts below its ends.
+    let (bar_left, bar_top) = ((width as i32 - MAP_SCALE_WIDTH) / 2, height as i32 - MAP_MARGIN as i32 - 40);
+    for step in 0..MAP_SCALE_WIDTH {
+        let shade = map_shade(f64::from(step) / f64::from(MAP_SCALE_WIDTH - 1));
+        root.draw(&Rectangle::new([(bar_left + step, bar_top), (bar_left + step + 1, bar_top + 14)], shade.filled()))?;
+    }
+    let style = |anchor| (font.as_str(), 13).into_font().color(&BLACK).pos(Pos::new(anchor, VPos::Top));
+    root.draw(&Text::new(labels.money.format(0.0), (bar_left, bar_top + 20), style(HPos::Left)))?;
+    root.draw(&Text::new(labels.money.format(max), (bar_left + MAP_SCALE_WIDTH, bar_top + 20), style(HPos::Right)))?;
+
+    root.present()?;
+    Ok(())
+}
+
+// A choropleth shade from light blue at 0 to dark blue at 1.
+fn map_shade(share: f64) -> RGBColor {
+    let mix = |light: u8, dark: u8| (f64::from(light) + (f64::from(dark) - f64::from(light)) * share).round() as u8;
+    RGBColor(mix(222, 8), mix(235, 81), mix(247, 156))
+}
+
+/// Nominal and inflation-adjusted monthly totals on one chart, the latter in `base` prices.
+pub fn draw_inflation_chart<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    nominal: &[(NaiveDate, f64)],
+    real: &[(NaiveDate, f64)],
+    base: NaiveDate,
+    labels: &ChartLabels,
+) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let base = match labels.locale {
+        Some(locale) => locale.month_label(base),
+        None => base.format("%Y-%m").to_string(),
+    };
+    let translations = &labels.translations;
+    let lines = [
+        (nominal, translations.get("Nominal Sales").to_string(), RED),
+        (real, translations.fill("Real Sales ({} prices)", &base), BLUE),
+    ];
+    draw_monthly_lines(root, "Nominal vs Real Monthly Sales", &lines, true, Overlay::default(), labels)
+}
+
+// Shade of the holiday bands behind the lines.
+const HOLIDAY: RGBColor = RGBColor(255, 140, 0);
+
+// The prior year's dashed line.
+const PRIOR_YEAR: RGBColor = RGBColor(150, 150, 150);
+
+// Dots of months at or above their budget, and below it.
+const ON_PLAN: RGBColor = RGBColor(0, 150, 0);
+const BELOW_PLAN: RGBColor = RGBColor(200, 0, 0);
+
+// The break-even level across the monthly trend.
+const BREAK_EVEN: RGBColor = RGBColor(128, 0, 128);
+
+// Map areas without sales, and the room around the map and for its scale, in pixels.
+const NO_SALES: RGBColor = RGBColor(220, 220, 220);
+const MAP_MARGIN: f64 = 20.0;
+const MAP_SCALE_HEIGHT: f64 = 60.0;
+const MAP_SCALE_WIDTH: i32 = 300;
+
+// Where the legend of a line chart goes, and whether it gets a frame and backdrop.
+struct Legend {
+    position: SeriesLabelPosition,
+    framed: bool,
+}
+
+// What a line chart is drawn with besides its lines: caption, font, legend, the holidays
+// shaded behind the lines, with the names of those that get one, and the overlay.
+struct Frame<'a> {
+    caption: &'a str,
+    font: &'a str,
+    legend: Legend,
+    holidays: &'a [(&'a Holiday, Option<String>)],
+    overlay: Overlay<'a>,
+}
+
+// Drawn on a line chart besides its solid lines: colored dots on them, and a dashed line behind.
+#[derive(Default)]
+struct Overlay<'a> {
+    dots: &'a [Dot],
+    dashed: Option<Line<'a>>,
+}
+
+type Dot = (NaiveDate, f64, RGBColor);
+
+// Tick labels in place of plotters' own.
+struct Ticks<'a> {
+    months: Option<&'a dyn Fn(&NaiveDate) -> String>,
+    amounts: Option<&'a dyn Fn(&f64) -> String>,
+}
+
+type Line<'a> = (&'a [(NaiveDate, f64)], String, RGBColor);
+
+// Without a locale the x axis keeps plotters' ISO dates; with one it steps by month so the
+// labels can name the month.
+fn draw_monthly_lines<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    caption: &str,
+    lines: &[Line],
+    framed_legend: bool,
+    overlay: Overlay,
+    labels: &ChartLabels,
+) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let (first, last) = span(lines)?;
+    let months = first..last;
+    let caption = labels.caption(caption);
+    let lines: Vec<Line> = lines.iter().map(|(data, label, color)| (*data, labels.text(label), *color)).collect();
+    let dashed = overlay.dashed.map(|(data, label, color)| (data, labels.text(&label), color));
+    let texts = std::iter::once(&caption).chain(lines.iter().chain(&dashed).map(|(_, label, _)| label));
+    let amounts = labels.amount_ticks();
+    let holidays: Vec<(&Holiday, Option<String>)> =
+        labels.holidays.iter().map(|holiday| (holiday, (holiday.end > holiday.start).then(|| labels.text(&holiday.name)))).collect();
+    let names = holidays.iter().filter_map(|(_, name)| name.as_ref());
+    let texts = texts.chain(names);
+    match labels.locale {
+        Some(locale) => {
+            let names: Vec<String> = lines[0].0.iter().map(|(month, _)| labels.text(&locale.month_label(*month))).collect();
+            let font = labels.font_for(texts.chain(&names).map(String::as_str));
+            let (caption, lines) = fit_lines(labels, &font, caption, lines);
+            let label = |month: &NaiveDate| labels.fit(&font, labels.text(&locale.month_label(*month)));
+            let holidays = fit_holidays(labels, &font, &holidays);
+            let overlay = Overlay { dots: overlay.dots, dashed: dashed.map(|(data, label, color)| (data, labels.fit(&font, label), color)) };
+            let frame = Frame { caption: &caption, font: &font, legend: labels.legend(framed_legend), holidays: &holidays, overlay };
+            let ticks = Ticks { months: Some(&label), amounts: amounts.as_deref() };
+            draw_lines(root, months.monthly(), &lines, frame, ticks)
+        }
+        None => {
+            let font = labels.font_for(texts.map(String::as_str));
+            let (caption, lines) = fit_lines(labels, &font, caption, lines);
+            let holidays = fit_holidays(labels, &font, &holidays);
+            let overlay = Overlay { dots: overlay.dots, dashed: dashed.map(|(data, label, color)| (data, labels.fit(&font, label), color)) };
+            let frame = Frame { caption: &caption, font: &font, legend: labels.legend(framed_legend), holidays: &holidays, overlay };
+            let ticks = Ticks { months: None, amounts: amounts.as_deref() };
+            draw_lines(root, months, &lines, frame, ticks)
+        }
+    }
+}
+
+// First and last month of any of `lines`.
+fn span(lines: &[Line]) -> Result<(NaiveDate, NaiveDate), Box<dyn Error>> {
+    let months = || lines.iter().flat_map(|(data, _, _)| data.iter()).map(|(month, _)| *month);
+    months().min().zip(months().max()).ok_or_else(|| "The data has no sales rows".into())
+}
+
+fn fit_lines<'a>(labels: &ChartLabels, font: &str, caption: String, lines: Vec<Line<'a>>) -> (String, Vec<Line<'a>>) {
+    let lines = lines.into_iter().map(|(data, label, color)| (data, labels.fit(font, label), color)).collect();
+    (labels.fit(font, caption), lines)
+}
+
+fn fit_holidays<'a>(labels: &ChartLabels, font: &str, holidays: &[(&'a Holiday, Option<String>)]) -> Vec<(&'a Holiday, Option<String>)> {
+    holidays.iter().map(|(holiday, name)| (*holiday, name.clone().map(|name| labels.fit(font, name)))).collect()
+}
+
+fn draw_lines<DB, X>(
+    root: &DrawingArea<DB, Shift>,
+    x: X,
+    lines: &[Line],
+    frame: Frame,
+    ticks: Ticks,
+) -> Result<(), Box<dyn Error>>
+where
+    DB: DrawingBackend,
+    DB::ErrorType: 'static,
+    X: AsRangedCoord<Value = NaiveDate>,
+    X::CoordDescType: ValueFormatter<NaiveDate>,
+{
+    root.fill(&WHITE)?;
+
+    let Frame { caption, font, legend, holidays, overlay } = frame;
+    let max = lines.iter().chain(&overlay.dashed).flat_map(|(data, _, _)| data.iter()).map(|(_, v)| *v).fold(0f64, f64::max);
+    let mut builder = ChartBuilder::on(root);
+    builder.caption(caption, (font, 30).into_font()).margin(10).x_label_area_size(40).y_label_area_size(60);
+    // Month labels also sit on the last month, which needs room to the right.
+    if ticks.months.is_some() {
+        builder.margin_right(40);
+    }
+    let mut chart = builder.build_cartesian_2d(x, 0f64..max)?;
+
+    let mut mesh = chart.configure_mesh();
+    mesh.label_style((font, 12));
+    if let Some(months) = ticks.months {
+        mesh.x_label_formatter(months);
+    }
+    if let Some(amounts) = ticks.amounts {
+        mesh.y_label_formatter(amounts);
+    }
+    mesh.draw()?;
+
+    // A day's band runs to the next midnight, cut off where the x axis ends, and is edged so
+    // a single day still shows on a chart of years. Names in the right half end at their band.
+    let (first, last) = span(lines)?;
+    let middle = first + (last - first) / 2;
+    for (holiday, name) in holidays {
+        let (start, end) = (holiday.start.max(first), (holiday.end + Days::new(1)).min(last));
+        if start >= end {
+            continue;
+        }
+        chart.draw_series(std::iter::once(Rectangle::new([(start, 0.0), (end, max)], HOLIDAY.mix(0.2).filled())))?;
+        chart.draw_series(std::iter::once(PathElement::new(vec![(start, 0.0), (start, max)], HOLIDAY.mix(0.6).stroke_width(2))))?;
+        if let Some(name) = name {
+            let (at, anchor) = if start > middle { (end, HPos::Right) } else { (start, HPos::Left) };
+            let style = (font, 11).into_font().color(&RGBColor(96, 96, 96)).pos(Pos::new(anchor, VPos::Top));
+            chart.draw_series(std::iter::once(Text::new(name.clone(), (at, max), style)))?;
+        }
+    }
+
+    if let Some((data, label, color)) = &overlay.dashed {
+        let color = *color;
+        chart
+            .draw_series(DashedLineSeries::new(data.iter().copied(), 6, 4, color.into()))?
+            .label(label.as_str())
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+    }
+    for (data, label, color) in lines {
+        let color = *color;
+        chart
+            .draw_series(LineSeries::new(data.iter().copied(), color))?
+            .label(label.as_str())
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+    }
+    chart.draw_series(overlay.dots.iter().map(|(month, sales, color)| Circle::new((*month, *sales), 4, color.filled())))?;
+
+    if legend.framed {
+        chart
+            .configure_series_labels()
+            .position(legend.position)
+            .label_font((font, 12))
+            .background_style(WHITE.mix(0.8)).border_style(BLACK).draw()?;
+    } else {
+        chart.configure_series_labels().position(legend.position).label_font((font, 12)).draw()?;
+    }
+
+    root.present()?;
+    Ok(())
+}
+
+const BAR_MARGIN: i32 = 5;
+
+/// Where one bar of the bar chart sits in the image, in pixels from the top left corner.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BarArea {
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+}
+
+pub fn draw_bar_chart<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    product_data: &[(String, f64)],
+    labels: &ChartLabels,
+) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    draw_bars(root, product_data, labels).map(|_| ())
+}
+
+// Draws the bar chart and returns each bar's corners in backend pixels.
+fn draw_bars<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    product_data: &[(String, f64)],
+    labels: &ChartLabels,
+) -> Result<Vec<BarArea>, Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    root.fill(&WHITE)?;
+
+    let bar_labels: Vec<String> = product_data.iter().map(|(product, sales)| labels.text(&format!("{}: {}", product, labels.money.format(*sales)))).collect();
+    let caption = labels.caption("Sales by Product");
+    let font = labels.font_for(std::iter::once(&caption).chain(&bar_labels).map(String::as_str));
+    let caption = labels.fit(&font, caption);
+    let bar_labels: Vec<String> = bar_labels.into_iter().map(|label| labels.fit(&font, label)).collect();
+    let mut chart = ChartBuilder::on(root)
+        .caption(caption, (font.as_str(), 30).into_font())
+        .margin(10)
+        .x_label_area_size(40)
+        .y_label_area_size(60)
+        .build_cartesian_2d(
+            0..product_data.len(),
+            0f64..product_data.iter().map(|(_, v)| *v).fold(0f64, f64::max),
+        )?;
+
+    let mut mesh = chart.configure_mesh();
+    mesh.label_style((font.as_str(), 12));
+    let amounts = labels.amount_ticks();
+    if let Some(amounts) = &amounts {
+        mesh.y_label_formatter(amounts);
+    }
+    mesh.draw()?;
+
+    chart.draw_series(
+        product_data.iter().enumerate().map(|(i, (_product, sales))| {
+            let color = Palette99::pick(i).mix(0.9);
+            let mut bar = Rectangle::new([(i, 0.0), (i + 1, *sales)], color.filled());
+            bar.set_margin(0, 0, BAR_MARGIN as u32, BAR_MARGIN as u32);
+            bar
+        }),
+    )?;
+    let areas = product_data
+        .iter()
+        .enumerate()
+        .map(|(i, (_, sales))| {
+            let (left, top) = chart.backend_coord(&(i, *sales));
+            let (right, bottom) = chart.backend_coord(&(i + 1, 0.0));
+            BarArea { left: left + BAR_MARGIN, top: top.min(bottom), right: right - BAR_MARGIN, bottom: top.max(bottom) }
+        })
+        .collect();
+
+    chart.draw_series(
+        product_data.iter().zip(bar_labels).enumerate().map(|(i, ((_, sales), label))| {
+            let style = (font.as_str(), 15).into_font();
+            if labels.rtl {
+                let style = style.into_text_style(root).pos(Pos::new(HPos::Right, VPos::Top));
+                EmptyElement::at((i + 1, *sales)) + Text::new(label, (-BAR_MARGIN, 15), style)
+            } else {
+                EmptyElement::at((i, *sales)) + Text::new(label, (0, 15), TextStyle::from(style))
+            }
+        }),
+    )?;
+
+    root.present()?;
+    Ok(areas)
+}
+
+/// Each product's total in both datasets of `comparison` as a pair of bars, the old in red and
+/// the new in blue.
+pub fn draw_comparison_bar_chart<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    comparison: &Comparison,
+    labels: &ChartLabels,
+) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    root.fill(&WHITE)?;
+
+    let names: Vec<String> = comparison.names.iter().map(|name| labels.text(name)).collect();
+    let products: Vec<String> = comparison.products.iter().map(|delta| labels.text(&delta.key)).collect();
+    let caption = labels.caption("Sales by Product Comparison");
+    let font = labels.font_for(std::iter::once(&caption).chain(&names).chain(&products).map(String::as_str));
+    let caption = labels.fit(&font, caption);
+    let names: Vec<String> = names.into_iter().map(|name| labels.fit(&font, name)).collect();
+    let products: Vec<String> = products.into_iter().map(|product| labels.fit(&font, product)).collect();
+    let max = comparison.products.iter().map(|delta| delta.old.max(delta.new)).fold(0f64, f64::max);
+    let mut chart = ChartBuilder::on(root)
+        .caption(caption, (font.as_str(), 30).into_font())
+        .margin(10)
+        .x_label_area_size(40)
+        .y_label_area_size(60)
+        // Headroom above the tallest pair for its name
+        .build_cartesian_2d(0..2 * comparison.products.len(), 0f64..max * 1.08)?;
+
+    // Products are named on their bars rather than on the x axis.
+    let unlabeled = |_: &usize| String::new();
+    let mut mesh = chart.configure_mesh();
+    mesh.label_style((font.as_str(), 12)).disable_x_mesh().x_label_formatter(&unlabeled);
+    let amounts = labels.amount_ticks();
+    if let Some(amounts) = &amounts {
+        mesh.y_label_formatter(amounts);
+    }
+    mesh.draw()?;
+
+    // The two bars of a product touch, and pairs are set apart.
+    for (side, color) in [(0, RED), (1, BLUE)] {
+        chart
+            .draw_series(comparison.products.iter().enumerate().map(|(i, delta)| {
+                let sales = if side == 0 { delta.old } else { delta.new };
+                let mut bar = Rectangle::new([(2 * i + side, 0.0), (2 * i + side + 1, sales)], color.mix(0.9).filled());
+                let (left, right) = if side == 0 { (BAR_MARGIN as u32, 0) } else { (0, BAR_MARGIN as u32) };
+                bar.set_margin(0, 0, left, right);
+                bar
+            }))?
+            .label(names[side].as_str())
+            .legend(move |(x, y)| Rectangle::new([(x, y - 5), (x + 10, y + 5)], color.filled()));
+    }
+    chart
+        .configure_series_labels()
+        .position(labels.legend(true).position)
+        .label_font((font.as_str(), 12))
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()?;
+
+    chart.draw_series(comparison.products.iter().zip(products).enumerate().map(|(i, (delta, label))| {
+        let style = (font.as_str(), 15).into_font();
+        let top = delta.old.max(delta.new);
+        if labels.rtl {
+            let style = style.into_text_style(root).pos(Pos::new(HPos::Right, VPos::Bottom));
+            EmptyElement::at((2 * i + 2, top)) + Text::new(label, (-BAR_MARGIN, -4), style)
+        } else {
+            let style = style.into_text_style(root).pos(Pos::new(HPos::Left, VPos::Bottom));
+            EmptyElement::at((2 * i, top)) + Text::new(label, (BAR_MARGIN, -4), style)
+        }
+    }))?;
+
+    root.present()?;
+    Ok(())
+}
+
+pub fn draw_pie_chart<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    product_data: &[(String, f64)],
+    labels: &ChartLabels,
+) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    root.fill(&WHITE)?;
+    let money = &labels.money;
+    let total_sales: f64 = product_data.iter().map(|(_, sales)| sales).sum();
+    let slice_labels: Vec<String> = product_data
+        .iter()
+        .map(|(product, sales)| labels.text(&format!("{}: {} ({})", product, money.format(*sales), money.percent(sales / total_sales * 100.0, false))))
+        .collect();
+    let caption = labels.caption("Sales by Product");
+    let font = labels.font_for(std::iter::once(&caption).chain(&slice_labels).map(String::as_str));
+    let caption = labels.fit(&font, caption);
+    let slice_labels: Vec<String> = slice_labels.into_iter().map(|label| labels.fit(&font, label)).collect();
+    // Pies are drawn at backend coordinates rather than the area's own, so one in a cell of a
+    // layout is moved there by hand, and made smaller for a small cell.
+    let (left, top) = root.get_base_pixel();
+    let root = root.titled(&caption, (font.as_str(), 30).into_font())?;
+
+    let (width, height) = root.dim_in_pixel();
+    let center = (left + width as i32 / 2, top + height as i32 / 2);
+    let radius = 180f64.min(f64::from(width.min(height)) / 3.0);
+
+    let sizes: Vec<f64> = product_data.iter().map(|(_, sales)| *sales).collect();
+    let colors: Vec<RGBColor> = (0..product_data.len())
+        .map(|idx| {
+            let (r, g, b) = Palette99::pick(idx).rgb();
+            RGBColor(r, g, b)
+        })
+        .collect();
+
+    let mut pie = Pie::new(&center, &radius, &sizes, &colors, &slice_labels);
+    pie.label_style((font.as_str(), 15).into_font());
+    root.draw(&pie)?;
+
+    root.present()?;
+    Ok(())
+}
+
+// Draws `$draw` onto `$root`, a drawing area of `$size` over the file at `$path`: an SVG file
+// when the name ends in `.svg`, and a PNG otherwise. The drawing functions are generic over the
+// backend, which a closure cannot be.
+#[cfg(all(feature = "bitmap", feature = "svg", not(target_arch = "wasm32")))]
+macro_rules! draw_file {
+    ($path:expr, $size:expr, |$root:ident| $draw:expr) => {{
+        let (path, size): (&Path, (u32, u32)) = ($path.as_ref(), $size);
+        if path.extension().is_some_and(|extension| extension == "svg") {
+            let $root = SVGBackend::new(path, size).into_drawing_area();
+            $draw
+        } else {
+            let $root = BitMapBackend::new(path, size).into_drawing_area();
+            $draw
+        }
+    }};
+}
+
+#[cfg(all(feature = "bitmap", not(feature = "svg"), not(target_arch = "wasm32")))]
+macro_rules! draw_file {
+    ($path:expr, $size:expr, |$root:ident| $draw:expr) => {{
+        let $root = BitMapBackend::new($path, $size).into_drawing_area();
+        $draw
+    }};
+}
+
+// Charts are 800x600 unless the labels ask for another size.
+pub(crate) const SIZE: (u32, u32) = (800, 600);
+
+// Chart files are only written natively and with the `bitmap` feature, as PNG or, named `.svg`
+// and with the `svg` feature, as SVG; in the browser the same drawing code targets SVG strings.
+
+#[cfg(all(feature = "bitmap", not(target_arch = "wasm32")))]
+pub fn create_line_chart(path: &Path, monthly_data: &[(NaiveDate, f64)], labels: &ChartLabels) -> Result<(), Box<dyn Error>> {
+    let _span = debug_span!("render", chart = "line", path = %path.display()).entered();
+    draw_file!(path, labels.size, |root| draw_line_chart(&root, monthly_data, &labels.for_chart("line")))
+}
+
+#[cfg(all(feature = "bitmap", not(target_arch = "wasm32")))]
+pub fn create_product_chart(path: &Path, product: &str, monthly_data: &[(NaiveDate, f64)], labels: &ChartLabels) -> Result<(), Box<dyn Error>> {
+    let _span = debug_span!("render", chart = "product", path = %path.display()).entered();
+    draw_file!(path, labels.size, |root| draw_product_chart(&root, product, monthly_data, &labels.for_chart("product")))
+}
+
+#[cfg(all(feature = "bitmap", not(target_arch = "wasm32")))]
+pub fn create_choropleth(path: &Path, map: &RegionMap, sales: &[Option<f64>], labels: &ChartLabels) -> Result<(), Box<dyn Error>> {
+    let _span = debug_span!("render", chart = "map", path = %path.display()).entered();
+    draw_file!(path, labels.size, |root| draw_choropleth(&root, map, sales, &labels.for_chart("map")))
+}
+
+#[cfg(all(feature = "bitmap", not(target_arch = "wasm32")))]
+pub fn create_scenario_chart(path: &Path, monthly_data: &[(NaiveDate, f64)], scenario: &[(NaiveDate, f64)], labels: &ChartLabels) -> Result<(), Box<dyn Error>> {
+    let _span = debug_span!("render", chart = "scenario", path = %path.display()).entered();
+    draw_file!(path, labels.size, |root| draw_scenario_chart(&root, monthly_data, scenario, &labels.for_chart("scenario")))
+}
+
+#[cfg(all(feature = "bitmap", not(target_arch = "wasm32")))]
+pub fn create_bump_chart(path: &Path, trends: &[ProductTrend], labels: &ChartLabels) -> Result<(), Box<dyn Error>> {
+    let _span = debug_span!("render", chart = "bump", path = %path.display()).entered();
+    draw_file!(path, labels.size, |root| draw_bump_chart(&root, trends, &labels.for_chart("bump")))
+}
+
+#[cfg(all(feature = "bitmap", not(target_arch = "wasm32")))]
+pub fn create_streamgraph(path: &Path, trends: &[ProductTrend], labels: &ChartLabels) -> Result<(), Box<dyn Error>> {
+    let _span = debug_span!("render", chart = "streamgraph", path = %path.display()).entered();
+    draw_file!(path, labels.size, |root| draw_streamgraph(&root, trends, &labels.for_chart("streamgraph")))
+}
+
+#[cfg(all(feature = "bitmap", not(target_arch = "wasm32")))]
+pub fn create_stacked_bar_chart(path: &Path, trends: &[ProductTrend], labels: &ChartLabels) -> Result<(), Box<dyn Error>> {
+    let _span = debug_span!("render", chart = "stacked_bar", path = %path.display()).entered();
+    draw_file!(path, labels.size, |root| draw_stacked_bar_chart(&root, trends, &labels.for_chart("stacked_bar")))
+}
+
+#[cfg(all(feature = "bitmap", not(target_arch = "wasm32")))]
+pub fn create_funnel_chart(path: &Path, stages: &[FunnelStage], labels: &ChartLabels) -> Result<(), Box<dyn Error>> {
+    let _span = debug_span!("render", chart = "funnel", path = %path.display()).entered();
+    draw_file!(path, labels.size, |root| draw_funnel_chart(&root, stages, &labels.for_chart("funnel")))
+}
+
+#[cfg(all(feature = "bitmap", not(target_arch = "wasm32")))]
+const EXECUTIVE_SUMMARY_SIZE: (u32, u32) = (1300, 560);
+
+#[cfg(all(feature = "bitmap", not(target_arch = "wasm32")))]
+pub fn create_kpi_card(path: &Path, card: &KpiCard, labels: &ChartLabels) -> Result<(), Box<dyn Error>> {
+    let _span = debug_span!("render", chart = "kpi", path = %path.display()).entered();
+    draw_file!(path, KPI_SIZE, |root| draw_kpi_card(&root, card, &labels.for_chart("kpi")))
+}
+
+#[cfg(all(feature = "bitmap", not(target_arch = "wasm32")))]
+pub fn create_executive_summary(path: &Path, data: &PlotData, labels: &ChartLabels) -> Result<(), Box<dyn Error>> {
+    let _span = debug_span!("render", chart = "executive_summary", path = %path.display()).entered();
+    draw_file!(path, EXECUTIVE_SUMMARY_SIZE, |root| draw_executive_summary(&root, data, &labels.for_chart("executive_summary")))
+}
+
+#[cfg(all(feature = "bitmap", not(target_arch = "wasm32")))]
+pub fn create_layout_chart(layout: &Layout, data: &PlotData, trends: &[ProductTrend], funnel: &[FunnelStage], labels: &ChartLabels) -> Result<(), Box<dyn Error>> {
+    let _span = debug_span!("render", chart = "layout", path = %layout.output.display()).entered();
+    draw_file!(&layout.output, layout.size, |root| draw_layout(&root, layout, data, trends, funnel, &labels.for_chart("layout")))
+}
+
+#[cfg(all(feature = "bitmap", not(target_arch = "wasm32")))]
+pub fn create_history_chart(path: &Path, runs: &[(String, f64)], labels: &ChartLabels) -> Result<(), Box<dyn Error>> {
+    let _span = debug_span!("render", chart = "history", path = %path.display()).entered();
+    draw_file!(path, labels.size, |root| draw_history_chart(&root, runs, &labels.for_chart("history")))
+}
+
+#[cfg(all(feature = "bitmap", not(target_arch = "wasm32")))]
+pub fn create_bar_chart(path: &Path, product_data: &[(String, f64)], labels: &ChartLabels) -> Result<(), Box<dyn Error>> {
+    let _span = debug_span!("render", chart = "bar", path = %path.display()).entered();
+    draw_file!(path, labels.size, |root| draw_bar_chart(&root, product_data, &labels.for_chart("bar")))
+}
+
+/// The bars `create_bar_chart` draws for `product_data` on a chart of `size`, in the same
+/// order, e.g. to make them clickable with an HTML image map.
+#[cfg(all(feature = "bitmap", not(target_arch = "wasm32")))]
+pub fn bar_chart_areas(product_data: &[(String, f64)], size: (u32, u32)) -> Result<Vec<BarArea>, Box<dyn Error>> {
+    let mut buffer = vec![0u8; (size.0 * size.1 * 3) as usize];
+    let areas = draw_bars(&BitMapBackend::with_buffer(&mut buffer, size).into_drawing_area(), product_data, &ChartLabels::default())?;
+    Ok(areas)
+}
+
+#[cfg(all(feature = "bitmap", not(target_arch = "wasm32")))]
+pub fn create_pie_chart(path: &Path, product_data: &[(String, f64)], labels: &ChartLabels) -> Result<(), Box<dyn Error>> {
+    let _span = debug_span!("render", chart = "pie", path = %path.display()).entered();
+    draw_file!(path, labels.size, |root| draw_pie_chart(&root, product_data, &labels.for_chart("pie")))
+}
+
+/// Writes `compare_line_chart.png` and `compare_bar_chart.png` for `comparison` into `output_dir`.
+#[cfg(all(feature = "bitmap", not(target_arch = "wasm32")))]
+pub fn render_comparison_charts(output_dir: &Path, comparison: &Comparison, labels: &ChartLabels) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let paths = [output_dir.join("compare_line_chart.png"), output_dir.join("compare_bar_chart.png")];
+    {
+        let _span = debug_span!("render", chart = "compare_line", path = %paths[0].display()).entered();
+        draw_file!(&paths[0], labels.size, |root| draw_comparison_line_chart(&root, comparison, &labels.for_chart("compare_line")))?;
+    }
+    let _span = debug_span!("render", chart = "compare_bar", path = %paths[1].display()).entered();
+    draw_file!(&paths[1], labels.size, |root| draw_comparison_bar_chart(&root, comparison, &labels.for_chart("compare_bar")))?;
+    Ok(paths.into())
+}
+
+#[cfg(all(feature = "bitmap", not(target_arch = "wasm32")))]
+pub fn create_inflation_chart(
+    path: &Path,
+    nominal: &[(NaiveDate, f64)],
+    real: &[(NaiveDate, f64)],
+    base: NaiveDate,
+    labels: &ChartLabels,
+) -> Result<(), Box<dyn Error>> {
+    let _span = debug_span!("render", chart = "inflation", path = %path.display()).entered();
+    draw_file!(path, labels.size, |root| draw_inflation_chart(&root, nominal, real, base, &labels.for_chart("inflation")))
+}
+
+#[cfg(all(feature = "bitmap", not(target_arch = "wasm32")))]
+pub fn create_budget_chart(path: &Path, monthly_data: &[(NaiveDate, f64)], targets: &[(NaiveDate, f64)], labels: &ChartLabels) -> Result<(), Box<dyn Error>> {
+    let _span = debug_span!("render", chart = "budget", path = %path.display()).entered();
+    draw_file!(path, labels.size, |root| draw_budget_chart(&root, monthly_data, targets, &labels.for_chart("budget")))
+}
+
+#[cfg(all(feature = "bitmap", not(target_arch = "wasm32")))]
+pub fn create_variance_chart(path: &Path, variance: &[(NaiveDate, f64)], labels: &ChartLabels) -> Result<(), Box<dyn Error>> {
+    let _span = debug_span!("render", chart = "variance", path = %path.display()).entered();
+    draw_file!(path, labels.size, |root| draw_variance_chart(&root, variance, &labels.for_chart("variance")))
+}
+
+/// Writes the faceted chart of `facets`, sized for a 400 by 300 pixel panel each.
+#[cfg(all(feature = "bitmap", not(target_arch = "wasm32")))]
+pub fn create_facet_chart(path: &Path, facets: &[Facet], by: &str, labels: &ChartLabels) -> Result<(), Box<dyn Error>> {
+    let _span = debug_span!("render", chart = "facet", path = %path.display()).entered();
+    let columns = facets_per_row(facets.len());
+    let size = (400 * columns as u32, 300 * facets.len().div_ceil(columns) as u32 + 50);
+    draw_file!(path, size, |root| draw_facet_chart(&root, facets, by, &labels.for_chart("facet")))
+}
+
+/// Renders the line, bar, and pie charts into `output_dir` and returns the written paths.
+/// `labels` sets the language of the months and the currency format.
+#[cfg(all(feature = "bitmap", not(target_arch = "wasm32")))]
+pub fn render_charts(
+    output_dir: &Path,
+    monthly_data: &[(NaiveDate, f64)],
+    product_data: &[(String, f64)],
+    labels: &ChartLabels,
+) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let paths = [output_dir.join("line_chart.png"), output_dir.join("bar_chart.png"), output_dir.join("pie_chart.png")];
+    create_line_chart(&paths[0], monthly_data, labels)?;
+    create_bar_chart(&paths[1], product_data, labels)?;
+    create_pie_chart(&paths[2], product_data, labels)?;
+    Ok(paths.into())
+}
+
+/// Draws the line, bar, and pie charts of a `SalesDataset` into PNG files in `output_dir`, with
+/// `labels` for their language, amounts, and size.
+#[cfg(all(feature = "bitmap", not(target_arch = "wasm32")))]
+#[derive(Clone, Debug)]
+pub struct ChartRenderer {
+    pub output_dir: PathBuf,
+    pub labels: ChartLabels,
+}
+
+#[cfg(all(feature = "bitmap", not(target_arch = "wasm32")))]
+impl ChartRenderer {
+    pub fn new<P: Into<PathBuf>>(output_dir: P) -> Self {
+        ChartRenderer { output_dir: output_dir.into(), labels: ChartLabels::default() }
+    }
+
+    /// Writes `line_chart.png`, `bar_chart.png`, and `pie_chart.png` and returns their paths.
+    pub fn render(&self, dataset: &crate::SalesDataset) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+        render_charts(&self.output_dir, &dataset.monthly, &dataset.products, &self.labels)
+    }
+
+    pub fn line_chart(&self, dataset: &crate::SalesDataset) -> Result<PathBuf, Box<dyn Error>> {
+        let path = self.output_dir.join("line_chart.png");
+        create_line_chart(&path, &dataset.monthly, &self.labels)?;
+        Ok(path)
+    }
+
+    pub fn bar_chart(&self, dataset: &crate::SalesDataset) -> Result<PathBuf, Box<dyn Error>> {
+        let path = self.output_dir.join("bar_chart.png");
+        create_bar_chart(&path, &dataset.products, &self.labels)?;
+        Ok(path)
+    }
+
+    pub fn pie_chart(&self, dataset: &crate::SalesDataset) -> Result<PathBuf, Box<dyn Error>> {
+        let path = self.output_dir.join("pie_chart.png");
+        create_pie_chart(&path, &dataset.products, &self.labels)?;
+        Ok(path)
+    }
+}
@@ -0,0 +1,222 @@
+use std::collections::hash_map::DefaultHasher;
+use std::error::Error;
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use csv::{ByteRecord, Position, ReaderBuilder};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+
+use crate::stream::{RunningTotals, TotalsState};
+use crate::{invalid_rows_error, ParseOptions, ParseReport, SalesTotals};
+
+// How far a run got through its input, and its totals up to there.
+#[derive(Serialize, Deserialize)]
+struct Checkpoint {
+    // The input as the run found it; a checkpoint of an input changed since is not resumed.
+    size: u64,
+    modified_ms: u64,
+    // The build and the options the totals were made with; a checkpoint of another version or
+    // other options is not resumed either.
+    #[serde(default)]
+    version: String,
+    #[serde(default)]
+    options: u64,
+    // Where the next row starts, to seek the reader back to.
+    byte: u64,
+    line: u64,
+    record: u64,
+    totals: TotalsState,
+}
+
+/// Totals the CSV file at `path` a row at a time, saving them to `checkpoint` every `every`
+/// rows. Started again after being interrupted, by Ctrl-C or the process being killed, on the
+/// same unchanged input, with the same options and the same version of sales_chart, it carries
+/// on after the last save instead of from the first row; otherwise it starts over. The
+/// checkpoint is removed once the input has been read to its end.
+///
+/// Rows are taken as `RunningTotals` takes streamed events: only months and products are
+/// totalled, suspicious rows are not flagged, and numeric dates like 03/04/2024 need an
+/// explicit day/month order.
+pub fn process_with_checkpoints(path: &Path, options: &ParseOptions, checkpoint: &Path, every: u64) -> Result<(SalesTotals, ParseReport), Box<dyn Error>> {
+    if options.cube.is_some() || options.daily || options.currency_filter.is_some() || options.exact {
+        return Err("Checkpointed runs only total months and products, without regions, days, currencies, or exact totals".into());
+    }
+    let version = env!("CARGO_PKG_VERSION").to_string();
+    let fingerprint = fingerprint(options);
+    let (size, modified_ms) = stamp(path)?;
+
+    let mut rdr = ReaderBuilder::new().has_headers(true).flexible(true).from_reader(BufReader::new(File::open(path)?));
+    let headers = rdr.headers()?.clone();
+    if let Some(stage) = options.stage_column.as_ref().filter(|name| headers.iter().any(|header| header.eq_ignore_ascii_case(name))) {
+        return Err(format!("Checkpointed runs do not total funnel stages; the input has a {} column", stage).into());
+    }
+    let mut totals = match load(checkpoint)? {
+        Some(saved) if saved.size == size && saved.modified_ms == modified_ms && saved.version == version && saved.options == fingerprint => {
+            let mut position = Position::new();
+            position.set_byte(saved.byte).set_line(saved.line).set_record(saved.record);
+            rdr.seek(position)?;
+            info!("Resuming {} from line {} ({} rows already read)", path.display(), saved.line, saved.totals.rows());
+            RunningTotals::resume(options, headers, saved.totals)?
+        }
+        Some(saved) if saved.size == size && saved.modified_ms == modified_ms => {
+            warn!("Checkpoint {} was saved by another version of sales_chart or with other options, starting over", checkpoint.display());
+            RunningTotals::new(options, headers)?
+        }
+        Some(_) => {
+            warn!("{} changed since checkpoint {} was saved, starting over", path.display(), checkpoint.display());
+            RunningTotals::new(options, headers)?
+        }
+        None => RunningTotals::new(options, headers)?,
+    };
+
+    let mut record = ByteRecord::new();
+    let mut since_save = 0;
+    while rdr.read_byte_record(&mut record)? {
+        // Rejected rows are kept in the report, like in `process_sales_data`.
+        let _ = totals.push_record(&record);
+        since_save += 1;
+        if since_save >= every {
+            let position = rdr.position();
+            let saved = Checkpoint { size, modified_ms, version: version.clone(), options: fingerprint, byte: position.byte(), line: position.line(), record: position.record(), totals: totals.state() };
+            save(checkpoint, &saved)?;
+            since_save = 0;
+        }
+    }
+    if checkpoint.exists() {
+        fs::remove_file(checkpoint).map_err(|e| format!("Cannot remove checkpoint {}: {}", checkpoint.display(), e))?;
+    }
+    totals.report.errors.sort_by_key(|e| e.line);
+    if !options.lenient && !totals.report.errors.is_empty() {
+        return Err(invalid_rows_error(&totals.report.errors));
+    }
+    let sales = totals.totals();
+    Ok((sales, totals.report))
+}
+
+// The size and modification time of the input, in milliseconds since 1970.
+fn stamp(path: &Path) -> Result<(u64, u64), Box<dyn Error>> {
+    let metadata = fs::metadata(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+    let modified_ms = metadata.modified()?.duration_since(UNIX_EPOCH).map_or(0, |age| age.as_millis() as u64);
+    Ok((metadata.len(), modified_ms))
+}
+
+// A hash of everything in `options` that changes the totals, which is all of it.
+fn fingerprint(options: &ParseOptions) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    format!("{:?}", options).hash(&mut hasher);
+    hasher.finish()
+}
+
+fn load(path: &Path) -> Result<Option<Checkpoint>, Box<dyn Error>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let file = File::open(path).map_err(|e| format!("Cannot read checkpoint {}: {}", path.display(), e))?;
+    let checkpoint = serde_json::from_reader(BufReader::new(file)).map_err(|e| format!("Invalid checkpoint {}: {}", path.display(), e))?;
+    Ok(Some(checkpoint))
+}
+
+// Written next to the checkpoint and renamed over it, so an interruption while saving leaves
+// the previous save intact.
+fn save(path: &Path, checkpoint: &Checkpoint) -> Result<(), Box<dyn Error>> {
+    let partial = path.with_extension("partial");
+    let file = File::create(&partial).map_err(|e| format!("Cannot create {}: {}", partial.display(), e))?;
+    let mut writer = BufWriter::new(file);
+    serde_json::to_writer(&mut writer, checkpoint)?;
+    writer.flush()?;
+    fs::rename(&partial, path).map_err(|e| format!("Cannot save checkpoint {}: {}", path.display(), e))?;
+    debug!(line = checkpoint.line, rows = checkpoint.totals.rows(), "saved checkpoint");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+
+    // An input of 60 rows with a repeated and an invalid one, in a directory of the test's own.
+    fn input(test: &str) -> (PathBuf, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("sales_chart-checkpoint-{}-{}", std::process::id(), test));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let mut csv = String::from("month,product,sales_amount\n");
+        for row in 0..60 {
+            csv += &format!("2023-{:02},Product {},{}.{}\n", row % 12 + 1, row % 7, row, row % 10);
+        }
+        csv += "2023-01,Product 0,0.0\n2023-13,Product 1,5\n";
+        let path = dir.join("sales.csv");
+        fs::write(&path, csv).unwrap();
+        (path, dir.join("sales.checkpoint"))
+    }
+
+    // What a run on `path` interrupted after its first `rows` rows has saved, with the totals
+    // of the rows it read unless `counted` is false.
+    fn interrupt(path: &Path, options: &ParseOptions, checkpoint: &Path, rows: u64, version: &str, counted: bool) {
+        let (size, modified_ms) = stamp(path).unwrap();
+        let mut rdr = ReaderBuilder::new().has_headers(true).flexible(true).from_path(path).unwrap();
+        let mut totals = RunningTotals::new(options, rdr.headers().unwrap().clone()).unwrap();
+        let mut record = ByteRecord::new();
+        for _ in 0..rows {
+            assert!(rdr.read_byte_record(&mut record).unwrap());
+            if counted {
+                let _ = totals.push_record(&record);
+            }
+        }
+        let position = rdr.position();
+        let saved = Checkpoint { size, modified_ms, version: version.to_string(), options: fingerprint(options), byte: position.byte(), line: position.line(), record: position.record(), totals: totals.state() };
+        save(checkpoint, &saved).unwrap();
+    }
+
+    fn options() -> ParseOptions {
+        ParseOptions { lenient: true, dedupe: true, ..ParseOptions::default() }
+    }
+
+    #[test]
+    fn a_resumed_run_adds_up_like_an_uninterrupted_one() {
+        let (path, checkpoint) = input("resumed");
+        let (whole, whole_report) = process_with_checkpoints(&path, &options(), &checkpoint, 1000).unwrap();
+        assert!(!checkpoint.exists());
+        for rows in [1, 25, 61] {
+            interrupt(&path, &options(), &checkpoint, rows, env!("CARGO_PKG_VERSION"), true);
+            let (resumed, report) = process_with_checkpoints(&path, &options(), &checkpoint, 10).unwrap();
+            assert_eq!(resumed, whole);
+            assert_eq!((report.rows, report.duplicates, report.errors.len()), (whole_report.rows, whole_report.duplicates, whole_report.errors.len()));
+            assert_eq!((report.rows, report.duplicates, report.errors.len()), (62, 1, 1));
+            assert!(!checkpoint.exists());
+        }
+    }
+
+    #[test]
+    fn a_run_carries_on_from_its_checkpoint() {
+        let (path, checkpoint) = input("carries-on");
+        interrupt(&path, &options(), &checkpoint, 60, env!("CARGO_PKG_VERSION"), false);
+        let (resumed, report) = process_with_checkpoints(&path, &options(), &checkpoint, 10).unwrap();
+        assert_eq!(resumed.1, HashMap::from([("Product 0".to_string(), 0.0)]));
+        assert_eq!(report.rows, 2);
+    }
+
+    #[test]
+    fn checkpoints_of_other_options_or_versions_are_not_resumed() {
+        let (path, checkpoint) = input("mismatch");
+        let (whole, _) = process_with_checkpoints(&path, &options(), &checkpoint, 1000).unwrap();
+        interrupt(&path, &options(), &checkpoint, 30, "0.0.0", false);
+        assert_eq!(process_with_checkpoints(&path, &options(), &checkpoint, 10).unwrap().0, whole);
+        interrupt(&path, &ParseOptions { product_filter: Some("Product 3".to_string()), ..options() }, &checkpoint, 30, env!("CARGO_PKG_VERSION"), false);
+        assert_eq!(process_with_checkpoints(&path, &options(), &checkpoint, 10).unwrap().0, whole);
+    }
+
+    #[test]
+    fn stages_are_not_totalled() {
+        let (path, checkpoint) = input("stages");
+        let staged = ParseOptions { stage_column: Some("Product".to_string()), ..options() };
+        let error = process_with_checkpoints(&path, &staged, &checkpoint, 10).unwrap_err().to_string();
+        assert_eq!(error, "Checkpointed runs do not total funnel stages; the input has a Product column");
+        let unstaged = ParseOptions { stage_column: Some("stage".to_string()), ..options() };
+        assert!(process_with_checkpoints(&path, &unstaged, &checkpoint, 10).is_ok());
+    }
+}
@@ -0,0 +1,178 @@
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use serde_json::Value;
+
+// US states and DC on a tile grid map, one square each at (column, row) from the top left,
+// roughly where they lie: exact borders make the small north-eastern states unreadable.
+const US_STATES: &[(&str, &str, i32, i32)] = &[
+    ("AK", "Alaska", 0, 0),
+    ("ME", "Maine", 11, 0),
+    ("WI", "Wisconsin", 6, 1),
+    ("VT", "Vermont", 10, 1),
+    ("NH", "New Hampshire", 11, 1),
+    ("WA", "Washington", 1, 2),
+    ("ID", "Idaho", 2, 2),
+    ("MT", "Montana", 3, 2),
+    ("ND", "North Dakota", 4, 2),
+    ("MN", "Minnesota", 5, 2),
+    ("IL", "Illinois", 6, 2),
+    ("MI", "Michigan", 7, 2),
+    ("NY", "New York", 9, 2),
+    ("MA", "Massachusetts", 10, 2),
+    ("OR", "Oregon", 1, 3),
+    ("NV", "Nevada", 2, 3),
+    ("WY", "Wyoming", 3, 3),
+    ("SD", "South Dakota", 4, 3),
+    ("IA", "Iowa", 5, 3),
+    ("IN", "Indiana", 6, 3),
+    ("OH", "Ohio", 7, 3),
+    ("PA", "Pennsylvania", 8, 3),
+    ("NJ", "New Jersey", 9, 3),
+    ("CT", "Connecticut", 10, 3),
+    ("RI", "Rhode Island", 11, 3),
+    ("CA", "California", 1, 4),
+    ("UT", "Utah", 2, 4),
+    ("CO", "Colorado", 3, 4),
+    ("NE", "Nebraska", 4, 4),
+    ("MO", "Missouri", 5, 4),
+    ("KY", "Kentucky", 6, 4),
+    ("WV", "West Virginia", 7, 4),
+    ("VA", "Virginia", 8, 4),
+    ("MD", "Maryland", 9, 4),
+    ("DE", "Delaware", 10, 4),
+    ("AZ", "Arizona", 2, 5),
+    ("NM", "New Mexico", 3, 5),
+    ("KS", "Kansas", 4, 5),
+    ("AR", "Arkansas", 5, 5),
+    ("TN", "Tennessee", 6, 5),
+    ("NC", "North Carolina", 7, 5),
+    ("SC", "South Carolina", 8, 5),
+    ("DC", "District of Columbia", 9, 5),
+    ("OK", "Oklahoma", 4, 6),
+    ("LA", "Louisiana", 5, 6),
+    ("MS", "Mississippi", 6, 6),
+    ("AL", "Alabama", 7, 6),
+    ("GA", "Georgia", 8, 6),
+    ("HI", "Hawaii", 0, 7),
+    ("TX", "Texas", 4, 7),
+    ("FL", "Florida", 9, 7),
+];
+
+/// A country, state, or other territory of a region map.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Area {
+    pub name: String,
+    /// Short name drawn on the area, such as a state's postal code
+    pub label: Option<String>,
+    /// Region values naming the area, in lower case
+    pub keys: Vec<String>,
+    /// Outer rings of the area's polygons as (x, y) points, y growing north
+    pub polygons: Vec<Vec<(f64, f64)>>,
+}
+
+/// Territories to color by their sales, for a choropleth map of a region column.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RegionMap {
+    pub areas: Vec<Area>,
+    /// Horizontal scale of the map's units against its vertical ones, below 1 for longitude
+    /// and latitude away from the equator
+    pub aspect: f64,
+}
+
+impl RegionMap {
+    /// The built-in `us-states` tile map, or else the path of a GeoJSON file.
+    pub fn from_arg(arg: &str) -> Result<Self, Box<dyn Error>> {
+        if arg.eq_ignore_ascii_case("us-states") {
+            return Ok(RegionMap::us_states());
+        }
+        let path = Path::new(arg);
+        if !path.exists() {
+            return Err(format!("{} is neither a GeoJSON file nor the built-in map us-states", arg).into());
+        }
+        RegionMap::from_path(path)
+    }
+
+    /// US states and DC as the squares of a tile grid map, named by postal code or full name.
+    pub fn us_states() -> Self {
+        let areas = US_STATES
+            .iter()
+            .map(|(code, name, column, row)| {
+                let (x, y) = (f64::from(*column), -f64::from(*row));
+                Area {
+                    name: name.to_string(),
+                    label: Some(code.to_string()),
+                    keys: vec![code.to_ascii_lowercase(), name.to_ascii_lowercase()],
+                    polygons: vec![vec![(x, y), (x + 0.95, y), (x + 0.95, y - 0.95), (x, y - 0.95)]],
+                }
+            })
+            .collect();
+        RegionMap { areas, aspect: 1.0 }
+    }
+
+    /// Reads a GeoJSON feature collection of polygons and multipolygons in longitude and latitude.
+    /// Each feature is named by its `name` property, or else its first text property, and a region
+    /// matches it by any of its text properties, such as a name or an ISO code. Holes are not cut
+    /// out of the areas.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let path = path.as_ref();
+        let invalid = |reason: String| format!("Invalid GeoJSON file {}: {}", path.display(), reason);
+        let contents = fs::read_to_string(path).map_err(|e| format!("Cannot read map {}: {}", path.display(), e))?;
+        let json: Value = serde_json::from_str(&contents).map_err(|e| invalid(e.to_string()))?;
+        let features = json["features"].as_array().ok_or_else(|| invalid("has no feature collection".to_string()))?;
+        let mut areas = Vec::new();
+        for (i, feature) in features.iter().enumerate() {
+            let texts: Vec<&str> = feature["properties"].as_object().into_iter().flatten().filter_map(|(_, value)| value.as_str()).collect();
+            let Some(name) = feature["properties"]["name"].as_str().or(texts.first().copied()) else {
+                return Err(invalid(format!("feature {} has no name", i + 1)).into());
+            };
+            let geometry = &feature["geometry"];
+            let polygons = match geometry["type"].as_str() {
+                Some("Polygon") => vec![&geometry["coordinates"][0]],
+                Some("MultiPolygon") => geometry["coordinates"].as_array().into_iter().flatten().map(|polygon| &polygon[0]).collect(),
+                // Points and lines have nothing to color.
+                _ => continue,
+            };
+            let polygons = polygons
+                .into_iter()
+                .map(|ring| {
+                    let points = ring.as_array().ok_or_else(|| invalid(format!("{} has a polygon without coordinates", name)))?;
+                    points
+                        .iter()
+                        .map(|point| match (point[0].as_f64(), point[1].as_f64()) {
+                            (Some(x), Some(y)) => Ok((x, y)),
+                            _ => Err(invalid(format!("{} has a point that is not a longitude and latitude", name))),
+                        })
+                        .collect::<Result<Vec<_>, _>>()
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let keys = texts.iter().map(|text| text.to_lowercase()).collect();
+            areas.push(Area { name: name.to_string(), label: None, keys, polygons });
+        }
+        let latitudes = areas.iter().flat_map(|area| &area.polygons).flatten().map(|(_, y)| *y);
+        let (south, north) = latitudes.fold((f64::MAX, f64::MIN), |(south, north), y| (south.min(y), north.max(y)));
+        let aspect = if south <= north { ((south + north) / 2.0).to_radians().cos().max(0.2) } else { 1.0 };
+        Ok(RegionMap { areas, aspect })
+    }
+
+    /// The area a region value names, ignoring case and surrounding spaces.
+    pub fn find(&self, region: &str) -> Option<usize> {
+        let region = region.trim().to_lowercase();
+        self.areas.iter().position(|area| area.keys.contains(&region))
+    }
+
+    /// The sales of each area from `regions`, totals by region value, None for areas without
+    /// any, and the region values that name no area.
+    pub fn shade(&self, regions: &[(String, f64)]) -> (Vec<Option<f64>>, Vec<String>) {
+        let mut sales = vec![None; self.areas.len()];
+        let mut unmatched = Vec::new();
+        for (region, total) in regions {
+            match self.find(region) {
+                Some(i) => *sales[i].get_or_insert(0.0) += total,
+                None => unmatched.push(region.clone()),
+            }
+        }
+        (sales, unmatched)
+    }
+}
@@ -0,0 +1,203 @@
+use std::error::Error;
+use std::mem;
+#[cfg(feature = "parallel")]
+use std::sync::mpsc;
+#[cfg(feature = "parallel")]
+use std::thread;
+
+use csv::ByteRecord;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use tracing::{debug, debug_span};
+
+use crate::date_order::DateOrder;
+use crate::source::{records, SalesSource};
+use crate::{aggregate_chunk, finish, schema, Columns, DuplicateTracker, Ingest, ParseOptions, ParseReport, Partial, SalesTotals, PARSE_CHUNK};
+
+// Chunks read ahead of the ones being totalled, per thread totalling them.
+#[cfg(feature = "parallel")]
+const QUEUED_CHUNKS: usize = 2;
+
+// The rows read but not totalled yet, and the totals of those that were.
+struct Aggregator<'a> {
+    #[cfg(feature = "scripting")]
+    headers: csv::StringRecord,
+    layout: schema::Layout,
+    columns: Columns<'a>,
+    options: &'a ParseOptions,
+    product_filter: Option<String>,
+    ingest: Ingest,
+    pending: Vec<ByteRecord>,
+    totals: Partial<'static>,
+    chunks: usize,
+}
+
+impl Aggregator<'_> {
+    fn push(&mut self, results: Vec<csv::Result<ByteRecord>>) -> Result<(), Box<dyn Error>> {
+        let mut records = Vec::with_capacity(results.len());
+        for result in results {
+            records.extend(self.ingest.take(result, &self.layout, &self.columns, self.options)?);
+        }
+        #[cfg(feature = "scripting")]
+        if let Some(script) = self.options.script.as_ref().filter(|script| script.transforms()) {
+            records = crate::transform_records(script, &self.headers, records, &mut self.ingest.errors);
+        }
+        self.pending.extend(records);
+        self.aggregate(false);
+        Ok(())
+    }
+
+    // Totals the pending rows in chunks of PARSE_CHUNK, a chunk for each thread at a time, or
+    // all of them with `all`. Cutting the chunks at the same rows as `process_sales_data` and
+    // merging them in file order gives the same totals to the last digit.
+    fn aggregate(&mut self, all: bool) {
+        #[cfg(feature = "parallel")]
+        let batch = PARSE_CHUNK * rayon::current_num_threads();
+        #[cfg(not(feature = "parallel"))]
+        let batch = PARSE_CHUNK;
+        let ready = if all { self.pending.len() } else { self.pending.len() / batch * batch };
+        if ready == 0 {
+            return;
+        }
+        let rest = self.pending.split_off(ready);
+        let records = mem::replace(&mut self.pending, rest);
+        let product_filter = self.product_filter.as_deref();
+        #[cfg(feature = "parallel")]
+        let chunks = records.par_chunks(PARSE_CHUNK);
+        #[cfg(not(feature = "parallel"))]
+        let chunks = records.chunks(PARSE_CHUNK);
+        let partials: Vec<_> = chunks.map(|records| aggregate_chunk(&self.layout, &self.columns, self.options, product_filter, 0, records)).collect();
+        self.chunks += partials.len();
+        for partial in partials {
+            // Rows are not kept to flag the suspicious ones among them.
+            let partial = Partial { values: Vec::new(), ..partial }.into_owned();
+            self.totals = mem::take(&mut self.totals).merge(partial);
+        }
+    }
+}
+
+// The rows of `source` in chunks of PARSE_CHUNK as they are read.
+fn read_chunks<S: SalesSource + ?Sized>(source: &mut S) -> impl Iterator<Item = Vec<csv::Result<ByteRecord>>> + '_ {
+    let mut records = records(source);
+    std::iter::from_fn(move || {
+        let chunk: Vec<_> = records.by_ref().take(PARSE_CHUNK).collect();
+        (!chunk.is_empty()).then_some(chunk)
+    })
+}
+
+/// `process_sales_source` totalling rows as they are read instead of after reading them all, so
+/// the rows themselves are not kept. With the `parallel` feature a thread reads chunks of rows
+/// ahead, through a bounded queue, while the rayon threads total them.
+///
+/// Memory grows with the months and products, and with the rows in two cases: the errors of
+/// rows skipped with `ParseOptions::lenient` are all kept for the report, and
+/// `ParseOptions::dedupe` keeps the key of every distinct row to look repeats up in.
+///
+/// Totals are the same as `process_sales_data` gives, with what needs every row at once left
+/// out: suspicious rows are not flagged, currencies cannot be converted, numeric dates like
+/// 03/04/2024 need an explicit day/month order, and duplicate keys are only looked for to
+/// leave them out with `ParseOptions::dedupe`.
+pub fn process_sales_chunked<S: SalesSource + Send + ?Sized>(source: &mut S, options: &ParseOptions) -> Result<(SalesTotals, ParseReport), Box<dyn Error>> {
+    if options.currency.is_some() {
+        return Err("Streamed runs cannot convert currencies, whose rates are only known once every row is read".into());
+    }
+    let headers = source.headers()?;
+    let order = match options.date_order {
+        DateOrder::Auto => None,
+        order => Some(order),
+    };
+    let layout = options.layout(&headers)?.with_date_order(order);
+    let columns = Columns::new(&headers, options)?;
+    let tracker = options.dedupe.then(DuplicateTracker::default);
+    let mut aggregator = Aggregator {
+        #[cfg(feature = "scripting")]
+        headers,
+        layout,
+        columns,
+        options,
+        product_filter: options.product_filter.as_ref().map(|filter| filter.to_lowercase()),
+        ingest: Ingest { rows: 0, duplicates: 0, tracker, errors: Vec::new() },
+        pending: Vec::new(),
+        totals: Partial::default(),
+        chunks: 0,
+    };
+
+    let span = debug_span!("stream").entered();
+    #[cfg(feature = "parallel")]
+    thread::scope(|scope| -> Result<(), Box<dyn Error>> {
+        let (sender, receiver) = mpsc::sync_channel(QUEUED_CHUNKS * rayon::current_num_threads());
+        // Stops once the input is read, or once the receiver is dropped on an error.
+        scope.spawn(move || read_chunks(source).try_for_each(|chunk| sender.send(chunk)));
+        receiver.into_iter().try_for_each(|chunk| aggregator.push(chunk))
+    })?;
+    #[cfg(not(feature = "parallel"))]
+    read_chunks(source).try_for_each(|chunk| aggregator.push(chunk))?;
+    aggregator.aggregate(true);
+    debug!(rows = aggregator.ingest.rows, chunks = aggregator.chunks, "streamed records");
+    drop(span);
+
+    finish(aggregator.totals, aggregator.ingest, Vec::new(), options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{process_sales_data, CubeSpec};
+
+    // Rows in a few batches of chunks and a part of one, with repeats and invalid rows among
+    // them, and amounts whose sums round differently in another order.
+    fn sales() -> String {
+        #[cfg(feature = "parallel")]
+        let batch = PARSE_CHUNK * rayon::current_num_threads();
+        #[cfg(not(feature = "parallel"))]
+        let batch = PARSE_CHUNK;
+        let mut csv = String::from("month,product,region,stage,sales_amount\n");
+        for row in 0..batch * 3 + 517 {
+            if row % 1000 == 999 {
+                csv += "2023-13-01,Widget,North,Lead,1\n";
+            } else if row % 250 == 7 {
+                csv += "2023-01-01,Product 0,North,Lead,0.1\n";
+            } else {
+                csv += &format!("2023-{:02}-{:02},Product {},{},{},{}.{}\n", row % 12 + 1, row % 28 + 1, row % 13, ["North", "South"][row % 2], ["Lead", "Won"][row % 3 % 2], row, row % 10);
+            }
+        }
+        csv
+    }
+
+    fn lenient() -> ParseOptions {
+        ParseOptions { lenient: true, schema: schema::Schema { allow_extra_columns: true, ..schema::Schema::default() }, ..ParseOptions::default() }
+    }
+
+    fn assert_chunked_like_a_reader(options: &ParseOptions) {
+        let csv = sales();
+        let (expected, expected_report) = process_sales_data(&mut csv::Reader::from_reader(csv.as_bytes()), options).unwrap();
+        let (totals, report) = process_sales_chunked(&mut csv::Reader::from_reader(csv.as_bytes()), options).unwrap();
+        assert_eq!(totals, expected);
+        assert_eq!((report.rows, report.missing_amounts), (expected_report.rows, expected_report.missing_amounts));
+        assert_eq!(report.errors.iter().map(|error| error.line).collect::<Vec<_>>(), expected_report.errors.iter().map(|error| error.line).collect::<Vec<_>>());
+        assert_eq!((&report.cube, &report.daily, &report.stages), (&expected_report.cube, &expected_report.daily, &expected_report.stages));
+        assert!(!report.errors.is_empty());
+    }
+
+    #[test]
+    fn chunked_totals_are_those_of_a_reader() {
+        assert_chunked_like_a_reader(&lenient());
+    }
+
+    #[test]
+    fn chunked_breakdowns_and_dedupe_are_those_of_a_reader() {
+        let options = ParseOptions {
+            dedupe: true,
+            cube: Some(CubeSpec { region_column: Some("region".to_string()) }),
+            daily: true,
+            stage_column: Some("stage".to_string()),
+            ..lenient()
+        };
+        assert_chunked_like_a_reader(&options);
+        let csv = sales();
+        let (_, report) = process_sales_chunked(&mut csv::Reader::from_reader(csv.as_bytes()), &options).unwrap();
+        let (_, expected_report) = process_sales_data(&mut csv::Reader::from_reader(csv.as_bytes()), &options).unwrap();
+        assert_eq!(report.duplicates, expected_report.duplicates);
+        assert!(report.duplicates > 0);
+    }
+}
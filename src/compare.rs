@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+
+use chrono::{Datelike, Months, NaiveDate};
+
+use crate::PlotData;
+
+/// A month or product with its total in both datasets.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Delta {
+    pub key: String,
+    pub old: f64,
+    pub new: f64,
+}
+
+impl Delta {
+    pub fn difference(&self) -> f64 {
+        self.new - self.old
+    }
+
+    /// The change in percent, or None from zero.
+    pub fn change(&self) -> Option<f64> {
+        (self.old != 0.0).then(|| (self.new - self.old) / self.old.abs() * 100.0)
+    }
+}
+
+/// Two datasets side by side, such as last quarter's export and this quarter's.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Comparison {
+    /// Names of the old and new dataset, such as their file names
+    pub names: [String; 2],
+    /// First and last month of each, as in `2024-01 – 2024-03`
+    pub ranges: [String; 2],
+    /// Monthly totals of each, on their own months
+    pub monthly: [Vec<(NaiveDate, f64)>; 2],
+    /// Months the new dataset is moved by to chart it over the old one: when they have no month
+    /// in common, its first month goes over the old first month. Zero otherwise.
+    pub offset: i32,
+    /// Months in order, named `2024-01` or, moved, `2024-01 / 2024-04`
+    pub months: Vec<Delta>,
+    /// Every product of either dataset, the best-selling first
+    pub products: Vec<Delta>,
+}
+
+impl Comparison {
+    pub fn new(names: [String; 2], old: &PlotData, new: &PlotData) -> Self {
+        let range = |monthly: &[(NaiveDate, f64)]| match (monthly.first(), monthly.last()) {
+            (Some((first, _)), Some((last, _))) if first != last => format!("{} – {}", first.format("%Y-%m"), last.format("%Y-%m")),
+            (Some((first, _)), _) => first.format("%Y-%m").to_string(),
+            _ => String::new(),
+        };
+        let ranges = [range(&old.0), range(&new.0)];
+        let overlap = old.0.iter().any(|(month, _)| new.0.iter().any(|(other, _)| other == month));
+        let offset = match (old.0.first(), new.0.first()) {
+            (Some((old_first, _)), Some((new_first, _))) if !overlap => months_between(*new_first, *old_first),
+            _ => 0,
+        };
+        let moved = shifted(&new.0, offset);
+
+        let mut months: Vec<(NaiveDate, Delta)> = Vec::new();
+        for (month, sales) in &old.0 {
+            months.push((*month, Delta { key: month.format("%Y-%m").to_string(), old: *sales, new: 0.0 }));
+        }
+        for ((month, sales), (original, _)) in moved.iter().zip(&new.0) {
+            let key = if offset == 0 { month.format("%Y-%m").to_string() } else { format!("{} / {}", month.format("%Y-%m"), original.format("%Y-%m")) };
+            match months.iter_mut().find(|(existing, _)| existing == month) {
+                Some((_, delta)) => {
+                    delta.key = key;
+                    delta.new = *sales;
+                }
+                None => months.push((*month, Delta { key, old: 0.0, new: *sales })),
+            }
+        }
+        months.sort_by_key(|(month, _)| *month);
+
+        let mut products: Vec<Delta> = old.1.iter().map(|(product, sales)| Delta { key: product.clone(), old: *sales, new: 0.0 }).collect();
+        let index: HashMap<String, usize> = products.iter().enumerate().map(|(i, delta)| (delta.key.clone(), i)).collect();
+        for (product, sales) in &new.1 {
+            match index.get(product) {
+                Some(i) => products[*i].new = *sales,
+                None => products.push(Delta { key: product.clone(), old: 0.0, new: *sales }),
+            }
+        }
+        products.sort_by(|a, b| b.old.max(b.new).total_cmp(&a.old.max(a.new)).then_with(|| a.key.cmp(&b.key)));
+
+        Comparison {
+            names,
+            ranges,
+            monthly: [old.0.clone(), new.0.clone()],
+            offset,
+            months: months.into_iter().map(|(_, delta)| delta).collect(),
+            products,
+        }
+    }
+
+    /// The new dataset's monthly totals on the months it is charted over.
+    pub fn charted_new(&self) -> Vec<(NaiveDate, f64)> {
+        shifted(&self.monthly[1], self.offset)
+    }
+
+    /// Total sales of the old and new dataset.
+    pub fn totals(&self) -> Delta {
+        let total = |monthly: &[(NaiveDate, f64)]| monthly.iter().map(|(_, sales)| sales).sum();
+        Delta { key: String::new(), old: total(&self.monthly[0]), new: total(&self.monthly[1]) }
+    }
+}
+
+fn shifted(monthly: &[(NaiveDate, f64)], offset: i32) -> Vec<(NaiveDate, f64)> {
+    let shift = |month: NaiveDate| {
+        if offset >= 0 {
+            month + Months::new(offset as u32)
+        } else {
+            month - Months::new(offset.unsigned_abs())
+        }
+    };
+    monthly.iter().map(|(month, sales)| (shift(*month), *sales)).collect()
+}
+
+fn months_between(from: NaiveDate, to: NaiveDate) -> i32 {
+    (to.year() - from.year()) * 12 + to.month() as i32 - from.month() as i32
+}
@@ -0,0 +1,267 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::number::{DigitGrouping, MoneyFormat};
+use crate::{key_to_date, CubeCell};
+
+// Everything the page needs is inlined: the data as JSON and the charts drawn as SVG by the
+// script, so the file can be mailed or archived and still works offline.
+const TEMPLATE: &str = r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>__TITLE__</title>
+<style>
+  body { font-family: sans-serif; margin: 0; color: #222; background: #f5f6f8; }
+  header { background: #fff; padding: 12px 24px; border-bottom: 1px solid #ddd; }
+  h1 { font-size: 20px; margin: 0 0 8px; }
+  .filters { display: flex; flex-wrap: wrap; gap: 16px; align-items: flex-start; }
+  .filters label { font-size: 13px; color: #555; display: block; margin-bottom: 4px; }
+  .products { max-height: 96px; overflow-y: auto; background: #fafafa; border: 1px solid #ddd; padding: 4px 8px; min-width: 200px; }
+  .products label { color: #222; margin: 0; }
+  button { font-size: 12px; margin-right: 4px; }
+  main { padding: 16px 24px; display: grid; gap: 16px; grid-template-columns: 1fr 1fr; }
+  .kpis { grid-column: 1 / -1; display: flex; gap: 16px; flex-wrap: wrap; }
+  .kpi, .panel { background: #fff; border: 1px solid #ddd; border-radius: 6px; padding: 12px 16px; }
+  .kpi { flex: 1; min-width: 140px; }
+  .kpi .value { font-size: 22px; font-weight: bold; }
+  .kpi .name { font-size: 12px; color: #666; }
+  .panel h2 { font-size: 15px; margin: 0 0 8px; }
+  .wide { grid-column: 1 / -1; }
+  table { border-collapse: collapse; width: 100%; font-size: 14px; }
+  td, th { padding: 4px 8px; border-bottom: 1px solid #eee; text-align: left; }
+  td.number { text-align: right; font-variant-numeric: tabular-nums; }
+  svg text { font-size: 11px; fill: #444; }
+  .empty { color: #888; padding: 24px; }
+</style>
+</head>
+<body>
+<header>
+  <h1>__TITLE__</h1>
+  <div class="filters">
+    <div><label for="from">From</label><select id="from"></select></div>
+    <div><label for="to">To</label><select id="to"></select></div>
+    <div id="region-filter"><label for="region">Region</label><select id="region"></select></div>
+    <div>
+      <label>Products <button id="all">All</button><button id="none">None</button></label>
+      <div class="products" id="products"></div>
+    </div>
+  </div>
+</header>
+<main>
+  <div class="kpis" id="kpis"></div>
+  <div class="panel wide"><h2>Monthly Sales Trend</h2><div id="trend"></div></div>
+  <div class="panel" id="product-panel"><h2>Sales by Product</h2><div id="bars"></div></div>
+  <div class="panel" id="region-panel"><h2>Sales by Region</h2><div id="regions"></div></div>
+</main>
+<script>
+const DATA = __DATA__;
+const $ = (id) => document.getElementById(id);
+// Same rules as MoneyFormat::number on the Rust side.
+const number = (value, decimals) => {
+  const [whole, fraction] = Math.abs(value).toFixed(decimals).split(".");
+  const groups = DATA.money.indian ? /\B(?=(\d{2})*\d{3}$)/g : /\B(?=(\d{3})+(?!\d))/g;
+  const digits = DATA.money.grouping ? whole.replace(groups, DATA.money.grouping) : whole;
+  const sign = value < 0 && /[1-9]/.test(whole + fraction) ? "-" : "";
+  return sign + digits + (fraction === undefined ? "" : DATA.money.decimal + fraction);
+};
+const money = (value) => {
+  const text = number(value, 2);
+  const digits = text.replace(/^-/, "");
+  return (text === digits ? "" : "-") + DATA.money.prefix + digits + DATA.money.suffix;
+};
+const escape = (text) => text.replace(/[&<>"]/g, (c) => ({ "&": "&amp;", "<": "&lt;", ">": "&gt;", '"': "&quot;" }[c]));
+const hasRegions = DATA.regions.length > 1 || (DATA.regions.length === 1 && DATA.regions[0] !== "");
+
+function option(value, text) {
+  const element = document.createElement("option");
+  element.value = value;
+  element.textContent = text;
+  return element;
+}
+
+DATA.months.forEach((month, i) => {
+  $("from").appendChild(option(i, month));
+  $("to").appendChild(option(i, month));
+});
+$("to").value = DATA.months.length - 1;
+$("region").appendChild(option(-1, "All regions"));
+DATA.regions.forEach((region, i) => $("region").appendChild(option(i, region || "(none)")));
+if (!hasRegions) {
+  $("region-filter").style.display = "none";
+  $("region-panel").style.display = "none";
+  $("product-panel").classList.add("wide");
+}
+DATA.products.forEach((product, i) => {
+  const label = document.createElement("label");
+  label.innerHTML = '<input type="checkbox" checked data-product="' + i + '"> ' + escape(product);
+  $("products").appendChild(label);
+});
+
+function filters() {
+  let from = Number($("from").value), to = Number($("to").value);
+  if (from > to) [from, to] = [to, from];
+  const products = new Set();
+  document.querySelectorAll("[data-product]").forEach((box) => box.checked && products.add(Number(box.dataset.product)));
+  return { from, to, region: Number($("region").value), products };
+}
+
+function slice({ from, to, region, products }) {
+  const monthly = new Array(to - from + 1).fill(0);
+  const byProduct = new Map(), byRegion = new Map();
+  for (const [month, product, cellRegion, sales] of DATA.cells) {
+    if (month < from || month > to || !products.has(product) || (region >= 0 && cellRegion !== region)) continue;
+    monthly[month - from] += sales;
+    byProduct.set(product, (byProduct.get(product) || 0) + sales);
+    byRegion.set(cellRegion, (byRegion.get(cellRegion) || 0) + sales);
+  }
+  const sorted = (map, names) => [...map].map(([i, sales]) => [names[i], sales]).sort((a, b) => b[1] - a[1] || a[0].localeCompare(b[0]));
+  return { monthly, products: sorted(byProduct, DATA.products), regions: sorted(byRegion, DATA.regions) };
+}
+
+function kpis({ from }, { monthly, products }) {
+  const total = monthly.reduce((sum, sales) => sum + sales, 0);
+  const best = monthly.reduce((best, sales, i) => (sales > monthly[best] ? i : best), 0);
+  const cards = [
+    ["Total sales", money(total)],
+    ["Average per month", money(total / monthly.length)],
+    ["Best month", DATA.months[from + best] + " · " + money(monthly[best])],
+    ["Products with sales", String(products.length)],
+  ];
+  $("kpis").innerHTML = cards
+    .map(([name, value]) => '<div class="kpi"><div class="value">' + escape(value) + '</div><div class="name">' + name + "</div></div>")
+    .join("");
+}
+
+function trend({ from }, monthly) {
+  const width = 1000, height = 280, left = 90, bottom = 30, top = 10;
+  const max = Math.max(...monthly, 0) || 1;
+  const x = (i) => left + (monthly.length > 1 ? (i * (width - left - 10)) / (monthly.length - 1) : (width - left) / 2);
+  const y = (sales) => top + (1 - Math.max(sales, 0) / max) * (height - top - bottom);
+  let svg = '<svg viewBox="0 0 ' + width + " " + height + '" width="100%">';
+  for (let step = 0; step <= 4; step++) {
+    const value = (max * step) / 4;
+    svg += '<line x1="' + left + '" x2="' + (width - 10) + '" y1="' + y(value) + '" y2="' + y(value) + '" stroke="#eee"/>';
+    svg += '<text x="' + (left - 6) + '" y="' + (y(value) + 4) + '" text-anchor="end">' + Math.round(value).toLocaleString("en-US") + "</text>";
+  }
+  const every = Math.ceil(monthly.length / 12);
+  monthly.forEach((_, i) => {
+    if (i % every === 0) svg += '<text x="' + x(i) + '" y="' + (height - 8) + '" text-anchor="middle">' + DATA.months[from + i] + "</text>";
+  });
+  svg += '<polyline fill="none" stroke="red" stroke-width="2" points="' + monthly.map((sales, i) => x(i) + "," + y(sales)).join(" ") + '"/>';
+  monthly.forEach((sales, i) => {
+    svg += '<circle cx="' + x(i) + '" cy="' + y(sales) + '" r="3" fill="red"><title>' + DATA.months[from + i] + ": " + money(sales) + "</title></circle>";
+  });
+  $("trend").innerHTML = svg + "</svg>";
+}
+
+function bars(element, rows) {
+  if (rows.length === 0) {
+    element.innerHTML = '<div class="empty">No sales match the filters</div>';
+    return;
+  }
+  const max = Math.max(...rows.map(([, sales]) => sales), 0) || 1;
+  const total = rows.reduce((sum, [, sales]) => sum + sales, 0);
+  element.innerHTML =
+    "<table>" +
+    rows
+      .map(([name, sales]) => {
+        const width = Math.max(0, (sales / max) * 100);
+        const bar = '<div style="background:steelblue;height:12px;width:' + width + '%"></div>';
+        const share = total ? number((sales / total) * 100, 1) + "%" : "";
+        return "<tr><td>" + escape(name || "(none)") + '</td><td style="width:40%">' + bar + '</td><td class="number">' + money(sales) + '</td><td class="number">' + share + "</td></tr>";
+      })
+      .join("") +
+    "</table>";
+}
+
+function render() {
+  const current = filters();
+  const sliced = slice(current);
+  kpis(current, sliced);
+  trend(current, sliced.monthly);
+  bars($("bars"), sliced.products);
+  if (hasRegions) bars($("regions"), sliced.regions);
+}
+
+document.querySelectorAll("select, input").forEach((element) => element.addEventListener("change", render));
+$("all").onclick = () => { document.querySelectorAll("[data-product]").forEach((box) => (box.checked = true)); render(); };
+$("none").onclick = () => { document.querySelectorAll("[data-product]").forEach((box) => (box.checked = false)); render(); };
+render();
+</script>
+</body>
+</html>
+"##;
+
+// Cells refer to months, products, and regions by index to keep the embedded JSON small.
+#[derive(Serialize)]
+struct Embedded<'a> {
+    months: Vec<String>,
+    products: Vec<&'a str>,
+    regions: Vec<&'a str>,
+    cells: Vec<(usize, usize, usize, f64)>,
+    money: Money,
+}
+
+#[derive(Serialize)]
+struct Money {
+    prefix: String,
+    suffix: String,
+    decimal: char,
+    grouping: Option<char>,
+    /// Lakh and crore grouping
+    indian: bool,
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Writes a single-page dashboard whose date range, product, and region filters re-slice
+/// `cube` in the browser. Cells for the same month, product, and region are summed; amounts
+/// are shown in `money`.
+pub fn write_dashboard<P: AsRef<Path>>(path: P, title: &str, cube: &[CubeCell], money: &MoneyFormat) -> Result<(), Box<dyn Error>> {
+    if cube.is_empty() {
+        return Err("The data has no sales rows".into());
+    }
+    let months: Vec<_> = cube.iter().map(|cell| cell.month).collect::<BTreeSet<_>>().into_iter().collect();
+    let regions: Vec<&str> = cube.iter().map(|cell| cell.region.as_str()).collect::<BTreeSet<_>>().into_iter().collect();
+    // Best-selling first, matching the order of the bar chart.
+    let mut totals: HashMap<&str, f64> = HashMap::new();
+    for cell in cube {
+        *totals.entry(cell.product.as_str()).or_insert(0.0) += cell.sales;
+    }
+    let mut products: Vec<(&str, f64)> = totals.into_iter().collect();
+    products.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+
+    let month_index: HashMap<_, _> = months.iter().enumerate().map(|(i, month)| (*month, i)).collect();
+    let product_index: HashMap<_, _> = products.iter().enumerate().map(|(i, (product, _))| (*product, i)).collect();
+    let region_index: HashMap<_, _> = regions.iter().enumerate().map(|(i, region)| (*region, i)).collect();
+    let mut cells: BTreeMap<(usize, usize, usize), f64> = BTreeMap::new();
+    for cell in cube {
+        let key = (month_index[&cell.month], product_index[cell.product.as_str()], region_index[cell.region.as_str()]);
+        *cells.entry(key).or_insert(0.0) += cell.sales;
+    }
+
+    let embedded = Embedded {
+        months: months.iter().map(|month| key_to_date(*month).format("%Y-%m").to_string()).collect(),
+        products: products.iter().map(|(product, _)| *product).collect(),
+        regions,
+        cells: cells.into_iter().map(|((month, product, region), sales)| (month, product, region, sales)).collect(),
+        money: {
+            let (prefix, suffix) = money.affixes();
+            let indian = money.digit_grouping == DigitGrouping::Indian;
+            Money { prefix, suffix, decimal: money.decimal, grouping: money.grouping, indian }
+        },
+    };
+    // `</` would end the script element early if a product name contained `</script>`.
+    let data = serde_json::to_string(&embedded)?.replace("</", "<\\/");
+    let html = TEMPLATE.replace("__TITLE__", &escape_html(title)).replace("__DATA__", &data);
+    fs::write(path, html)?;
+    Ok(())
+}
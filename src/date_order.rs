@@ -0,0 +1,123 @@
+use std::error::Error;
+
+use chrono::NaiveDate;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DateOrder {
+    #[default]
+    Auto,
+    /// 03/04/2023 is 3 April
+    Dmy,
+    /// 03/04/2023 is 4 March
+    Mdy,
+}
+
+// Splits `NN/NN/YYYY` (also `.` or `-` separated) into its two leading numbers and the year.
+fn split_numeric_date(field: &[u8]) -> Option<(u32, u32, i32)> {
+    let text = std::str::from_utf8(field).ok()?.trim();
+    let separator = text.chars().find(|c| matches!(c, '/' | '.' | '-'))?;
+    let mut parts = text.split(separator);
+    let (first, second, year) = (parts.next()?, parts.next()?, parts.next()?);
+    if parts.next().is_some() || first.len() > 2 || second.len() > 2 || year.len() != 4 {
+        return None;
+    }
+    Some((first.parse().ok()?, second.parse().ok()?, year.parse().ok()?))
+}
+
+pub fn parse_numeric_date(field: &[u8], order: DateOrder) -> Option<NaiveDate> {
+    let (first, second, year) = split_numeric_date(field)?;
+    let (day, month) = match order {
+        DateOrder::Dmy => (first, second),
+        DateOrder::Mdy => (second, first),
+        DateOrder::Auto => return None,
+    };
+    NaiveDate::from_ymd_opt(year, month, day)
+}
+
+/// Settles `Auto` by looking at every `NN/NN/YYYY` value in the column: whichever position
+/// ever holds a number above 12 must be the day.
+pub fn resolve_date_order<'a>(
+    order: DateOrder,
+    fields: impl Iterator<Item = &'a [u8]>,
+) -> Result<Option<DateOrder>, Box<dyn Error>> {
+    if order != DateOrder::Auto {
+        return Ok(Some(order));
+    }
+
+    let (mut numeric, mut day_first, mut month_first) = (0u64, None, None);
+    for field in fields {
+        let Some((first, second, _)) = split_numeric_date(field) else {
+            continue;
+        };
+        numeric += 1;
+        if first > 12 && day_first.is_none() {
+            day_first = Some(String::from_utf8_lossy(field).into_owned());
+        }
+        if second > 12 && month_first.is_none() {
+            month_first = Some(String::from_utf8_lossy(field).into_owned());
+        }
+    }
+
+    match (day_first, month_first) {
+        _ if numeric == 0 => Ok(None),
+        (Some(dmy), Some(mdy)) => Err(format!(
+            "Inconsistent date order: \"{}\" is day-first but \"{}\" is month-first",
+            dmy, mdy
+        )
+        .into()),
+        (Some(_), None) => Ok(Some(DateOrder::Dmy)),
+        (None, Some(_)) => Ok(Some(DateOrder::Mdy)),
+        (None, None) => Err(format!(
+            "Ambiguous date order: none of the {} dates like 03/04/2023 has a day above 12, \
+             pass --date-order dmy or --date-order mdy",
+            numeric
+        )
+        .into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve(order: DateOrder, fields: &[&str]) -> Result<Option<DateOrder>, Box<dyn Error>> {
+        resolve_date_order(order, fields.iter().map(|field| field.as_bytes()))
+    }
+
+    #[test]
+    fn parses_numeric_dates_in_either_order() {
+        assert_eq!(parse_numeric_date(b"03/04/2023", DateOrder::Dmy), NaiveDate::from_ymd_opt(2023, 4, 3));
+        assert_eq!(parse_numeric_date(b"03/04/2023", DateOrder::Mdy), NaiveDate::from_ymd_opt(2023, 3, 4));
+        assert_eq!(parse_numeric_date(b" 3.4.2023 ", DateOrder::Dmy), NaiveDate::from_ymd_opt(2023, 4, 3));
+        assert_eq!(parse_numeric_date(b"03/04/2023", DateOrder::Auto), None);
+        assert_eq!(parse_numeric_date(b"31/04/2023", DateOrder::Dmy), None);
+        assert_eq!(parse_numeric_date(b"2023-04-03", DateOrder::Dmy), None);
+        assert_eq!(parse_numeric_date(b"03/04/23", DateOrder::Dmy), None);
+    }
+
+    #[test]
+    fn a_day_above_12_settles_the_order() {
+        assert_eq!(resolve(DateOrder::Auto, &["03/04/2023", "13/04/2023"]).unwrap(), Some(DateOrder::Dmy));
+        assert_eq!(resolve(DateOrder::Auto, &["04/03/2023", "04/13/2023"]).unwrap(), Some(DateOrder::Mdy));
+        assert_eq!(resolve(DateOrder::Auto, &["12/12/2023", "2023-01", "31/12/2023"]).unwrap(), Some(DateOrder::Dmy));
+    }
+
+    #[test]
+    fn columns_without_numeric_dates_need_no_order() {
+        assert_eq!(resolve(DateOrder::Auto, &["2023-01", "2023-02-15"]).unwrap(), None);
+        assert_eq!(resolve(DateOrder::Auto, &[]).unwrap(), None);
+    }
+
+    #[test]
+    fn an_explicit_order_is_kept() {
+        assert_eq!(resolve(DateOrder::Mdy, &["13/04/2023"]).unwrap(), Some(DateOrder::Mdy));
+    }
+
+    #[test]
+    fn ambiguous_and_inconsistent_orders_are_errors() {
+        let ambiguous = resolve(DateOrder::Auto, &["03/04/2023", "12/01/2023"]).unwrap_err().to_string();
+        assert!(ambiguous.starts_with("Ambiguous date order: none of the 2 dates"), "{}", ambiguous);
+        let inconsistent = resolve(DateOrder::Auto, &["13/04/2023", "04/13/2023"]).unwrap_err().to_string();
+        assert_eq!(inconsistent, "Inconsistent date order: \"13/04/2023\" is day-first but \"04/13/2023\" is month-first");
+    }
+}
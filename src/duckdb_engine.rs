@@ -0,0 +1,49 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::File;
+
+use chrono::NaiveDate;
+use csv::ReaderBuilder;
+use duckdb::Connection;
+
+use crate::{date_to_key, validate_csv_structure, SalesTotals};
+
+pub fn process_sales_data(
+    path: &str,
+    query: Option<&str>,
+) -> Result<SalesTotals, Box<dyn Error>> {
+    let mut rdr = ReaderBuilder::new().has_headers(true).from_reader(File::open(path)?);
+    validate_csv_structure(rdr.headers()?)?;
+
+    let filter = query.map(|q| format!("WHERE {}", q)).unwrap_or_default();
+    let sql = format!(
+        "SELECT month, product, SUM(CAST(sales_amount AS DOUBLE)) \
+         FROM read_csv('{}', header = true, all_varchar = true) \
+         {} \
+         GROUP BY month, product",
+        path.replace('\'', "''"),
+        filter,
+    );
+
+    let conn = Connection::open_in_memory()?;
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map([], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, f64>(2)?))
+    })?;
+
+    let mut sales_by_month = HashMap::new();
+    let mut sales_by_product = HashMap::new();
+    for row in rows {
+        let (date_str, product, sales) = row?;
+        let month = NaiveDate::parse_from_str(&format!("{}-01", date_str), "%Y-%m-%d")
+            .map_err(|e| format!("Invalid date format in \"{}\": {}", date_str, e))?;
+        if !sales.is_finite() {
+            return Err(format!("The sales of {} in {} add up to {}, not a finite number", product, date_str, sales).into());
+        }
+
+        *sales_by_month.entry(date_to_key(&month)).or_insert(0.0) += sales;
+        *sales_by_product.entry(product).or_insert(0.0) += sales;
+    }
+
+    Ok((sales_by_month, sales_by_product))
+}
@@ -0,0 +1,102 @@
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use lettre::message::header::ContentType;
+use lettre::message::{Attachment, Mailbox, MultiPart, SinglePart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+use serde::Deserialize;
+
+use crate::charts::ChartLabels;
+use crate::report::{charts, html_report, image_type};
+use crate::summary::RunSummary;
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Security {
+    /// TLS from the first byte, usually port 465
+    Tls,
+    /// Plain connection upgraded with STARTTLS, usually port 587
+    #[default]
+    Starttls,
+    /// No encryption, only for local relays
+    None,
+}
+
+/// SMTP server and recipients, read from a TOML file. The password is taken from the
+/// environment variable named by `password_env` so it never has to live in the file.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EmailConfig {
+    pub host: String,
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub security: Security,
+    pub username: Option<String>,
+    pub password_env: Option<String>,
+    pub from: String,
+    pub to: Vec<String>,
+    #[serde(default)]
+    pub cc: Vec<String>,
+    #[serde(default = "default_subject")]
+    pub subject: String,
+}
+
+fn default_subject() -> String {
+    "Sales report".to_string()
+}
+
+impl EmailConfig {
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let contents = fs::read_to_string(path.as_ref())?;
+        let config: EmailConfig = toml::from_str(&contents)
+            .map_err(|e| format!("Invalid email config {}: {}", path.as_ref().display(), e))?;
+        if config.to.is_empty() {
+            return Err(format!("Invalid email config {}: no recipients in `to`", path.as_ref().display()).into());
+        }
+        Ok(config)
+    }
+
+    fn transport(&self) -> Result<SmtpTransport, Box<dyn Error>> {
+        let mut builder = match self.security {
+            Security::Tls => SmtpTransport::relay(&self.host)?,
+            Security::Starttls => SmtpTransport::starttls_relay(&self.host)?,
+            Security::None => SmtpTransport::builder_dangerous(&self.host),
+        };
+        if let Some(port) = self.port {
+            builder = builder.port(port);
+        }
+        if let Some(username) = &self.username {
+            let password = match &self.password_env {
+                Some(name) => std::env::var(name).map_err(|_| format!("Email password variable {} is not set", name))?,
+                None => String::new(),
+            };
+            builder = builder.credentials(Credentials::new(username.clone(), password));
+        }
+        Ok(builder.build())
+    }
+}
+
+fn content_id(path: &Path) -> String {
+    path.file_stem().unwrap_or_default().to_string_lossy().into_owned()
+}
+
+pub fn send_report(config: &EmailConfig, summary: &RunSummary, labels: &ChartLabels) -> Result<(), Box<dyn Error>> {
+    let mut builder = Message::builder().from(config.from.parse::<Mailbox>()?).subject(&config.subject);
+    for to in &config.to {
+        builder = builder.to(to.parse()?);
+    }
+    for cc in &config.cc {
+        builder = builder.cc(cc.parse()?);
+    }
+
+    let html = html_report(summary, labels, |chart| Ok(format!("cid:{}", content_id(chart))))?;
+    let mut body = MultiPart::related().singlepart(SinglePart::html(html));
+    for chart in charts(summary) {
+        body = body.singlepart(Attachment::new_inline(content_id(chart)).body(fs::read(chart)?, ContentType::parse(image_type(chart))?));
+    }
+
+    config.transport()?.send(&builder.multipart(body)?)?;
+    Ok(())
+}
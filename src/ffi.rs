@@ -0,0 +1,91 @@
+use std::cell::RefCell;
+use std::error::Error;
+use std::ffi::{c_char, CStr, CString};
+use std::fs::File;
+use std::panic;
+use std::path::Path;
+use std::ptr;
+
+use crate::charts::ChartRenderer;
+use crate::{ParseOptions, SalesReport};
+
+// C entry points for services embedding the library (P/Invoke from C#, JNA or the foreign
+// function API from Java). Strings cross the boundary as NUL-terminated UTF-8; include/sales_chart.h
+// is generated from this file with `cbindgen --config cbindgen.toml --output include/sales_chart.h`.
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: String) {
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+unsafe fn path_arg<'a>(value: *const c_char, name: &str) -> Result<&'a Path, Box<dyn Error>> {
+    if value.is_null() {
+        return Err(format!("{} is NULL", name).into());
+    }
+    let value = CStr::from_ptr(value).to_str().map_err(|_| format!("{} is not valid UTF-8", name))?;
+    Ok(Path::new(value))
+}
+
+fn process(csv_path: &Path, output_dir: Option<&Path>, lenient: bool) -> Result<String, Box<dyn Error>> {
+    let file = File::open(csv_path).map_err(|e| format!("Cannot open {}: {}", csv_path.display(), e))?;
+    let report = SalesReport::from_csv_with(file, &ParseOptions { lenient, ..ParseOptions::default() })?;
+    if let Some(output_dir) = output_dir {
+        if report.dataset.monthly.is_empty() {
+            return Err("The data has no sales rows".into());
+        }
+        ChartRenderer::new(output_dir).render(&report.dataset)?;
+    }
+    Ok(serde_json::to_string(&report.dataset.aggregates())?)
+}
+
+/// Parses the sales CSV at `csv_path` and, unless `output_dir` is NULL, writes
+/// `line_chart.png`, `bar_chart.png`, and `pie_chart.png` into it. Returns the totals as JSON
+/// (`{"monthly": [{"month", "sales"}], "products": [{"product", "sales"}]}`), to be released with
+/// `sales_chart_free_string`, or NULL on failure; `sales_chart_last_error` then says why.
+/// Malformed rows fail the call unless `lenient` is set, in which case they are skipped.
+///
+/// # Safety
+///
+/// `csv_path` must point to a NUL-terminated string, and so must `output_dir` when not NULL.
+#[no_mangle]
+pub unsafe extern "C" fn sales_chart_process(csv_path: *const c_char, output_dir: *const c_char, lenient: bool) -> *mut c_char {
+    let result = panic::catch_unwind(|| {
+        let csv_path = path_arg(csv_path, "csv_path")?;
+        let output_dir = if output_dir.is_null() { None } else { Some(path_arg(output_dir, "output_dir")?) };
+        process(csv_path, output_dir, lenient)
+    });
+    match result {
+        Ok(Ok(json)) => CString::new(json).map_or(ptr::null_mut(), CString::into_raw),
+        Ok(Err(e)) => {
+            set_last_error(e.to_string());
+            ptr::null_mut()
+        }
+        Err(_) => {
+            set_last_error("sales_chart panicked while processing the file".to_string());
+            ptr::null_mut()
+        }
+    }
+}
+
+/// The message of the last failed call on this thread, or NULL if none has failed. The
+/// string belongs to the library and stays valid until the next failing call on this thread.
+#[no_mangle]
+pub extern "C" fn sales_chart_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |message| message.as_ptr()))
+}
+
+/// Releases a string returned by `sales_chart_process`. NULL is ignored.
+///
+/// # Safety
+///
+/// `value` must be NULL or a pointer returned by this library that has not been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn sales_chart_free_string(value: *mut c_char) {
+    if !value.is_null() {
+        drop(CString::from_raw(value));
+    }
+}
@@ -0,0 +1,173 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Mutex;
+
+use font_kit::family_name::FamilyName;
+use font_kit::properties::Properties;
+use font_kit::source::SystemSource;
+use tracing::{debug, warn};
+use unicode_segmentation::UnicodeSegmentation;
+
+// Tried in order when the chart font lacks glyphs, mostly for Chinese, Japanese, and Korean
+// product names: the usual CJK fonts of Linux distributions, macOS, and Windows, then fonts
+// with emoji as well as Latin letters. Colour emoji fonts are left out: they have no letters
+// for the rest of a label, and their glyphs are bitmaps plotters cannot draw.
+const FALLBACK_FAMILIES: &[&str] = &[
+    "Noto Sans CJK JP",
+    "Noto Sans CJK SC",
+    "Noto Sans JP",
+    "Noto Sans SC",
+    "Source Han Sans",
+    "WenQuanYi Zen Hei",
+    "WenQuanYi Micro Hei",
+    "Droid Sans Fallback",
+    "Hiragino Sans",
+    "Hiragino Kaku Gothic ProN",
+    "PingFang SC",
+    "Apple SD Gothic Neo",
+    "Yu Gothic",
+    "Meiryo",
+    "Microsoft YaHei",
+    "Malgun Gothic",
+    "MS Gothic",
+    "Arial Unicode MS",
+    "Symbola",
+    "Segoe UI Symbol",
+    "Segoe UI Emoji",
+    "DejaVu Sans",
+];
+
+// Fonts are looked up once per requested family and set of characters.
+static RESOLVED: Mutex<BTreeMap<(String, BTreeSet<char>), String>> = Mutex::new(BTreeMap::new());
+
+// Which characters each family has glyphs for, as far as asked; None for families not installed.
+static GLYPHS: Mutex<BTreeMap<String, Option<BTreeMap<char, bool>>>> = Mutex::new(BTreeMap::new());
+
+// Missing characters already warned about, as each chart asks about its own labels.
+static WARNED: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
+// Characters that only change how their neighbours look: joiners, variation selectors such as
+// the one asking for emoji presentation, and the tags of subdivision flags. Fonts seldom have
+// glyphs for them and plotters would draw each as a box.
+fn is_invisible(c: char) -> bool {
+    matches!(c, '\u{200C}' | '\u{200D}' | '\u{FE00}'..='\u{FE0F}' | '\u{E0020}'..='\u{E007F}' | '\u{E0100}'..='\u{E01EF}')
+}
+
+fn family_name(family: &str) -> FamilyName {
+    match family {
+        "sans-serif" => FamilyName::SansSerif,
+        "serif" => FamilyName::Serif,
+        "monospace" => FamilyName::Monospace,
+        name => FamilyName::Title(name.to_string()),
+    }
+}
+
+/// Whether a font of `family`, a name or one of `sans-serif`, `serif`, and `monospace`, is installed.
+pub fn is_installed(family: &str) -> bool {
+    SystemSource::new().select_best_match(&[family_name(family)], &Properties::new()).is_ok()
+}
+
+// The characters the installed font of `family` has no glyph for, or None without such a font.
+fn missing_glyphs(family: &str, chars: &[char]) -> Option<Vec<char>> {
+    let mut glyphs = GLYPHS.lock().unwrap_or_else(|e| e.into_inner());
+    let entry = glyphs.entry(family.to_string()).or_insert_with(|| Some(BTreeMap::new()));
+    let known = entry.as_mut()?;
+    let unknown: Vec<char> = chars.iter().copied().filter(|c| !known.contains_key(c)).collect();
+    if !unknown.is_empty() {
+        let font = SystemSource::new().select_best_match(&[family_name(family)], &Properties::new()).ok().and_then(|font| font.load().ok());
+        let Some(font) = font else {
+            *entry = None;
+            return None;
+        };
+        for c in unknown {
+            known.insert(c, font.glyph_for_char(c).is_some());
+        }
+    }
+    Some(chars.iter().copied().filter(|c| !known[c]).collect())
+}
+
+/// The font family to draw `texts` in: `requested` if its font has a glyph for every
+/// character, otherwise the first installed CJK- or emoji-capable font that does, so product
+/// names show up instead of boxes, or failing that the one lacking the fewest. Text in plain
+/// ASCII is taken to be covered.
+pub fn font_for<'a>(requested: &str, texts: impl IntoIterator<Item = &'a str>) -> String {
+    let texts: Vec<&str> = texts.into_iter().collect();
+    if texts.iter().all(|text| text.is_ascii()) {
+        return requested.to_string();
+    }
+    // Letters count too, as an emoji font may have none.
+    let mut chars: Vec<char> = Vec::new();
+    for c in texts.iter().flat_map(|text| text.chars()).filter(|c| !c.is_whitespace() && !is_invisible(*c)) {
+        if !chars.contains(&c) {
+            chars.push(c);
+        }
+    }
+    let key = (requested.to_string(), chars.iter().copied().collect());
+    let mut resolved = RESOLVED.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(family) = resolved.get(&key) {
+        return family.clone();
+    }
+    let family = pick(requested, &chars);
+    resolved.insert(key, family.clone());
+    family
+}
+
+// Without a font for everything, the one missing the fewest characters, `requested` on a tie.
+fn pick(requested: &str, chars: &[char]) -> String {
+    let mut best = (requested, missing_glyphs(requested, chars).unwrap_or_else(|| chars.to_vec()));
+    if best.1.is_empty() {
+        return requested.to_string();
+    }
+    for family in FALLBACK_FAMILIES {
+        match missing_glyphs(family, chars) {
+            Some(missing) if missing.is_empty() => {
+                debug!("Drawing chart text in {}, as {} has no glyphs for some of it", family, requested);
+                return family.to_string();
+            }
+            Some(missing) if missing.len() < best.1.len() => best = (family, missing),
+            _ => {}
+        }
+    }
+    let (family, missing) = best;
+    let shown: String = missing.iter().take(10).collect();
+    if WARNED.lock().unwrap_or_else(|e| e.into_inner()).insert(shown.clone()) {
+        warn!(
+            "No installed font can draw {} in the chart labels, which are shown without them; install a CJK or emoji font such as Noto Sans CJK or Symbola, or pick one with --font",
+            shown
+        );
+    }
+    family.to_string()
+}
+
+/// `text` without what the font of `family` cannot draw, so a label reads `Pizza Large` rather
+/// than showing a box when no installed font has its 🍕. A grapheme goes as a whole when its
+/// first character has no glyph, so no skin-tone modifier or accent is left behind on its own;
+/// joiners and variation selectors, which have nothing to draw, always go.
+pub fn drawable<'a>(family: &str, text: &'a str) -> Cow<'a, str> {
+    if text.is_ascii() {
+        return Cow::Borrowed(text);
+    }
+    let chars: Vec<char> = text.chars().filter(|c| !c.is_whitespace() && !is_invisible(*c)).collect();
+    let missing: BTreeSet<char> = missing_glyphs(family, &chars).unwrap_or_default().into_iter().collect();
+    if missing.is_empty() && !text.chars().any(is_invisible) {
+        return Cow::Borrowed(text);
+    }
+    let mut kept = String::with_capacity(text.len());
+    let mut dropped = false;
+    for grapheme in text.graphemes(true) {
+        let mut chars = grapheme.chars().filter(|c| !is_invisible(*c));
+        match chars.next() {
+            Some(first) if !missing.contains(&first) => {
+                kept.push(first);
+                kept.extend(chars.filter(|c| !missing.contains(c)));
+            }
+            Some(_) => dropped = true,
+            None => {}
+        }
+    }
+    // Squeeze the spaces around what went, as in `Pizza 🍕 Large`.
+    if dropped {
+        kept = kept.split_whitespace().collect::<Vec<_>>().join(" ");
+    }
+    Cow::Owned(kept)
+}
@@ -0,0 +1,217 @@
+#[cfg(feature = "fx")]
+use std::collections::BTreeMap;
+use std::collections::{BTreeSet, HashMap};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+use csv::StringRecord;
+
+use crate::source::{records, SalesSource};
+use crate::{parse_month, DateKey};
+
+/// Value of one unit of a currency in the base currency, per month or for every month.
+#[derive(Clone, Debug, Default)]
+pub struct ExchangeRates {
+    base: String,
+    monthly: HashMap<String, HashMap<DateKey, f64>>,
+    fixed: HashMap<String, f64>,
+}
+
+impl ExchangeRates {
+    pub fn new(base: &str) -> Self {
+        ExchangeRates { base: base.to_ascii_uppercase(), ..ExchangeRates::default() }
+    }
+
+    /// Reads a `currency,rate[,month]` CSV file. Rows without a month apply to every month
+    /// that has no rate of its own.
+    pub fn from_path<P: AsRef<Path>>(path: P, base: &str) -> Result<Self, Box<dyn Error>> {
+        let path = path.as_ref();
+        let mut rdr = csv::ReaderBuilder::new().trim(csv::Trim::All).flexible(true).from_path(path)?;
+        let mut rates = ExchangeRates::new(base);
+        for (i, record) in rdr.records().enumerate() {
+            let record = record?;
+            let invalid = |reason: String| format!("Invalid exchange rate file {}: row {} {}", path.display(), i + 2, reason);
+            let currency = record.get(0).filter(|c| !c.is_empty()).ok_or_else(|| invalid("has no currency".to_string()))?;
+            let rate = record
+                .get(1)
+                .and_then(|rate| rate.parse::<f64>().ok())
+                .filter(|rate| rate.is_finite() && *rate > 0.0)
+                .ok_or_else(|| invalid(format!("needs a positive rate for {}", currency)))?;
+            match record.get(2).filter(|month| !month.is_empty()) {
+                Some(month) => rates.insert(currency, parse_month(month.as_bytes()).map_err(&invalid)?, rate),
+                None => {
+                    rates.fixed.insert(currency.to_ascii_uppercase(), rate);
+                }
+            }
+        }
+        Ok(rates)
+    }
+
+    pub fn base(&self) -> &str {
+        &self.base
+    }
+
+    pub fn insert(&mut self, currency: &str, month: DateKey, rate: f64) {
+        self.monthly.entry(currency.to_ascii_uppercase()).or_default().insert(month, rate);
+    }
+
+    pub fn rate(&self, currency: &str, month: DateKey) -> Option<f64> {
+        let lookup = |currency: &str| {
+            if currency == self.base {
+                return Some(1.0);
+            }
+            let monthly = self.monthly.get(currency).and_then(|months| months.get(&month));
+            monthly.or_else(|| self.fixed.get(currency)).copied()
+        };
+        lookup(currency).or_else(|| lookup(&currency.to_ascii_uppercase()))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum RateSource {
+    /// A `currency,rate[,month]` CSV file
+    Table(PathBuf),
+    /// An API answering `<url>/<YYYY-MM-DD>?base=<currency>` with a `rates` object, as the
+    /// ECB-backed frankfurter.dev and exchangerate.host do; answers are kept in `cache`.
+    Api { url: String, cache: PathBuf },
+}
+
+/// Converts amounts written in the currency named by `column` into `base` before aggregation.
+#[derive(Clone, Debug)]
+pub struct CurrencyConversion {
+    pub column: String,
+    pub base: String,
+    pub source: RateSource,
+}
+
+impl CurrencyConversion {
+    /// Rates for every currency and month that appears in the data. Each month uses the rate
+    /// published for its first day.
+    #[cfg_attr(not(feature = "fx"), allow(unused_variables))]
+    pub fn rates(&self, needed: &BTreeSet<(String, DateKey)>) -> Result<ExchangeRates, Box<dyn Error>> {
+        match &self.source {
+            RateSource::Table(path) => ExchangeRates::from_path(path, &self.base),
+            #[cfg(feature = "fx")]
+            RateSource::Api { url, cache } => fetch_rates(url, cache, &self.base, needed),
+            #[cfg(not(feature = "fx"))]
+            RateSource::Api { .. } => Err("sales_chart was built without the `fx` feature".into()),
+        }
+    }
+}
+
+/// Keeps only the rows in one currency, for data in several currencies without exchange rates
+/// to convert them, where each currency is charted on its own.
+#[derive(Clone, Debug)]
+pub struct CurrencyFilter {
+    pub column: String,
+    pub currency: String,
+    /// Rows that leave the currency column empty count as this currency
+    pub includes_blank: bool,
+}
+
+impl CurrencyFilter {
+    /// Index of the currency column among `headers`.
+    pub fn index(&self, headers: &StringRecord) -> Result<usize, String> {
+        headers.iter().position(|h| h.eq_ignore_ascii_case(&self.column)).ok_or_else(|| format!("Missing currency column: {}", self.column))
+    }
+
+    pub fn matches(&self, code: &[u8]) -> bool {
+        let code = code.trim_ascii();
+        if code.is_empty() {
+            self.includes_blank
+        } else {
+            code.eq_ignore_ascii_case(self.currency.as_bytes())
+        }
+    }
+}
+
+/// The currencies in `column`, upper-cased and sorted, with `blank` standing for rows that
+/// leave it empty.
+pub fn currencies<S: SalesSource + ?Sized>(source: &mut S, column: &str, blank: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let index = source
+        .headers()?
+        .iter()
+        .position(|h| h.eq_ignore_ascii_case(column))
+        .ok_or_else(|| format!("Missing currency column: {}", column))?;
+    let mut found = BTreeSet::new();
+    for record in records(source) {
+        // Malformed rows are reported when the data is parsed.
+        let Some(code) = record.ok().and_then(|record| record.get(index).map(|code| code.trim_ascii().to_ascii_uppercase())) else {
+            continue;
+        };
+        found.insert(if code.is_empty() { blank.to_ascii_uppercase() } else { String::from_utf8_lossy(&code).into_owned() });
+    }
+    Ok(found.into_iter().collect())
+}
+
+/// The usual symbol of a currency, or its code for others, which amounts show as `CHF 12.50`.
+pub fn currency_symbol(code: &str) -> String {
+    match code.to_ascii_uppercase().as_str() {
+        "USD" => "$".to_string(),
+        "EUR" => "€".to_string(),
+        "GBP" => "£".to_string(),
+        "JPY" | "CNY" => "¥".to_string(),
+        "INR" => "₹".to_string(),
+        "KRW" => "₩".to_string(),
+        other => other.to_string(),
+    }
+}
+
+// Cache layout: base currency -> date -> currency -> units of that currency per base unit,
+// exactly as the API answered. Historical rates do not change, so entries never expire.
+#[cfg(feature = "fx")]
+type RateCache = BTreeMap<String, BTreeMap<String, BTreeMap<String, f64>>>;
+
+#[cfg(feature = "fx")]
+fn fetch_rates(url: &str, cache_path: &Path, base: &str, needed: &BTreeSet<(String, DateKey)>) -> Result<ExchangeRates, Box<dyn Error>> {
+    #[derive(serde::Deserialize)]
+    struct Response {
+        rates: BTreeMap<String, f64>,
+    }
+
+    let base = base.to_ascii_uppercase();
+    let mut cache: RateCache = match std::fs::read_to_string(cache_path) {
+        Ok(contents) => serde_json::from_str(&contents)
+            .map_err(|e| format!("Invalid exchange rate cache {}: {}", cache_path.display(), e))?,
+        Err(_) => RateCache::new(),
+    };
+
+    let mut rates = ExchangeRates::new(&base);
+    let mut fetched = false;
+    let months: BTreeSet<DateKey> = needed
+        .iter()
+        .filter(|(currency, _)| !currency.eq_ignore_ascii_case(&base))
+        .map(|(_, month)| *month)
+        .collect();
+    for month in months {
+        let date = first_day(month);
+        let published = cache.entry(base.clone()).or_default();
+        if !published.contains_key(&date) {
+            let request = format!("{}/{}?base={}", url, date, base);
+            let failed = |e: &dyn std::fmt::Display| format!("Fetching exchange rates from {} failed: {}", request, e);
+            let body = ureq::get(&request)
+                .call()
+                .and_then(|mut response| response.body_mut().read_to_string())
+                .map_err(|e| failed(&e))?;
+            let response: Response = serde_json::from_str(&body).map_err(|e| failed(&e))?;
+            published.insert(date.clone(), response.rates);
+            fetched = true;
+        }
+        for (currency, per_base) in &published[&date] {
+            if *per_base > 0.0 {
+                rates.insert(currency, month, 1.0 / per_base);
+            }
+        }
+    }
+
+    if fetched {
+        std::fs::write(cache_path, serde_json::to_string_pretty(&cache)?)
+            .map_err(|e| format!("Cannot write exchange rate cache {}: {}", cache_path.display(), e))?;
+    }
+    Ok(rates)
+}
+
+#[cfg(feature = "fx")]
+fn first_day(month: DateKey) -> String {
+    crate::key_to_date(month).format("%Y-%m-%d").to_string()
+}
@@ -0,0 +1,137 @@
+use std::error::Error;
+use std::f64::consts::TAU;
+use std::io::Write;
+
+use chrono::{Datelike, Months, NaiveDate};
+use csv::WriterBuilder;
+
+/// What `generate` writes. The same settings and seed always produce the same file.
+#[derive(Clone, Debug)]
+pub struct GeneratorConfig {
+    pub rows: u64,
+    pub products: usize,
+    pub months: u32,
+    pub start: NaiveDate,
+    pub seed: u64,
+    /// Lift sales towards December and let them sag over the summer
+    pub seasonality: bool,
+}
+
+// SplitMix64: tiny, and unlike a library generator its output for a seed never changes
+// between releases, which is what makes a seed in a bug report reproducible.
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in [0, 1).
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn range(&mut self, low: f64, high: f64) -> f64 {
+        low + (high - low) * self.next_f64()
+    }
+
+    /// Standard normal, by the Box-Muller transform.
+    fn normal(&mut self) -> f64 {
+        let u = 1.0 - self.next_f64();
+        (-2.0 * u.ln()).sqrt() * (TAU * self.next_f64()).cos()
+    }
+}
+
+struct Product {
+    name: String,
+    /// Share of the rows before growth is applied; a few products sell most
+    popularity: f64,
+    /// Typical amount of one sale
+    price: f64,
+    /// Monthly change in popularity, between -2% and +3%
+    growth: f64,
+}
+
+fn products(config: &GeneratorConfig, rng: &mut Rng) -> Vec<Product> {
+    let width = config.products.to_string().len();
+    (0..config.products)
+        .map(|i| Product {
+            name: format!("Product_{:0width$}", i + 1, width = width),
+            popularity: 1.0 / ((i + 1) as f64).powf(0.8),
+            price: (rng.range(20f64.ln(), 500f64.ln())).exp(),
+            growth: rng.range(-0.02, 0.03),
+        })
+        .collect()
+}
+
+// Volume relative to an average month: highest in December, lowest in June.
+fn season(month: NaiveDate) -> f64 {
+    1.0 + 0.25 * (TAU * (month.month() as f64 - 12.0) / 12.0).cos()
+}
+
+/// Writes a `month,product,sales_amount` CSV of synthetic sales: product popularity falls off
+/// with rank, each product grows or shrinks a little every month, and amounts vary around a
+/// per-product price with the occasional larger order.
+pub fn generate<W: Write>(writer: W, config: &GeneratorConfig) -> Result<(), Box<dyn Error>> {
+    if config.products == 0 || config.months == 0 {
+        return Err("Need at least one product and one month to generate sales".into());
+    }
+    let mut rng = Rng(config.seed);
+    let products = products(config, &mut rng);
+    let months: Vec<NaiveDate> = (0..config.months)
+        .map(|i| config.start.checked_add_months(Months::new(i)).ok_or("Generated months run past the end of the calendar"))
+        .collect::<Result<_, _>>()?;
+
+    // Overall volume grows 1% a month; rows are split between months in proportion.
+    let volume: Vec<f64> = months
+        .iter()
+        .enumerate()
+        .map(|(i, month)| 1.01f64.powi(i as i32) * if config.seasonality { season(*month) } else { 1.0 })
+        .collect();
+    let total_volume: f64 = volume.iter().sum();
+
+    let mut wtr = WriterBuilder::new().from_writer(writer);
+    wtr.write_record(["month", "product", "sales_amount"])?;
+    let (mut written, mut cumulative) = (0u64, 0.0);
+    for (i, (month, volume)) in months.iter().zip(&volume).enumerate() {
+        cumulative += volume;
+        let until = if i + 1 == months.len() { config.rows } else { (config.rows as f64 * cumulative / total_volume) as u64 };
+        let mut weights: Vec<f64> = products.iter().map(|p| p.popularity * (1.0 + p.growth).powi(i as i32)).collect();
+        let mut sum = 0.0;
+        for weight in &mut weights {
+            sum += *weight;
+            *weight = sum;
+        }
+        let label = month.format("%Y-%m").to_string();
+        for _ in written..until {
+            let pick = rng.next_f64() * sum;
+            let product = &products[weights.partition_point(|weight| *weight <= pick).min(products.len() - 1)];
+            // Mostly single items, sometimes a handful.
+            let quantity = 1.0 + ((1.0 - rng.next_f64()).ln() / 0.65f64.ln()).floor();
+            let amount = product.price * quantity * (0.1 * rng.normal()).exp();
+            wtr.write_record([label.as_str(), product.name.as_str(), &format!("{:.2}", amount)])?;
+        }
+        written = until;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+/// Parses a row count such as `50000`, `250k`, or `1M`.
+pub fn parse_count(value: &str) -> Result<u64, String> {
+    let value = value.trim().replace('_', "");
+    let (digits, scale) = match value.char_indices().last() {
+        Some((i, 'k' | 'K')) => (&value[..i], 1_000),
+        Some((i, 'm' | 'M')) => (&value[..i], 1_000_000),
+        _ => (value.as_str(), 1),
+    };
+    let count: f64 = digits.parse().map_err(|_| format!("Invalid count: {} (expected e.g. 50000, 250k, or 1M)", value))?;
+    if count < 0.0 || !count.is_finite() {
+        return Err(format!("Invalid count: {}", value));
+    }
+    Ok((count * scale as f64).round() as u64)
+}
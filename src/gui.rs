@@ -0,0 +1,204 @@
+use std::error::Error;
+
+use chrono::NaiveDate;
+use minifb::{Key, KeyRepeat, MouseButton, MouseMode, Window, WindowOptions};
+use plotters::coord::Shift;
+use plotters::prelude::*;
+
+use crate::charts::{draw_bar_chart, draw_line_chart, draw_pie_chart, ChartLabels};
+use crate::PlotData;
+
+// Window layout, top to bottom: chart tabs, the chart at its PNG size, the month range slider.
+const WIDTH: usize = 800;
+const TABS_HEIGHT: usize = 40;
+const CHART_HEIGHT: usize = 600;
+const SLIDER_HEIGHT: usize = 60;
+const HEIGHT: usize = TABS_HEIGHT + CHART_HEIGHT + SLIDER_HEIGHT;
+const TAB_WIDTH: usize = 120;
+const TRACK_LEFT: f32 = 80.0;
+const TRACK_RIGHT: f32 = 720.0;
+const TRACK_Y: i32 = 22;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ChartKind {
+    Line,
+    Bar,
+    Pie,
+}
+
+const KINDS: [(ChartKind, &str); 3] = [(ChartKind::Line, "Line"), (ChartKind::Bar, "Bar"), (ChartKind::Pie, "Pie")];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Handle {
+    Start,
+    End,
+}
+
+struct Preview {
+    months: Vec<NaiveDate>,
+    kind: ChartKind,
+    start: usize,
+    end: usize,
+    /// The range `data` was loaded for; differs from `start..=end` while a handle is dragged
+    loaded: (usize, usize),
+    data: Result<PlotData, String>,
+    labels: ChartLabels,
+}
+
+impl Preview {
+    fn track_x(&self, index: usize) -> f32 {
+        match self.months.len() {
+            0 | 1 => (TRACK_LEFT + TRACK_RIGHT) / 2.0,
+            n => TRACK_LEFT + (TRACK_RIGHT - TRACK_LEFT) * index as f32 / (n - 1) as f32,
+        }
+    }
+
+    fn nearest_month(&self, x: f32) -> usize {
+        let last = self.months.len().saturating_sub(1);
+        let step = (TRACK_RIGHT - TRACK_LEFT) / last.max(1) as f32;
+        (((x - TRACK_LEFT) / step).round().max(0.0) as usize).min(last)
+    }
+
+    /// Moves a handle, keeping at least one month between the two so the line chart has a span.
+    fn set_handle(&mut self, handle: Handle, index: usize) -> bool {
+        let before = (self.start, self.end);
+        match handle {
+            Handle::Start => self.start = index.min(self.end.saturating_sub(1)),
+            Handle::End => self.end = index.max(self.start + 1).min(self.months.len() - 1),
+        }
+        (self.start, self.end) != before
+    }
+
+    fn draw(&self, buffer: &mut [u8]) -> Result<(), Box<dyn Error>> {
+        let root = BitMapBackend::with_buffer(buffer, (WIDTH as u32, HEIGHT as u32)).into_drawing_area();
+        root.fill(&WHITE)?;
+        let (tabs, rest) = root.split_vertically(TABS_HEIGHT as u32);
+        let (chart, slider) = rest.split_vertically(CHART_HEIGHT as u32);
+
+        for (i, (kind, name)) in KINDS.iter().enumerate() {
+            let left = (i * TAB_WIDTH) as i32;
+            let fill = if *kind == self.kind { BLUE.mix(0.25) } else { BLACK.mix(0.06) };
+            tabs.draw(&Rectangle::new([(left + 2, 4), (left + TAB_WIDTH as i32 - 2, TABS_HEIGHT as i32)], fill.filled()))?;
+            tabs.draw(&Text::new(format!("{} ({})", name, i + 1), (left + 14, 12), ("sans-serif", 20).into_font()))?;
+        }
+
+        match &self.data {
+            Ok((monthly_data, product_data)) if !monthly_data.is_empty() => match self.kind {
+                ChartKind::Line => draw_line_chart(&chart, monthly_data, &self.labels)?,
+                ChartKind::Bar => draw_bar_chart(&chart, product_data, &self.labels)?,
+                ChartKind::Pie => draw_pie_chart(&chart, product_data, &self.labels)?,
+            },
+            Ok(_) => message(&chart, "No sales in the selected months")?,
+            Err(e) => message(&chart, e)?,
+        }
+
+        self.draw_slider(&slider)
+    }
+
+    fn draw_slider<DB: DrawingBackend>(&self, area: &DrawingArea<DB, Shift>) -> Result<(), Box<dyn Error>>
+    where
+        DB::ErrorType: 'static,
+    {
+        let font = ("sans-serif", 15).into_font();
+        let (start, end) = (self.track_x(self.start) as i32, self.track_x(self.end) as i32);
+        area.draw(&PathElement::new(vec![(TRACK_LEFT as i32, TRACK_Y), (TRACK_RIGHT as i32, TRACK_Y)], BLACK.mix(0.3).stroke_width(4)))?;
+        area.draw(&PathElement::new(vec![(start, TRACK_Y), (end, TRACK_Y)], BLUE.stroke_width(4)))?;
+        for (x, month) in [(start, self.months[self.start]), (end, self.months[self.end])] {
+            area.draw(&Circle::new((x, TRACK_Y), 8, BLUE.filled()))?;
+            area.draw(&Text::new(month.format("%Y-%m").to_string(), (x - 24, TRACK_Y + 14), font.clone()))?;
+        }
+        Ok(())
+    }
+}
+
+fn message<DB: DrawingBackend>(area: &DrawingArea<DB, Shift>, text: &str) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    area.fill(&WHITE)?;
+    area.draw(&Text::new(text.to_string(), (40, CHART_HEIGHT as i32 / 2), ("sans-serif", 20).into_font()))?;
+    Ok(())
+}
+
+/// Shows the charts of `data` in a window until it is closed. Tabs (or keys 1-3) switch
+/// between the line, bar, and pie chart; dragging the slider handles (or Left/Right, with
+/// Shift for the first month) narrows the months, and `load` re-aggregates that range.
+pub fn show<F>(title: &str, data: PlotData, labels: ChartLabels, mut load: F) -> Result<(), Box<dyn Error>>
+where
+    F: FnMut(NaiveDate, NaiveDate) -> Result<PlotData, Box<dyn Error>>,
+{
+    let months: Vec<NaiveDate> = data.0.iter().map(|(month, _)| *month).collect();
+    if months.is_empty() {
+        return Err("The data has no sales rows".into());
+    }
+    let end = months.len() - 1;
+    let mut preview = Preview { months, kind: ChartKind::Line, start: 0, end, loaded: (0, end), data: Ok(data), labels };
+
+    let mut window = Window::new(title, WIDTH, HEIGHT, WindowOptions::default())
+        .map_err(|e| format!("Cannot open the preview window: {}", e))?;
+    window.set_target_fps(30);
+
+    let mut rgb = vec![0u8; WIDTH * HEIGHT * 3];
+    let mut pixels = vec![0u32; WIDTH * HEIGHT];
+    let mut dirty = true;
+    let mut dragging = None;
+    while window.is_open() && !window.is_key_down(Key::Escape) {
+        let mouse = window.get_mouse_pos(MouseMode::Discard);
+        let down = window.get_mouse_down(MouseButton::Left);
+        if let (Some((x, y)), true, None) = (mouse, down, dragging) {
+            let y = y as usize;
+            if y < TABS_HEIGHT {
+                if let Some((kind, _)) = KINDS.get(x as usize / TAB_WIDTH) {
+                    dirty |= preview.kind != *kind;
+                    preview.kind = *kind;
+                }
+            } else if y >= TABS_HEIGHT + CHART_HEIGHT && preview.months.len() > 1 {
+                let closer_to_start = (x - preview.track_x(preview.start)).abs() <= (x - preview.track_x(preview.end)).abs();
+                dragging = Some(if closer_to_start { Handle::Start } else { Handle::End });
+            }
+        }
+        if let (Some(handle), Some((x, _))) = (dragging, mouse) {
+            let index = preview.nearest_month(x);
+            dirty |= preview.set_handle(handle, index);
+        }
+        if !down {
+            dragging = None;
+        }
+
+        for key in window.get_keys_pressed(KeyRepeat::Yes) {
+            let shift = window.is_key_down(Key::LeftShift) || window.is_key_down(Key::RightShift);
+            let handle = if shift { Handle::Start } else { Handle::End };
+            let current = if shift { preview.start } else { preview.end };
+            dirty |= match key {
+                Key::Key1 | Key::Key2 | Key::Key3 => {
+                    let kind = match key {
+                        Key::Key1 => ChartKind::Line,
+                        Key::Key2 => ChartKind::Bar,
+                        _ => ChartKind::Pie,
+                    };
+                    std::mem::replace(&mut preview.kind, kind) != kind
+                }
+                Key::Left if preview.months.len() > 1 => preview.set_handle(handle, current.saturating_sub(1)),
+                Key::Right if preview.months.len() > 1 => preview.set_handle(handle, current + 1),
+                _ => false,
+            };
+        }
+
+        if dragging.is_none() && preview.loaded != (preview.start, preview.end) {
+            let (from, to) = (preview.months[preview.start], preview.months[preview.end]);
+            preview.data = load(from, to).map_err(|e| e.to_string());
+            preview.loaded = (preview.start, preview.end);
+            window.set_title(&format!("{} ({} to {})", title, from.format("%Y-%m"), to.format("%Y-%m")));
+            dirty = true;
+        }
+        if dirty {
+            preview.draw(&mut rgb)?;
+            for (pixel, rgb) in pixels.iter_mut().zip(rgb.chunks_exact(3)) {
+                *pixel = u32::from_be_bytes([0, rgb[0], rgb[1], rgb[2]]);
+            }
+            dirty = false;
+        }
+        window.update_with_buffer(&pixels, WIDTH, HEIGHT).map_err(|e| format!("Cannot update the preview window: {}", e))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,114 @@
+use std::error::Error;
+use std::path::Path;
+
+use chrono::NaiveDate;
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::summary::RunSummary;
+use crate::PlotData;
+
+/// A run recorded in the history, without its monthly and product totals.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StoredRun {
+    pub id: i64,
+    /// When the run finished, as RFC 3339 local time
+    pub run_at: String,
+    /// The input charted, such as its path
+    pub dataset: String,
+    pub total_sales: f64,
+    pub rows: u64,
+}
+
+/// A SQLite database of every run's totals by month and product, to chart how the totals moved
+/// from run to run and to redraw a past run's charts without its input.
+pub struct RunHistory {
+    conn: Connection,
+}
+
+impl RunHistory {
+    /// Opens the database at `path`, creating it and its tables the first time.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let path = path.as_ref();
+        let conn = Connection::open(path).map_err(|e| format!("Cannot open run history {}: {}", path.display(), e))?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS runs (
+                 id INTEGER PRIMARY KEY,
+                 run_at TEXT NOT NULL,
+                 dataset TEXT NOT NULL,
+                 total_sales REAL NOT NULL,
+                 rows INTEGER NOT NULL,
+                 UNIQUE (dataset, run_at)
+             );
+             CREATE TABLE IF NOT EXISTS monthly (
+                 run_id INTEGER NOT NULL REFERENCES runs (id) ON DELETE CASCADE,
+                 month TEXT NOT NULL,
+                 sales REAL NOT NULL,
+                 PRIMARY KEY (run_id, month)
+             );
+             CREATE TABLE IF NOT EXISTS products (
+                 run_id INTEGER NOT NULL REFERENCES runs (id) ON DELETE CASCADE,
+                 position INTEGER NOT NULL,
+                 product TEXT NOT NULL,
+                 sales REAL NOT NULL,
+                 PRIMARY KEY (run_id, position)
+             );",
+        )?;
+        Ok(RunHistory { conn })
+    }
+
+    /// Stores the totals of `summary` as a run of `dataset` at `run_at`, in one transaction,
+    /// and returns its id.
+    pub fn record(&mut self, dataset: &str, run_at: &str, summary: &RunSummary) -> Result<i64, Box<dyn Error>> {
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "INSERT INTO runs (run_at, dataset, total_sales, rows) VALUES (?1, ?2, ?3, ?4)",
+            params![run_at, dataset, summary.total_sales, summary.rows as i64],
+        )?;
+        let id = tx.last_insert_rowid();
+        {
+            let mut month = tx.prepare("INSERT INTO monthly (run_id, month, sales) VALUES (?1, ?2, ?3)")?;
+            for total in &summary.aggregates.monthly {
+                month.execute(params![id, total.month, total.sales])?;
+            }
+            let mut product = tx.prepare("INSERT INTO products (run_id, position, product, sales) VALUES (?1, ?2, ?3, ?4)")?;
+            for (position, total) in summary.aggregates.products.iter().enumerate() {
+                product.execute(params![id, position as i64, total.product, total.sales])?;
+            }
+        }
+        tx.commit()?;
+        Ok(id)
+    }
+
+    /// The runs of `dataset` in the order they happened, or of every dataset with None.
+    pub fn runs(&self, dataset: Option<&str>) -> Result<Vec<StoredRun>, Box<dyn Error>> {
+        let mut statement = self.conn.prepare(
+            "SELECT id, run_at, dataset, total_sales, rows FROM runs WHERE ?1 IS NULL OR dataset = ?1 ORDER BY run_at, id",
+        )?;
+        let runs = statement
+            .query_map(params![dataset], |row| {
+                Ok(StoredRun { id: row.get(0)?, run_at: row.get(1)?, dataset: row.get(2)?, total_sales: row.get(3)?, rows: row.get::<_, i64>(4)? as u64 })
+            })?
+            .collect::<Result<_, _>>()?;
+        Ok(runs)
+    }
+
+    /// The monthly and product totals stored for run `id`, as the charts take them.
+    pub fn plot_data(&self, id: i64) -> Result<PlotData, Box<dyn Error>> {
+        let found: Option<i64> = self.conn.query_row("SELECT id FROM runs WHERE id = ?1", params![id], |row| row.get(0)).optional()?;
+        if found.is_none() {
+            return Err(format!("The run history has no run {}", id).into());
+        }
+        let mut months = self.conn.prepare("SELECT month, sales FROM monthly WHERE run_id = ?1 ORDER BY month")?;
+        let monthly = months
+            .query_map(params![id], |row| Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?)))?
+            .map(|row| {
+                let (month, sales) = row?;
+                let date = NaiveDate::parse_from_str(&format!("{}-01", month), "%Y-%m-%d").map_err(|_| format!("The run history has a month \"{}\"", month))?;
+                Ok((date, sales))
+            })
+            .collect::<Result<_, Box<dyn Error>>>()?;
+        let mut products = self.conn.prepare("SELECT product, sales FROM products WHERE run_id = ?1 ORDER BY position")?;
+        let products = products.query_map(params![id], |row| Ok((row.get(0)?, row.get(1)?)))?.collect::<Result<_, _>>()?;
+        Ok((monthly, products))
+    }
+}
@@ -0,0 +1,157 @@
+use std::error::Error;
+use std::path::Path;
+
+use chrono::{Datelike, Days, NaiveDate, Weekday};
+use serde::Serialize;
+
+/// A holiday, or with a later `end` a promotion or other period of several days.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Holiday {
+    pub name: String,
+    pub start: NaiveDate,
+    /// Last day, inclusive
+    pub end: NaiveDate,
+}
+
+impl Holiday {
+    fn day(name: &str, date: NaiveDate) -> Self {
+        Holiday { name: name.to_string(), start: date, end: date }
+    }
+
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        (self.start..=self.end).contains(&date)
+    }
+}
+
+/// Holidays and promotions to mark on the charts and to compare sales on against other days.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HolidayCalendar {
+    /// A country's public holidays on their dates, without the weekdays they are observed on
+    /// when they fall on a weekend: `US` (federal) or `GB` (England and Wales bank holidays)
+    Country(String),
+    /// Read from a `date,name[,end]` CSV file
+    Listed(Vec<Holiday>),
+}
+
+impl HolidayCalendar {
+    /// A built-in country code such as `US`, or else the path of a `date,name[,end]` CSV file,
+    /// where rows with an end date are periods such as a promotion.
+    pub fn from_arg(arg: &str) -> Result<Self, Box<dyn Error>> {
+        if ["US", "GB"].iter().any(|country| country.eq_ignore_ascii_case(arg)) {
+            return Ok(HolidayCalendar::Country(arg.to_ascii_uppercase()));
+        }
+        let path = Path::new(arg);
+        if !path.exists() {
+            return Err(format!("{} is neither a holiday file nor one of the built-in countries US and GB", arg).into());
+        }
+        let mut rdr = csv::ReaderBuilder::new().trim(csv::Trim::All).flexible(true).from_path(path)?;
+        let mut holidays = Vec::new();
+        for (i, record) in rdr.records().enumerate() {
+            let record = record?;
+            let invalid = |reason: String| format!("Invalid holiday file {}: row {} {}", path.display(), i + 2, reason);
+            let date = |field: Option<&str>| {
+                field.map(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| invalid(format!("has \"{}\" for a YYYY-MM-DD date", date))))
+            };
+            let start = date(record.get(0)).unwrap_or_else(|| Err(invalid("has no date".to_string())))?;
+            let name = record.get(1).filter(|name| !name.is_empty()).ok_or_else(|| invalid("has no name".to_string()))?;
+            let end = date(record.get(2).filter(|end| !end.is_empty())).transpose()?.unwrap_or(start);
+            if end < start {
+                return Err(invalid(format!("ends on {} before it starts", end)).into());
+            }
+            holidays.push(Holiday { name: name.to_string(), start, end });
+        }
+        Ok(HolidayCalendar::Listed(holidays))
+    }
+
+    /// The holidays overlapping `from` to `to`, in date order.
+    pub fn between(&self, from: NaiveDate, to: NaiveDate) -> Vec<Holiday> {
+        let mut holidays: Vec<Holiday> = match self {
+            HolidayCalendar::Country(country) => (from.year()..=to.year()).flat_map(|year| country_holidays(country, year)).collect(),
+            HolidayCalendar::Listed(holidays) => holidays.clone(),
+        };
+        holidays.retain(|holiday| holiday.end >= from && holiday.start <= to);
+        holidays.sort_by_key(|holiday| holiday.start);
+        holidays
+    }
+}
+
+// The `n`th `weekday` of a month, counting from 1, or from the end with a negative `n`.
+fn nth_weekday(year: i32, month: u32, weekday: Weekday, n: i32) -> NaiveDate {
+    if n > 0 {
+        NaiveDate::from_weekday_of_month_opt(year, month, weekday, n as u8).expect("months have four of each weekday")
+    } else {
+        let next = if month == 12 { NaiveDate::from_ymd_opt(year + 1, 1, 1) } else { NaiveDate::from_ymd_opt(year, month + 1, 1) };
+        let last = next.expect("valid month").pred_opt().expect("valid date");
+        let back = (7 + last.weekday().num_days_from_monday() - weekday.num_days_from_monday()) % 7;
+        last - Days::new(u64::from(back) + 7 * (n.unsigned_abs() as u64 - 1))
+    }
+}
+
+// Easter Sunday by the anonymous Gregorian algorithm.
+fn easter(year: i32) -> NaiveDate {
+    let a = year % 19;
+    let b = year / 100;
+    let c = year % 100;
+    let d = (19 * a + b - b / 4 - (b - (8 * b + 13) / 25) + 15) % 30;
+    let e = (32 + 2 * (b % 4) + 2 * (c / 4) - d - (c % 4)) % 7;
+    let f = d + e - 7 * ((a + 11 * d + 19 * e) / 433);
+    let month = (f + 90) / 25;
+    let day = (f + 33 * month + 19) % 32;
+    NaiveDate::from_ymd_opt(year, month as u32, day as u32).expect("Easter falls in March or April")
+}
+
+fn country_holidays(country: &str, year: i32) -> Vec<Holiday> {
+    let date = |month, day| NaiveDate::from_ymd_opt(year, month, day).expect("fixed holidays are valid dates");
+    match country {
+        "US" => vec![
+            Holiday::day("New Year's Day", date(1, 1)),
+            Holiday::day("Martin Luther King Jr. Day", nth_weekday(year, 1, Weekday::Mon, 3)),
+            Holiday::day("Presidents' Day", nth_weekday(year, 2, Weekday::Mon, 3)),
+            Holiday::day("Memorial Day", nth_weekday(year, 5, Weekday::Mon, -1)),
+            Holiday::day("Juneteenth", date(6, 19)),
+            Holiday::day("Independence Day", date(7, 4)),
+            Holiday::day("Labor Day", nth_weekday(year, 9, Weekday::Mon, 1)),
+            Holiday::day("Columbus Day", nth_weekday(year, 10, Weekday::Mon, 2)),
+            Holiday::day("Veterans Day", date(11, 11)),
+            Holiday::day("Thanksgiving", nth_weekday(year, 11, Weekday::Thu, 4)),
+            Holiday::day("Christmas Day", date(12, 25)),
+        ],
+        "GB" => vec![
+            Holiday::day("New Year's Day", date(1, 1)),
+            Holiday::day("Good Friday", easter(year) - Days::new(2)),
+            Holiday::day("Easter Monday", easter(year) + Days::new(1)),
+            Holiday::day("Early May bank holiday", nth_weekday(year, 5, Weekday::Mon, 1)),
+            Holiday::day("Spring bank holiday", nth_weekday(year, 5, Weekday::Mon, -1)),
+            Holiday::day("Summer bank holiday", nth_weekday(year, 8, Weekday::Mon, -1)),
+            Holiday::day("Christmas Day", date(12, 25)),
+            Holiday::day("Boxing Day", date(12, 26)),
+        ],
+        _ => Vec::new(),
+    }
+}
+
+/// Average sales per day on holidays and on other days, over the days with sales.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct HolidayAverages {
+    pub holiday_days: usize,
+    pub holiday_average: f64,
+    pub other_days: usize,
+    pub other_average: f64,
+}
+
+/// Compares `daily` totals on the days of `holidays` with the rest; None without daily totals.
+pub fn holiday_averages(daily: &[(NaiveDate, f64)], holidays: &[Holiday]) -> Option<HolidayAverages> {
+    if daily.is_empty() {
+        return None;
+    }
+    let (mut on, mut off) = (Vec::new(), Vec::new());
+    for (day, sales) in daily {
+        if holidays.iter().any(|holiday| holiday.contains(*day)) {
+            on.push(*sales);
+        } else {
+            off.push(*sales);
+        }
+    }
+    let average = |sales: &[f64]| if sales.is_empty() { 0.0 } else { sales.iter().sum::<f64>() / sales.len() as f64 };
+    Some(HolidayAverages { holiday_days: on.len(), holiday_average: average(&on), other_days: off.len(), other_average: average(&off) })
+}
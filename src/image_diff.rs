@@ -0,0 +1,75 @@
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+
+use image::{Rgb, RgbImage};
+
+/// How much of a chart changed between two renders.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ImageDiff {
+    /// Size of the compared canvas, large enough for both images
+    pub width: u32,
+    pub height: u32,
+    /// Set when the two images are not the same size
+    pub resized: bool,
+    pub changed_pixels: u64,
+}
+
+impl ImageDiff {
+    pub fn total_pixels(&self) -> u64 {
+        self.width as u64 * self.height as u64
+    }
+
+    pub fn changed_percent(&self) -> f64 {
+        match self.total_pixels() {
+            0 => 0.0,
+            total => self.changed_pixels as f64 / total as f64 * 100.0,
+        }
+    }
+}
+
+impl fmt::Display for ImageDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} of {} pixels changed ({:.2}%)", self.changed_pixels, self.total_pixels(), self.changed_percent())?;
+        if self.resized {
+            write!(f, ", and the image size changed")?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads an image file in any supported format as 8-bit RGB.
+pub fn open(path: &Path) -> Result<RgbImage, Box<dyn Error>> {
+    Ok(image::open(path).map_err(|e| format!("Cannot read image {}: {}", path.display(), e))?.to_rgb8())
+}
+
+/// Compares two images pixel by pixel and returns the counts with a visual diff: the new
+/// image faded out, with every changed pixel in red. `same` decides whether two pixels match;
+/// where only one image has pixels, they always count as changed.
+pub fn compare<F>(old: &RgbImage, new: &RgbImage, mut same: F) -> (ImageDiff, RgbImage)
+where
+    F: FnMut(Rgb<u8>, Rgb<u8>) -> bool,
+{
+    let (width, height) = (old.width().max(new.width()), old.height().max(new.height()));
+    let mut changed_pixels = 0;
+    let visual = RgbImage::from_fn(width, height, |x, y| {
+        let pixel = |image: &RgbImage| (x < image.width() && y < image.height()).then(|| *image.get_pixel(x, y));
+        match (pixel(old), pixel(new)) {
+            (Some(a), Some(b)) if same(a, b) => Rgb(b.0.map(|channel| 255 - (255 - channel) / 4)),
+            _ => {
+                changed_pixels += 1;
+                Rgb([255, 0, 0])
+            }
+        }
+    });
+    (ImageDiff { width, height, resized: old.dimensions() != new.dimensions(), changed_pixels }, visual)
+}
+
+/// Compares two image files and writes the visual diff to `output`. A pixel counts as
+/// changed when any channel differs by more than `tolerance`.
+pub fn diff_images(old: &Path, new: &Path, output: &Path, tolerance: u8) -> Result<ImageDiff, Box<dyn Error>> {
+    let (old, new) = (open(old)?, open(new)?);
+    let (diff, visual) = compare(&old, &new, |Rgb(a), Rgb(b)| a.iter().zip(&b).all(|(a, b)| a.abs_diff(*b) <= tolerance));
+    visual.save(output).map_err(|e| format!("Cannot write {}: {}", output.display(), e))?;
+    Ok(diff)
+}
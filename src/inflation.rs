@@ -0,0 +1,96 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::Path;
+
+use chrono::{Datelike, NaiveDate};
+
+use crate::{date_to_key, key_to_date, parse_month, DateKey};
+
+// US CPI-U, all items, annual averages (BLS series CUUR0000SA0, 1982-84 = 100).
+const US_CPI: &[(i32, f64)] = &[
+    (2000, 172.2),
+    (2001, 177.1),
+    (2002, 179.9),
+    (2003, 184.0),
+    (2004, 188.9),
+    (2005, 195.3),
+    (2006, 201.6),
+    (2007, 207.342),
+    (2008, 215.303),
+    (2009, 214.537),
+    (2010, 218.056),
+    (2011, 224.939),
+    (2012, 229.594),
+    (2013, 232.957),
+    (2014, 236.736),
+    (2015, 237.017),
+    (2016, 240.007),
+    (2017, 245.120),
+    (2018, 251.107),
+    (2019, 255.657),
+    (2020, 258.811),
+    (2021, 270.970),
+    (2022, 292.655),
+    (2023, 304.702),
+    (2024, 313.689),
+];
+
+/// A price index (CPI or any deflator) by month, or by year for series published annually.
+#[derive(Clone, Debug, Default)]
+pub struct PriceIndex {
+    monthly: BTreeMap<DateKey, f64>,
+    yearly: BTreeMap<i32, f64>,
+}
+
+impl PriceIndex {
+    /// The built-in US consumer price index, one value per year.
+    pub fn us_cpi() -> Self {
+        PriceIndex { monthly: BTreeMap::new(), yearly: US_CPI.iter().copied().collect() }
+    }
+
+    /// Reads a `month,index` CSV file; the month may be `YYYY-MM` or a bare year.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let path = path.as_ref();
+        let mut rdr = csv::ReaderBuilder::new().trim(csv::Trim::All).from_path(path)?;
+        let mut index = PriceIndex::default();
+        for (i, record) in rdr.records().enumerate() {
+            let record = record?;
+            let invalid = |reason: &str| format!("Invalid price index file {}: row {} {}", path.display(), i + 2, reason);
+            let (Some(period), Some(value)) = (record.get(0), record.get(1)) else {
+                return Err(invalid("needs a month and an index column").into());
+            };
+            let value = value.parse::<f64>().ok().filter(|v| *v > 0.0).ok_or_else(|| invalid("needs a positive index"))?;
+            match period.parse::<i32>() {
+                Ok(year) => index.yearly.insert(year, value),
+                Err(_) => index.monthly.insert(parse_month(period.as_bytes()).map_err(|e| invalid(&e))?, value),
+            };
+        }
+        Ok(index)
+    }
+
+    pub fn value(&self, month: NaiveDate) -> Option<f64> {
+        self.monthly.get(&date_to_key(&month)).or_else(|| self.yearly.get(&month.year())).copied()
+    }
+
+    /// Restates monthly totals in the prices of `base` (the latest month when not given):
+    /// each amount is scaled by index(base) / index(month).
+    pub fn deflate(&self, monthly_data: &[(NaiveDate, f64)], base: Option<NaiveDate>) -> Result<Vec<(NaiveDate, f64)>, String> {
+        let missing = |month: NaiveDate| format!("The price index has no value for {}", month.format("%Y-%m"));
+        let Some(base) = base.or_else(|| monthly_data.last().map(|(month, _)| *month)) else {
+            return Ok(Vec::new());
+        };
+        let base_value = self.value(base).ok_or_else(|| missing(base))?;
+        monthly_data
+            .iter()
+            .map(|&(month, sales)| {
+                let value = self.value(month).ok_or_else(|| missing(month))?;
+                Ok((month, sales * base_value / value))
+            })
+            .collect()
+    }
+}
+
+/// First of the month for a `YYYY-MM` string, for choosing the base month of `deflate`.
+pub fn parse_base_month(value: &str) -> Result<NaiveDate, String> {
+    parse_month(value.as_bytes()).map(key_to_date)
+}
@@ -0,0 +1,59 @@
+use std::error::Error;
+use std::time::{Duration, Instant};
+
+use ::kafka::consumer::{Consumer, FetchOffset};
+use tracing::warn;
+
+use crate::stream::RunningTotals;
+
+/// Where to read sales events from and how often to hand the totals back for re-rendering.
+pub struct KafkaSource {
+    pub brokers: Vec<String>,
+    pub topic: String,
+    pub refresh: Duration,
+}
+
+/// Reads the topic from its earliest retained message, so the totals cover everything the
+/// brokers still hold, then keeps following it. `refresh` is called at most once per
+/// `source.refresh` and only when events arrived since the previous call; rejected events
+/// are printed as warnings and skipped. Only returns on a broker error.
+pub fn consume(
+    source: &KafkaSource,
+    totals: &mut RunningTotals,
+    refresh: &mut dyn FnMut(&RunningTotals),
+) -> Result<(), Box<dyn Error>> {
+    let mut consumer = Consumer::from_hosts(source.brokers.clone())
+        .with_topic(source.topic.clone())
+        .with_fallback_offset(FetchOffset::Earliest)
+        .with_fetch_max_wait_time(Duration::from_millis(500))
+        .create()
+        .map_err(|e| format!("Cannot subscribe to Kafka topic {} on {}: {}", source.topic, source.brokers.join(","), e))?;
+
+    let mut last_refresh = Instant::now();
+    let mut pending = false;
+    loop {
+        let sets = consumer.poll().map_err(|e| format!("Reading Kafka topic {} failed: {}", source.topic, e))?;
+        for set in sets.iter() {
+            for message in set.messages() {
+                pending = true;
+                if let Err(error) = totals.push(message.value, message.offset as u64 + 1) {
+                    let column = error.column.map(|c| format!(", column {}", c)).unwrap_or_default();
+                    warn!(
+                        "Skipped event at partition {} offset {}{}: {}",
+                        set.partition(),
+                        message.offset,
+                        column,
+                        error.reason
+                    );
+                }
+            }
+            consumer.consume_messageset(set)?;
+        }
+
+        if pending && last_refresh.elapsed() >= source.refresh {
+            refresh(totals);
+            last_refresh = Instant::now();
+            pending = false;
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+/// What a cell of a layout draws.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CellChart {
+    Line,
+    Bar,
+    Pie,
+    Streamgraph,
+    StackedBar,
+    Bump,
+    Funnel,
+    /// One metric card, picked by the cell's `metric`
+    Kpi,
+    /// One product's trend, picked by the cell's `product`
+    Product,
+}
+
+/// A metric card a `kpi` cell shows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Metric {
+    TotalRevenue,
+    Growth,
+    BestMonth,
+    TopProduct,
+}
+
+/// One chart of a layout and the grid cells it covers.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Cell {
+    pub chart: CellChart,
+    /// Top row and left column, counting from 1
+    pub row: u32,
+    pub column: u32,
+    #[serde(default = "one")]
+    pub row_span: u32,
+    #[serde(default = "one")]
+    pub column_span: u32,
+    /// For a line chart, draw the prior year behind it or not, instead of as `--prior-year` has it
+    #[serde(default)]
+    pub prior_year: Option<bool>,
+    #[serde(default)]
+    pub metric: Option<Metric>,
+    #[serde(default)]
+    pub product: Option<String>,
+}
+
+fn one() -> u32 {
+    1
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Config {
+    #[serde(default)]
+    output: Option<PathBuf>,
+    #[serde(default = "default_width")]
+    width: u32,
+    #[serde(default = "default_height")]
+    height: u32,
+    rows: u32,
+    columns: u32,
+    #[serde(default)]
+    cell: Vec<Cell>,
+}
+
+fn default_width() -> u32 {
+    1600
+}
+
+fn default_height() -> u32 {
+    900
+}
+
+/// A dashboard image of several charts on a grid of equal rows and columns, each chart
+/// covering one or more cells.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Layout {
+    /// The image written, `layout_chart.png` unless the layout names one
+    pub output: PathBuf,
+    pub size: (u32, u32),
+    pub rows: u32,
+    pub columns: u32,
+    pub cells: Vec<Cell>,
+}
+
+impl Layout {
+    /// Reads a TOML file such as `rows = 2`, `columns = 3`, and a `[[cell]]` table per chart with
+    /// its `chart`, `row`, `column`, and optionally `row_span`, `column_span`, and the chart's
+    /// options. Cells may not overlap or run off the grid.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path).map_err(|e| format!("Cannot read layout {}: {}", path.display(), e))?;
+        let invalid = |reason: String| format!("Invalid layout {}: {}", path.display(), reason);
+        let config: Config = toml::from_str(&contents).map_err(|e| invalid(e.to_string()))?;
+        if config.rows == 0 || config.columns == 0 || config.width == 0 || config.height == 0 {
+            return Err(invalid("needs at least one row and column and a size above zero".to_string()).into());
+        }
+        if config.width < config.columns * MIN_CELL || config.height < config.rows * MIN_CELL {
+            return Err(invalid(format!("{}x{} pixels leave less than {} per row or column", config.width, config.height, MIN_CELL)).into());
+        }
+        for (i, cell) in config.cell.iter().enumerate() {
+            let name = format!("cell {} ({:?})", i + 1, cell.chart).to_lowercase();
+            if cell.row == 0 || cell.column == 0 || cell.row_span == 0 || cell.column_span == 0 {
+                return Err(invalid(format!("{} counts rows and columns from 1", name)).into());
+            }
+            if cell.row + cell.row_span - 1 > config.rows || cell.column + cell.column_span - 1 > config.columns {
+                return Err(invalid(format!("{} runs off the {}x{} grid", name, config.rows, config.columns)).into());
+            }
+            if let Some(j) = config.cell[..i].iter().position(|other| overlap(cell, other)) {
+                return Err(invalid(format!("{} overlaps cell {}", name, j + 1)).into());
+            }
+            match cell.chart {
+                CellChart::Kpi if cell.metric.is_none() => {
+                    return Err(invalid(format!("{} needs a metric: total_revenue, growth, best_month, or top_product", name)).into())
+                }
+                CellChart::Product if cell.product.is_none() => return Err(invalid(format!("{} needs a product", name)).into()),
+                _ => {}
+            }
+        }
+        Ok(Layout {
+            output: config.output.unwrap_or_else(|| PathBuf::from("layout_chart.png")),
+            size: (config.width, config.height),
+            rows: config.rows,
+            columns: config.columns,
+            cells: config.cell,
+        })
+    }
+
+    /// The pixel rectangle of each cell on an image of `(width, height)`, as its left upper
+    /// corner and its size, in cell order.
+    pub fn areas(&self, (width, height): (u32, u32)) -> Vec<((u32, u32), (u32, u32))> {
+        let x = |column: u32| width * column / self.columns;
+        let y = |row: u32| height * row / self.rows;
+        self.cells
+            .iter()
+            .map(|cell| {
+                let (left, top) = (x(cell.column - 1), y(cell.row - 1));
+                ((left, top), (x(cell.column - 1 + cell.column_span) - left, y(cell.row - 1 + cell.row_span) - top))
+            })
+            .collect()
+    }
+}
+
+// The fewest pixels a row or column may have, room for little more than a caption.
+const MIN_CELL: u32 = 100;
+
+fn overlap(a: &Cell, b: &Cell) -> bool {
+    a.row < b.row + b.row_span && b.row < a.row + a.row_span && a.column < b.column + b.column_span && b.column < a.column + a.column_span
+}
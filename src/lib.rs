@@ -0,0 +1,1029 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::io::Read;
+use std::ops::{AddAssign, RangeInclusive};
+use std::path::Path;
+use std::str::FromStr;
+use chrono::{Datelike, Months, NaiveDate};
+use csv::{ByteRecord, StringRecord, WriterBuilder};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, debug_span, trace, warn};
+
+use schema::FieldError;
+use source::SalesSource;
+
+#[cfg(feature = "duckdb")]
+pub mod duckdb_engine;
+#[cfg(feature = "email")]
+pub mod email;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod fonts;
+#[cfg(feature = "gui")]
+pub mod gui;
+#[cfg(feature = "history")]
+pub mod history;
+#[cfg(feature = "kafka")]
+pub mod kafka;
+#[cfg(feature = "polars")]
+pub mod polars_engine;
+#[cfg(feature = "server")]
+pub mod preview;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(all(feature = "bitmap", feature = "svg", not(target_arch = "wasm32")))]
+pub mod report;
+#[cfg(feature = "scripting")]
+pub mod script;
+#[cfg(feature = "server")]
+pub mod server;
+#[cfg(feature = "slack")]
+pub mod slack;
+#[cfg(all(feature = "bitmap", not(target_arch = "wasm32")))]
+pub mod snapshot;
+#[cfg(feature = "tui")]
+pub mod tui;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "webhook")]
+pub mod webhook;
+pub mod alert;
+pub mod bidi;
+pub mod budget;
+pub mod chart_cache;
+pub mod charts;
+pub mod checkpoint;
+pub mod chunked;
+pub mod choropleth;
+pub mod compare;
+pub mod dashboard;
+pub mod date_order;
+pub mod fx;
+pub mod generate;
+pub mod holidays;
+pub mod image_diff;
+pub mod inflation;
+pub mod layout;
+pub mod locale;
+pub mod narrative;
+pub mod number;
+pub mod products;
+pub mod quality;
+pub mod retail;
+pub mod scenario;
+pub mod schema;
+pub mod source;
+pub mod split;
+pub mod stream;
+pub mod summary;
+pub mod suspicious;
+pub mod timings;
+pub mod translations;
+pub mod upload;
+pub mod validate;
+pub mod vega;
+pub mod verify;
+
+pub type DateKey = i32;
+pub type SalesTotals = (HashMap<DateKey, f64>, HashMap<String, f64>);
+pub type PlotData = (Vec<(NaiveDate, f64)>, Vec<(String, f64)>);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MissingValuePolicy {
+    #[default]
+    Error,
+    Skip,
+    Zero,
+}
+
+#[derive(Clone, Debug)]
+pub struct MissingValues {
+    pub policy: MissingValuePolicy,
+    pub sentinels: Vec<String>,
+}
+
+impl Default for MissingValues {
+    fn default() -> Self {
+        MissingValues {
+            policy: MissingValuePolicy::Error,
+            sentinels: vec![String::new(), "N/A".to_string(), "-".to_string()],
+        }
+    }
+}
+
+impl MissingValues {
+    pub fn is_missing(&self, field: &[u8]) -> bool {
+        let field = field.trim_ascii();
+        self.sentinels.iter().any(|s| s.as_bytes().eq_ignore_ascii_case(field))
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum DuplicateKey {
+    /// Same month, product, and amount
+    #[default]
+    Exact,
+    /// Same value in an identifier column, e.g. a transaction id
+    Column(String),
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ParseOptions {
+    pub lenient: bool,
+    pub schema: schema::Schema,
+    pub missing: MissingValues,
+    pub duplicate_key: DuplicateKey,
+    pub dedupe: bool,
+    pub suspicion: suspicious::SuspicionRules,
+    pub number: number::NumberFormat,
+    pub date_order: date_order::DateOrder,
+    /// Zone whose local date decides the month of timestamps with an offset; UTC by default
+    pub timezone: chrono_tz::Tz,
+    /// Totals go by the fiscal periods of this calendar, each keyed as the month of its number
+    pub calendar: Option<retail::RetailCalendar>,
+    pub currency: Option<fx::CurrencyConversion>,
+    /// Only rows in this currency are read, as if the others were not in the file
+    pub currency_filter: Option<fx::CurrencyFilter>,
+    /// Only rows whose month falls in this range are aggregated
+    pub months: Option<RangeInclusive<DateKey>>,
+    /// Only rows whose product name contains this text, ignoring case, are aggregated
+    pub product_filter: Option<String>,
+    /// Also total sales by month, product, and region into `ParseReport::cube`
+    pub cube: Option<CubeSpec>,
+    /// Also total sales by day into `ParseReport::daily`, for rows whose date has a day
+    pub daily: bool,
+    /// Column naming each row's stage in a sales funnel, such as lead, quote, or closed; its
+    /// totals go into `ParseReport::stages` when the file has it
+    pub stage_column: Option<String>,
+    /// Add amounts up as exact decimals instead of in floating point, so totals of cent
+    /// amounts stay exact to the cent however many rows there are
+    pub exact: bool,
+    /// Script whose `transform` function changes rows or leaves them out before they are totalled
+    #[cfg(feature = "scripting")]
+    pub script: Option<std::sync::Arc<script::Script>>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct CubeSpec {
+    /// Column naming each row's region; without one every row is in region ""
+    pub region_column: Option<String>,
+}
+
+/// Sales of one product in one region and month.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CubeCell {
+    pub month: DateKey,
+    pub product: String,
+    pub region: String,
+    pub sales: f64,
+}
+
+impl ParseOptions {
+    pub fn includes_month(&self, month: DateKey) -> bool {
+        self.months.as_ref().is_none_or(|months| months.contains(&month))
+    }
+
+    pub fn layout(&self, headers: &StringRecord) -> Result<schema::Layout, Box<dyn Error>> {
+        let mut schema = self.schema.clone();
+        let extra_columns = match &self.duplicate_key {
+            DuplicateKey::Column(name) => Some(name),
+            DuplicateKey::Exact => None,
+        };
+        let region_column = self.cube.as_ref().and_then(|cube| cube.region_column.as_ref());
+        let currency_column = self.currency.as_ref().map(|c| &c.column).or(self.currency_filter.as_ref().map(|f| &f.column));
+        for name in extra_columns.into_iter().chain(currency_column).chain(region_column).chain(&self.stage_column) {
+            if !schema.columns.iter().any(|c| c.name.eq_ignore_ascii_case(name)) {
+                schema.columns.push(schema::ColumnSpec::optional(name));
+            }
+        }
+        let mut layout = schema
+            .resolve(headers)?
+            .with_missing_values(self.missing.clone())
+            .with_number_format(self.number.clone())
+            .with_timezone(self.timezone)
+            .with_calendar(self.calendar.clone());
+
+        let key_indices = match &self.duplicate_key {
+            DuplicateKey::Exact => vec![layout.month_index, layout.product_index, layout.sales_index],
+            DuplicateKey::Column(name) => {
+                let index = headers
+                    .iter()
+                    .position(|h| h.eq_ignore_ascii_case(name))
+                    .ok_or_else(|| format!("Missing duplicate key column: {}", name))?;
+                vec![index]
+            }
+        };
+        if let Some(conversion) = &self.currency {
+            let index = headers
+                .iter()
+                .position(|h| h.eq_ignore_ascii_case(&conversion.column))
+                .ok_or_else(|| format!("Missing currency column: {}", conversion.column))?;
+            layout = layout.with_currency_column(index, &conversion.column);
+        }
+        Ok(layout.with_key_indices(key_indices))
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ParseReport {
+    pub rows: u64,
+    pub errors: Vec<RowError>,
+    pub missing_amounts: u64,
+    pub duplicates: u64,
+    pub amount_stats: quality::AmountStats,
+    pub suspicious: Vec<suspicious::SuspiciousRow>,
+    /// Sorted by month, product, and region; empty unless `ParseOptions::cube` is set
+    pub cube: Vec<CubeCell>,
+    /// Sales per day, in order; empty unless `ParseOptions::daily` is set
+    pub daily: Vec<(DateKey, f64)>,
+    /// Sales per funnel stage, the largest first; empty without a `ParseOptions::stage_column`
+    /// in the file
+    pub stages: Vec<(String, f64)>,
+    /// The total of every amount aggregated, added up exactly, with `ParseOptions::exact`
+    pub exact_total: Option<f64>,
+}
+
+impl ParseReport {
+    pub fn outliers(&self) -> u64 {
+        self.suspicious
+            .iter()
+            .filter(|row| matches!(row.reason, suspicious::Suspicion::Outlier { .. }))
+            .count() as u64
+    }
+}
+
+// Keys are the key fields of a row, each after its length so no two keys run together the
+// same way. They are saved as a list, since a JSON object's keys must be strings.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(from = "Vec<(Vec<u8>, u64)>", into = "Vec<(Vec<u8>, u64)>")]
+pub struct DuplicateTracker {
+    seen: HashMap<Vec<u8>, u64>,
+    key: Vec<u8>,
+}
+
+impl From<Vec<(Vec<u8>, u64)>> for DuplicateTracker {
+    fn from(seen: Vec<(Vec<u8>, u64)>) -> Self {
+        DuplicateTracker { seen: seen.into_iter().collect(), key: Vec::new() }
+    }
+}
+
+impl From<DuplicateTracker> for Vec<(Vec<u8>, u64)> {
+    fn from(tracker: DuplicateTracker) -> Self {
+        tracker.seen.into_iter().collect()
+    }
+}
+
+impl DuplicateTracker {
+    /// Returns the line of the first occurrence when `record` repeats an earlier key.
+    pub fn check(&mut self, layout: &schema::Layout, record: &ByteRecord, line: u64) -> Option<u64> {
+        self.key.clear();
+        for &index in layout.key_indices() {
+            let field = record.get(index).unwrap_or_default();
+            self.key.extend_from_slice(&(field.len() as u64).to_le_bytes());
+            self.key.extend_from_slice(field);
+        }
+        if let Some(first) = self.seen.get(self.key.as_slice()) {
+            return Some(*first);
+        }
+        self.seen.insert(self.key.clone(), line);
+        None
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RowError {
+    pub line: u64,
+    pub column: Option<String>,
+    pub raw: String,
+    pub reason: String,
+}
+
+impl RowError {
+    pub fn new(record: &ByteRecord, error: FieldError) -> Self {
+        RowError {
+            line: record.position().map_or(0, |pos| pos.line()),
+            column: error.column,
+            raw: raw_row(record),
+            reason: error.reason,
+        }
+    }
+}
+
+impl fmt::Display for RowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.column {
+            Some(column) => write!(f, "line {}, column {}: {}", self.line, column, self.reason),
+            None => write!(f, "line {}: {}", self.line, self.reason),
+        }
+    }
+}
+
+const LISTED_ERRORS: usize = 20;
+
+// Strict mode still reads the whole file so one run reports every bad row, not just the first.
+pub(crate) fn invalid_rows_error(errors: &[RowError]) -> Box<dyn Error> {
+    let mut message = format!("Found {} invalid rows:", errors.len());
+    for error in errors.iter().take(LISTED_ERRORS) {
+        message.push_str(&format!("\n  {}", error));
+    }
+    if errors.len() > LISTED_ERRORS {
+        message.push_str(&format!("\n  ... and {} more", errors.len() - LISTED_ERRORS));
+    }
+    message.push_str("\nUse --lenient to skip them and write them to an error report");
+    message.into()
+}
+
+pub fn date_to_key(date: &NaiveDate) -> DateKey {
+    date.num_days_from_ce()
+}
+
+pub fn key_to_date(key: DateKey) -> NaiveDate {
+    NaiveDate::from_num_days_from_ce_opt(key).expect("date key out of range")
+}
+
+pub fn missing_months(months: impl IntoIterator<Item = DateKey>) -> Vec<NaiveDate> {
+    let present: std::collections::BTreeSet<NaiveDate> = months.into_iter().map(key_to_date).collect();
+    let (Some(&first), Some(&last)) = (present.first(), present.last()) else {
+        return Vec::new();
+    };
+
+    let mut missing = Vec::new();
+    let mut month = first;
+    while month < last {
+        if !present.contains(&month) {
+            missing.push(month);
+        }
+        month = month + Months::new(1);
+    }
+    missing
+}
+
+pub fn validate_csv_structure(headers: &StringRecord) -> Result<(), Box<dyn Error>> {
+    if headers.len() != 3 {
+        return Err("Invalid column length".into());
+    }
+
+    let expected_headers = ["month", "product", "sales_amount"];
+    for &expected in &expected_headers {
+        if !headers.iter().any(|h| h.to_lowercase() == expected) {
+            return Err(format!("Missing column: {}", expected).into());
+        }
+    }
+
+    Ok(())
+}
+
+// Fast path for the canonical `YYYY-MM` layout; anything else goes through chrono.
+fn parse_month_fast(field: &[u8]) -> Option<DateKey> {
+    let digit = |b: u8| b.is_ascii_digit().then(|| (b - b'0') as i32);
+    match *field {
+        [y0, y1, y2, y3, b'-', m0, m1] => {
+            let year = digit(y0)? * 1000 + digit(y1)? * 100 + digit(y2)? * 10 + digit(y3)?;
+            let month = digit(m0)? * 10 + digit(m1)?;
+            NaiveDate::from_ymd_opt(year, month as u32, 1).map(|date| date_to_key(&date))
+        }
+        _ => None,
+    }
+}
+
+pub fn parse_month(field: &[u8]) -> Result<DateKey, String> {
+    if let Some(key) = parse_month_fast(field) {
+        return Ok(key);
+    }
+
+    let date_str = String::from_utf8_lossy(field);
+    let month = NaiveDate::parse_from_str(&format!("{}-01", date_str), "%Y-%m-%d")
+        .map_err(|e| format!("Invalid date format in \"{}\": {}", date_str, e))?;
+    Ok(date_to_key(&month))
+}
+
+pub fn parse_amount(field: &[u8]) -> Result<f64, String> {
+    lexical_core::parse(field)
+        .map_err(|e| format!("Invalid sales number in \"{}\": {}", String::from_utf8_lossy(field), e))
+        .and_then(|amount| finite_amount(amount, &String::from_utf8_lossy(field)))
+}
+
+// NaN and infinities parse as numbers but cannot be added up into a total.
+pub(crate) fn finite_amount(amount: f64, text: &str) -> Result<f64, String> {
+    if amount.is_finite() {
+        Ok(amount)
+    } else {
+        Err(format!("Invalid sales number in \"{}\": not a finite number", text))
+    }
+}
+
+pub(crate) fn raw_row(record: &ByteRecord) -> String {
+    let mut wtr = WriterBuilder::new().terminator(csv::Terminator::Any(b'\n')).from_writer(Vec::new());
+    let _ = wtr.write_byte_record(record);
+    let raw = wtr.into_inner().unwrap_or_default();
+    String::from_utf8_lossy(&raw).trim_end_matches('\n').to_string()
+}
+
+const PARSE_CHUNK: usize = 4096;
+
+// Products are keyed by slices borrowed from the record buffers, so a name is only
+// copied into a `String` once per distinct product rather than once per row.
+type Key<'a> = Cow<'a, [u8]>;
+
+#[derive(Default)]
+struct Partial<'a> {
+    sales_by_month: HashMap<DateKey, Total>,
+    sales_by_product: HashMap<Key<'a>, Total>,
+    errors: Vec<RowError>,
+    missing_amounts: u64,
+    amounts: quality::AmountStats,
+    values: Vec<(usize, DateKey, f64)>,
+    cube: HashMap<(DateKey, Key<'a>, Key<'a>), Total>,
+    sales_by_day: HashMap<DateKey, Total>,
+    sales_by_stage: HashMap<Key<'a>, Total>,
+    total: Total,
+}
+
+impl Partial<'_> {
+    // With its keys copied, to outlive the records they were borrowed from.
+    fn into_owned(self) -> Partial<'static> {
+        let owned = |key: Key| Cow::Owned(key.into_owned());
+        Partial {
+            sales_by_month: self.sales_by_month,
+            sales_by_product: self.sales_by_product.into_iter().map(|(product, sales)| (owned(product), sales)).collect(),
+            errors: self.errors,
+            missing_amounts: self.missing_amounts,
+            amounts: self.amounts,
+            values: self.values,
+            cube: self.cube.into_iter().map(|((month, product, region), sales)| ((month, owned(product), owned(region)), sales)).collect(),
+            sales_by_day: self.sales_by_day,
+            sales_by_stage: self.sales_by_stage.into_iter().map(|(stage, sales)| (owned(stage), sales)).collect(),
+            total: self.total,
+        }
+    }
+
+    fn merge(mut self, other: Self) -> Self {
+        for (k, v) in other.sales_by_month {
+            *self.sales_by_month.entry(k).or_default() += v;
+        }
+        for (k, v) in other.sales_by_product {
+            *self.sales_by_product.entry(k).or_default() += v;
+        }
+        self.errors.extend(other.errors);
+        self.missing_amounts += other.missing_amounts;
+        self.amounts = self.amounts.merge(other.amounts);
+        self.values.extend(other.values);
+        for (k, v) in other.cube {
+            *self.cube.entry(k).or_default() += v;
+        }
+        for (k, v) in other.sales_by_day {
+            *self.sales_by_day.entry(k).or_default() += v;
+        }
+        for (k, v) in other.sales_by_stage {
+            *self.sales_by_stage.entry(k).or_default() += v;
+        }
+        self.total += other.total;
+        self
+    }
+}
+
+// A running total of amounts, in floating point or, with `ParseOptions::exact`, in decimal.
+// Floating-point totals are compensated (Neumaier): `compensation` keeps the low-order digits
+// each addition to `sum` rounds away, so millions of rows add up without visible error.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+enum Total {
+    Float { sum: f64, compensation: f64 },
+    Exact(Decimal),
+}
+
+impl Default for Total {
+    fn default() -> Self {
+        Total::Float { sum: 0.0, compensation: 0.0 }
+    }
+}
+
+impl Total {
+    // One row's amount. Exactly, it is read back from the shortest digits that give `sales`,
+    // which are the file's own for amounts of up to 15 significant digits.
+    fn of(sales: f64, exact: bool) -> Result<Total, String> {
+        if !exact {
+            return Ok(Total::Float { sum: sales, compensation: 0.0 });
+        }
+        Decimal::from_str(&sales.to_string()).map(Total::Exact).map_err(|_| format!("The amount {} is too large to add up exactly", sales))
+    }
+
+    // Through the digits of an exact total, which gives the f64 nearest to it.
+    fn value(self) -> f64 {
+        match self {
+            Total::Float { sum, compensation } => sum + compensation,
+            Total::Exact(value) => value.to_string().parse().unwrap_or_default(),
+        }
+    }
+}
+
+impl AddAssign for Total {
+    fn add_assign(&mut self, other: Total) {
+        *self = match (*self, other) {
+            (Total::Float { sum, compensation }, Total::Float { sum: other, compensation: other_compensation }) => {
+                let total = sum + other;
+                // Past f64::MAX nothing is left to compensate, and inf - inf would make it NaN.
+                let lost = if !total.is_finite() {
+                    0.0
+                } else if sum.abs() >= other.abs() {
+                    (sum - total) + other
+                } else {
+                    (other - total) + sum
+                };
+                Total::Float { sum: total, compensation: compensation + other_compensation + lost }
+            }
+            (Total::Exact(a), Total::Exact(b)) => match a.checked_add(b) {
+                Some(total) => Total::Exact(total),
+                None => {
+                    warn!("A total of {} and {} is too large to add up exactly, adding it up in floating point", a, b);
+                    Total::Float { sum: self.value() + other.value(), compensation: 0.0 }
+                }
+            },
+            // A fresh total taking its first exact amount, or an exact one adding a chunk of no
+            // amounts.
+            (Total::Float { sum: 0.0, compensation: 0.0 }, Total::Exact(b)) => Total::Exact(b),
+            (Total::Exact(a), Total::Float { sum: 0.0, compensation: 0.0 }) => Total::Exact(a),
+            (a, b) => Total::Float { sum: a.value() + b.value(), compensation: 0.0 },
+        }
+    }
+}
+
+// The rows `script` keeps, as it changed them, in order; rows it fails on go into `errors`.
+#[cfg(feature = "scripting")]
+fn transform_records(script: &script::Script, headers: &StringRecord, records: Vec<ByteRecord>, errors: &mut Vec<RowError>) -> Vec<ByteRecord> {
+    let _span = debug_span!("transform", records = records.len()).entered();
+    #[cfg(feature = "parallel")]
+    let results: Vec<_> = records.par_iter().map(|record| script.transform(headers, record)).collect();
+    #[cfg(not(feature = "parallel"))]
+    let results: Vec<_> = records.iter().map(|record| script.transform(headers, record)).collect();
+    let mut kept = Vec::with_capacity(records.len());
+    for (record, result) in records.iter().zip(results) {
+        match result {
+            Ok(changed) => kept.extend(changed),
+            Err(reason) => errors.push(RowError { line: record.position().map_or(0, |pos| pos.line()), column: None, raw: raw_row(record), reason }),
+        }
+    }
+    kept
+}
+
+// `filter` is already lowercase.
+fn product_matches(product: &[u8], filter: &str) -> bool {
+    String::from_utf8_lossy(product).to_lowercase().contains(filter)
+}
+
+pub fn process_sales_data<R: Read>(
+    rdr: &mut csv::Reader<R>,
+    options: &ParseOptions,
+) -> Result<(SalesTotals, ParseReport), Box<dyn Error>> {
+    process_sales_source(rdr, options)
+}
+
+/// `process_sales_data` for the rows of any `SalesSource`, such as JSON Lines.
+pub fn process_sales_source<S: SalesSource + ?Sized>(source: &mut S, options: &ParseOptions) -> Result<(SalesTotals, ParseReport), Box<dyn Error>> {
+    let headers = source.headers()?;
+    process_records(&headers, source::records(source), options)
+}
+
+/// `process_sales_data` for the CSV file at `path`, read with a header row and rows of any
+/// length. The file is split into ranges of whole rows parsed on separate threads, rather than
+/// read a row at a time, and the rows are then totalled as if read in one pass.
+pub fn process_sales_file(path: &Path, options: &ParseOptions) -> Result<(SalesTotals, ParseReport), Box<dyn Error>> {
+    let (headers, records) = split::read_records(path)?;
+    process_records(&headers, records.into_iter().map(Ok), options)
+}
+
+// The columns read besides those of the layout, found once in the headers.
+struct Columns<'a> {
+    region: Option<usize>,
+    stage: Option<usize>,
+    currency_filter: Option<(&'a fx::CurrencyFilter, usize)>,
+}
+
+impl<'a> Columns<'a> {
+    fn new(headers: &StringRecord, options: &'a ParseOptions) -> Result<Self, Box<dyn Error>> {
+        let region = match options.cube.as_ref().and_then(|cube| cube.region_column.as_ref()) {
+            Some(name) => Some(
+                headers
+                    .iter()
+                    .position(|h| h.eq_ignore_ascii_case(name))
+                    .ok_or_else(|| format!("Missing region column: {}", name))?,
+            ),
+            None => None,
+        };
+        let stage = options.stage_column.as_ref().and_then(|name| headers.iter().position(|h| h.eq_ignore_ascii_case(name)));
+        let currency_filter = match &options.currency_filter {
+            Some(filter) => Some((filter, filter.index(headers)?)),
+            None => None,
+        };
+        Ok(Columns { region, stage, currency_filter })
+    }
+}
+
+// The rows read so far: how many, how many repeated an earlier key, and which were malformed.
+// Without a tracker duplicates are not looked for.
+struct Ingest {
+    rows: u64,
+    duplicates: u64,
+    tracker: Option<DuplicateTracker>,
+    errors: Vec<RowError>,
+}
+
+impl Ingest {
+    // The record of `result` when it is to be totalled: in the currency filtered for, and not a
+    // duplicate left out by `ParseOptions::dedupe`. Errors other than a row's length end the run.
+    fn take(&mut self, result: csv::Result<ByteRecord>, layout: &schema::Layout, columns: &Columns, options: &ParseOptions) -> Result<Option<ByteRecord>, String> {
+        match result {
+            Ok(record) => {
+                if columns.currency_filter.is_some_and(|(filter, index)| !record.get(index).is_some_and(|code| filter.matches(code))) {
+                    return Ok(None);
+                }
+                self.rows += 1;
+                let line = record.position().map_or(0, |pos| pos.line());
+                if self.tracker.as_mut().is_some_and(|tracker| tracker.check(layout, &record, line).is_some()) {
+                    self.duplicates += 1;
+                    if options.dedupe {
+                        return Ok(None);
+                    }
+                }
+                Ok(Some(record))
+            }
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
+
+// The totals of one chunk of records, the `chunk`th of PARSE_CHUNK records.
+fn aggregate_chunk<'a>(layout: &schema::Layout, columns: &Columns, options: &ParseOptions, product_filter: Option<&str>, chunk: usize, records: &'a [ByteRecord]) -> Partial<'a> {
+    let mut partial = Partial::default();
+    for (offset, record) in records.iter().enumerate() {
+        let row = match layout.parse_row(record) {
+            Ok(row) => row,
+            Err(error) => {
+                partial.errors.push(RowError::new(record, error));
+                continue;
+            }
+        };
+
+        partial.missing_amounts += u64::from(row.missing_amount);
+        let Some((month, sales)) = row.values.filter(|(month, _)| options.includes_month(*month)) else {
+            continue;
+        };
+        let product = &record[layout.product_index];
+        if !product_filter.is_none_or(|filter| product_matches(product, filter)) {
+            continue;
+        }
+        let amount = match Total::of(sales, options.exact) {
+            Ok(amount) => amount,
+            Err(reason) => {
+                partial.errors.push(RowError::new(record, FieldError { column: None, reason }));
+                continue;
+            }
+        };
+        if !row.missing_amount {
+            partial.amounts.push(sales);
+            partial.values.push((chunk * PARSE_CHUNK + offset, month, sales));
+        }
+        *partial.sales_by_month.entry(month).or_default() += amount;
+        *partial.sales_by_product.entry(Cow::Borrowed(product)).or_default() += amount;
+        partial.total += amount;
+        if options.cube.is_some() {
+            let region = columns.region.and_then(|index| record.get(index)).unwrap_or_default();
+            *partial.cube.entry((month, Cow::Borrowed(product), Cow::Borrowed(region))).or_default() += amount;
+        }
+        if let Some(day) = row.day.filter(|_| options.daily) {
+            *partial.sales_by_day.entry(day).or_default() += amount;
+        }
+        if let Some(stage) = columns.stage.and_then(|index| record.get(index)).map(<[u8]>::trim_ascii).filter(|stage| !stage.is_empty()) {
+            *partial.sales_by_stage.entry(Cow::Borrowed(stage)).or_default() += amount;
+        }
+    }
+    trace!(chunk, records = records.len(), errors = partial.errors.len(), "parsed chunk");
+    partial
+}
+
+fn process_records<I: Iterator<Item = csv::Result<ByteRecord>>>(
+    headers: &StringRecord,
+    results: I,
+    options: &ParseOptions,
+) -> Result<(SalesTotals, ParseReport), Box<dyn Error>> {
+    let layout = options.layout(headers)?;
+    let columns = Columns::new(headers, options)?;
+
+    let ingest_span = debug_span!("ingest").entered();
+    let mut ingest = Ingest { rows: 0, duplicates: 0, tracker: Some(DuplicateTracker::default()), errors: Vec::new() };
+    let mut records = Vec::new();
+    for result in results {
+        records.extend(ingest.take(result, &layout, &columns, options)?);
+    }
+    #[cfg(feature = "scripting")]
+    if let Some(script) = options.script.as_ref().filter(|script| script.transforms()) {
+        records = transform_records(script, headers, records, &mut ingest.errors);
+    }
+
+    let order = date_order::resolve_date_order(
+        options.date_order,
+        records.iter().filter_map(|record| record.get(layout.month_index)),
+    )?;
+    let mut layout = layout.with_date_order(order);
+    if let Some(conversion) = &options.currency {
+        let rates = conversion.rates(&layout.currency_months(&records))?;
+        layout = layout.with_exchange_rates(rates);
+    }
+    debug!(rows = ingest.rows, duplicates = ingest.duplicates, malformed = ingest.errors.len(), "read records");
+    drop(ingest_span);
+
+    let _aggregate = debug_span!("aggregate", records = records.len()).entered();
+    let product_filter = options.product_filter.as_ref().map(|filter| filter.to_lowercase());
+    // Fixed-size chunks merged in file order keep the floating-point summation order, and so
+    // the totals and the rendered charts, identical from run to run whatever the thread count.
+    #[cfg(feature = "parallel")]
+    let chunks = records.par_chunks(PARSE_CHUNK);
+    #[cfg(not(feature = "parallel"))]
+    let chunks = records.chunks(PARSE_CHUNK);
+    let partial = chunks
+        .enumerate()
+        .map(|(chunk, records)| aggregate_chunk(&layout, &columns, options, product_filter.as_deref(), chunk, records))
+        .collect::<Vec<_>>()
+        .into_iter()
+        .fold(Partial::default(), Partial::merge);
+
+    let suspicious = suspicious::flag_rows(&partial.values, &records, &partial.amounts, &options.suspicion);
+    finish(partial, ingest, suspicious, options)
+}
+
+// The totals and report of a run from the merged totals of all its chunks.
+fn finish(partial: Partial, ingest: Ingest, suspicious: Vec<suspicious::SuspiciousRow>, options: &ParseOptions) -> Result<(SalesTotals, ParseReport), Box<dyn Error>> {
+    let mut errors = ingest.errors;
+    errors.extend(partial.errors);
+    errors.sort_by_key(|e| e.line);
+    if !options.lenient && !errors.is_empty() {
+        return Err(invalid_rows_error(&errors));
+    }
+
+    let sales_by_product = partial
+        .sales_by_product
+        .into_iter()
+        .map(|(product, sales)| {
+            let product = std::str::from_utf8(&product)
+                .map_err(|e| format!("Invalid UTF-8 in product \"{}\": {}", String::from_utf8_lossy(&product), e))?;
+            Ok((product.to_string(), sales.value()))
+        })
+        .collect::<Result<HashMap<String, f64>, String>>()?;
+
+    let mut cube: Vec<CubeCell> = partial
+        .cube
+        .into_iter()
+        .map(|((month, product, region), sales)| CubeCell {
+            month,
+            product: String::from_utf8_lossy(&product).into_owned(),
+            region: String::from_utf8_lossy(&region).trim().to_string(),
+            sales: sales.value(),
+        })
+        .collect();
+    cube.sort_by(|a, b| (a.month, &a.product, &a.region).cmp(&(b.month, &b.product, &b.region)));
+
+    let mut stages: Vec<(String, f64)> =
+        partial.sales_by_stage.into_iter().map(|(stage, sales)| (String::from_utf8_lossy(&stage).into_owned(), sales.value())).collect();
+    stages.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    let mut daily: Vec<(DateKey, f64)> = partial.sales_by_day.into_iter().map(|(day, sales)| (day, sales.value())).collect();
+    daily.sort_by_key(|(day, _)| *day);
+
+    let report = ParseReport {
+        rows: ingest.rows,
+        errors,
+        missing_amounts: partial.missing_amounts,
+        duplicates: ingest.duplicates,
+        amount_stats: partial.amounts,
+        suspicious,
+        cube,
+        daily,
+        stages,
+        exact_total: options.exact.then(|| partial.total.value()),
+    };
+    let sales_by_month: HashMap<DateKey, f64> = partial.sales_by_month.into_iter().map(|(month, sales)| (month, sales.value())).collect();
+
+    let totals = sales_by_month.values().chain(sales_by_product.values()).chain(report.cube.iter().map(|cell| &cell.sales)).chain(report.daily.iter().map(|(_, sales)| sales));
+    if totals.chain(report.stages.iter().map(|(_, sales)| sales)).any(|sales| !sales.is_finite()) {
+        return Err("The sales add up to more than a total can hold".into());
+    }
+    Ok(((sales_by_month, sales_by_product), report))
+}
+
+pub fn write_error_report<P: AsRef<Path>>(path: P, errors: &[RowError]) -> Result<(), Box<dyn Error>> {
+    let mut wtr = csv::Writer::from_path(path)?;
+    wtr.write_record(["line", "column", "raw_row", "reason"])?;
+    for error in errors {
+        let column = error.column.as_deref().unwrap_or_default();
+        wtr.write_record([error.line.to_string().as_str(), column, &error.raw, &error.reason])?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+pub fn prepare_data_for_plotting(sales_by_month: HashMap<DateKey, f64>, sales_by_product: HashMap<String, f64>) -> PlotData {
+    let by_sales = |a: &(String, f64), b: &(String, f64)| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0));
+    let mut product_data: Vec<(String, f64)> = sales_by_product.into_iter().collect();
+    #[cfg(feature = "parallel")]
+    let monthly_data = {
+        let mut monthly_data: Vec<(NaiveDate, f64)> = sales_by_month.into_par_iter().map(|(k, v)| (key_to_date(k), v)).collect();
+        monthly_data.par_sort_unstable_by_key(|&(date, _)| date);
+        product_data.par_sort_unstable_by(by_sales);
+        monthly_data
+    };
+    #[cfg(not(feature = "parallel"))]
+    let monthly_data = {
+        let mut monthly_data: Vec<(NaiveDate, f64)> = sales_by_month.into_iter().map(|(k, v)| (key_to_date(k), v)).collect();
+        monthly_data.sort_unstable_by_key(|&(date, _)| date);
+        product_data.sort_unstable_by(by_sales);
+        monthly_data
+    };
+
+    (monthly_data, product_data)
+}
+
+/// Sales totalled by month, in date order, and by product, best-selling first: what the charts
+/// are drawn from.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SalesDataset {
+    pub monthly: Vec<(NaiveDate, f64)>,
+    pub products: Vec<(String, f64)>,
+}
+
+impl SalesDataset {
+    pub fn new((sales_by_month, sales_by_product): SalesTotals) -> Self {
+        prepare_data_for_plotting(sales_by_month, sales_by_product).into()
+    }
+
+    pub fn total_sales(&self) -> f64 {
+        self.monthly.iter().fold(0.0, |total, (_, sales)| total + sales)
+    }
+
+    /// The totals as the chart functions that take `PlotData` want them.
+    pub fn plot_data(&self) -> PlotData {
+        (self.monthly.clone(), self.products.clone())
+    }
+
+    /// The totals with months written as `%Y-%m`, ready to serialize.
+    pub fn aggregates(&self) -> summary::Aggregates {
+        summary::Aggregates::new(&self.plot_data())
+    }
+}
+
+impl From<PlotData> for SalesDataset {
+    fn from((monthly, products): PlotData) -> Self {
+        SalesDataset { monthly, products }
+    }
+}
+
+/// The dataset of a sales CSV, and what reading its rows found: how many there were, which were
+/// invalid or duplicated, and any breakdowns the `ParseOptions` asked for.
+#[derive(Clone, Debug)]
+pub struct SalesReport {
+    pub dataset: SalesDataset,
+    pub parse: ParseReport,
+}
+
+impl SalesReport {
+    /// Reads a sales CSV with a header row from `reader` with the default options, so that an
+    /// invalid row fails it.
+    pub fn from_csv<R: Read>(reader: R) -> Result<Self, Box<dyn Error>> {
+        SalesReport::from_csv_with(reader, &ParseOptions::default())
+    }
+
+    /// Reads a sales CSV with a header row and rows of any length from `reader`.
+    pub fn from_csv_with<R: Read>(reader: R, options: &ParseOptions) -> Result<Self, Box<dyn Error>> {
+        let mut rdr = csv::ReaderBuilder::new().has_headers(true).flexible(true).from_reader(reader);
+        let (totals, parse) = process_sales_data(&mut rdr, options)?;
+        Ok(SalesReport { dataset: SalesDataset::new(totals), parse })
+    }
+
+    /// Reads the rows of `source`, such as a `source::JsonSource`.
+    pub fn from_source<S: SalesSource + ?Sized>(source: &mut S, options: &ParseOptions) -> Result<Self, Box<dyn Error>> {
+        let (totals, parse) = process_sales_source(source, options)?;
+        Ok(SalesReport { dataset: SalesDataset::new(totals), parse })
+    }
+
+    /// Reads the sales CSV at `path`, on several threads as `process_sales_file` does.
+    pub fn from_path<P: AsRef<Path>>(path: P, options: &ParseOptions) -> Result<Self, Box<dyn Error>> {
+        let (totals, parse) = process_sales_file(path.as_ref(), options)?;
+        Ok(SalesReport { dataset: SalesDataset::new(totals), parse })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn float_total(amounts: &[f64]) -> f64 {
+        let mut total = Total::default();
+        for &amount in amounts {
+            total += Total::of(amount, false).unwrap();
+        }
+        total.value()
+    }
+
+    fn run(csv: &str, options: &ParseOptions) -> Result<(SalesTotals, ParseReport), Box<dyn Error>> {
+        process_sales_data(&mut csv::Reader::from_reader(csv.as_bytes()), options)
+    }
+
+    #[test]
+    fn compensation_keeps_what_plain_addition_rounds_away() {
+        let amounts = [1e16, 1.0, 1.0, 1.0, 1.0];
+        assert_eq!(amounts.iter().sum::<f64>(), 1e16);
+        assert_eq!(float_total(&amounts), 1e16 + 4.0);
+    }
+
+    #[test]
+    fn compensation_survives_a_larger_amount_cancelling_out() {
+        // Kahan summation gives 0 here; Neumaier's keeps the ones whichever side is larger.
+        assert_eq!(float_total(&[1.0, 1e100, 1.0, -1e100]), 2.0);
+    }
+
+    #[test]
+    fn tenths_add_up_to_the_nearest_f64() {
+        assert_eq!(float_total(&[0.1; 10]), 1.0);
+        assert_ne!([0.1; 10].iter().sum::<f64>(), 1.0);
+    }
+
+    #[test]
+    fn chunks_merge_like_rows() {
+        let (mut left, mut right) = (Total::default(), Total::default());
+        for amount in [1e16, 1.0, 1.0] {
+            left += Total::of(amount, false).unwrap();
+        }
+        for amount in [1.0, 1.0, -1e16] {
+            right += Total::of(amount, false).unwrap();
+        }
+        left += right;
+        assert_eq!(left.value(), 4.0);
+    }
+
+    #[test]
+    fn an_overflowing_total_is_infinite_not_nan() {
+        assert_eq!(float_total(&[f64::MAX, f64::MAX]), f64::INFINITY);
+    }
+
+    #[test]
+    fn totals_past_f64_max_are_an_error() {
+        let csv = "month,product,sales_amount\n2023-01,A,1e308\n2023-01,A,1e308\n";
+        let error = run(csv, &ParseOptions::default()).unwrap_err().to_string();
+        assert_eq!(error, "The sales add up to more than a total can hold");
+    }
+
+    fn sales_of(totals: &SalesTotals, product: &str) -> f64 {
+        totals.1[product]
+    }
+
+    #[test]
+    fn exact_repeats_are_counted_and_kept_without_dedupe() {
+        let csv = "month,product,sales_amount\n2023-01,A,10\n2023-01,A,10\n2023-01,A,15\n";
+        let (totals, report) = run(csv, &ParseOptions::default()).unwrap();
+        assert_eq!(report.duplicates, 1);
+        assert_eq!(sales_of(&totals, "A"), 35.0);
+    }
+
+    #[test]
+    fn dedupe_leaves_repeats_out_of_the_totals() {
+        let csv = "month,product,sales_amount\n2023-01,A,10\n2023-01,A,10\n2023-02,A,10\n";
+        let (totals, report) = run(csv, &ParseOptions { dedupe: true, ..ParseOptions::default() }).unwrap();
+        assert_eq!(report.duplicates, 1);
+        assert_eq!(sales_of(&totals, "A"), 20.0);
+    }
+
+    #[test]
+    fn keys_are_compared_field_by_field() {
+        // Run together, both rows would read 2023-01A110.
+        let csv = "month,product,sales_amount\n2023-01,A1,10\n2023-01,A,110\n";
+        let (totals, report) = run(csv, &ParseOptions { dedupe: true, ..ParseOptions::default() }).unwrap();
+        assert_eq!(report.duplicates, 0);
+        assert_eq!(sales_of(&totals, "A1") + sales_of(&totals, "A"), 120.0);
+    }
+
+    #[test]
+    fn a_key_column_finds_repeats_whatever_the_other_fields() {
+        let csv = "id,month,product,sales_amount\nT1,2023-01,A,10\nT2,2023-01,A,10\nT1,2023-02,B,99\n";
+        let options = ParseOptions { duplicate_key: DuplicateKey::Column("id".to_string()), ..ParseOptions::default() };
+        let (totals, report) = run(csv, &options).unwrap();
+        assert_eq!(report.duplicates, 1);
+        assert_eq!((sales_of(&totals, "A"), sales_of(&totals, "B")), (20.0, 99.0));
+
+        let (totals, report) = run(csv, &ParseOptions { dedupe: true, ..options }).unwrap();
+        assert_eq!(report.duplicates, 1);
+        assert_eq!(sales_of(&totals, "A"), 20.0);
+        assert!(!totals.1.contains_key("B"));
+    }
+
+    #[test]
+    fn products_sort_by_sales_even_when_one_is_nan() {
+        let products = HashMap::from([("A".to_string(), 1.0), ("B".to_string(), f64::NAN), ("C".to_string(), 3.0)]);
+        let (_, products) = prepare_data_for_plotting(HashMap::new(), products);
+        let names: Vec<&str> = products.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["B", "C", "A"]);
+    }
+}
@@ -0,0 +1,60 @@
+use chrono::{Locale, NaiveDate};
+
+// Languages whose main region is not the language code in capitals, for tags like `ja`.
+const DEFAULT_REGIONS: &[(&str, &str)] = &[
+    ("ar", "SA"),
+    ("cs", "CZ"),
+    ("da", "DK"),
+    ("el", "GR"),
+    ("en", "US"),
+    ("fa", "IR"),
+    ("he", "IL"),
+    ("hi", "IN"),
+    ("ja", "JP"),
+    ("ko", "KR"),
+    ("nb", "NO"),
+    ("no", "NO"),
+    ("sl", "SI"),
+    ("sv", "SE"),
+    ("uk", "UA"),
+    ("ur", "PK"),
+    ("zh", "CN"),
+];
+
+/// The language month names, weekday names, and dates are written in on charts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DateLocale(pub Locale);
+
+impl DateLocale {
+    /// Looks up a locale by a BCP 47-ish tag such as `de`, `fr-CA`, or `pt_BR`; a bare
+    /// language picks its main region.
+    pub fn from_tag(tag: &str) -> Result<Self, String> {
+        let mut parts = tag.split(['-', '_']);
+        let language = parts.next().unwrap_or_default().to_lowercase();
+        let region = match parts.next() {
+            Some(region) => region.to_uppercase(),
+            None => DEFAULT_REGIONS
+                .iter()
+                .find(|(code, _)| *code == language)
+                .map_or_else(|| language.to_uppercase(), |(_, region)| region.to_string()),
+        };
+        let name = if language == "no" { format!("nb_{}", region) } else { format!("{}_{}", language, region) };
+        Locale::try_from(name.as_str()).map(DateLocale).map_err(|_| format!("Unsupported locale: {}", tag))
+    }
+
+    /// `date` formatted with chrono's `strftime` syntax, with `%B`, `%b`, `%A`, `%a`, `%x`
+    /// and the like in this language.
+    pub fn format(&self, date: NaiveDate, pattern: &str) -> String {
+        date.format_localized(pattern, self.0).to_string()
+    }
+
+    /// Whether the language is written right to left, like Arabic or Hebrew.
+    pub fn is_rtl(&self) -> bool {
+        crate::bidi::is_rtl_language(&self.0.to_string())
+    }
+
+    /// A month as an axis label, e.g. `janv. 2024` in French.
+    pub fn month_label(&self, month: NaiveDate) -> String {
+        self.format(month, "%b %Y").trim().to_string()
+    }
+}
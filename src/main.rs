@@ -1,233 +1,2320 @@
 use std::collections::HashMap;
 use std::error::Error;
+use std::fmt::Debug;
 use std::fs::File;
-use std::sync::Arc;
-use chrono::NaiveDate;
-use csv::{ReaderBuilder, StringRecord};
-use plotters::prelude::*;
-use rayon::prelude::*;
+use std::io::{self, BufRead, BufReader, BufWriter, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Mutex, PoisonError};
+#[cfg(feature = "scripting")]
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use chrono::{Local, NaiveDate};
+use chrono_tz::Tz;
+use clap::{ArgAction, Args, Parser, Subcommand, ValueEnum};
+use croner::Cron;
+use csv::ReaderBuilder;
+use notify::{RecursiveMode, Watcher};
+use tracing::{debug, debug_span, error, info, warn, Level};
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::fmt::time::Uptime;
+use tracing_subscriber::prelude::*;
+use sales_chart::charts::{
+    create_bar_chart, create_budget_chart, create_inflation_chart, create_line_chart, create_pie_chart, prior_year, render_comparison_charts,
+    create_bump_chart, create_choropleth, create_executive_summary, create_facet_chart, create_funnel_chart, create_kpi_card, create_layout_chart,
+    create_product_chart, create_scenario_chart, create_stacked_bar_chart, create_streamgraph, create_variance_chart, kpi_cards,
+    parse_chart_decimals, ChartLabels, MAX_BUMP,
+};
+use sales_chart::chart_cache::ChartCache;
+use sales_chart::checkpoint::process_with_checkpoints;
+use sales_chart::chunked::process_sales_chunked;
+use sales_chart::choropleth::RegionMap;
+use sales_chart::compare::Comparison;
+use sales_chart::alert::{check_alerts, AlertRule};
+use sales_chart::budget::{BreakEven, Budget};
+use sales_chart::date_order::DateOrder;
+use sales_chart::fx::{currencies, currency_symbol, CurrencyConversion, CurrencyFilter, RateSource};
+use sales_chart::generate::{generate, parse_count, GeneratorConfig};
+use sales_chart::holidays::{holiday_averages, Holiday, HolidayAverages, HolidayCalendar};
+use sales_chart::image_diff::diff_images;
+use sales_chart::layout::Layout;
+use sales_chart::inflation::{parse_base_month, PriceIndex};
+use sales_chart::locale::DateLocale;
+use sales_chart::narrative::narrative;
+use sales_chart::number::{parse_decimals, DigitGrouping, MoneyFormat, NumberFormat, NumberLocale, Rounding, SymbolPosition};
+use sales_chart::dashboard::write_dashboard;
+use sales_chart::products::{
+    fuzzy_merge, near_duplicates, normalize_products, Grouping, ProductAliases, ProductCategories, ProductMerge, UNCATEGORIZED,
+};
+use sales_chart::quality::QualityReport;
+use sales_chart::report::{write_comparison_report, write_html_report};
+use sales_chart::retail::RetailCalendar;
+use sales_chart::scenario::Scenario;
+use sales_chart::schema::Schema;
+use sales_chart::source::{open_source, InputFormat, SalesSource};
+#[cfg(feature = "scripting")]
+use sales_chart::script::Script;
+use sales_chart::translations::Translations;
+use sales_chart::stream::RunningTotals;
+use sales_chart::summary::{facets, funnel, product_trends, Facet, FunnelStage, RunSummary};
+use sales_chart::suspicious::{write_suspicious_report, SuspicionRules};
+use sales_chart::timings::{take_stage_timings, StageTimings};
+use sales_chart::upload::Destination;
+use sales_chart::validate::validate_sales_data;
+use sales_chart::vega;
+use sales_chart::verify::compare_outputs;
+#[cfg(any(feature = "gui", feature = "tui"))]
+use sales_chart::date_to_key;
+use sales_chart::{
+    key_to_date, prepare_data_for_plotting, process_sales_file, process_sales_source, write_error_report, CubeCell, CubeSpec, DuplicateKey, MissingValuePolicy,
+    MissingValues, ParseOptions, ParseReport, PlotData, SalesTotals,
+};
 
-type DateKey = i32;
+const INPUT_PATH: &str = "large_sales_data.csv";
+const ERROR_REPORT_PATH: &str = "errors.csv";
+// Spreadsheet saves arrive as a burst of events; wait for them to settle before re-running.
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(500);
+// How long --follow sleeps at the end of the file before looking for appended rows.
+const FOLLOW_POLL: Duration = Duration::from_millis(250);
 
-fn date_to_key(date: &NaiveDate) -> DateKey {
-    date.num_days_from_ce()
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Engine {
+    /// Built-in CSV reader with rayon folds
+    Native,
+    /// Embedded DuckDB, grouping and filtering in SQL
+    Duckdb,
+    /// Polars lazy frames for ingestion, filtering, and group-bys
+    Polars,
 }
 
-fn key_to_date(key: DateKey) -> NaiveDate {
-    NaiveDate::from_num_days_from_ce(key)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum GroupBy {
+    Product,
+    Category,
+    Brand,
 }
 
-fn validate_csv_structure(headers: &StringRecord) -> Result<(), Box<dyn Error>> {
-    if headers.len() != 3 {
-        return Err("Invalid column length".into());
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum AlertAction {
+    /// Exit with a nonzero status once the run is delivered
+    Fail,
+    /// Only log the alerts and pass them on to the notifications
+    Notify,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// PNG images
+    Png,
+    /// SVG images, which stay sharp at any size in web pages and PDFs
+    Svg,
+    /// Vega-Lite JSON specs with the data inlined, to embed in Observable, Kibana, or a web page
+    Vega,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum BasicChart {
+    /// Monthly sales, line_chart.png
+    Line,
+    /// Sales by product, bar_chart.png
+    Bar,
+    /// Each product's share of the sales, pie_chart.png
+    Pie,
+}
+
+impl BasicChart {
+    fn file_name(self) -> &'static str {
+        match self {
+            BasicChart::Line => "line_chart.png",
+            BasicChart::Bar => "bar_chart.png",
+            BasicChart::Pie => "pie_chart.png",
+        }
     }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum MissingAmount {
+    /// Fail on the first missing amount
+    Error,
+    /// Drop rows whose amount is missing
+    Skip,
+    /// Count missing amounts as zero sales
+    Zero,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum DateOrderArg {
+    /// Infer day/month order from the whole column, failing if it cannot be decided
+    Auto,
+    /// Day first, as in 31/01/2024
+    Dmy,
+    /// Month first, as in 01/31/2024
+    Mdy,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum InputFormatArg {
+    /// Comma-separated, with a header row
+    Csv,
+    /// A JSON array of objects, one per row
+    Json,
+    /// A JSON object on each line (JSON Lines)
+    #[value(alias = "jsonl")]
+    Ndjson,
+    /// An Excel workbook, read from its first sheet or the --sheet
+    Xlsx,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum SymbolPositionArg {
+    /// Before the amount, as in $1234.50
+    Prefix,
+    /// After the amount, as in 1234,50 €
+    Suffix,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum DigitGroupingArg {
+    /// Every three digits, as in 12,345,678
+    Thousands,
+    /// Lakh and crore, as in 1,23,45,678, with value axes labelled in L and Cr
+    Indian,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum RoundingArg {
+    /// To the nearest, ties to the even digit, as in 0.125 to 0.12
+    HalfEven,
+    /// To the nearest, ties away from zero, as in 0.125 to 0.13
+    HalfUp,
+    /// Away from zero, as in 0.121 to 0.13
+    Up,
+    /// Toward zero, as in 0.129 to 0.12
+    Down,
+}
+
+#[derive(Args)]
+struct InputArgs {
+    /// Format of the input, by default going by its extension: json for .json, ndjson for .ndjson and .jsonl, xlsx for
+    /// .xlsx, .xlsm, .xlsb, .xls, and .ods, and csv otherwise
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    input_format: Option<InputFormatArg>,
+
+    /// Sheet of an Excel input to read, instead of its first (needs the `excel` feature)
+    #[arg(long, value_name = "NAME")]
+    sheet: Option<String>,
+
+    /// TOML schema describing expected columns, types, date formats, and value ranges
+    #[arg(long, value_name = "FILE")]
+    schema: Option<PathBuf>,
+
+    /// How empty or sentinel values in the amount column are treated
+    #[arg(long, value_enum, default_value_t = MissingAmount::Error)]
+    missing_amount: MissingAmount,
+
+    /// Comma-separated amount values treated as missing, in addition to empty cells
+    #[arg(long, value_delimiter = ',', default_value = "N/A,-")]
+    null_values: Vec<String>,
+
+    /// Locale whose decimal and grouping separators amounts are written in, e.g. de for 1.234,56
+    #[arg(long, value_name = "LOCALE", value_parser = NumberLocale::from_tag)]
+    number_locale: Option<NumberLocale>,
+
+    /// Day/month order of dates written like 03/04/2024
+    #[arg(long, value_enum, default_value_t = DateOrderArg::Auto)]
+    date_order: DateOrderArg,
+
+    /// Reporting timezone, e.g. America/New_York: timestamps with an offset fall in the month of their local date there
+    #[arg(long, value_name = "ZONE", default_value = "UTC")]
+    timezone: Tz,
 
-    let expected_headers = ["month", "product", "sales_amount"];
-    for &expected in &expected_headers {
-        if !headers.iter().any(|h| h.to_lowercase() == expected) {
-            return Err(format!("Missing column: {}", expected).into());
+    /// TOML file defining a 4-4-5, 4-5-4, or 5-4-4 retail calendar to total sales by fiscal period instead of by month
+    #[arg(long, value_name = "FILE")]
+    retail_calendar: Option<PathBuf>,
+
+    /// Strip currency symbols ($, €, £, ¥), thousands separators, and whitespace from amounts
+    #[arg(long)]
+    clean_numbers: bool,
+
+    /// Flag amounts further than this many standard deviations from the mean as suspicious
+    #[arg(long, value_name = "N", default_value_t = 3.0)]
+    outlier_std_devs: f64,
+
+    /// Column identifying a row (e.g. transaction_id) for duplicate detection, instead of month+product+amount
+    #[arg(long, value_name = "COLUMN")]
+    duplicate_key: Option<String>,
+
+    /// Column holding each row's sales funnel stage (e.g. lead, quote, closed); files that have it also get a funnel chart
+    #[arg(long, value_name = "COLUMN", default_value = "stage")]
+    stage_column: String,
+
+    /// Add amounts up as exact decimals rather than in floating point, so totals stay exact to the cent on large files
+    #[arg(long)]
+    exact: bool,
+}
+
+impl InputArgs {
+    fn format_of(&self, path: &Path) -> InputFormat {
+        match self.input_format {
+            Some(InputFormatArg::Csv) => InputFormat::Csv,
+            Some(InputFormatArg::Json) => InputFormat::Json,
+            Some(InputFormatArg::Ndjson) => InputFormat::JsonLines,
+            Some(InputFormatArg::Xlsx) => InputFormat::Xlsx,
+            None => InputFormat::of_path(path),
         }
     }
 
-    Ok(())
+    // The rows of the input at `path`, from the --sheet of a workbook.
+    fn source(&self, path: &Path) -> Result<Box<dyn SalesSource + Send>, Box<dyn Error>> {
+        let format = self.format_of(path);
+        if self.sheet.is_some() && format != InputFormat::Xlsx {
+            return Err("--sheet only applies to Excel input".into());
+        }
+        #[cfg(feature = "excel")]
+        if let Some(sheet) = &self.sheet {
+            return Ok(Box::new(sales_chart::source::XlsxSource::open(path, Some(sheet))?));
+        }
+        open_source(path, format)
+    }
+
+    fn parse_options(&self, lenient: bool, dedupe: bool) -> Result<ParseOptions, Box<dyn Error>> {
+        let schema = match &self.schema {
+            Some(path) => Schema::from_path(path)?,
+            None => Schema::default(),
+        };
+        let policy = match self.missing_amount {
+            MissingAmount::Error => MissingValuePolicy::Error,
+            MissingAmount::Skip => MissingValuePolicy::Skip,
+            MissingAmount::Zero => MissingValuePolicy::Zero,
+        };
+        let mut sentinels = vec![String::new()];
+        sentinels.extend(self.null_values.iter().map(|v| v.trim().to_string()));
+
+        Ok(ParseOptions {
+            lenient,
+            schema,
+            missing: MissingValues { policy, sentinels },
+            duplicate_key: self.duplicate_key.clone().map_or(DuplicateKey::Exact, DuplicateKey::Column),
+            dedupe,
+            number: NumberFormat {
+                locale: self.number_locale,
+                clean: self.clean_numbers,
+            },
+            date_order: match self.date_order {
+                DateOrderArg::Auto => DateOrder::Auto,
+                DateOrderArg::Dmy => DateOrder::Dmy,
+                DateOrderArg::Mdy => DateOrder::Mdy,
+            },
+            timezone: self.timezone,
+            calendar: self.retail_calendar.as_ref().map(RetailCalendar::from_path).transpose()?,
+            suspicion: SuspicionRules {
+                std_devs: self.outlier_std_devs,
+                ..SuspicionRules::default()
+            },
+            currency: None,
+            currency_filter: None,
+            months: None,
+            product_filter: None,
+            cube: None,
+            daily: false,
+            stage_column: Some(self.stage_column.clone()),
+            exact: self.exact,
+            #[cfg(feature = "scripting")]
+            script: None,
+        })
+    }
 }
 
-fn process_sales_data(rdr: &mut csv::Reader<File>) -> Result<(HashMap<DateKey, f64>, HashMap<String, f64>), Box<dyn Error>> {
-    let headers = rdr.headers()?.clone();
-    validate_csv_structure(&headers)?;
+#[derive(Parser)]
+#[command(version, about = "Generate sales charts from a CSV export")]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
 
-    let month_index = headers.iter().position(|h| h.to_lowercase() == "month").unwrap();
-    let product_index = headers.iter().position(|h| h.to_lowercase() == "product").unwrap();
-    let sales_index = headers.iter().position(|h| h.to_lowercase() == "sales_amount").unwrap();
+    /// CSV file charted without a subcommand, and by --follow and --gui
+    #[arg(long = "input", value_name = "FILE", default_value = INPUT_PATH)]
+    input_file: PathBuf,
 
-    let records: Vec<StringRecord> = rdr.records().collect::<Result<_, _>>()?;
+    /// Directory the charts are written to, created if it does not exist (default the working directory)
+    #[arg(long, value_name = "DIR")]
+    output_dir: Option<PathBuf>,
 
-    let (sales_by_month, sales_by_product): (HashMap<DateKey, f64>, HashMap<String, f64>) = records
-        .par_iter()
-        .try_fold(
-            || (HashMap::new(), HashMap::new()),
-            |(mut sales_by_month, mut sales_by_product), record| {
-                if record.len() != 3 {
-                    return Err("Invalid column length in data row".into());
-                }
+    /// Which of the line, bar, and pie charts to write, separated by commas
+    #[arg(long, value_enum, value_delimiter = ',', default_values_t = [BasicChart::Line, BasicChart::Bar, BasicChart::Pie])]
+    charts: Vec<BasicChart>,
+
+    /// Width of the charts in pixels
+    #[arg(long, value_name = "PIXELS", default_value_t = 800, value_parser = clap::value_parser!(u32).range(200..=10000))]
+    width: u32,
+
+    /// Height of the charts in pixels
+    #[arg(long, value_name = "PIXELS", default_value_t = 600, value_parser = clap::value_parser!(u32).range(150..=10000))]
+    height: u32,
+
+    /// Log more: -v adds per-stage timings, -vv everything
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    verbose: u8,
+
+    /// Only log warnings and errors
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Aggregation engine used to load and group the data
+    #[arg(long, value_enum, default_value_t = Engine::Native)]
+    engine: Engine,
+
+    /// SQL predicate applied to rows before aggregation (duckdb and polars engines only)
+    #[arg(long, value_name = "WHERE")]
+    query: Option<String>,
+
+    /// Skip malformed rows instead of aborting, and list them in errors.csv
+    #[arg(long)]
+    lenient: bool,
+
+    /// Drop duplicate rows before aggregation instead of only reporting them
+    #[arg(long)]
+    dedupe: bool,
+
+    /// CSV file with `product,category` (and optionally `brand`) columns joined onto the sales data
+    #[arg(long, value_name = "FILE")]
+    categories: Option<PathBuf>,
+
+    /// Chart totals per product, or per category/brand from --categories
+    #[arg(long, value_enum, default_value_t = GroupBy::Product)]
+    group_by: GroupBy,
+
+    /// Also chart monthly sales in a grid of panels on shared axes, one per value of this column, or per category or brand with --categories
+    #[arg(long, value_name = "COLUMN|category|brand")]
+    facet: Option<String>,
+
+    /// Also write a bump chart of the best-selling products' rank in each month
+    #[arg(long)]
+    bump_chart: bool,
+
+    /// Also write a streamgraph of each product's monthly sales, stacked around a centered baseline
+    #[arg(long)]
+    streamgraph: bool,
+
+    /// Also write a stacked bar chart of each month's sales by product, to show the product mix over time
+    #[arg(long)]
+    stacked_bar: bool,
+
+    /// Also write small metric-card images of the total revenue, month-over-month growth, best month, and top product
+    #[arg(long)]
+    kpi_cards: bool,
+
+    /// Also write a one-page overview image of the metric cards, a sparkline of the monthly trend, and the top products
+    #[arg(long)]
+    executive_summary: bool,
+
+    /// Also write a dashboard image of the charts laid out on a grid as this TOML file describes
+    #[arg(long, value_name = "FILE")]
+    layout: Option<PathBuf>,
+
+    /// File format of the charts
+    #[arg(long, visible_alias = "format", value_enum, default_value_t = OutputFormat::Png)]
+    output_format: OutputFormat,
+
+    /// Keep a hash of what each chart was drawn from in this JSON file, and skip drawing a chart again while its data,
+    /// captions, and file are unchanged, which makes repeated --watch and schedule runs quick
+    #[arg(long, value_name = "FILE")]
+    chart_cache: Option<PathBuf>,
+
+    #[arg(skip)]
+    opened_chart_cache: Mutex<Option<ChartCache>>,
+
+    /// Also write a trend chart per product, named by this template with {product} for the product (default product_{product}.png)
+    #[arg(long, value_name = "TEMPLATE", num_args = 0..=1, default_missing_value = "product_{product}.png")]
+    per_product: Option<String>,
+
+    /// Merge product names a small edit distance apart (e.g. "Widgit A" into "Widget A")
+    #[arg(long)]
+    fuzzy_merge: bool,
+
+    /// CSV file with `from,to` columns renaming products (e.g. old SKU codes) before aggregation
+    #[arg(long, value_name = "FILE")]
+    product_aliases: Option<PathBuf>,
+
+    /// Merge product names that differ only in whitespace or case, and list the merges
+    #[arg(long)]
+    normalize_products: bool,
+
+    /// POST a JSON summary (artifacts, totals, anomalies) to this URL after every run, including failed ones
+    #[arg(long, value_name = "URL")]
+    webhook: Option<String>,
+
+    /// Slack incoming-webhook URL that receives the headline numbers after every run
+    #[arg(long, value_name = "URL")]
+    slack_webhook: Option<String>,
+
+    /// Slack channel ID to upload the charts to, using the bot token in SLACK_BOT_TOKEN
+    #[arg(long, value_name = "CHANNEL")]
+    slack_channel: Option<String>,
+
+    /// TOML file with SMTP server and recipients; the HTML report with inline charts is mailed after each run
+    #[arg(long, value_name = "FILE")]
+    email_config: Option<PathBuf>,
+
+    /// Raise an alert when the totals cross a limit, such as "latest < 80% of average" or "product drop > 30%"; repeat for several
+    #[arg(long, value_name = "RULE", value_parser = |text: &str| AlertRule::parse(text).map_err(|e| e.to_string()))]
+    alert: Vec<AlertRule>,
+
+    /// What a raised --alert does
+    #[arg(long, value_enum, default_value_t = AlertAction::Fail)]
+    alert_action: AlertAction,
+
+    /// Upload the charts and reports after each run: s3://bucket/prefix/, sftp://user@host/dir/, or ftp://user@host/dir/
+    #[arg(long, value_name = "URL", value_parser = Destination::parse)]
+    upload: Option<Destination>,
+
+    /// Keep the input open and chart rows appended to it, refreshing at most every SECONDS (default 10)
+    #[arg(long, value_name = "SECONDS", num_args = 0..=1, default_missing_value = "10")]
+    follow: Option<u64>,
+
+    /// Column holding each row's currency code (EUR, GBP, ...); amounts are converted to --base-currency
+    #[arg(long, value_name = "COLUMN")]
+    currency_column: Option<String>,
+
+    /// Currency the totals are charted in when --currency-column is set
+    #[arg(long, value_name = "CODE", default_value = "USD")]
+    base_currency: String,
+
+    /// Exchange rates: a `currency,rate[,month]` CSV file, or an API URL such as https://api.frankfurter.dev/v1
+    #[arg(long, value_name = "FILE|URL")]
+    fx_rates: Option<String>,
+
+    /// File caching the rates fetched from an --fx-rates API between runs
+    #[arg(long, value_name = "FILE", default_value = "fx_rates.json")]
+    fx_cache: PathBuf,
+
+    /// Draw the same months a year earlier behind the monthly trend as a grey dashed line, for data spanning more than a year
+    #[arg(long)]
+    prior_year: bool,
+
+    /// Monthly fixed costs; the line chart draws the sales that cover them as a break-even line and marks the months below it
+    #[arg(long, value_name = "AMOUNT")]
+    fixed_costs: Option<f64>,
+
+    /// What each unit sold costs, raising the break-even sales by the share of every sale it takes up
+    #[arg(long, value_name = "AMOUNT", requires_all = ["fixed_costs", "unit_price"])]
+    unit_cost: Option<f64>,
+
+    /// What each unit sells for, to weigh --unit-cost against, since the sales data holds amounts rather than units
+    #[arg(long, value_name = "AMOUNT", requires = "unit_cost")]
+    unit_price: Option<f64>,
+
+    /// What-if change to chart against the actual sales, such as "+10% on Product A from 2024-06"; repeat to combine several
+    #[arg(long, value_name = "ADJUSTMENT", allow_hyphen_values = true)]
+    scenario: Vec<String>,
+
+    /// Sales targets for an extra actual-vs-budget chart and a variance per month: a `month,target` CSV file
+    #[arg(long, value_name = "FILE")]
+    budget: Option<PathBuf>,
+
+    /// Price index for an extra inflation-adjusted chart: a `month,index` CSV file, or us-cpi for the built-in US CPI
+    #[arg(long, value_name = "FILE|us-cpi")]
+    cpi: Option<String>,
+
+    /// Month whose prices the inflation-adjusted chart is expressed in (default: the latest month)
+    #[arg(long, value_name = "YYYY-MM", value_parser = parse_base_month)]
+    cpi_base: Option<NaiveDate>,
+
+    /// Language the charts name months in, e.g. de, fr, es, or ja (default: ISO dates)
+    #[arg(long, value_name = "LOCALE", value_parser = DateLocale::from_tag)]
+    locale: Option<DateLocale>,
+
+    /// Holidays to shade on the line charts and compare daily sales on: a built-in country (US or GB) or a
+    /// `date,name[,end]` CSV file of holidays and promotions
+    #[arg(long, value_name = "US|GB|FILE")]
+    holidays: Option<String>,
+
+    /// TOML file translating chart titles, legend entries, and report headings, one `"English" = "translation"` per line
+    #[arg(long, value_name = "FILE", value_parser = |path: &str| Translations::from_path(path).map_err(|e| e.to_string()))]
+    translations: Option<Translations>,
+
+    /// Currency symbol amounts are shown with in charts, reports, and messages
+    #[arg(long, value_name = "SYMBOL", default_value = "$")]
+    currency_symbol: String,
+
+    /// Whether the currency symbol goes before or after the amount
+    #[arg(long, value_enum, default_value_t = SymbolPositionArg::Prefix)]
+    currency_position: SymbolPositionArg,
+
+    /// Decimal separator of shown amounts and percentages
+    #[arg(long, value_name = "CHAR", default_value_t = '.')]
+    decimal_separator: char,
+
+    /// Separator between thousands of shown amounts (default: none)
+    #[arg(long, value_name = "CHAR")]
+    grouping_separator: Option<char>,
+
+    /// Where the grouping separator goes; indian groups by lakh and crore, with `,` unless --grouping-separator says otherwise
+    #[arg(long, value_enum, default_value_t = DigitGroupingArg::Thousands)]
+    digit_grouping: DigitGroupingArg,
+
+    /// Decimal places of amounts in chart labels, reports, and messages (default: 2); when given, fractional numbers in --summary-json are rounded to them too
+    #[arg(long, value_name = "N", value_parser = parse_decimals)]
+    decimals: Option<usize>,
+
+    /// How amounts and percentages are rounded to the places shown
+    #[arg(long, value_enum, default_value_t = RoundingArg::HalfEven)]
+    rounding: RoundingArg,
+
+    /// Decimal places of one chart's amounts, such as `pie=0` or `bar=0`, over --decimals; repeat for more charts
+    #[arg(long, value_name = "CHART=N", value_parser = parse_chart_decimals)]
+    chart_decimals: Vec<(String, usize)>,
+
+    /// Installed font family for chart text, e.g. "Noto Sans CJK JP"; text it cannot draw falls back to an installed CJK font
+    #[arg(long, value_name = "FAMILY", default_value = "sans-serif")]
+    font: String,
+
+    /// Lay chart labels, legends, and the HTML report out right to left (default with an Arabic, Hebrew, Persian, or Urdu --locale)
+    #[arg(long)]
+    rtl: bool,
+
+    /// Open the line chart, or the first file written without one, in the default image viewer after the first successful run
+    #[arg(long)]
+    open: bool,
+
+    /// Order of the funnel chart's stages from the top, separated by commas, instead of the largest first
+    #[arg(long, value_name = "STAGE,...", value_delimiter = ',')]
+    stages: Vec<String>,
+
+    /// Show the charts in a window with a chart selector and a month range slider
+    #[arg(long)]
+    gui: bool,
+
+    /// Also write a single-page HTML dashboard with date range, product, and region filters
+    #[arg(long, value_name = "FILE")]
+    dashboard: Option<PathBuf>,
+
+    /// Column holding each row's region, for the dashboard's region filter and --map
+    #[arg(long, value_name = "COLUMN")]
+    region_column: Option<String>,
+
+    /// Also write a choropleth map of sales by --region-column: the built-in us-states tile map, or a GeoJSON file
+    #[arg(long, value_name = "us-states|FILE", requires = "region_column")]
+    map: Option<String>,
 
-                let date_str = &record[month_index];
-                let month = NaiveDate::parse_from_str(&format!("{}-01", date_str), "%Y-%m-%d")
-                    .map_err(|e| format!("Invalid date format in \"{}\": {}", date_str, e))?;
-                let product = record[product_index].to_string();
-                let sales: f64 = record[sales_index]
-                    .parse()
-                    .map_err(|e| format!("Invalid sales number in \"{}\": {}", record[sales_index].to_string(), e))?;
+    /// Also write an HTML report with the charts embedded and sortable tables of their totals
+    #[arg(long, value_name = "FILE")]
+    html_report: Option<PathBuf>,
 
-                *sales_by_month.entry(date_to_key(&month)).or_insert(0.0) += sales;
-                *sales_by_product.entry(product).or_insert(0.0) += sales;
+    /// Tera template to render the --html-report from instead of its built-in layout, such as a Markdown one
+    /// (needs the `templates` feature)
+    #[arg(long, value_name = "FILE", requires = "html_report")]
+    report_template: Option<PathBuf>,
 
-                Ok((sales_by_month, sales_by_product))
+    /// Compare every chart and report written with the file of the same name in this directory, such as a previous
+    /// run's outputs, and exit with status 1 unless they are all byte-identical
+    #[arg(long, value_name = "DIR")]
+    verify: Option<PathBuf>,
+
+    /// After each run, write a JSON summary (inputs, row counts, skipped rows, artifacts, stage
+    /// timings) to this file, or as a single line to stdout with `-`
+    #[arg(long, value_name = "FILE")]
+    summary_json: Option<PathBuf>,
+
+    /// Read the input a row at a time, saving the totals so far to this file, so a run that is interrupted resumes where it was
+    #[arg(long, value_name = "FILE")]
+    checkpoint: Option<PathBuf>,
+
+    /// Total the input's rows as they are read rather than once all are read, so a multi-GB file needs memory for its months
+    /// and products only
+    #[arg(long, conflicts_with_all = ["checkpoint", "follow"])]
+    stream: bool,
+
+    /// Rows between two saves of the --checkpoint file
+    #[arg(long, value_name = "ROWS", default_value = "1M", value_parser = parse_count)]
+    checkpoint_every: u64,
+
+    /// After each run, store its monthly and product totals in this SQLite database and chart the total sales per run
+    /// (needs the `history` feature)
+    #[arg(long, value_name = "FILE")]
+    history: Option<PathBuf>,
+
+    /// Rhai script with a transform(row) function changing rows or leaving them out before they are totalled, and a
+    /// metrics(totals) function adding custom metrics to the log, --summary-json, and --html-report (needs the `scripting` feature)
+    #[arg(long, value_name = "FILE")]
+    script: Option<PathBuf>,
+
+    #[cfg(feature = "scripting")]
+    #[arg(skip)]
+    loaded_script: OnceLock<Arc<Script>>,
+
+    /// Write rows flagged as suspicious (outliers, far-off dates, zero amounts) to this CSV file
+    #[arg(long, value_name = "FILE")]
+    suspicious_report: Option<PathBuf>,
+
+    #[command(flatten)]
+    input: InputArgs,
+}
+
+impl Cli {
+    fn parse_options(&self) -> Result<ParseOptions, Box<dyn Error>> {
+        self.parse_options_in(None)
+    }
+
+    // `currency` picks the rows in one currency, for --currency-column without rates to
+    // convert the amounts, which only runs that write the charts to files can split by.
+    fn parse_options_in(&self, currency: Option<&str>) -> Result<ParseOptions, Box<dyn Error>> {
+        let mut options = self.input.parse_options(self.lenient, self.dedupe)?;
+        options.currency = match (&self.currency_column, &self.fx_rates) {
+            (Some(column), Some(rates)) => Some(CurrencyConversion {
+                column: column.clone(),
+                base: self.base_currency.to_ascii_uppercase(),
+                source: if rates.starts_with("http://") || rates.starts_with("https://") {
+                    RateSource::Api { url: rates.trim_end_matches('/').to_string(), cache: self.fx_cache.clone() }
+                } else {
+                    RateSource::Table(PathBuf::from(rates))
+                },
+            }),
+            (Some(column), None) => {
+                let currency = currency.ok_or("--currency-column needs exchange rates from --fx-rates here, as only charting to files splits the data by currency")?;
+                options.currency_filter = Some(CurrencyFilter {
+                    column: column.clone(),
+                    currency: currency.to_string(),
+                    includes_blank: currency.eq_ignore_ascii_case(&self.base_currency),
+                });
+                None
+            }
+            (None, Some(_)) => return Err("--fx-rates needs a --currency-column naming each row's currency".into()),
+            (None, None) => None,
+        };
+        let region_column = match (self.facet_column(), &self.region_column) {
+            (Some(facet), Some(region)) if facet != region => {
+                return Err(format!("--facet {} and --region-column {} need to name the same column", facet, region).into())
+            }
+            (Some(facet), _) => Some(facet.to_string()),
+            (None, region) => region.clone(),
+        };
+        if self.needs_cube() {
+            options.cube = Some(CubeSpec { region_column });
+        }
+        options.daily = self.holidays.is_some();
+        #[cfg(feature = "scripting")]
+        {
+            options.script = self.script()?;
+        }
+        Ok(options)
+    }
+
+    // Loaded once, so the script's top-level statements run once however often the input is read.
+    #[cfg(feature = "scripting")]
+    fn script(&self) -> Result<Option<Arc<Script>>, Box<dyn Error>> {
+        let Some(path) = &self.script else {
+            return Ok(None);
+        };
+        if let Some(script) = self.loaded_script.get() {
+            return Ok(Some(script.clone()));
+        }
+        let script = Arc::new(Script::from_path(path)?);
+        Ok(Some(self.loaded_script.get_or_init(|| script).clone()))
+    }
+
+    fn money_format(&self) -> MoneyFormat {
+        MoneyFormat {
+            symbol: self.currency_symbol.clone(),
+            position: match self.currency_position {
+                SymbolPositionArg::Prefix => SymbolPosition::Prefix,
+                SymbolPositionArg::Suffix => SymbolPosition::Suffix,
+            },
+            decimal: self.decimal_separator,
+            grouping: self.grouping_separator.or((self.digit_grouping == DigitGroupingArg::Indian).then_some(',')),
+            digit_grouping: match self.digit_grouping {
+                DigitGroupingArg::Thousands => DigitGrouping::Thousands,
+                DigitGroupingArg::Indian => DigitGrouping::Indian,
             },
-        )
-        .try_reduce(
-            || (HashMap::new(), HashMap::new()),
-            |(mut acc_month, mut acc_product), (month, product)| {
-                for (k, v) in month {
-                    *acc_month.entry(k).or_insert(0.0) += v;
+            decimals: self.decimals.unwrap_or(2),
+            rounding: self.rounding(),
+        }
+    }
+
+    fn rounding(&self) -> Rounding {
+        match self.rounding {
+            RoundingArg::HalfEven => Rounding::HalfEven,
+            RoundingArg::HalfUp => Rounding::HalfUp,
+            RoundingArg::Up => Rounding::Up,
+            RoundingArg::Down => Rounding::Down,
+        }
+    }
+
+    fn chart_labels(&self) -> ChartLabels {
+        ChartLabels {
+            locale: self.locale,
+            money: self.money_format(),
+            translations: self.translations.clone().unwrap_or_default(),
+            font: self.font.clone(),
+            rtl: self.rtl || self.locale.is_some_and(|locale| locale.is_rtl()),
+            prior_year: self.prior_year,
+            // Browsers lay out the text of SVG images themselves.
+            visual_order: self.output_format != OutputFormat::Svg,
+            chart_decimals: self.chart_decimals.clone(),
+            size: (self.width, self.height),
+            ..ChartLabels::default()
+        }
+    }
+
+    /// The monthly sales covering --fixed-costs and, with a unit cost and price, the cost of the units sold.
+    fn break_even(&self) -> Result<Option<f64>, Box<dyn Error>> {
+        let Some(fixed_costs) = self.fixed_costs else {
+            return Ok(None);
+        };
+        let costs = BreakEven { fixed_costs, unit_cost: self.unit_cost.unwrap_or(0.0), unit_price: self.unit_price.unwrap_or(1.0) };
+        costs.sales().map(Some)
+    }
+
+    // Labels showing amounts in `currency`'s symbol, for data charted by currency.
+    fn chart_labels_in(&self, currency: Option<&str>) -> ChartLabels {
+        let mut labels = self.chart_labels();
+        if let Some(currency) = currency {
+            labels.money.symbol = currency_symbol(currency);
+        }
+        labels
+    }
+
+    // The column --facet splits the charts by, unless it names a grouping of --categories.
+    fn facet_column(&self) -> Option<&str> {
+        self.facet.as_deref().filter(|facet| self.categories.is_none() || !["category", "brand"].contains(facet))
+    }
+
+    // Whether an output needs each product's sales by month, and by region if there is a
+    // region column. The dashboard slices the cube in the browser; the HTML reports use it to
+    // drill down from the bar chart into each product's monthly totals, and the server's Grafana
+    // datasource for each product's series.
+    fn needs_cube(&self) -> bool {
+        self.dashboard.is_some()
+            || self.html_report.is_some()
+            || self.email_config.is_some()
+            || matches!(self.command, Some(Command::Preview { .. }))
+            || matches!(self.command, Some(Command::Serve { .. }))
+            || self.facet.is_some()
+            || self.per_product.is_some()
+            || self.map.is_some()
+            || !self.scenario.is_empty()
+            || self.bump_chart
+            || self.streamgraph
+            || self.stacked_bar
+            || self.layout.is_some()
+            || self.alert.iter().any(AlertRule::needs_products)
+    }
+
+    fn holiday_calendar(&self) -> Result<Option<HolidayCalendar>, Box<dyn Error>> {
+        self.holidays.as_deref().map(HolidayCalendar::from_arg).transpose()
+    }
+
+    /// Files besides the input that change the results, such as product aliases or a schema.
+    fn config_files(&self) -> Vec<PathBuf> {
+        let fx_table = self.fx_rates.as_ref().filter(|rates| !rates.starts_with("http://") && !rates.starts_with("https://"));
+        let holidays = self.holidays.as_ref().filter(|holidays| Path::new(holidays).exists());
+        let map = self.map.as_ref().filter(|map| Path::new(map).exists());
+        [self.input.schema.clone(), self.input.retail_calendar.clone(), self.categories.clone(), self.product_aliases.clone(), fx_table.map(PathBuf::from), self.budget.clone()]
+            .into_iter()
+            .chain([holidays.map(PathBuf::from), map.map(PathBuf::from), self.layout.clone(), self.script.clone(), self.report_template.clone()])
+            .flatten()
+            .collect()
+    }
+
+    /// The file a chart named like `line_chart.png` is written to in the --output-format, in the --output-dir.
+    fn chart_file(&self, name: &str) -> PathBuf {
+        let name = match self.output_format {
+            OutputFormat::Png => name.to_string(),
+            OutputFormat::Svg => format!("{}.svg", name.strip_suffix(".png").unwrap_or(name)),
+            OutputFormat::Vega => format!("{}.{}", name.strip_suffix(".png").unwrap_or(name), vega::EXTENSION),
+        };
+        match &self.output_dir {
+            Some(dir) => dir.join(name),
+            None => PathBuf::from(name),
+        }
+    }
+
+    /// The --output-dir, created if it does not exist yet.
+    fn chart_dir(&self) -> Result<&Path, Box<dyn Error>> {
+        let Some(dir) = &self.output_dir else {
+            return Ok(Path::new("."));
+        };
+        std::fs::create_dir_all(dir).map_err(|e| format!("Cannot create --output-dir {}: {}", dir.display(), e))?;
+        Ok(dir)
+    }
+
+    /// Fails unless the --font is installed, before drawing any chart with it. Vega-Lite specs
+    /// leave the text to the viewer.
+    fn check_font(&self) -> Result<(), Box<dyn Error>> {
+        if self.output_format != OutputFormat::Vega && !sales_chart::fonts::is_installed(&self.font) {
+            return Err(format!("No font named {} is installed", self.font).into());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Check a CSV file for structural and data problems without producing charts
+    Validate {
+        /// CSV file to check
+        input: PathBuf,
+
+        #[command(flatten)]
+        options: InputArgs,
+    },
+    /// Serve charts and JSON aggregates over HTTP, for the input file or uploaded CSVs
+    Serve {
+        #[arg(long, default_value_t = 8080)]
+        port: u16,
+
+        /// Largest request body accepted, in bytes, e.g. 500k or 100M; larger uploads are refused with 413
+        #[arg(long, value_name = "BYTES", default_value = "100M", value_parser = parse_count)]
+        max_upload: u64,
+
+        /// CSV file answered by GET requests
+        #[arg(default_value = INPUT_PATH)]
+        input: PathBuf,
+    },
+    /// Serve the HTML report locally and reload it in the browser whenever the input or a config file changes
+    Preview {
+        #[arg(long, default_value_t = 8000)]
+        port: u16,
+
+        /// CSV file to report on
+        #[arg(default_value = INPUT_PATH)]
+        input: PathBuf,
+    },
+    /// Keep running and regenerate the charts on a cron schedule
+    Schedule {
+        /// Cron expression in local time, e.g. "0 7 * * MON" for Mondays at 07:00
+        #[arg(long)]
+        cron: String,
+
+        /// CSV file to chart
+        #[arg(default_value = INPUT_PATH)]
+        input: PathBuf,
+    },
+    /// Consume sales events from a Kafka topic and keep the charts up to date with running totals
+    Kafka {
+        /// Comma-separated bootstrap brokers
+        #[arg(long, value_delimiter = ',', default_value = "localhost:9092")]
+        brokers: Vec<String>,
+
+        /// Topic carrying one sale per message, as a CSV row or a JSON object
+        #[arg(long)]
+        topic: String,
+
+        /// Column names of CSV events, in order (also the keys read from JSON events)
+        #[arg(long, value_delimiter = ',', default_value = "month,product,sales_amount")]
+        columns: Vec<String>,
+
+        /// Minimum number of seconds between chart refreshes while events keep arriving
+        #[arg(long, value_name = "SECONDS", default_value_t = 10)]
+        refresh: u64,
+    },
+    /// Browse the monthly trend, product totals, and key metrics in an interactive terminal dashboard
+    Tui {
+        /// CSV file to explore
+        #[arg(default_value = INPUT_PATH)]
+        input: PathBuf,
+    },
+    /// Regenerate the charts every time the input file changes
+    Watch {
+        /// CSV file to watch
+        input: PathBuf,
+    },
+    /// Write a synthetic sales CSV for benchmarks, demos, and bug reports without real data
+    Generate {
+        /// Number of rows, e.g. 50000, 250k, or 1M
+        #[arg(long, default_value = "10000", value_parser = parse_count)]
+        rows: u64,
+
+        /// Number of products, a few of them selling most
+        #[arg(long, default_value_t = 5)]
+        products: usize,
+
+        /// Number of consecutive months from --start
+        #[arg(long, default_value_t = 12)]
+        months: u32,
+
+        /// First month of the data
+        #[arg(long, value_name = "YYYY-MM", default_value = "2024-01", value_parser = parse_base_month)]
+        start: NaiveDate,
+
+        /// Seed for the random numbers; the same seed and options give the same file (default: random)
+        #[arg(long)]
+        seed: Option<u64>,
+
+        /// Peak in December and dip in the summer instead of a flat year
+        #[arg(long)]
+        seasonality: bool,
+
+        /// CSV file to write, or `-` for stdout
+        #[arg(long, short, value_name = "FILE", default_value = "synthetic_sales.csv")]
+        output: PathBuf,
+    },
+    /// Chart two sales files against each other, such as last quarter's export and this quarter's,
+    /// and write a report with a table of what changed
+    Compare {
+        old: PathBuf,
+        new: PathBuf,
+
+        /// Where to write the comparison report
+        #[arg(long, short, value_name = "FILE", default_value = "comparison.html")]
+        output: PathBuf,
+    },
+    /// Compare two chart images, write a visual diff, and fail when too much of the chart changed
+    Diff {
+        old: PathBuf,
+        new: PathBuf,
+
+        /// Where to write the diff image
+        #[arg(long, value_name = "FILE", default_value = "diff.png")]
+        output: PathBuf,
+
+        /// Largest per-channel difference (0-255) that still counts as the same pixel
+        #[arg(long, default_value_t = 0)]
+        tolerance: u8,
+
+        /// Percentage of pixels allowed to change before the command fails
+        #[arg(long, value_name = "PERCENT", default_value_t = 0.0)]
+        threshold: f64,
+    },
+    /// List the runs stored by --history, or redraw the line, bar, and pie charts of one of them from its stored totals
+    History {
+        /// SQLite database written by --history
+        database: PathBuf,
+
+        /// Only the runs of this input
+        #[arg(long, value_name = "INPUT")]
+        dataset: Option<String>,
+
+        /// Redraw the charts of the run with this id instead of listing the runs
+        #[arg(long, value_name = "ID")]
+        run: Option<i64>,
+    },
+}
+
+fn load_sales_data(cli: &Cli, input: &Path, currency: Option<&str>) -> Result<(SalesTotals, ParseReport), Box<dyn Error>> {
+    if cli.engine != Engine::Native {
+        let native = [
+            ("--lenient", cli.lenient),
+            ("--number-locale", cli.input.number_locale.is_some()),
+            ("--clean-numbers", cli.input.clean_numbers),
+            ("--checkpoint", cli.checkpoint.is_some()),
+            ("JSON or Excel input", cli.input.format_of(input) != InputFormat::Csv),
+            ("--stream", cli.stream),
+            ("--exact", cli.input.exact),
+            ("--missing-amount", cli.input.missing_amount != MissingAmount::Error),
+            ("--timezone", cli.input.timezone != Tz::UTC),
+            ("--suspicious-report", cli.suspicious_report.is_some()),
+            ("--dedupe", cli.dedupe),
+            ("--duplicate-key", cli.input.duplicate_key.is_some()),
+            ("--retail-calendar", cli.input.retail_calendar.is_some()),
+            ("--holidays", cli.holidays.is_some()),
+            ("--schema", cli.input.schema.is_some()),
+            ("--currency-column", cli.currency_column.is_some()),
+            ("--facet", cli.facet.is_some()),
+            ("--per-product", cli.per_product.is_some()),
+            ("--streamgraph", cli.streamgraph),
+            ("--stacked-bar", cli.stacked_bar),
+            ("--kpi-cards", cli.kpi_cards),
+            ("--executive-summary", cli.executive_summary),
+            ("--layout", cli.layout.is_some()),
+            ("--bump-chart", cli.bump_chart),
+            ("--alert \"product drop\"", cli.alert.iter().any(AlertRule::needs_products)),
+            ("--scenario", !cli.scenario.is_empty()),
+            ("--map", cli.map.is_some()),
+            ("--dashboard", cli.dashboard.is_some()),
+            ("--script", cli.script.is_some()),
+        ];
+        let given: Vec<&str> = native.iter().filter(|(_, given)| *given).map(|(option, _)| *option).collect();
+        if !given.is_empty() {
+            let verb = if given.len() == 1 { "is" } else { "are" };
+            return Err(format!("{} {} only supported with --engine native", given.join(", "), verb).into());
+        }
+    }
+    if cli.checkpoint.is_some() && cli.input.format_of(input) != InputFormat::Csv {
+        return Err("--checkpoint only reads CSV input".into());
+    }
+
+    let _span = debug_span!("load", engine = ?cli.engine).entered();
+    let totals: Result<SalesTotals, Box<dyn Error>> = match cli.engine {
+        Engine::Native => {
+            if cli.query.is_some() {
+                return Err("--query is only supported with --engine duckdb or --engine polars".into());
+            }
+            if let Some(checkpoint) = &cli.checkpoint {
+                let options = cli.parse_options_in(currency)?;
+                if options.cube.is_some() {
+                    return Err("--checkpoint only totals months and products, not each product's months as --html-report, --dashboard, and the per-product charts need".into());
                 }
-                for (k, v) in product {
-                    *acc_product.entry(k).or_insert(0.0) += v;
+                return process_with_checkpoints(input, &options, checkpoint, cli.checkpoint_every);
+            }
+            if cli.stream {
+                if cli.suspicious_report.is_some() {
+                    return Err("--stream does not keep the rows to flag the suspicious ones for --suspicious-report".into());
                 }
-                Ok((acc_month, acc_product))
-            },
-        )?;
+                return process_sales_chunked(&mut *cli.input.source(input)?, &cli.parse_options_in(currency)?);
+            }
+            return process_input(cli, input, &cli.parse_options_in(currency)?);
+        }
+        #[cfg(feature = "duckdb")]
+        Engine::Duckdb => sales_chart::duckdb_engine::process_sales_data(&input.to_string_lossy(), cli.query.as_deref()),
+        #[cfg(not(feature = "duckdb"))]
+        Engine::Duckdb => Err("sales_chart was built without the `duckdb` feature".into()),
+        #[cfg(feature = "polars")]
+        Engine::Polars => sales_chart::polars_engine::process_sales_data(&input.to_string_lossy(), cli.query.as_deref()),
+        #[cfg(not(feature = "polars"))]
+        Engine::Polars => Err("sales_chart was built without the `polars` feature".into()),
+    };
+
+    Ok((totals?, ParseReport::default()))
+}
+
+// The totals of the input at `input`: a CSV file on several threads, other formats a row at a time.
+fn process_input(cli: &Cli, input: &Path, options: &ParseOptions) -> Result<(SalesTotals, ParseReport), Box<dyn Error>> {
+    match cli.input.format_of(input) {
+        InputFormat::Csv if cli.input.sheet.is_none() => process_sales_file(input, options),
+        _ => process_sales_source(&mut *cli.input.source(input)?, options),
+    }
+}
+
+fn run_validate(input: &Path, options: &InputArgs) -> Result<(), Box<dyn Error>> {
+    let mut source = options.source(input)?;
+    let options = options.parse_options(false, false)?;
+    let report = validate_sales_data(&mut *source, &options)?;
+
+    println!("Validation report for {}", input.display());
+    println!("{}", report);
+
+    if !report.is_ok() {
+        return Err(format!("{} failed validation with {} problems", input.display(), report.problem_count()).into());
+    }
+    Ok(())
+}
+
+fn run_generate(config: &GeneratorConfig, output: &Path) -> Result<(), Box<dyn Error>> {
+    if output == Path::new("-") {
+        return generate(io::stdout().lock(), config);
+    }
+    generate(BufWriter::new(File::create(output)?), config)?;
+    info!("Generated {} rows with --seed {} into {}", config.rows, config.seed, output.display());
+    Ok(())
+}
+
+fn run_compare(cli: &Cli, old: &Path, new: &Path, output: &Path) -> Result<(), Box<dyn Error>> {
+    let (old_data, _) = analyze_in(cli, old, None, false)?;
+    let (new_data, _) = analyze_in(cli, new, None, false)?;
+    let name = |path: &Path| path.file_name().map_or_else(|| path.display().to_string(), |name| name.to_string_lossy().into_owned());
+    let comparison = Comparison::new([name(old), name(new)], &old_data, &new_data);
+    if comparison.offset != 0 {
+        info!("No months in common, charting {} over {} month by month", comparison.ranges[1], comparison.ranges[0]);
+    }
+    let labels = cli.chart_labels();
+    cli.check_font()?;
+    let charts = render_comparison_charts(cli.chart_dir()?, &comparison, &labels)?;
+    for path in &charts {
+        info!("Chart saved as {}", path.display());
+    }
+    let totals = comparison.totals();
+    let change = totals.change().map_or(String::new(), |change| format!(" ({})", labels.money.percent(change, true)));
+    info!("Total sales {} in {}, {} in {}{}", labels.money.format(totals.old), old.display(), labels.money.format(totals.new), new.display(), change);
+    write_comparison_report(output, &comparison, &charts, &labels)?;
+    info!("Report saved as {}", output.display());
+    Ok(())
+}
+
+fn run_diff(old: &Path, new: &Path, output: &Path, tolerance: u8, threshold: f64) -> Result<(), Box<dyn Error>> {
+    let diff = diff_images(old, new, output, tolerance)?;
+    println!("{}: {}", new.display(), diff);
+    info!("Diff saved as {}", output.display());
+    if diff.resized || diff.changed_percent() > threshold {
+        return Err(format!("{} differs from {} by more than {}%", new.display(), old.display(), threshold).into());
+    }
+    Ok(())
+}
+
+fn run_watch(cli: &Cli, input: &Path) -> Result<(), Box<dyn Error>> {
+    let file_name = input.file_name().ok_or_else(|| format!("Not a file: {}", input.display()))?;
+    // Watch the directory rather than the file: many editors save by replacing the file,
+    // which would silently end a watch on the original inode.
+    let directory = match input.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    watcher.watch(directory, RecursiveMode::NonRecursive)?;
+
+    let touches_input = |event: &notify::Result<notify::Event>| {
+        matches!(event, Ok(event) if !event.kind.is_access() && event.paths.iter().any(|p| p.file_name() == Some(file_name)))
+    };
+
+    loop {
+        if let Err(e) = run(cli, input) {
+            error!("Error processing sales data: {}", e);
+        }
+        info!("Watching {} for changes (Ctrl-C to stop)", input.display());
 
-    Ok((sales_by_month, sales_by_product))
+        loop {
+            let event = rx.recv()?;
+            if touches_input(&event) {
+                break;
+            }
+        }
+        while rx.recv_timeout(WATCH_DEBOUNCE).is_ok() {}
+        info!("{} changed, regenerating", input.display());
+    }
+}
+
+// Like `tail -f`: rows are read as whole lines, so a row written in two pieces is only
+// counted once its newline arrives. A file that shrinks is read again from the top.
+fn run_follow(cli: &Cli, input: &Path, refresh: Duration) -> Result<(), Box<dyn Error>> {
+    let options = cli.parse_options()?;
+    cli.check_font()?;
+    cli.chart_dir()?;
+    info!("Following {} (Ctrl-C to stop)", input.display());
+
+    loop {
+        let mut reader = BufReader::new(File::open(input)?);
+        let mut line = String::new();
+        let mut offset = 0;
+        while !line.ends_with('\n') {
+            let read = reader.read_line(&mut line)?;
+            offset += read as u64;
+            if read == 0 {
+                std::thread::sleep(FOLLOW_POLL);
+            }
+        }
+        let headers = ReaderBuilder::new().from_reader(line.as_bytes()).headers()?.clone();
+        let mut totals = RunningTotals::new(&options, headers)?;
+
+        let mut line_number = 1;
+        let mut pending = true;
+        let mut last_refresh: Option<Instant> = None;
+        line.clear();
+        loop {
+            let read = reader.read_line(&mut line)?;
+            offset += read as u64;
+            if read > 0 {
+                if !line.ends_with('\n') {
+                    continue;
+                }
+                line_number += 1;
+                if !line.trim().is_empty() {
+                    if let Err(error) = totals.push(line.as_bytes(), line_number) {
+                        warn!("Skipped {}", error);
+                    }
+                    pending = true;
+                }
+                line.clear();
+                continue;
+            }
+
+            // At the end of the file: refresh once caught up, then wait for more rows.
+            if pending && last_refresh.is_none_or(|at| at.elapsed() >= refresh) {
+                refresh_charts(cli, &totals);
+                last_refresh = Some(Instant::now());
+                pending = false;
+            }
+            if std::fs::metadata(input)?.len() < offset {
+                info!("{} was truncated, reading it again", input.display());
+                break;
+            }
+            std::thread::sleep(FOLLOW_POLL);
+        }
+    }
+}
+
+fn run_schedule(cli: &Cli, expression: &str, input: &Path) -> Result<(), Box<dyn Error>> {
+    let cron: Cron = expression.parse().map_err(|e| format!("Invalid cron expression \"{}\": {}", expression, e))?;
+
+    loop {
+        let now = Local::now();
+        let next = cron.find_next_occurrence(&now, false)?;
+        info!("Next run at {}", next.format("%Y-%m-%d %H:%M %Z"));
+        std::thread::sleep((next - now).to_std().unwrap_or_default());
+
+        info!("Scheduled run at {}", Local::now().format("%Y-%m-%d %H:%M:%S"));
+        if let Err(e) = run(cli, input) {
+            error!("Error processing sales data: {}", e);
+        }
+    }
 }
 
-fn prepare_data_for_plotting(sales_by_month: HashMap<DateKey, f64>, sales_by_product: HashMap<String, f64>) 
-    -> (Vec<(NaiveDate, f64)>, Vec<(String, f64)>) {
-    let mut monthly_data: Vec<(NaiveDate, f64)> = sales_by_month
-        .into_par_iter()
-        .map(|(k, v)| (key_to_date(k), v))
-        .collect();
-    monthly_data.par_sort_unstable_by_key(|&(date, _)| date);
+#[cfg(feature = "kafka")]
+fn run_kafka(cli: &Cli, brokers: &[String], topic: &str, columns: &[String], refresh: u64) -> Result<(), Box<dyn Error>> {
+    use sales_chart::kafka::{consume, KafkaSource};
 
-    let mut product_data: Vec<(String, f64)> = sales_by_product.into_iter().collect();
-    product_data.par_sort_unstable_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+    let options = cli.parse_options()?;
+    cli.check_font()?;
+    let mut totals = RunningTotals::new(&options, csv::StringRecord::from(columns.to_vec()))?;
+    let source = KafkaSource {
+        brokers: brokers.to_vec(),
+        topic: topic.to_string(),
+        refresh: Duration::from_secs(refresh),
+    };
 
-    (monthly_data, product_data)
+    info!("Consuming sales events from {} on {} (Ctrl-C to stop)", topic, brokers.join(","));
+    consume(&source, &mut totals, &mut |totals| refresh_charts(cli, totals))
+}
+
+// The window re-reads the input for each month range the slider picks, so bar and pie
+// charts show the products' sales in those months only.
+#[cfg(feature = "gui")]
+fn run_gui(cli: &Cli, input: &Path) -> Result<(), Box<dyn Error>> {
+    if cli.engine != Engine::Native {
+        return Err("--gui is only supported with --engine native".into());
+    }
+    cli.check_font()?;
+    let (data, report) = analyze(cli, input)?;
+    let title = format!("Sales charts - {}", input.display());
+    let labels = cli.chart_labels().for_data(&data, report.rows);
+    sales_chart::gui::show(&title, data, labels, |from, to| {
+        let mut options = cli.parse_options()?;
+        options.months = Some(date_to_key(&from)..=date_to_key(&to));
+        let ((sales_by_month, sales_by_product), _) = process_input(cli, input, &options)?;
+        Ok(prepare_data_for_plotting(sales_by_month, tidy_products(cli, sales_by_product, true)?))
+    })
 }
 
-fn create_line_chart(monthly_data: &[(NaiveDate, f64)]) -> Result<(), Box<dyn Error>> {
-    let root = BitMapBackend::new("line_chart.png", (800, 600)).into_drawing_area();
-    root.fill(&WHITE)?;
+// Messages from the first load are printed before the dashboard takes over the terminal and
+// reappear when it closes; later loads for a changed filter are quiet.
+#[cfg(feature = "tui")]
+fn run_tui(cli: &Cli, input: &Path) -> Result<(), Box<dyn Error>> {
+    if cli.engine != Engine::Native {
+        return Err("tui is only supported with --engine native".into());
+    }
+    let load = |filter: &sales_chart::tui::Filter, quiet: bool| -> Result<(PlotData, ParseReport), Box<dyn Error>> {
+        let mut options = cli.parse_options()?;
+        options.months = filter.months.as_ref().map(|months| date_to_key(months.start())..=date_to_key(months.end()));
+        options.product_filter = Some(filter.product.clone()).filter(|product| !product.is_empty());
+        let ((sales_by_month, sales_by_product), report) = process_input(cli, input, &options)?;
+        Ok((prepare_data_for_plotting(sales_by_month, tidy_products(cli, sales_by_product, quiet)?), report))
+    };
+    let (data, report) = load(&sales_chart::tui::Filter::default(), false)?;
+    sales_chart::tui::run(input, data, &report, cli.money_format(), |filter| load(filter, true))
+}
 
-    let mut chart = ChartBuilder::on(&root)
-        .caption("Monthly Sales Trend", ("sans-serif", 30).into_font())
-        .margin(10)
-        .x_label_area_size(40)
-        .y_label_area_size(60)
-        .build_cartesian_2d(
-            monthly_data.first().unwrap().0..monthly_data.last().unwrap().0,
-            0f64..monthly_data.iter().map(|(_, v)| *v).fold(0f64, f64::max),
-        )?;
+// Re-render from running totals; a failed refresh is reported and the next one still happens.
+fn refresh_charts(cli: &Cli, totals: &RunningTotals) {
+    let (sales_by_month, sales_by_product) = totals.totals();
+    let rendered = tidy_products(cli, sales_by_product, false)
+        .map(|sales_by_product| prepare_data_for_plotting(sales_by_month, sales_by_product))
+        .and_then(|data| basic_charts(cli, &data, &cli.chart_labels().for_data(&data, totals.report.rows), None))
+        .and_then(|paths| save_chart_cache(cli).map(|()| paths));
+    let total_sales = totals.total_sales();
+    match rendered {
+        Ok(paths) => {
+            info!(
+                "{} Charts refreshed from {} rows ({} rejected, {} duplicates), total sales {}",
+                Local::now().format("%H:%M:%S"),
+                totals.report.rows,
+                totals.report.errors.len(),
+                totals.report.duplicates,
+                cli.money_format().format(total_sales)
+            );
+            if cli.open {
+                open_chart(cli, &paths);
+            }
+        }
+        Err(e) => error!("Error refreshing charts: {}", e),
+    }
+}
 
-    chart.configure_mesh().draw()?;
+// Logs go to stderr so stdout stays clean for `generate -o -` and reports. Beyond the default
+// level, every span logs its duration when it closes.
+// Stage timings for the run summary come from the same spans as the log, so the timing layer
+// listens to debug spans even when the log shows only warnings.
+fn init_logging(verbose: u8, quiet: bool, timings: bool) {
+    let level = match (quiet, verbose) {
+        (true, _) => Level::WARN,
+        (false, 0) => Level::INFO,
+        (false, 1) => Level::DEBUG,
+        (false, _) => Level::TRACE,
+    };
+    let layer = tracing_subscriber::fmt::layer().with_writer(io::stderr).with_ansi(io::stderr().is_terminal()).with_target(false);
+    // Dependencies only get a say about their warnings.
+    let targets = Targets::new().with_target("sales_chart", level).with_default(Level::WARN);
+    let stage_timings = timings.then(|| StageTimings.with_filter(Targets::new().with_target("sales_chart", Level::DEBUG)));
+    if verbose == 0 {
+        tracing_subscriber::registry().with(stage_timings).with(layer.without_time().with_filter(targets)).init();
+    } else {
+        let layer = layer.with_timer(Uptime::default()).with_span_events(FmtSpan::CLOSE);
+        tracing_subscriber::registry().with(stage_timings).with(layer.with_filter(targets)).init();
+    }
+}
 
-    chart
-        .draw_series(LineSeries::new(
-            monthly_data.iter().map(|(x, y)| (*x, *y)),
-            &RED,
-        ))?
-        .label("Total Sales")
-        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], &RED));
+fn main() -> Result<(), Box<dyn Error>> {
+    let cli = Cli::parse();
+    init_logging(cli.verbose, cli.quiet, cli.summary_json.is_some());
+
+    #[cfg(not(feature = "webhook"))]
+    if cli.webhook.is_some() {
+        return Err("sales_chart was built without the `webhook` feature".into());
+    }
+    #[cfg(feature = "email")]
+    if let Some(path) = &cli.email_config {
+        sales_chart::email::EmailConfig::from_path(path)?;
+    }
+    #[cfg(not(feature = "email"))]
+    if cli.email_config.is_some() {
+        return Err("sales_chart was built without the `email` feature".into());
+    }
+    if let Some(destination) = &cli.upload {
+        let enabled = match destination {
+            Destination::S3 { .. } => cfg!(feature = "s3"),
+            Destination::Sftp(_) => cfg!(feature = "sftp"),
+            Destination::Ftp(_) => cfg!(feature = "ftp"),
+        };
+        if !enabled {
+            return Err(format!("sales_chart was built without the `{}` feature", destination.feature()).into());
+        }
+    }
+    #[cfg(not(feature = "fx"))]
+    if cli.fx_rates.as_ref().is_some_and(|rates| rates.starts_with("http://") || rates.starts_with("https://")) {
+        return Err("sales_chart was built without the `fx` feature".into());
+    }
+    #[cfg(not(feature = "slack"))]
+    if cli.slack_webhook.is_some() || cli.slack_channel.is_some() {
+        return Err("sales_chart was built without the `slack` feature".into());
+    }
+
+    #[cfg(not(feature = "history"))]
+    if cli.history.is_some() {
+        return Err("sales_chart was built without the `history` feature".into());
+    }
+    #[cfg(not(feature = "templates"))]
+    if cli.report_template.is_some() {
+        return Err("sales_chart was built without the `templates` feature".into());
+    }
+    #[cfg(not(feature = "scripting"))]
+    if cli.script.is_some() {
+        return Err("sales_chart was built without the `scripting` feature".into());
+    }
+
+    #[cfg(not(feature = "gui"))]
+    if cli.gui {
+        return Err("sales_chart was built without the `gui` feature".into());
+    }
+    #[cfg(feature = "gui")]
+    if cli.gui {
+        if cli.command.is_some() || cli.follow.is_some() {
+            return Err("--gui charts the --input file and cannot be combined with a subcommand or --follow".into());
+        }
+        return run_gui(&cli, &cli.input_file);
+    }
+
+    if let Some(seconds) = cli.follow {
+        if cli.command.is_some() {
+            return Err("--follow charts the --input file and cannot be combined with a subcommand".into());
+        }
+        if cli.input.format_of(&cli.input_file) != InputFormat::Csv {
+            return Err("--follow only reads CSV input".into());
+        }
+        return run_follow(&cli, &cli.input_file, Duration::from_secs(seconds));
+    }
+
+    match &cli.command {
+        Some(Command::Validate { input, options }) => run_validate(input, options),
+        #[cfg(feature = "server")]
+        Some(Command::Serve { port, max_upload, input }) => {
+            sales_chart::server::serve(*port, input, &|input: &Path| analyze(&cli, input), *max_upload)
+        }
+        #[cfg(not(feature = "server"))]
+        Some(Command::Serve { .. }) => Err("sales_chart was built without the `server` feature".into()),
+        #[cfg(feature = "server")]
+        Some(Command::Preview { port, input }) => {
+            cli.check_font()?;
+            sales_chart::preview::preview(*port, input, &cli.config_files(), &cli.chart_labels(), &|input: &Path| analyze(&cli, input))
+        }
+        #[cfg(not(feature = "server"))]
+        Some(Command::Preview { .. }) => Err("sales_chart was built without the `server` feature".into()),
+        Some(Command::Schedule { cron, input }) => run_schedule(&cli, cron, input),
+        #[cfg(feature = "kafka")]
+        Some(Command::Kafka { brokers, topic, columns, refresh }) => run_kafka(&cli, brokers, topic, columns, *refresh),
+        #[cfg(not(feature = "kafka"))]
+        Some(Command::Kafka { .. }) => Err("sales_chart was built without the `kafka` feature".into()),
+        #[cfg(feature = "tui")]
+        Some(Command::Tui { input }) => run_tui(&cli, input),
+        #[cfg(not(feature = "tui"))]
+        Some(Command::Tui { .. }) => Err("sales_chart was built without the `tui` feature".into()),
+        Some(Command::Watch { input }) => run_watch(&cli, input),
+        Some(Command::Generate { rows, products, months, start, seed, seasonality, output }) => {
+            let seed = seed.unwrap_or_else(|| SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_nanos() as u64));
+            let config = GeneratorConfig { rows: *rows, products: *products, months: *months, start: *start, seed, seasonality: *seasonality };
+            run_generate(&config, output)
+        }
+        Some(Command::Compare { old, new, output }) => run_compare(&cli, old, new, output),
+        Some(Command::Diff { old, new, output, tolerance, threshold }) => run_diff(old, new, output, *tolerance, *threshold),
+        #[cfg(feature = "history")]
+        Some(Command::History { database, dataset, run }) => run_history(&cli, database, dataset.as_deref(), *run),
+        #[cfg(not(feature = "history"))]
+        Some(Command::History { .. }) => Err("sales_chart was built without the `history` feature".into()),
+        None => run(&cli, &cli.input_file).inspect_err(|e| error!("Error processing sales data: {}", e)),
+    }
+}
 
-    chart.configure_series_labels().draw()?;
+fn run(cli: &Cli, input: &Path) -> Result<(), Box<dyn Error>> {
+    let result = debug_span!("run").in_scope(|| chart(cli, input));
+    let mut summary = match &result {
+        Ok(summary) => summary.clone(),
+        Err(e) => RunSummary::failure(input, &e.to_string()),
+    };
+    summary.config_files = cli.config_files();
+    summary.timings = take_stage_timings();
 
-    root.present()?;
-    println!("Line chart saved as line_chart.png");
+    if let Some(path) = &cli.summary_json {
+        let rounding = cli.decimals.map(|decimals| (decimals, cli.rounding()));
+        match write_summary_json(path, &summary, rounding) {
+            Ok(()) if path != Path::new("-") => info!("Run summary saved as {}", path.display()),
+            Ok(()) => {}
+            Err(e) => warn!("Writing the run summary failed: {}", e),
+        }
+    }
+
+    #[cfg(feature = "webhook")]
+    if let Some(url) = &cli.webhook {
+        match sales_chart::webhook::post_summary(url, &summary) {
+            Ok(()) => info!("Run summary sent to {}", url),
+            Err(e) => warn!("{}", e),
+        }
+    }
+
+    #[cfg(feature = "slack")]
+    deliver_to_slack(cli, input, &result);
+
+    #[cfg(feature = "email")]
+    if let (Some(path), Ok(summary)) = (&cli.email_config, &result) {
+        let sent = sales_chart::email::EmailConfig::from_path(path)
+            .and_then(|config| sales_chart::email::send_report(&config, summary, &cli.chart_labels()).map(|_| config.to.join(", ")));
+        match sent {
+            Ok(recipients) => info!("Report emailed to {}", recipients),
+            Err(e) => warn!("Sending the report email failed: {}", e),
+        }
+    }
+
+    if let (true, Ok(summary)) = (cli.open, &result) {
+        open_chart(cli, &summary.artifacts);
+    }
+
+    let summary = result?;
+    if cli.alert_action == AlertAction::Fail && !summary.alerts.is_empty() {
+        let raised = if summary.alerts.len() == 1 { "alert was" } else { "alerts were" };
+        return Err(format!("{} {} raised", summary.alerts.len(), raised).into());
+    }
     Ok(())
 }
 
-fn create_bar_chart(product_data: &[(String, f64)]) -> Result<(), Box<dyn Error>> {
-    let root = BitMapBackend::new("bar_chart.png", (800, 600)).into_drawing_area();
-    root.fill(&WHITE)?;
-
-    let mut chart = ChartBuilder::on(&root)
-        .caption("Sales by Product", ("sans-serif", 30).into_font())
-        .margin(10)
-        .x_label_area_size(40)
-        .y_label_area_size(60)
-        .build_cartesian_2d(
-            0..product_data.len(),
-            0f64..product_data.iter().map(|(_, v)| *v).fold(0f64, f64::max),
-        )?;
-
-    chart.configure_mesh().draw()?;
-
-    chart.draw_series(
-        product_data.iter().enumerate().map(|(i, (_product, sales))| {
-            let color = Palette99::pick(i).mix(0.9);
-            let mut bar = Rectangle::new([(i, 0.0), (i + 1, *sales)], color.filled());
-            bar.set_margin(0, 0, 5, 5);
-            bar
-        }),
-    )?;
-
-    chart.draw_series(
-        product_data.iter().enumerate().map(|(i, (product, sales))| {
-            EmptyElement::at((i, *sales))
-                + Text::new(
-                    format!("{}: ${:.2}", product, sales),
-                    (0, 15),
-                    ("sans-serif", 15).into_font(),
-                )
-        }),
-    )?;
-
-    root.present()?;
-    println!("Bar chart saved as bar_chart.png");
+// On stdout the summary is a single line, as tools like Airflow take the last line of output.
+fn write_summary_json(path: &Path, summary: &RunSummary, rounding: Option<(usize, Rounding)>) -> Result<(), Box<dyn Error>> {
+    let mut json = serde_json::to_value(summary)?;
+    if let Some((decimals, rounding)) = rounding {
+        round_numbers(&mut json, decimals, rounding);
+    }
+    if path == Path::new("-") {
+        println!("{}", serde_json::to_string(&json)?);
+    } else {
+        let file = File::create(path).map_err(|e| format!("Cannot create {}: {}", path.display(), e))?;
+        serde_json::to_writer_pretty(BufWriter::new(file), &json)?;
+    }
     Ok(())
 }
 
-fn create_pie_chart(product_data: &[(String, f64)]) -> Result<(), Box<dyn Error>> {
-    let root = BitMapBackend::new("pie_chart.png", (800, 600)).into_drawing_area();
-    root.fill(&WHITE)?;
+// Rounds every fractional number in `json`, leaving counts alone.
+fn round_numbers(json: &mut serde_json::Value, decimals: usize, rounding: Rounding) {
+    match json {
+        serde_json::Value::Number(number) if number.is_f64() => {
+            if let Some(rounded) = number.as_f64().and_then(|value| serde_json::Number::from_f64(rounding.round(value, decimals))) {
+                *number = rounded;
+            }
+        }
+        serde_json::Value::Array(values) => values.iter_mut().for_each(|value| round_numbers(value, decimals, rounding)),
+        serde_json::Value::Object(fields) => fields.values_mut().for_each(|value| round_numbers(value, decimals, rounding)),
+        _ => {}
+    }
+}
 
-    let total_sales: f64 = product_data.iter().map(|(_, sales)| sales).sum();
-    let drawing_area = root.centered_at((400, 300));
-    let size = 300;
+static OPENED: AtomicBool = AtomicBool::new(false);
 
-    let mut chart = ChartBuilder::on(&drawing_area)
-        .caption("Sales by Product", ("sans-serif", 30).into_font())
-        .build_cartesian_2d(-1.0..1.0, -1.0..1.0)?;
+// Opens the line chart out of the files of a run, or the first of them when it was not drawn.
+fn open_chart(cli: &Cli, paths: &[PathBuf]) {
+    let line = cli.chart_file(BasicChart::Line.file_name());
+    if let Some(path) = paths.iter().find(|path| **path == line).or_else(|| paths.first()) {
+        open_once(path);
+    }
+}
 
-    chart.configure_mesh().disable_mesh().draw()?;
+// Watch, schedule, and follow keep rewriting the same files, which viewers pick up on
+// their own, so the viewer is only launched the first time.
+fn open_once(path: &Path) {
+    if OPENED.swap(true, Ordering::Relaxed) {
+        return;
+    }
+    let mut command = if cfg!(target_os = "macos") {
+        process::Command::new("open")
+    } else if cfg!(windows) {
+        let mut command = process::Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    } else {
+        process::Command::new("xdg-open")
+    };
+    match command.arg(path).spawn() {
+        Ok(_) => info!("Opened {}", path.display()),
+        Err(e) => warn!("Cannot open {} in the default viewer: {}", path.display(), e),
+    }
+}
+
+#[cfg(feature = "slack")]
+fn deliver_to_slack(cli: &Cli, input: &Path, result: &Result<RunSummary, Box<dyn Error>>) {
+    use sales_chart::slack;
+
+    let summary = match result {
+        Ok(summary) => summary.clone(),
+        Err(e) => RunSummary::failure(input, &e.to_string()),
+    };
+    if let Some(url) = &cli.slack_webhook {
+        match slack::post_message(url, &slack::headline(&summary, &cli.money_format())) {
+            Ok(()) => info!("Headline posted to Slack"),
+            Err(e) => warn!("{}", e),
+        }
+    }
+    if let (Some(channel), Ok(_)) = (&cli.slack_channel, result) {
+        let uploaded = std::env::var("SLACK_BOT_TOKEN")
+            .map_err(|_| "--slack-channel needs a bot token in SLACK_BOT_TOKEN".into())
+            .and_then(|token| slack::upload_charts(&token, channel, &summary));
+        match uploaded {
+            Ok(()) => info!("Charts uploaded to Slack channel {}", channel),
+            Err(e) => warn!("{}", e),
+        }
+    }
+}
 
-    let mut start_angle = 0.0;
-    for (idx, (product, sales)) in product_data.iter().enumerate() {
-        let angle = sales / total_sales * 360.0;
-        let color = Palette99::pick(idx);
+fn chart(cli: &Cli, input: &Path) -> Result<RunSummary, Box<dyn Error>> {
+    cli.check_font()?;
+    cli.chart_dir()?;
+    if cli.output_format == OutputFormat::Vega {
+        let drawn = [
+            ("--map", cli.map.is_some()),
+            ("--kpi-cards", cli.kpi_cards),
+            ("--executive-summary", cli.executive_summary),
+            ("--layout", cli.layout.is_some()),
+            ("--history", cli.history.is_some()),
+            ("--html-report", cli.html_report.is_some()),
+            ("--email-config", cli.email_config.is_some()),
+        ];
+        let given: Vec<&str> = drawn.iter().filter(|(_, given)| *given).map(|(option, _)| *option).collect();
+        if !given.is_empty() {
+            let verb = if given.len() == 1 { "needs" } else { "need" };
+            return Err(format!("{} {} PNG or SVG charts, which --output-format vega does not write", given.join(", "), verb).into());
+        }
+    }
+    // Without rates to convert them, amounts in different currencies are not added up.
+    let currency = match (&cli.currency_column, &cli.fx_rates) {
+        (Some(column), None) => {
+            let found = currencies(&mut *cli.input.source(input)?, column, &cli.base_currency)?;
+            if found.len() > 1 {
+                return chart_by_currency(cli, input, &found);
+            }
+            Some(found.into_iter().next().unwrap_or_else(|| cli.base_currency.to_ascii_uppercase()))
+        }
+        _ => None,
+    };
+    let calendar = cli.holiday_calendar()?;
+    let layout = cli.layout.as_ref().map(Layout::from_path).transpose()?;
+    let (data, report) = analyze_in(cli, input, currency.as_deref(), false)?;
+    let mut labels = cli.chart_labels_in(currency.as_deref()).for_data(&data, report.rows);
+    labels.holidays = chart_holidays(cli, calendar.as_ref(), &data.0);
+    labels.break_even = cli.break_even()?;
+    if let Some(level) = labels.break_even {
+        let below = data.0.iter().filter(|(_, sales)| *sales < level).count();
+        info!("{} of {} months below break-even sales of {}", below, data.0.len(), labels.money.format(level));
+    }
+    if cli.prior_year && prior_year(&data.0).is_empty() {
+        warn!("No month has sales a year earlier, so the line chart has no prior-year line");
+    }
+    let mut artifacts = basic_charts(cli, &data, &labels, None)?;
+    for path in &artifacts {
+        info!("Chart saved as {}", path.display());
+    }
+    if let Some(cpi) = &cli.cpi {
+        let index = match cpi.as_str() {
+            "us-cpi" => PriceIndex::us_cpi(),
+            path => PriceIndex::from_path(path)?,
+        };
+        let real = index.deflate(&data.0, cli.cpi_base)?;
+        let base = cli.cpi_base.or_else(|| data.0.last().map(|(month, _)| *month));
+        if let Some(base) = base {
+            let path = cli.chart_file("real_line_chart.png");
+            write_chart(cli, &path, &(&data.0, &real, base), &labels, |path| create_inflation_chart(path, &data.0, &real, base, &labels), || vega::inflation_chart(&data.0, &real, base, &labels))?;
+            let total_real: f64 = real.iter().map(|(_, sales)| sales).sum();
+            let total_real = labels.money.format(total_real);
+            info!("Chart saved as {} (total sales {} in {} prices)", path.display(), total_real, base.format("%Y-%m"));
+            artifacts.push(path);
+        }
+    }
+    let mut variance = Vec::new();
+    if let Some(path) = &cli.budget {
+        let budget = Budget::from_path(path)?;
+        let targets = budget.targets(&data.0);
+        if targets.is_empty() {
+            warn!("{} has no targets for the months charted", path.display());
+        } else {
+            let chart = cli.chart_file("budget_chart.png");
+            write_chart(cli, &chart, &(&data.0, &targets), &labels, |path| create_budget_chart(path, &data.0, &targets, &labels), || vega::budget_chart(&data.0, &targets, &labels))?;
+            variance = budget.variance(&data.0);
+            let on_plan = variance.iter().filter(|month| month.on_plan()).count();
+            info!("Chart saved as {} ({} of {} budgeted months at or above plan)", chart.display(), on_plan, variance.len());
+            artifacts.push(chart);
+            // Both list the budgeted months in order.
+            let differences: Vec<(NaiveDate, f64)> = targets.iter().zip(&variance).map(|((month, _), month_variance)| (*month, month_variance.variance)).collect();
+            let chart = cli.chart_file("variance_chart.png");
+            write_chart(cli, &chart, &differences, &labels, |path| create_variance_chart(path, &differences, &labels), || vega::variance_chart(&differences, &labels))?;
+            info!("Chart saved as {}", chart.display());
+            artifacts.push(chart);
+        }
+    }
+    if !cli.scenario.is_empty() {
+        let scenario = Scenario::parse(&cli.scenario)?;
+        for product in scenario.unknown_products(&report.cube) {
+            warn!("The scenario changes {}, which has no sales", product);
+        }
+        let adjusted = scenario.apply(&data.0, &report.cube);
+        let path = cli.chart_file("scenario_chart.png");
+        write_chart(cli, &path, &(&data.0, &adjusted), &labels, |path| create_scenario_chart(path, &data.0, &adjusted, &labels), || vega::scenario_chart(&data.0, &adjusted, &labels))?;
+        let (actual, planned): (f64, f64) = (data.0.iter().map(|(_, sales)| sales).sum(), adjusted.iter().map(|(_, sales)| sales).sum());
+        info!(
+            "Chart saved as {} ({}: total sales {} against {} actual)",
+            path.display(),
+            scenario.describe().join(", "),
+            labels.money.format(planned),
+            labels.money.format(actual)
+        );
+        artifacts.push(path);
+    }
+    if let Some(by) = &cli.facet {
+        let facets = chart_facets(cli, by, &data.0, &report.cube)?;
+        let path = cli.chart_file("facet_chart.png");
+        write_chart(cli, &path, &(&facets, by), &labels, |path| create_facet_chart(path, &facets, by, &labels), || vega::facet_chart(&facets, by, &labels))?;
+        info!("Chart saved as {} ({} panels by {})", path.display(), facets.len(), by);
+        artifacts.push(path);
+    }
+    if let Some(map) = &cli.map {
+        let map = RegionMap::from_arg(map)?;
+        let regions: Vec<(String, f64)> =
+            facets(&data.0, &report.cube, |cell| cell.region.clone()).into_iter().map(|facet| (facet.name, facet.monthly.iter().map(|(_, sales)| sales).sum())).collect();
+        let (sales, unmatched) = map.shade(&regions);
+        if !unmatched.is_empty() {
+            warn!("{} regions are not on the map, left out: {}", unmatched.len(), unmatched.join(", "));
+        }
+        let path = cli.chart_file("map_chart.png");
+        draw_chart(cli, &path, &(&map, &sales), &labels, |path| create_choropleth(path, &map, &sales, &labels))?;
+        info!("Chart saved as {} ({} of {} areas with sales)", path.display(), sales.iter().flatten().count(), sales.len());
+        artifacts.push(path);
+    }
+    if let Some(cards) = kpi_cards(&data, &labels).filter(|_| cli.kpi_cards) {
+        for card in &cards {
+            let path = cli.chart_file(&format!("{}.png", card.name));
+            draw_chart(cli, &path, card, &labels, |path| create_kpi_card(path, card, &labels))?;
+            artifacts.push(path);
+        }
+        info!("Metric cards saved as {}", cards.iter().map(|card| format!("{}.png", card.name)).collect::<Vec<_>>().join(", "));
+    }
+    if cli.executive_summary {
+        let path = cli.chart_file("executive_summary.png");
+        draw_chart(cli, &path, &data, &labels, |path| create_executive_summary(path, &data, &labels))?;
+        info!("Chart saved as {}", path.display());
+        artifacts.push(path);
+    }
+    if cli.streamgraph {
+        let path = cli.chart_file("streamgraph_chart.png");
+        let trends = product_trends(&data, &report.cube);
+        write_chart(cli, &path, &trends, &labels, |path| create_streamgraph(path, &trends, &labels), || vega::streamgraph(&trends, &labels))?;
+        info!("Chart saved as {}", path.display());
+        artifacts.push(path);
+    }
+    if cli.stacked_bar {
+        let path = cli.chart_file("stacked_bar_chart.png");
+        let trends = product_trends(&data, &report.cube);
+        write_chart(cli, &path, &trends, &labels, |path| create_stacked_bar_chart(path, &trends, &labels), || vega::stacked_bar_chart(&trends, &labels))?;
+        info!("Chart saved as {}", path.display());
+        artifacts.push(path);
+    }
+    if cli.bump_chart {
+        let trends = product_trends(&data, &report.cube);
+        let path = cli.chart_file("bump_chart.png");
+        write_chart(cli, &path, &trends, &labels, |path| create_bump_chart(path, &trends, &labels), || vega::bump_chart(&trends, &labels))?;
+        if trends.len() > MAX_BUMP {
+            info!("Chart saved as {} (the {} best-selling of {} products)", path.display(), MAX_BUMP, trends.len());
+        } else {
+            info!("Chart saved as {}", path.display());
+        }
+        artifacts.push(path);
+    }
+    let funnel = funnel_chart(cli, &report, None, &labels, &mut artifacts)?;
+    if let Some(layout) = layout {
+        let trends = product_trends(&data, &report.cube);
+        draw_chart(cli, &layout.output, &(&layout, &data, &trends, &funnel), &labels, |_| create_layout_chart(&layout, &data, &trends, &funnel, &labels))?;
+        info!("Chart saved as {} ({} charts on a {}x{} grid)", layout.output.display(), layout.cells.len(), layout.rows, layout.columns);
+        artifacts.push(layout.output);
+    }
+    let mut product_charts = Vec::new();
+    if let Some(template) = &cli.per_product {
+        let template = cli.chart_file(template).to_string_lossy().into_owned();
+        for (trend, path) in product_trends(&data, &report.cube).iter().zip(product_chart_paths(&template, &data.1)?) {
+            write_chart(cli, &path, &trend, &labels, |path| create_product_chart(path, &trend.product, &trend.monthly, &labels), || vega::product_chart(&trend.product, &trend.monthly, &labels))?;
+            product_charts.push(path);
+        }
+        info!("Charts saved for {} products as {}", product_charts.len(), template);
+        artifacts.extend(product_charts.iter().cloned());
+    }
+    save_chart_cache(cli)?;
+    info!("All charts created successfully!");
 
-        chart.draw_series(std::iter::once(Sector::new(
-            (0, 0),
-            size,
-            start_angle.deg(),
-            (start_angle + angle).deg(),
-            color.filled(),
-        )))?;
+    if cli.lenient {
+        artifacts.push(PathBuf::from(ERROR_REPORT_PATH));
+    }
+    artifacts.extend(cli.suspicious_report.clone());
+    if let Some(path) = &cli.dashboard {
+        let title = format!("Sales dashboard - {}", input.display());
+        write_dashboard(path, &title, &report.cube, &labels.money)?;
+        info!("Dashboard saved as {}", path.display());
+        artifacts.push(path.clone());
+    }
+    let holidays = holiday_summary(calendar.as_ref(), &report, &labels.money);
+    let summary = RunSummary { holidays, budget: variance, product_charts, funnel, ..RunSummary::success(input, &data, &report, artifacts) };
+    publish(cli, summary, &labels)
+}
 
-        let mid_angle = start_angle + angle / 2.0;
-        let (x, y) = (mid_angle.cos(), mid_angle.sin());
-        
-        chart.draw_series(std::iter::once(Text::new(
-            format!("{}: ${:.2} ({:.1}%)", product, sales, sales / total_sales * 100.0),
-            (x * size as f64 * 0.7, y * size as f64 * 0.7),
-            ("sans-serif", 15).into_font(),
-        )))?;
+// The --charts of `data` out of the line, bar, and pie charts, named after `currency` when the
+// data is charted by currency.
+fn basic_charts(cli: &Cli, data: &PlotData, labels: &ChartLabels, currency: Option<&str>) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let mut files = Vec::new();
+    for chart in [BasicChart::Line, BasicChart::Bar, BasicChart::Pie].into_iter().filter(|chart| cli.charts.contains(chart)) {
+        let path = in_currency(&cli.chart_file(chart.file_name()), currency);
+        match chart {
+            BasicChart::Line => write_chart(cli, &path, &data.0, labels, |path| create_line_chart(path, &data.0, labels), || vega::line_chart(&data.0, labels))?,
+            BasicChart::Bar => write_chart(cli, &path, &data.1, labels, |path| create_bar_chart(path, &data.1, labels), || vega::bar_chart(&data.1, labels))?,
+            BasicChart::Pie => write_chart(cli, &path, &data.1, labels, |path| create_pie_chart(path, &data.1, labels), || vega::pie_chart(&data.1, labels))?,
+        }
+        files.push(path);
+    }
+    Ok(files)
+}
 
-        start_angle += angle;
+// Writes a chart to `path`, drawn by `draw` as a PNG or an SVG image by the file's extension, or
+// as the Vega-Lite spec of `spec` with --output-format vega. `inputs` and `labels` are what it shows, for --chart-cache.
+fn write_chart<D, S>(cli: &Cli, path: &Path, inputs: &dyn Debug, labels: &ChartLabels, draw: D, spec: S) -> Result<(), Box<dyn Error>>
+where
+    D: FnOnce(&Path) -> Result<(), Box<dyn Error>>,
+    S: FnOnce() -> serde_json::Value,
+{
+    match cli.output_format {
+        OutputFormat::Png | OutputFormat::Svg => draw_chart(cli, path, inputs, labels, draw),
+        OutputFormat::Vega => draw_chart(cli, path, inputs, labels, |path| vega::write_spec(path, &spec())),
     }
+}
 
-    root.present()?;
-    println!("Pie chart saved as pie_chart.png");
+// Draws the chart at `path` with `draw`, unless --chart-cache has it drawn from the same
+// `inputs` and `labels` already and the file unchanged since.
+fn draw_chart<D>(cli: &Cli, path: &Path, inputs: &dyn Debug, labels: &ChartLabels, draw: D) -> Result<(), Box<dyn Error>>
+where
+    D: FnOnce(&Path) -> Result<(), Box<dyn Error>>,
+{
+    let Some(file) = &cli.chart_cache else {
+        return draw(path);
+    };
+    let mut opened = cli.opened_chart_cache.lock().unwrap_or_else(PoisonError::into_inner);
+    let cache = match &mut *opened {
+        Some(cache) => cache,
+        None => opened.insert(ChartCache::open(file)?),
+    };
+    let inputs = ChartCache::inputs(inputs, labels);
+    if cache.is_current(path, inputs) {
+        debug!("{} is unchanged, not drawn again", path.display());
+        cache.kept += 1;
+        return Ok(());
+    }
+    draw(path)?;
+    cache.record(path, inputs);
+    cache.drawn += 1;
     Ok(())
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let file = File::open("large_sales_data.csv")?;
-    let mut rdr = ReaderBuilder::new().has_headers(true).from_reader(file);
+// Saves --chart-cache once the charts of a run are drawn.
+fn save_chart_cache(cli: &Cli) -> Result<(), Box<dyn Error>> {
+    let mut opened = cli.opened_chart_cache.lock().unwrap_or_else(PoisonError::into_inner);
+    let Some(cache) = opened.as_mut() else {
+        return Ok(());
+    };
+    if cache.kept > 0 {
+        info!("{} charts unchanged since the last run, {} drawn", cache.kept, cache.drawn);
+    }
+    cache.save()
+}
 
-    match process_sales_data(&mut rdr) {
-        Ok((sales_by_month, sales_by_product)) => {
-            let (monthly_data, product_data) = prepare_data_for_plotting(sales_by_month, sales_by_product);
-            create_line_chart(&monthly_data)?;
-            create_bar_chart(&product_data)?;
-            create_pie_chart(&product_data)?;
-            println!("All charts created successfully!");
+// The --per-product file of each product, with `{product}` in `template` replaced by the
+// product's name. Characters other than letters, digits, `-`, and `.` become `_` so the name
+// is safe on any file system and in a URL.
+fn product_chart_paths(template: &str, products: &[(String, f64)]) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    if !template.contains("{product}") {
+        return Err(format!("--per-product {} needs {{product}} in the file name to tell the products apart", template).into());
+    }
+    let mut written: HashMap<PathBuf, &str> = HashMap::new();
+    let mut paths = Vec::new();
+    for (product, _) in products {
+        let name: String = product.chars().map(|c| if c.is_alphanumeric() || c == '-' || c == '.' { c } else { '_' }).collect();
+        let path = PathBuf::from(template.replace("{product}", &name));
+        if let Some(other) = written.insert(path.clone(), product) {
+            return Err(format!("--per-product would write both {} and {} to {}", other, product, path.display()).into());
         }
-        Err(e) => {
-            eprintln!("Error processing sales data: {}", e);
-            return Err(e);
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+// Panels of a faceted chart beyond this many are left out, the smallest sellers first.
+const MAX_FACETS: usize = 36;
+
+// The panels of --facet: one per value of its column, or per category or brand of the
+// --categories file.
+fn chart_facets(cli: &Cli, by: &str, monthly: &[(NaiveDate, f64)], cube: &[CubeCell]) -> Result<Vec<Facet>, Box<dyn Error>> {
+    let mut panels = match (cli.facet_column(), &cli.categories) {
+        (Some(_), _) | (None, None) => facets(monthly, cube, |cell| cell.region.clone()),
+        (None, Some(path)) => {
+            let grouping = if by == "brand" { Grouping::Brand } else { Grouping::Category };
+            let categories = ProductCategories::from_path(path)?;
+            // Grouped data is already keyed by the grouping.
+            match (cli.group_by, grouping) {
+                (GroupBy::Product, _) => {
+                    facets(monthly, cube, |cell| categories.lookup(&cell.product, grouping).unwrap_or(UNCATEGORIZED).to_string())
+                }
+                (GroupBy::Category, Grouping::Category) | (GroupBy::Brand, Grouping::Brand) => facets(monthly, cube, |cell| cell.product.clone()),
+                _ => return Err(format!("--facet {} cannot split data grouped by another --group-by", by).into()),
+            }
         }
+    };
+    if panels.len() > MAX_FACETS {
+        warn!("Charting the {} best-selling of {} {} values in the faceted chart", MAX_FACETS, panels.len(), by);
+        panels.truncate(MAX_FACETS);
+    }
+    Ok(panels)
+}
+
+// The holidays within the months on the line charts. Fiscal periods are charted as the months
+// standing for them, where holiday dates would land in the wrong period.
+fn chart_holidays(cli: &Cli, calendar: Option<&HolidayCalendar>, monthly: &[(NaiveDate, f64)]) -> Vec<Holiday> {
+    match (calendar, monthly.first(), monthly.last()) {
+        (Some(calendar), Some((first, _)), Some((last, _))) if cli.input.retail_calendar.is_none() => calendar.between(*first, *last),
+        _ => Vec::new(),
     }
+}
+
+// Average sales on holidays and other days, for data with daily dates.
+fn holiday_summary(calendar: Option<&HolidayCalendar>, report: &ParseReport, money: &MoneyFormat) -> Option<HolidayAverages> {
+    let calendar = calendar?;
+    let daily: Vec<(NaiveDate, f64)> = report.daily.iter().map(|(day, sales)| (key_to_date(*day), *sales)).collect();
+    let (first, last) = (daily.first()?.0, daily.last()?.0);
+    let averages = holiday_averages(&daily, &calendar.between(first, last))?;
+    info!(
+        "Average daily sales {} on {} holidays, {} on {} other days",
+        money.format(averages.holiday_average),
+        averages.holiday_days,
+        money.format(averages.other_average),
+        averages.other_days
+    );
+    Some(averages)
+}
 
+// Each of `currencies` is read and charted on its own, into files named after it such as
+// `line_chart_EUR.png`, and gets its own section of the HTML report.
+fn chart_by_currency(cli: &Cli, input: &Path, currencies: &[String]) -> Result<RunSummary, Box<dyn Error>> {
+    // Options charting totals across the currencies, or amounts in one of them.
+    let converting = [
+        ("--cpi", cli.cpi.is_some()),
+        ("--dashboard", cli.dashboard.is_some()),
+        ("--budget", cli.budget.is_some()),
+        ("--facet", cli.facet.is_some()),
+        ("--per-product", cli.per_product.is_some()),
+        ("--map", cli.map.is_some()),
+        ("--fixed-costs", cli.fixed_costs.is_some()),
+        ("--scenario", !cli.scenario.is_empty()),
+        ("--bump-chart", cli.bump_chart),
+        ("--streamgraph", cli.streamgraph),
+        ("--stacked-bar", cli.stacked_bar),
+        ("--kpi-cards", cli.kpi_cards),
+        ("--executive-summary", cli.executive_summary),
+        ("--layout", cli.layout.is_some()),
+    ];
+    let given: Vec<&str> = converting.iter().filter(|(_, given)| *given).map(|(option, _)| *option).collect();
+    if !given.is_empty() {
+        let verb = if given.len() == 1 { "needs" } else { "need" };
+        return Err(format!("{} {} --fx-rates to convert data in more than one currency", given.join(", "), verb).into());
+    }
+    info!("Charting {} currencies separately: {}", currencies.len(), currencies.join(", "));
+    let calendar = cli.holiday_calendar()?;
+    let mut sections = Vec::new();
+    let mut artifacts = Vec::new();
+    for currency in currencies {
+        let (data, report) = analyze_in(cli, input, Some(currency), true)?;
+        let mut labels = cli.chart_labels_in(Some(currency)).for_data(&data, report.rows);
+        labels.holidays = chart_holidays(cli, calendar.as_ref(), &data.0);
+        let mut files = basic_charts(cli, &data, &labels, Some(currency))?;
+        for path in &files {
+            info!("Chart saved as {}", path.display());
+        }
+        let funnel = funnel_chart(cli, &report, Some(currency), &labels, &mut files)?;
+        if cli.lenient {
+            files.push(in_currency(Path::new(ERROR_REPORT_PATH), Some(currency)));
+        }
+        files.extend(cli.suspicious_report.as_deref().map(|path| in_currency(path, Some(currency))));
+        artifacts.extend(files.iter().cloned());
+        let holidays = holiday_summary(calendar.as_ref(), &report, &labels.money);
+        sections.push(RunSummary { currency: Some(currency.clone()), holidays, funnel, ..RunSummary::success(input, &data, &report, files) });
+    }
+    save_chart_cache(cli)?;
+    info!("All charts created successfully!");
+    publish(cli, RunSummary::by_currency(input, sections, artifacts), &cli.chart_labels())
+}
+
+// The funnel of the data's stages in --stages order, charted when the data has a stage column.
+fn funnel_chart(cli: &Cli, report: &ParseReport, currency: Option<&str>, labels: &ChartLabels, artifacts: &mut Vec<PathBuf>) -> Result<Vec<FunnelStage>, Box<dyn Error>> {
+    if report.stages.is_empty() {
+        if !cli.stages.is_empty() {
+            warn!("--stages is ignored: the data has no {} column", cli.input.stage_column);
+        }
+        return Ok(Vec::new());
+    }
+    let unlisted: Vec<&str> =
+        report.stages.iter().map(|(stage, _)| stage.as_str()).filter(|stage| !cli.stages.is_empty() && !cli.stages.iter().any(|listed| listed.eq_ignore_ascii_case(stage))).collect();
+    if !unlisted.is_empty() {
+        warn!("{} stages are not in --stages, added at the bottom of the funnel: {}", unlisted.len(), unlisted.join(", "));
+    }
+    let stages = funnel(&report.stages, &cli.stages);
+    let path = in_currency(&cli.chart_file("funnel_chart.png"), currency);
+    write_chart(cli, &path, &stages, labels, |path| create_funnel_chart(path, &stages, labels), || vega::funnel_chart(&stages, labels))?;
+    info!("Chart saved as {} ({} stages)", path.display(), stages.len());
+    artifacts.push(path);
+    Ok(stages)
+}
+
+// The --script's metrics of each currency of `summary`, or of the whole run when it is not
+// split by currency, logged as they are added.
+#[cfg(feature = "scripting")]
+fn add_metrics(script: &Script, summary: &mut RunSummary) -> Result<(), Box<dyn Error>> {
+    let split = !summary.currencies.is_empty();
+    let sections: Vec<&mut RunSummary> = if split { summary.currencies.iter_mut().collect() } else { vec![summary] };
+    for section in sections {
+        section.metrics = script.metrics(section)?;
+        let currency = section.currency.as_ref().filter(|_| split).map_or(String::new(), |currency| format!(" ({})", currency));
+        for (name, value) in &section.metrics {
+            info!("{}{}: {}", name, currency, value.as_str().map_or_else(|| value.to_string(), str::to_string));
+        }
+    }
     Ok(())
-}
\ No newline at end of file
+}
+
+// Stores the run, or each currency of a run split by currency as a dataset of its own, after
+// comparing it with the dataset's previous run, and charts the total of every run so far into
+// `chart`.
+#[cfg(feature = "history")]
+fn record_history(path: &Path, chart: &Path, summary: &mut RunSummary, labels: &ChartLabels) -> Result<(), Box<dyn Error>> {
+    use sales_chart::charts::create_history_chart;
+    use sales_chart::history::RunHistory;
+    use sales_chart::summary::{Aggregates, RunChanges};
+
+    let mut history = RunHistory::open(path)?;
+    let run_at = Local::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, false);
+    let input = summary.input.display().to_string();
+    let split = !summary.currencies.is_empty();
+    let sections: Vec<&mut RunSummary> = if split { summary.currencies.iter_mut().collect() } else { vec![&mut *summary] };
+    let mut charts = Vec::new();
+    for section in sections {
+        let currency = section.currency.clone().filter(|_| split);
+        let dataset = currency.as_ref().map_or(input.clone(), |currency| format!("{} ({})", input, currency));
+        if let Some(previous) = history.runs(Some(&dataset))?.pop() {
+            let changes = RunChanges::between(&previous.run_at, &Aggregates::new(&history.plot_data(previous.id)?), &section.aggregates);
+            log_run_changes(&dataset, &changes, &labels.money);
+            section.run_changes = Some(changes);
+        }
+        let id = history.record(&dataset, &run_at, section)?;
+        let runs: Vec<(String, f64)> = history
+            .runs(Some(&dataset))?
+            .into_iter()
+            .map(|run| (chrono::DateTime::parse_from_rfc3339(&run.run_at).map_or(run.run_at.clone(), |at| at.format("%Y-%m-%d %H:%M").to_string()), run.total_sales))
+            .collect();
+        let file = in_currency(chart, currency.as_deref());
+        create_history_chart(&file, &runs, labels)?;
+        info!("Run {} stored in {}; chart saved as {} ({} runs)", id, path.display(), file.display(), runs.len());
+        charts.push(file);
+    }
+    summary.artifacts.extend(charts);
+    Ok(())
+}
+
+#[cfg(feature = "history")]
+fn log_run_changes(dataset: &str, changes: &sales_chart::summary::RunChanges, money: &MoneyFormat) {
+    if changes.is_empty() {
+        info!("{} has the same totals as in the run of {}", dataset, changes.previous_run);
+        return;
+    }
+    info!(
+        "Since the run of {}, {} has {} new products, {} disappeared products, and {} changed months",
+        changes.previous_run,
+        dataset,
+        changes.new_products.len(),
+        changes.removed_products.len(),
+        changes.changed_months.len()
+    );
+    if !changes.new_products.is_empty() {
+        info!("New products: {}", changes.new_products.join(", "));
+    }
+    if !changes.removed_products.is_empty() {
+        warn!("Products no longer in the data: {}", changes.removed_products.join(", "));
+    }
+    for month in &changes.changed_months {
+        warn!("{} changed from {} to {}", month.month, money.format(month.previous), money.format(month.current));
+    }
+}
+
+// Lists the stored runs, or redraws one run's charts into the current directory without reading
+// its input again.
+#[cfg(feature = "history")]
+fn run_history(cli: &Cli, database: &Path, dataset: Option<&str>, run: Option<i64>) -> Result<(), Box<dyn Error>> {
+    use sales_chart::history::RunHistory;
+
+    let history = RunHistory::open(database)?;
+    let runs = history.runs(dataset)?;
+    let Some(id) = run else {
+        let labels = cli.chart_labels();
+        for run in &runs {
+            println!("{:>6}  {}  {}  {}", run.id, run.run_at, labels.money.format(run.total_sales), run.dataset);
+        }
+        return Ok(());
+    };
+    let data = history.plot_data(id)?;
+    let rows = runs.iter().find(|stored| stored.id == id).map_or(0, |stored| stored.rows);
+    let labels = cli.chart_labels().for_data(&data, rows);
+    cli.check_font()?;
+    cli.chart_dir()?;
+    for path in basic_charts(cli, &data, &labels, None)? {
+        info!("Chart saved as {}", path.display());
+    }
+    save_chart_cache(cli)
+}
+
+// `path` of one currency where the data is charted by currency: `errors.csv` becomes `errors_EUR.csv`.
+fn in_currency(path: &Path, currency: Option<&str>) -> PathBuf {
+    let Some(currency) = currency else {
+        return path.to_path_buf();
+    };
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    match path.extension() {
+        Some(extension) => path.with_file_name(format!("{}_{}.{}", stem, currency, extension.to_string_lossy())),
+        None => path.with_file_name(format!("{}_{}", stem, currency)),
+    }
+}
+
+// What is made from the finished outputs: the run history, the HTML report, the --verify check, and uploads.
+fn publish(cli: &Cli, mut summary: RunSummary, labels: &ChartLabels) -> Result<RunSummary, Box<dyn Error>> {
+    #[cfg(feature = "scripting")]
+    if let Some(script) = cli.script()? {
+        add_metrics(&script, &mut summary)?;
+    }
+    for section in &mut summary.currencies {
+        section.narrative = narrative(section, &labels.money);
+    }
+    summary.narrative = narrative(&summary, &labels.money);
+    for section in &mut summary.currencies {
+        section.alerts = check_alerts(&cli.alert, section, &labels.money);
+    }
+    summary.alerts = check_alerts(&cli.alert, &summary, &labels.money);
+    for alert in &summary.alerts {
+        warn!("Alert: {}", alert);
+    }
+    #[cfg(feature = "history")]
+    if let Some(path) = &cli.history {
+        record_history(path, &cli.chart_file("history_chart.png"), &mut summary, labels)?;
+    }
+    if let Some(path) = &cli.html_report {
+        match &cli.report_template {
+            #[cfg(feature = "templates")]
+            Some(template) => sales_chart::report::write_templated_report(path, template, &summary, labels)?,
+            _ => write_html_report(path, &summary, labels)?,
+        }
+        info!("Report saved as {}", path.display());
+        summary.artifacts.push(path.clone());
+    }
+    let artifacts = &summary.artifacts;
+    if let Some(golden_dir) = &cli.verify {
+        let mismatches = compare_outputs(artifacts, golden_dir)?;
+        if !mismatches.is_empty() {
+            let listed: Vec<String> = mismatches.iter().map(|m| m.to_string()).collect();
+            return Err(format!("{} outputs differ from {}:\n  {}", mismatches.len(), golden_dir.display(), listed.join("\n  ")).into());
+        }
+        info!("All {} outputs match {}", artifacts.len(), golden_dir.display());
+    }
+    if let Some(destination) = &cli.upload {
+        for url in destination.upload(artifacts)? {
+            info!("Uploaded {}", url);
+        }
+    }
+    Ok(summary)
+}
+
+// Everything between reading the input and drawing: parsing, reporting, and product clean-up.
+#[cfg(any(feature = "gui", feature = "server"))]
+fn analyze(cli: &Cli, input: &Path) -> Result<(PlotData, ParseReport), Box<dyn Error>> {
+    analyze_in(cli, input, None, false)
+}
+
+// `analyze` of the rows in `currency` only; with `split`, the data has other currencies too
+// and the reports written here are named after this one.
+fn analyze_in(cli: &Cli, input: &Path, currency: Option<&str>, split: bool) -> Result<(PlotData, ParseReport), Box<dyn Error>> {
+    let _span = debug_span!("analyze", input = %input.display(), currency).entered();
+    let section = currency.filter(|_| split);
+    let ((sales_by_month, sales_by_product), mut report) = load_sales_data(cli, input, currency)?;
+    if cli.lenient {
+        let path = in_currency(Path::new(ERROR_REPORT_PATH), section);
+        write_error_report(&path, &report.errors)?;
+        info!("Skipped {} malformed rows, see {}", report.errors.len(), path.display());
+    }
+    if report.duplicates > 0 {
+        if cli.dedupe {
+            info!("Collapsed {} duplicate rows", report.duplicates);
+        } else {
+            warn!("Found {} duplicate rows, use --dedupe to collapse them", report.duplicates);
+        }
+    }
+    if !report.suspicious.is_empty() {
+        info!("Flagged {} suspicious rows", report.suspicious.len());
+    }
+    if let Some(path) = &cli.suspicious_report {
+        let path = in_currency(path, section);
+        write_suspicious_report(&path, &report.suspicious)?;
+        info!("Suspicious rows written to {}", path.display());
+    }
+    if report.missing_amounts > 0 {
+        match cli.input.missing_amount {
+            MissingAmount::Zero => info!("Treated {} missing amounts as zero", report.missing_amounts),
+            _ => info!("Skipped {} rows with missing amounts", report.missing_amounts),
+        }
+    }
+
+    let (sales_by_product, names) = tidy_product_names(cli, sales_by_product, false)?;
+    for cell in &mut report.cube {
+        if let Some(name) = names.get(&cell.product) {
+            cell.product.clone_from(name);
+        }
+    }
+
+    let total_sales = report.exact_total.unwrap_or_else(|| sales_by_month.values().sum());
+    info!(
+        "Aggregated {} months and {} {}, total sales {}",
+        sales_by_month.len(),
+        sales_by_product.len(),
+        match cli.group_by {
+            GroupBy::Product => "products",
+            GroupBy::Category => "categories",
+            GroupBy::Brand => "brands",
+        },
+        cli.chart_labels_in(currency).money.format(total_sales)
+    );
+    if cli.engine == Engine::Native {
+        info!("{}", QualityReport::new(&report, &sales_by_month));
+    }
+
+    Ok((prepare_data_for_plotting(sales_by_month, sales_by_product), report))
+}
+
+fn tidy_products(cli: &Cli, sales_by_product: HashMap<String, f64>, quiet: bool) -> Result<HashMap<String, f64>, Box<dyn Error>> {
+    tidy_product_names(cli, sales_by_product, quiet).map(|(sales_by_product, _)| sales_by_product)
+}
+
+// Aliases, name normalization, fuzzy merging, and category grouping, in that order. Also
+// returns the name each original product ended up under. `quiet` suppresses the messages,
+// for callers that own the terminal.
+type ProductNames = HashMap<String, String>;
+
+fn tidy_product_names(
+    cli: &Cli,
+    mut sales_by_product: HashMap<String, f64>,
+    quiet: bool,
+) -> Result<(HashMap<String, f64>, ProductNames), Box<dyn Error>> {
+    let aliases = cli.product_aliases.as_ref().map(ProductAliases::from_path).transpose()?;
+    let categories = cli.categories.as_ref().map(ProductCategories::from_path).transpose()?;
+    let grouping = match cli.group_by {
+        GroupBy::Product => None,
+        GroupBy::Category => Some(Grouping::Category),
+        GroupBy::Brand => Some(Grouping::Brand),
+    };
+    if grouping.is_some() && categories.is_none() {
+        return Err("--group-by category and --group-by brand need a --categories file".into());
+    }
+    let mut notes = Vec::new();
+    let mut warnings = Vec::new();
+    let mut names: ProductNames = sales_by_product.keys().map(|name| (name.clone(), name.clone())).collect();
+    let follow = |names: &mut ProductNames, merges: &[ProductMerge]| {
+        let into: HashMap<&str, &str> =
+            merges.iter().flat_map(|merge| merge.from.iter().map(|from| (from.as_str(), merge.into.as_str()))).collect();
+        for name in names.values_mut() {
+            if let Some(target) = into.get(name.as_str()) {
+                *name = target.to_string();
+            }
+        }
+    };
+
+    if let Some(aliases) = &aliases {
+        let (renamed, merges) = aliases.apply(sales_by_product);
+        sales_by_product = renamed;
+        follow(&mut names, &merges);
+        for merge in &merges {
+            notes.push(format!("Renamed products {}", merge));
+        }
+    }
+    if cli.normalize_products {
+        let (normalized, merges) = normalize_products(sales_by_product);
+        sales_by_product = normalized;
+        follow(&mut names, &merges);
+        for merge in &merges {
+            notes.push(format!("Merged product names {}", merge));
+        }
+    }
+    let pairs = near_duplicates(&sales_by_product);
+    if cli.fuzzy_merge {
+        let (merged, merges) = fuzzy_merge(sales_by_product, &pairs);
+        sales_by_product = merged;
+        follow(&mut names, &merges);
+        for merge in &merges {
+            notes.push(format!("Fuzzy-merged products {}", merge));
+        }
+    } else {
+        for pair in &pairs {
+            warnings.push(format!(
+                "product {:?} looks like a misspelling of {:?}, use --fuzzy-merge to merge them",
+                pair.name, pair.similar_to
+            ));
+        }
+    }
+
+    if let (Some(categories), Some(grouping)) = (&categories, grouping) {
+        let (grouped, unmapped) = categories.group(&sales_by_product, grouping);
+        sales_by_product = grouped;
+        for name in names.values_mut() {
+            *name = categories.lookup(name, grouping).unwrap_or(UNCATEGORIZED).to_string();
+        }
+        if !unmapped.is_empty() {
+            warnings.push(format!(
+                "{} products are not in the categories file, grouped as Uncategorized: {}",
+                unmapped.len(),
+                unmapped.join(", ")
+            ));
+        }
+    }
+
+    if !quiet {
+        notes.iter().for_each(|note| info!("{}", note));
+        warnings.iter().for_each(|warning| warn!("{}", warning));
+    }
+    Ok((sales_by_product, names))
+}
@@ -0,0 +1,149 @@
+use chrono::NaiveDate;
+
+use crate::budget::BudgetVariance;
+use crate::fx::currency_symbol;
+use crate::number::MoneyFormat;
+use crate::summary::{ProductTrend, RunSummary};
+
+// Growing or declining this many months in a row gets a product a sentence of its own.
+const STREAK_MONTHS: usize = 3;
+// Names listed in one sentence before the rest are counted instead.
+const MAX_LISTED: usize = 3;
+
+/// A few sentences on the totals of `summary`, such as "Sales grew 12.0% month on month to
+/// $48,210.00 in 2024-06, led by Product B (+$3,120.00). Product D declined for the third
+/// consecutive month." Which product led a change and which kept growing or declining come
+/// from the per-product monthly totals, so they are left out when the run did not collect
+/// them. A run split by currency gets the sentences of each currency in turn, and a failed run
+/// none.
+pub fn narrative(summary: &RunSummary, money: &MoneyFormat) -> String {
+    if !summary.currencies.is_empty() {
+        let sections: Vec<String> = summary
+            .currencies
+            .iter()
+            .map(|section| (section.currency.as_deref().unwrap_or_default(), narrative(section, money)))
+            .filter(|(_, text)| !text.is_empty())
+            .map(|(currency, text)| format!("{}: {}", currency, text))
+            .collect();
+        return sections.join(" ");
+    }
+    let money = &match &summary.currency {
+        Some(currency) => MoneyFormat { symbol: currency_symbol(currency), ..money.clone() },
+        None => money.clone(),
+    };
+    let monthly = &summary.aggregates.monthly;
+    let Some(latest) = monthly.last() else {
+        return String::new();
+    };
+    let mut sentences = Vec::new();
+    let previous = monthly.len().checked_sub(2).map(|i| &monthly[i]);
+    sentences.push(match (previous, summary.latest_change) {
+        (Some(_), Some(change)) if change.abs() < 0.05 => format!("Sales held steady at {} in {}.", money.format(latest.sales), latest.month),
+        (Some(_), Some(change)) => {
+            let verb = if change > 0.0 { "grew" } else { "fell" };
+            let mut sentence = format!("Sales {} {} month on month to {} in {}", verb, money.percent(change.abs(), false), money.format(latest.sales), latest.month);
+            if let Some((product, difference)) = biggest_mover(&summary.product_trends, change > 0.0) {
+                let amount = if difference > 0.0 { format!("+{}", money.format(difference)) } else { money.format(difference) };
+                let lead = if change > 0.0 { "led by" } else { "most of all in" };
+                sentence.push_str(&format!(", {} {} ({})", lead, product, amount));
+            }
+            sentence + "."
+        }
+        (Some(previous), None) => format!("Sales were {} in {}, after none in {}.", money.format(latest.sales), latest.month, previous.month),
+        (None, _) => format!("Sales were {} in {}.", money.format(latest.sales), latest.month),
+    });
+    let products = &summary.aggregates.products;
+    if let Some(best) = products.iter().max_by(|a, b| a.sales.total_cmp(&b.sales)).filter(|_| products.len() > 1 && summary.total_sales > 0.0) {
+        sentences.push(format!("{} sold the most overall, {} of all sales.", best.product, money.percent(best.sales / summary.total_sales * 100.0, false)));
+    }
+    sentences.extend(streak_sentences(&summary.product_trends));
+    let missing = &summary.anomalies.missing_months;
+    if !missing.is_empty() {
+        let months = if missing.len() == 1 { "month" } else { "months" };
+        sentences.push(format!("{} {} had no sales: {}.", missing.len(), months, listing(missing, "other months")));
+    }
+    sentences.extend(budget_sentence(&summary.budget, money));
+    sentences.join(" ")
+}
+
+// The product whose sales rose the most from the month before the latest, or fell the most,
+// and by how much; None when no product moved that way.
+fn biggest_mover(trends: &[ProductTrend], grew: bool) -> Option<(&str, f64)> {
+    let differences = trends.iter().filter_map(|trend| match trend.monthly.as_slice() {
+        [.., (_, previous), (_, last)] => Some((trend.product.as_str(), last - previous)),
+        _ => None,
+    });
+    if grew {
+        differences.filter(|(_, difference)| *difference > 0.0).max_by(|a, b| a.1.total_cmp(&b.1))
+    } else {
+        differences.filter(|(_, difference)| *difference < 0.0).min_by(|a, b| a.1.total_cmp(&b.1))
+    }
+}
+
+// How many months in a row up to the latest the sales kept rising, or falling when negative.
+fn streak(monthly: &[(NaiveDate, f64)]) -> isize {
+    let changes: Vec<f64> = monthly.windows(2).rev().map(|pair| pair[1].1 - pair[0].1).collect();
+    let Some(&latest) = changes.first().filter(|change| **change != 0.0) else {
+        return 0;
+    };
+    let months = changes.iter().take_while(|change| change.signum() == latest.signum() && **change != 0.0).count() as isize;
+    if latest > 0.0 {
+        months
+    } else {
+        -months
+    }
+}
+
+// A sentence per length of streak, declines first and the longest first.
+fn streak_sentences(trends: &[ProductTrend]) -> Vec<String> {
+    let mut streaks: Vec<(isize, &str)> =
+        trends.iter().map(|trend| (streak(&trend.monthly), trend.product.as_str())).filter(|(months, _)| months.unsigned_abs() >= STREAK_MONTHS).collect();
+    streaks.sort_by_key(|(months, _)| (*months > 0, -(months.unsigned_abs() as isize)));
+    streaks
+        .chunk_by(|a, b| a.0 == b.0)
+        .map(|group| {
+            let months = group[0].0;
+            let names: Vec<String> = group.iter().map(|(_, product)| product.to_string()).collect();
+            let verb = if months > 0 { "grew" } else { "declined" };
+            format!("{} {} for the {} consecutive month.", listing(&names, "other products"), verb, ordinal(months.unsigned_abs()))
+        })
+        .collect()
+}
+
+// How sales over the budgeted months compared with their targets.
+fn budget_sentence(budget: &[BudgetVariance], money: &MoneyFormat) -> Option<String> {
+    let target: f64 = budget.iter().map(|month| month.target).sum();
+    if budget.is_empty() || target == 0.0 {
+        return None;
+    }
+    let variance: f64 = budget.iter().map(|month| month.variance).sum();
+    let months = if budget.len() == 1 { "the budgeted month".to_string() } else { format!("the {} budgeted months", budget.len()) };
+    let side = if variance >= 0.0 { "above" } else { "below" };
+    Some(format!("Sales were {} {} budget over {}.", money.percent((variance / target * 100.0).abs(), false), side, months))
+}
+
+// "A", "A and B", "A, B, and C", or the first few and how many `others` there are besides.
+fn listing(names: &[String], others: &str) -> String {
+    match names {
+        [] => String::new(),
+        [name] => name.clone(),
+        [first, second] => format!("{} and {}", first, second),
+        _ if names.len() <= MAX_LISTED => format!("{}, and {}", names[..names.len() - 1].join(", "), names[names.len() - 1]),
+        _ => format!("{}, and {} {}", names[..MAX_LISTED].join(", "), names.len() - MAX_LISTED, others),
+    }
+}
+
+fn ordinal(n: usize) -> String {
+    const WORDS: [&str; 12] = ["first", "second", "third", "fourth", "fifth", "sixth", "seventh", "eighth", "ninth", "tenth", "eleventh", "twelfth"];
+    if let Some(word) = n.checked_sub(1).and_then(|i| WORDS.get(i)) {
+        return word.to_string();
+    }
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{}{}", n, suffix)
+}
@@ -0,0 +1,394 @@
+use crate::{finite_amount, parse_amount};
+
+const SPACES: &[char] = &[' ', '\u{a0}', '\u{202f}'];
+const CURRENCY_SYMBOLS: &[char] = &['$', '€', '£', '¥'];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NumberLocale {
+    pub decimal: char,
+    pub grouping: &'static [char],
+}
+
+impl NumberLocale {
+    pub const POINT: NumberLocale = NumberLocale { decimal: '.', grouping: &[','] };
+    pub const COMMA_DOT: NumberLocale = NumberLocale { decimal: ',', grouping: &['.'] };
+    pub const COMMA_SPACE: NumberLocale = NumberLocale { decimal: ',', grouping: SPACES };
+    pub const POINT_APOSTROPHE: NumberLocale = NumberLocale { decimal: '.', grouping: &['\'', '\u{2019}'] };
+
+    /// Looks up the separators for a BCP 47-ish tag such as `de`, `fr-FR`, or `de_CH`.
+    pub fn from_tag(tag: &str) -> Result<Self, String> {
+        let tag = tag.to_lowercase().replace('_', "-");
+        if tag == "de-ch" || tag == "it-ch" || tag == "fr-ch" {
+            return Ok(NumberLocale::POINT_APOSTROPHE);
+        }
+        let language = tag.split('-').next().unwrap_or_default();
+        match language {
+            "en" | "ja" | "zh" | "ko" | "he" | "th" | "hi" | "ms" => Ok(NumberLocale::POINT),
+            "de" | "es" | "it" | "nl" | "pt" | "id" | "tr" | "da" | "ro" | "el" | "hr" | "sl" | "sr" => {
+                Ok(NumberLocale::COMMA_DOT)
+            }
+            "fr" | "sv" | "no" | "nb" | "nn" | "fi" | "pl" | "ru" | "cs" | "sk" | "uk" | "hu" | "bg" | "et"
+            | "lv" | "lt" => Ok(NumberLocale::COMMA_SPACE),
+            _ => Err(format!("Unsupported number locale: {}", tag)),
+        }
+    }
+
+    fn normalize(&self, text: &str) -> String {
+        text.trim()
+            .chars()
+            .filter(|c| !self.grouping.contains(c))
+            .map(|c| if c == self.decimal { '.' } else { c })
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct NumberFormat {
+    pub locale: Option<NumberLocale>,
+    pub clean: bool,
+}
+
+impl NumberFormat {
+    pub fn parse(&self, field: &[u8]) -> Result<f64, String> {
+        if self.locale.is_none() && !self.clean {
+            return parse_amount(field);
+        }
+
+        let text = String::from_utf8_lossy(field);
+        let mut normalized = text.to_string();
+        if self.clean {
+            normalized = normalized.chars().filter(|c| !CURRENCY_SYMBOLS.contains(c)).collect();
+            // Without a locale, commas can only be thousands separators.
+            if self.locale.is_none() {
+                normalized.retain(|c| c != ',');
+            }
+            normalized = normalized.trim().to_string();
+        }
+
+        match &self.locale {
+            Some(locale) => lexical_core::parse(locale.normalize(&normalized).as_bytes())
+                .map_err(|_| format!("Invalid sales number in \"{}\": expected decimal separator '{}'", text, locale.decimal))
+                .and_then(|amount| finite_amount(amount, &text)),
+            None => lexical_core::parse(normalized.as_bytes())
+                .map_err(|e| format!("Invalid sales number in \"{}\": {}", text, e))
+                .and_then(|amount| finite_amount(amount, &text)),
+        }
+    }
+}
+
+/// Which side of the amount the currency symbol goes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SymbolPosition {
+    #[default]
+    Prefix,
+    Suffix,
+}
+
+/// Where the grouping separator goes in the whole part of an amount.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DigitGrouping {
+    /// Every three digits, as in 12,345,678
+    #[default]
+    Thousands,
+    /// After the last three digits and then every two, for lakh and crore: 1,23,45,678
+    Indian,
+}
+
+/// How a number is rounded to the decimal places shown. Numbers round as they are written,
+/// so 2.675 is a tie even though the nearest binary value is a little below it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Rounding {
+    /// To the nearest, ties to the even digit, as in 0.125 to 0.12
+    #[default]
+    HalfEven,
+    /// To the nearest, ties away from zero, as in 0.125 to 0.13
+    HalfUp,
+    /// Away from zero, as in 0.121 to 0.13
+    Up,
+    /// Toward zero, dropping the digits not shown, as in 0.129 to 0.12
+    Down,
+}
+
+/// The most decimal places shown; an f64 holds no more than 15 to 17 significant digits.
+pub const MAX_DECIMALS: usize = 12;
+
+/// Reads a number of decimal places, from 0 to `MAX_DECIMALS`.
+pub fn parse_decimals(value: &str) -> Result<usize, String> {
+    value.parse::<usize>().ok().filter(|&decimals| decimals <= MAX_DECIMALS).ok_or_else(|| format!("expected 0 to {} decimal places, got \"{}\"", MAX_DECIMALS, value))
+}
+
+impl Rounding {
+    /// `value` rounded to `decimals` places.
+    pub fn round(self, value: f64, decimals: usize) -> f64 {
+        let rounded: f64 = self.fixed(value, decimals).parse().unwrap_or(value.abs());
+        rounded.copysign(value)
+    }
+
+    // The digits of `value` without its sign, rounded to `decimals` places.
+    fn fixed(self, value: f64, decimals: usize) -> String {
+        // `{}` writes the shortest digits that read back as `value`, never in exponent form.
+        let exact = format!("{}", value.abs());
+        if !value.is_finite() {
+            return exact;
+        }
+        let (whole, fraction) = exact.split_once('.').unwrap_or((&exact, ""));
+        let (kept, dropped) = fraction.split_at(decimals.min(fraction.len()));
+        let mut digits = format!("{}{}{}", whole, kept, "0".repeat(decimals - kept.len())).into_bytes();
+        let first = dropped.bytes().next().unwrap_or(b'0');
+        let beyond = dropped.bytes().skip(1).any(|digit| digit != b'0');
+        let up = match self {
+            Rounding::HalfEven => first > b'5' || (first == b'5' && (beyond || digits.last().is_some_and(|digit| (digit - b'0') % 2 == 1))),
+            Rounding::HalfUp => first >= b'5',
+            Rounding::Up => first != b'0' || beyond,
+            Rounding::Down => false,
+        };
+        if up {
+            match digits.iter().rposition(|&digit| digit != b'9') {
+                Some(i) => {
+                    digits[i] += 1;
+                    digits[i + 1..].fill(b'0');
+                }
+                None => {
+                    digits.fill(b'0');
+                    digits.insert(0, b'1');
+                }
+            }
+        }
+        let mut text = String::from_utf8(digits).unwrap_or_default();
+        if decimals > 0 {
+            text.insert(text.len() - decimals, '.');
+        }
+        text
+    }
+}
+
+/// How amounts are displayed in charts, reports, and messages. The default writes `$1234.50`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MoneyFormat {
+    pub symbol: String,
+    pub position: SymbolPosition,
+    pub decimal: char,
+    /// Separator between groups of digits, if any
+    pub grouping: Option<char>,
+    pub digit_grouping: DigitGrouping,
+    /// Decimal places of amounts; percentages and compact amounts keep their one place
+    pub decimals: usize,
+    pub rounding: Rounding,
+}
+
+impl Default for MoneyFormat {
+    fn default() -> Self {
+        MoneyFormat {
+            symbol: "$".to_string(),
+            position: SymbolPosition::Prefix,
+            decimal: '.',
+            grouping: None,
+            digit_grouping: DigitGrouping::Thousands,
+            decimals: 2,
+            rounding: Rounding::HalfEven,
+        }
+    }
+}
+
+impl MoneyFormat {
+    /// Text before and after the digits. A suffix, or a prefix ending in a letter such as
+    /// `CHF`, is set apart by a space.
+    pub fn affixes(&self) -> (String, String) {
+        match self.position {
+            SymbolPosition::Prefix if self.symbol.ends_with(char::is_alphabetic) => (format!("{} ", self.symbol), String::new()),
+            SymbolPosition::Prefix => (self.symbol.clone(), String::new()),
+            SymbolPosition::Suffix => (String::new(), format!(" {}", self.symbol)),
+        }
+    }
+
+    /// `value` rounded to `decimals` places the format's way, with its separators but no symbol.
+    pub fn number(&self, value: f64, decimals: usize) -> String {
+        let fixed = self.rounding.fixed(value, decimals);
+        let (whole, fraction) = fixed.split_once('.').map_or((fixed.as_str(), None), |(whole, fraction)| (whole, Some(fraction)));
+        // Values that round to zero keep no sign.
+        let mut text = String::from(if value < 0.0 && fixed.bytes().any(|b| b.is_ascii_digit() && b != b'0') { "-" } else { "" });
+        for (i, digit) in whole.chars().enumerate() {
+            let left = whole.len() - i;
+            let boundary = match self.digit_grouping {
+                DigitGrouping::Thousands => left % 3 == 0,
+                DigitGrouping::Indian => left >= 3 && (left - 3) % 2 == 0,
+            };
+            if let Some(grouping) = self.grouping.filter(|_| i > 0 && boundary) {
+                text.push(grouping);
+            }
+            text.push(digit);
+        }
+        if let Some(fraction) = fraction {
+            text.push(self.decimal);
+            text.push_str(fraction);
+        }
+        text
+    }
+
+    /// A percentage with one decimal place, e.g. `12.5%`, or `+12.5%` when `signed`.
+    pub fn percent(&self, value: f64, signed: bool) -> String {
+        let number = self.number(value, 1);
+        let plus = if signed && !number.starts_with('-') { "+" } else { "" };
+        format!("{}{}%", plus, number)
+    }
+
+    /// The amount rounded to the format's places, cents by default, e.g. `-$1234.50` or `1.234,50 €`.
+    pub fn format(&self, amount: f64) -> String {
+        let number = self.number(amount, self.decimals);
+        let (sign, digits) = number.strip_prefix('-').map_or(("", number.as_str()), |digits| ("-", digits));
+        let (prefix, suffix) = self.affixes();
+        format!("{}{}{}{}", sign, prefix, digits, suffix)
+    }
+
+    /// The amount in a few digits, e.g. `$1.2M`, or `₹12.5L` and `₹3.4Cr` in lakh and crore
+    /// with Indian grouping.
+    pub fn compact(&self, amount: f64) -> String {
+        let units: &[(f64, &str)] = match self.digit_grouping {
+            DigitGrouping::Thousands => &[(1e9, "B"), (1e6, "M"), (1e3, "K")],
+            DigitGrouping::Indian => &[(1e7, "Cr"), (1e5, "L"), (1e3, "K")],
+        };
+        let (scaled, unit) = units.iter().find(|(size, _)| amount.abs() >= *size).map_or((amount, ""), |(size, unit)| (amount / size, *unit));
+        let number = self.number(scaled, if unit.is_empty() { 0 } else { 1 });
+        let number = number.strip_suffix(&format!("{}0", self.decimal)).unwrap_or(&number);
+        let (sign, digits) = number.strip_prefix('-').map_or(("", number), |digits| ("-", digits));
+        let (prefix, suffix) = self.affixes();
+        format!("{}{}{}{}{}", sign, prefix, digits, unit, suffix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn in_locale(tag: &str) -> NumberFormat {
+        NumberFormat { locale: Some(NumberLocale::from_tag(tag).unwrap()), clean: false }
+    }
+
+    #[test]
+    fn locales_read_their_own_separators() {
+        assert_eq!(in_locale("de").parse(b"1.234,56"), Ok(1234.56));
+        assert_eq!(in_locale("de-DE").parse(b"-12,5"), Ok(-12.5));
+        assert_eq!(in_locale("fr").parse(b"1 234,56"), Ok(1234.56));
+        assert_eq!(in_locale("fr_FR").parse("1\u{a0}234,56".as_bytes()), Ok(1234.56));
+        assert_eq!(in_locale("fr").parse("1\u{202f}234\u{202f}567,8".as_bytes()), Ok(1234567.8));
+        assert_eq!(in_locale("de-CH").parse(b"1'234.56"), Ok(1234.56));
+        assert_eq!(in_locale("en").parse(b"1,234.56"), Ok(1234.56));
+    }
+
+    #[test]
+    fn locale_errors_name_the_decimal_separator() {
+        assert_eq!(in_locale("de").parse(b"12 Euro"), Err("Invalid sales number in \"12 Euro\": expected decimal separator ','".to_string()));
+        assert!(in_locale("de").parse(b"NaN").is_err());
+        assert!(NumberLocale::from_tag("xx").is_err());
+    }
+
+    #[test]
+    fn clean_numbers_drop_symbols_and_thousands_commas() {
+        let clean = NumberFormat { locale: None, clean: true };
+        assert_eq!(clean.parse(b"$1,234.50"), Ok(1234.5));
+        assert_eq!(clean.parse("€ 12".as_bytes()), Ok(12.0));
+        assert_eq!(clean.parse("£1,000".as_bytes()), Ok(1000.0));
+        assert_eq!(clean.parse("¥-5".as_bytes()), Ok(-5.0));
+        assert!(clean.parse(b"USD 12").is_err());
+    }
+
+    #[test]
+    fn clean_numbers_keep_a_decimal_comma_locale() {
+        let clean = NumberFormat { clean: true, ..in_locale("de") };
+        assert_eq!(clean.parse("1.234,50 €".as_bytes()), Ok(1234.5));
+        assert_eq!(clean.parse("€12,5".as_bytes()), Ok(12.5));
+    }
+
+    #[test]
+    fn without_options_amounts_are_plain_numbers() {
+        assert_eq!(NumberFormat::default().parse(b"1234.5"), Ok(1234.5));
+        assert!(NumberFormat::default().parse(b"1,234.5").is_err());
+        assert!(NumberFormat::default().parse(b"$12").is_err());
+    }
+
+    fn indian() -> MoneyFormat {
+        MoneyFormat { symbol: "₹".to_string(), grouping: Some(','), digit_grouping: DigitGrouping::Indian, ..MoneyFormat::default() }
+    }
+
+    #[test]
+    fn indian_grouping_is_three_digits_then_pairs() {
+        let money = indian();
+        assert_eq!(money.number(123.0, 0), "123");
+        assert_eq!(money.number(1234.0, 0), "1,234");
+        assert_eq!(money.number(12345.0, 0), "12,345");
+        assert_eq!(money.number(100000.0, 0), "1,00,000");
+        assert_eq!(money.number(12345678.0, 0), "1,23,45,678");
+        assert_eq!(money.number(1234567890.0, 0), "1,23,45,67,890");
+    }
+
+    #[test]
+    fn indian_grouping_leaves_the_sign_and_fraction_alone() {
+        let money = indian();
+        assert_eq!(money.format(-1234567.891), "-₹12,34,567.89");
+        assert_eq!(money.number(999999.995, 2), "10,00,000.00");
+        assert_eq!(money.number(-0.001, 2), "0.00");
+    }
+
+    #[test]
+    fn indian_compact_amounts_are_lakh_and_crore() {
+        let money = indian();
+        assert_eq!(money.compact(1250000.0), "₹12.5L");
+        assert_eq!(money.compact(34000000.0), "₹3.4Cr");
+        assert_eq!(money.compact(100000.0), "₹1L");
+        assert_eq!(money.compact(4500.0), "₹4.5K");
+        assert_eq!(money.compact(999.0), "₹999");
+    }
+
+    #[test]
+    fn rounding_modes_as_documented() {
+        assert_eq!(Rounding::HalfEven.fixed(0.125, 2), "0.12");
+        assert_eq!(Rounding::HalfEven.fixed(0.135, 2), "0.14");
+        assert_eq!(Rounding::HalfUp.fixed(0.125, 2), "0.13");
+        assert_eq!(Rounding::Up.fixed(0.121, 2), "0.13");
+        assert_eq!(Rounding::Down.fixed(0.129, 2), "0.12");
+    }
+
+    #[test]
+    fn ties_are_the_digits_as_written() {
+        // The f64 nearest 2.675 is a little below it, but the digits as written are a tie.
+        assert_eq!(Rounding::HalfUp.fixed(2.675, 2), "2.68");
+        assert_eq!(Rounding::HalfEven.fixed(2.675, 2), "2.68");
+        assert_eq!(Rounding::HalfEven.fixed(0.1251, 2), "0.13");
+        assert_eq!(Rounding::Up.fixed(0.1200, 2), "0.12");
+    }
+
+    #[test]
+    fn fixed_pads_carries_and_drops_the_sign() {
+        assert_eq!(Rounding::HalfEven.fixed(1.5, 3), "1.500");
+        assert_eq!(Rounding::HalfEven.fixed(12.0, 0), "12");
+        assert_eq!(Rounding::HalfEven.fixed(0.5, 0), "0");
+        assert_eq!(Rounding::HalfEven.fixed(1.5, 0), "2");
+        assert_eq!(Rounding::HalfUp.fixed(9.995, 2), "10.00");
+        assert_eq!(Rounding::Up.fixed(99.91, 0), "100");
+        assert_eq!(Rounding::HalfUp.fixed(-2.345, 2), "2.35");
+        assert_eq!(Rounding::HalfEven.fixed(1e21, 1), "1000000000000000000000.0");
+    }
+
+    #[test]
+    fn round_keeps_the_sign() {
+        assert_eq!(Rounding::HalfEven.round(-2.5, 0), -2.0);
+        assert_eq!(Rounding::HalfUp.round(-2.5, 0), -3.0);
+        assert_eq!(Rounding::Down.round(-2.59, 1), -2.5);
+        assert_eq!(Rounding::HalfEven.round(1234.5678, 2), 1234.57);
+    }
+
+    #[test]
+    fn decimals_are_limited() {
+        assert_eq!(parse_decimals("0"), Ok(0));
+        assert_eq!(parse_decimals("12"), Ok(MAX_DECIMALS));
+        assert!(parse_decimals("13").is_err());
+        assert!(parse_decimals("-1").is_err());
+    }
+
+    #[test]
+    fn thousands_grouping_is_every_three_digits() {
+        let money = MoneyFormat { grouping: Some(','), ..MoneyFormat::default() };
+        assert_eq!(money.number(12345678.0, 0), "12,345,678");
+        assert_eq!(money.number(123456.0, 0), "123,456");
+    }
+}
@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::File;
+
+use chrono::NaiveDate;
+use csv::ReaderBuilder;
+use polars::prelude::*;
+use polars::sql::SQLContext;
+
+use crate::{date_to_key, validate_csv_structure, SalesTotals};
+
+pub fn process_sales_data(path: &str, query: Option<&str>) -> Result<SalesTotals, Box<dyn Error>> {
+    let mut rdr = ReaderBuilder::new().has_headers(true).from_reader(File::open(path)?);
+    let headers = rdr.headers()?.clone();
+    validate_csv_structure(&headers)?;
+    let column = |name: &str| headers.iter().find(|h| h.to_lowercase() == name).unwrap().to_string();
+
+    let mut sales = LazyCsvReader::new(PlPath::new(path))
+        .with_has_header(true)
+        .with_infer_schema_length(Some(0))
+        .finish()?
+        .select([
+            col(column("month")).alias("month"),
+            col(column("product")).alias("product"),
+            col(column("sales_amount")).strict_cast(DataType::Float64).alias("sales_amount"),
+        ]);
+
+    if let Some(query) = query {
+        let mut ctx = SQLContext::new();
+        ctx.register("sales", sales);
+        sales = ctx.execute(&format!("SELECT * FROM sales WHERE {}", query))?;
+    }
+
+    // sum() skips missing amounts, which the native engine fails on by default.
+    let missing = sales.clone().select([col("sales_amount").null_count()]).collect()?;
+    let missing = missing.column("sales_amount")?.cast(&DataType::UInt64)?.u64()?.get(0).unwrap_or(0);
+    if missing > 0 {
+        return Err(format!("Found {} rows with a missing sales amount; use --engine native with --missing-amount skip or zero to chart them", missing).into());
+    }
+
+    let grouped = sales
+        .group_by([col("month"), col("product")])
+        .agg([col("sales_amount").sum()])
+        .collect()?;
+
+    let months = grouped.column("month")?.str()?;
+    let products = grouped.column("product")?.str()?;
+    let amounts = grouped.column("sales_amount")?.f64()?;
+
+    let mut sales_by_month = HashMap::new();
+    let mut sales_by_product = HashMap::new();
+    for ((date_str, product), sales) in months.into_iter().zip(products).zip(amounts) {
+        let date_str = date_str.ok_or("Missing value in month column")?;
+        let product = product.ok_or("Missing value in product column")?;
+        let sales = sales.ok_or("Missing value in sales_amount column")?;
+        let month = NaiveDate::parse_from_str(&format!("{}-01", date_str), "%Y-%m-%d")
+            .map_err(|e| format!("Invalid date format in \"{}\": {}", date_str, e))?;
+        if !sales.is_finite() {
+            return Err(format!("The sales of {} in {} add up to {}, not a finite number", product, date_str, sales).into());
+        }
+
+        *sales_by_month.entry(date_to_key(&month)).or_insert(0.0) += sales;
+        *sales_by_product.entry(product.to_string()).or_insert(0.0) += sales;
+    }
+
+    Ok((sales_by_month, sales_by_product))
+}
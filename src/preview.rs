@@ -0,0 +1,114 @@
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use notify::{RecursiveMode, Watcher};
+use tiny_http::{Header, Response, Server};
+use tracing::{error, info};
+
+use crate::charts::{render_charts, ChartLabels};
+use crate::narrative::narrative;
+use crate::report::standalone_html_report;
+use crate::server::{Analyze, Job};
+use crate::summary::RunSummary;
+
+// The page polls /version and reloads once it changes; the version moves on every change to a
+// watched file. Polling keeps the server single-threaded and survives the server restarting.
+const RELOAD_SCRIPT: &str = r#"<script>
+(() => {
+  const version = "__VERSION__";
+  setInterval(() => {
+    fetch("/version").then((reply) => reply.text()).then((current) => current !== version && location.reload(), () => {});
+  }, 500);
+})();
+</script>
+"#;
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+fn build(input: &Path, labels: &ChartLabels, analyze: &Analyze<'_>) -> Result<String, Box<dyn Error>> {
+    let (data, report) = analyze(input)?;
+    if data.0.is_empty() {
+        return Err("The data has no sales rows".into());
+    }
+    let job = Job::new()?;
+    let charts = render_charts(&job.dir, &data.0, &data.1, &labels.for_data(&data, report.rows))?;
+    let mut summary = RunSummary::success(input, &data, &report, charts);
+    summary.narrative = narrative(&summary, &labels.money);
+    standalone_html_report(&summary, labels)
+}
+
+// Errors become a page of their own, which keeps polling so fixing the file brings the report back.
+fn page(input: &Path, labels: &ChartLabels, analyze: &Analyze<'_>, version: u64) -> String {
+    let html = build(input, labels, analyze).unwrap_or_else(|e| {
+        error!("Error building the preview: {}", e);
+        format!(
+            "<html><body style=\"font-family: sans-serif\">\n<h1>Cannot build the report</h1>\n<pre>{}</pre>\n</body></html>\n",
+            escape_html(&e.to_string())
+        )
+    });
+    let script = RELOAD_SCRIPT.replace("__VERSION__", &version.to_string());
+    match html.rfind("</body>") {
+        Some(end) => format!("{}{}{}", &html[..end], script, &html[end..]),
+        None => html + script.as_str(),
+    }
+}
+
+/// Serves the HTML report for `input` on `http://localhost:<port>/` and reloads the page in
+/// the browser whenever `input` or one of the `watched` files changes on disk.
+pub fn preview(
+    port: u16,
+    input: &Path,
+    watched: &[PathBuf],
+    labels: &ChartLabels,
+    analyze: &Analyze<'_>,
+) -> Result<(), Box<dyn Error>> {
+    let version = Arc::new(AtomicU64::new(0));
+    let files: Vec<PathBuf> = std::iter::once(input.to_path_buf()).chain(watched.iter().cloned()).collect();
+    let names: Vec<_> = files.iter().filter_map(|file| file.file_name().map(|name| name.to_os_string())).collect();
+    let changed = Arc::clone(&version);
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        // Matching by name alone may also catch a same-named file in another watched directory,
+        // which costs no more than a spare reload.
+        if let Ok(event) = event {
+            if !event.kind.is_access() && event.paths.iter().any(|path| path.file_name().is_some_and(|name| names.iter().any(|n| n == name))) {
+                changed.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+    })?;
+    // Directories rather than files, so editors that save by replacing the file keep triggering.
+    for file in &files {
+        let directory = match file.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        watcher.watch(directory, RecursiveMode::NonRecursive)?;
+    }
+
+    let server = Server::http(("127.0.0.1", port)).map_err(|e| format!("Cannot listen on port {}: {}", port, e))?;
+    info!("Previewing the report for {} on http://localhost:{}", input.display(), port);
+    let html_type = Header::from_bytes("Content-Type", "text/html; charset=utf-8").expect("valid header");
+    let mut cached: Option<(u64, String)> = None;
+    for request in server.incoming_requests() {
+        let current = version.load(Ordering::SeqCst);
+        let path = request.url().split('?').next().unwrap_or_default().to_string();
+        let reply = match path.as_str() {
+            "/" => {
+                if cached.as_ref().is_none_or(|(built, _)| *built != current) {
+                    cached = Some((current, page(input, labels, analyze, current)));
+                }
+                let html = cached.as_ref().map(|(_, html)| html.clone()).unwrap_or_default();
+                Response::from_string(html).with_header(html_type.clone())
+            }
+            "/version" => Response::from_string(current.to_string()),
+            _ => Response::from_string("Not found\n").with_status_code(404),
+        };
+        if let Err(e) = request.respond(reply) {
+            error!("Error answering {}: {}", path, e);
+        }
+    }
+    Ok(())
+}
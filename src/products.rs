@@ -0,0 +1,293 @@
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+
+#[derive(Clone, Debug)]
+pub struct ProductMerge {
+    pub into: String,
+    pub from: Vec<String>,
+}
+
+impl fmt::Display for ProductMerge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let from: Vec<String> = self.from.iter().map(|name| format!("{:?}", name)).collect();
+        write!(f, "{} -> {:?}", from.join(", "), self.into)
+    }
+}
+
+// Merged totals are summed in name order rather than hash order, so the same input always
+// produces the same floating-point result.
+fn sorted_by_name(sales_by_product: HashMap<String, f64>) -> Vec<(String, f64)> {
+    let mut products: Vec<(String, f64)> = sales_by_product.into_iter().collect();
+    products.sort_by(|a, b| a.0.cmp(&b.0));
+    products
+}
+
+pub fn tidy_name(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// Groups products that differ only in surrounding/repeated whitespace or case. Each group is
+// shown under its best-selling spelling so the charts keep a human-looking label.
+pub fn normalize_products(sales_by_product: HashMap<String, f64>) -> (HashMap<String, f64>, Vec<ProductMerge>) {
+    let mut groups: HashMap<String, Vec<(String, f64)>> = HashMap::new();
+    for (name, sales) in sales_by_product {
+        groups.entry(tidy_name(&name).to_lowercase()).or_default().push((name, sales));
+    }
+
+    let mut normalized = HashMap::new();
+    let mut merges = Vec::new();
+    for (_, mut variants) in groups {
+        variants.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        let into = tidy_name(&variants[0].0);
+        let total: f64 = variants.iter().map(|(_, sales)| sales).sum();
+
+        if variants.len() > 1 || variants[0].0 != into {
+            let mut from: Vec<String> = variants.into_iter().map(|(name, _)| name).collect();
+            from.sort();
+            merges.push(ProductMerge { into: into.clone(), from });
+        }
+        normalized.insert(into, total);
+    }
+
+    merges.sort_by(|a, b| a.into.cmp(&b.into));
+    (normalized, merges)
+}
+
+/// Renames from a `from,to` CSV file, e.g. retired SKU codes to current product names.
+#[derive(Clone, Debug, Default)]
+pub struct ProductAliases {
+    renames: HashMap<String, String>,
+}
+
+impl ProductAliases {
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let path = path.as_ref();
+        let mut rdr = csv::ReaderBuilder::new().trim(csv::Trim::All).from_path(path)?;
+        let mut renames = HashMap::new();
+        for (i, record) in rdr.records().enumerate() {
+            let record = record?;
+            let (Some(from), Some(to)) = (record.get(0), record.get(1)) else {
+                return Err(format!("Invalid alias file {}: row {} needs a from and a to column", path.display(), i + 2).into());
+            };
+            if from.is_empty() || to.is_empty() {
+                return Err(format!("Invalid alias file {}: row {} has an empty name", path.display(), i + 2).into());
+            }
+            if let Some(previous) = renames.insert(from.to_string(), to.to_string()) {
+                if previous != to {
+                    return Err(format!(
+                        "Invalid alias file {}: \"{}\" maps to both \"{}\" and \"{}\"",
+                        path.display(),
+                        from,
+                        previous,
+                        to
+                    )
+                    .into());
+                }
+            }
+        }
+        Ok(ProductAliases { renames })
+    }
+
+    pub fn rename<'a>(&'a self, name: &'a str) -> &'a str {
+        self.renames.get(name.trim()).map_or(name, String::as_str)
+    }
+
+    pub fn apply(&self, sales_by_product: HashMap<String, f64>) -> (HashMap<String, f64>, Vec<ProductMerge>) {
+        let mut renamed: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        let mut result = HashMap::new();
+        for (name, sales) in sorted_by_name(sales_by_product) {
+            let target = self.rename(&name).to_string();
+            if target != name {
+                renamed.entry(target.clone()).or_default().push(name);
+            }
+            *result.entry(target).or_insert(0.0) += sales;
+        }
+
+        let merges = renamed
+            .into_iter()
+            .map(|(into, mut from)| {
+                from.sort();
+                ProductMerge { into, from }
+            })
+            .collect();
+        (result, merges)
+    }
+}
+
+// Levenshtein distance that also counts swapping two adjacent characters as one edit.
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut before: Vec<usize> = Vec::new();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for i in 0..a.len() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for j in 0..b.len() {
+            let substitution = previous[j] + usize::from(a[i] != b[j]);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+            if i > 0 && j > 0 && a[i] == b[j - 1] && a[i - 1] == b[j] {
+                current[j + 1] = current[j + 1].min(before[j - 1] + 1);
+            }
+        }
+        before = std::mem::replace(&mut previous, current);
+    }
+    previous[b.len()]
+}
+
+// "Widget A" and "Widget B" are one edit apart but clearly different products: when the only
+// differing word is a short code or a number, the names are variants rather than typos.
+fn is_variant_pair(a: &str, b: &str) -> bool {
+    let split = |name: &str| name.split([' ', '_', '-']).map(str::to_string).collect::<Vec<_>>();
+    let (a, b) = (split(a), split(b));
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut differing = a.iter().zip(&b).filter(|(x, y)| x != y);
+    match (differing.next(), differing.next()) {
+        (Some((x, y)), None) => {
+            let numeric = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+            (x.chars().count() <= 2 && y.chars().count() <= 2) || (numeric(x) && numeric(y))
+        }
+        _ => false,
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct NearDuplicate {
+    pub name: String,
+    pub similar_to: String,
+    pub distance: usize,
+}
+
+/// Pairs of product names within a small edit distance of each other, ignoring case. Each
+/// pair lists the lower-selling name first.
+pub fn near_duplicates(sales_by_product: &HashMap<String, f64>) -> Vec<NearDuplicate> {
+    let mut names: Vec<(&String, f64, String, Vec<char>)> = sales_by_product
+        .iter()
+        .map(|(name, &sales)| {
+            let folded = tidy_name(name).to_lowercase();
+            let chars = folded.chars().collect();
+            (name, sales, folded, chars)
+        })
+        .collect();
+    names.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+
+    let mut pairs = Vec::new();
+    for (i, (name, _, folded, chars)) in names.iter().enumerate() {
+        let max_distance = if chars.len() > 10 { 2 } else { 1 };
+        let similar = names[..i].iter().find_map(|(other, _, other_folded, other_chars)| {
+            if chars.len().abs_diff(other_chars.len()) > max_distance || is_variant_pair(folded, other_folded) {
+                return None;
+            }
+            let distance = edit_distance(chars, other_chars);
+            (distance <= max_distance).then_some((other, distance))
+        });
+        if let Some((other, distance)) = similar {
+            pairs.push(NearDuplicate { name: name.to_string(), similar_to: other.to_string(), distance });
+        }
+    }
+    pairs.sort_by(|a, b| a.similar_to.cmp(&b.similar_to).then_with(|| a.name.cmp(&b.name)));
+    pairs
+}
+
+/// Folds every near-duplicate into the best-selling name it resembles.
+pub fn fuzzy_merge(
+    mut sales_by_product: HashMap<String, f64>,
+    pairs: &[NearDuplicate],
+) -> (HashMap<String, f64>, Vec<ProductMerge>) {
+    let targets: HashMap<&str, &str> = pairs.iter().map(|p| (p.name.as_str(), p.similar_to.as_str())).collect();
+    let resolve = |name: &str| {
+        let mut name = targets.get(name).copied().unwrap_or(name).to_string();
+        while let Some(&target) = targets.get(name.as_str()) {
+            name = target.to_string();
+        }
+        name
+    };
+
+    let mut merged: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for pair in pairs {
+        let into = resolve(&pair.name);
+        if let Some(sales) = sales_by_product.remove(&pair.name) {
+            *sales_by_product.entry(into.clone()).or_insert(0.0) += sales;
+            merged.entry(into).or_default().push(pair.name.clone());
+        }
+    }
+
+    let merges = merged
+        .into_iter()
+        .map(|(into, mut from)| {
+            from.sort();
+            ProductMerge { into, from }
+        })
+        .collect();
+    (sales_by_product, merges)
+}
+
+pub const UNCATEGORIZED: &str = "Uncategorized";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Grouping {
+    Category,
+    Brand,
+}
+
+/// Product to category (and optionally brand) lookup from a `product,category[,brand]` CSV file.
+#[derive(Clone, Debug, Default)]
+pub struct ProductCategories {
+    categories: HashMap<String, String>,
+    brands: HashMap<String, String>,
+}
+
+impl ProductCategories {
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let path = path.as_ref();
+        let mut rdr = csv::ReaderBuilder::new().trim(csv::Trim::All).from_path(path)?;
+        let headers = rdr.headers()?.clone();
+        let position = |name: &str| headers.iter().position(|h| h.eq_ignore_ascii_case(name));
+        let Some(product_index) = position("product") else {
+            return Err(format!("Invalid category file {}: missing column: product", path.display()).into());
+        };
+        let (category_index, brand_index) = (position("category"), position("brand"));
+        if category_index.is_none() && brand_index.is_none() {
+            return Err(format!("Invalid category file {}: needs a category or brand column", path.display()).into());
+        }
+
+        let mut mapping = ProductCategories::default();
+        for record in rdr.records() {
+            let record = record?;
+            let product = record.get(product_index).unwrap_or_default();
+            let insert = |index: Option<usize>, map: &mut HashMap<String, String>| {
+                if let Some(value) = index.and_then(|i| record.get(i)).filter(|v| !v.is_empty()) {
+                    map.insert(product.to_string(), value.to_string());
+                }
+            };
+            insert(category_index, &mut mapping.categories);
+            insert(brand_index, &mut mapping.brands);
+        }
+        Ok(mapping)
+    }
+
+    pub fn lookup(&self, product: &str, grouping: Grouping) -> Option<&str> {
+        let map = match grouping {
+            Grouping::Category => &self.categories,
+            Grouping::Brand => &self.brands,
+        };
+        map.get(product.trim()).map(String::as_str)
+    }
+
+    /// Re-keys product totals by category or brand. Products missing from the file are
+    /// collected under "Uncategorized" and also returned so they can be reported.
+    pub fn group(&self, sales_by_product: &HashMap<String, f64>, grouping: Grouping) -> (HashMap<String, f64>, Vec<String>) {
+        let mut grouped = HashMap::new();
+        let mut unmapped = Vec::new();
+        for (product, sales) in sorted_by_name(sales_by_product.clone()) {
+            let group = self.lookup(&product, grouping).unwrap_or_else(|| {
+                unmapped.push(product.clone());
+                UNCATEGORIZED
+            });
+            *grouped.entry(group.to_string()).or_insert(0.0) += sales;
+        }
+        unmapped.sort();
+        (grouped, unmapped)
+    }
+}
@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::File;
+use std::path::PathBuf;
+
+use chrono::NaiveDate;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::charts::{create_bar_chart, create_line_chart, create_pie_chart, ChartLabels};
+use crate::{ParseOptions, SalesReport};
+
+fn value_error(e: Box<dyn Error>) -> PyErr {
+    PyValueError::new_err(e.to_string())
+}
+
+/// Parsed and aggregated sales from one CSV file.
+#[pyclass(frozen, module = "sales_chart")]
+pub struct SalesData {
+    monthly: Vec<(NaiveDate, f64)>,
+    products: Vec<(String, f64)>,
+    #[pyo3(get)]
+    rows: u64,
+    #[pyo3(get)]
+    duplicates: u64,
+    #[pyo3(get)]
+    errors: Vec<String>,
+}
+
+#[pymethods]
+impl SalesData {
+    /// Monthly totals as `(datetime.date, float)` pairs in date order.
+    #[getter]
+    fn monthly(&self) -> Vec<(NaiveDate, f64)> {
+        self.monthly.clone()
+    }
+
+    /// Product totals as `(name, float)` pairs, best-selling first.
+    #[getter]
+    fn products(&self) -> Vec<(String, f64)> {
+        self.products.clone()
+    }
+
+    /// Monthly totals keyed by date, e.g. for `pandas.Series(data.monthly_totals())`.
+    fn monthly_totals(&self) -> HashMap<NaiveDate, f64> {
+        self.monthly.iter().copied().collect()
+    }
+
+    fn product_totals(&self) -> HashMap<String, f64> {
+        self.products.iter().cloned().collect()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "SalesData(months={}, products={}, rows={}, errors={})",
+            self.monthly.len(),
+            self.products.len(),
+            self.rows,
+            self.errors.len()
+        )
+    }
+}
+
+/// Reads and aggregates a sales CSV. Malformed rows raise `ValueError` unless `lenient`, in
+/// which case they are skipped and listed in `errors`.
+#[pyfunction]
+#[pyo3(signature = (path, lenient = false, dedupe = false))]
+fn load_sales(py: Python<'_>, path: PathBuf, lenient: bool, dedupe: bool) -> PyResult<SalesData> {
+    let SalesReport { dataset, parse } = py
+        .detach(|| {
+            let file = File::open(&path).map_err(|e| format!("Cannot open {}: {}", path.display(), e))?;
+            let options = ParseOptions { lenient, dedupe, ..ParseOptions::default() };
+            SalesReport::from_csv_with(file, &options).map_err(|e| e.to_string())
+        })
+        .map_err(PyValueError::new_err)?;
+    Ok(SalesData {
+        monthly: dataset.monthly,
+        products: dataset.products,
+        rows: parse.rows,
+        duplicates: parse.duplicates,
+        errors: parse.errors.iter().map(|e| e.to_string()).collect(),
+    })
+}
+
+/// Writes the `line`, `bar`, or `pie` chart of `data` to a PNG file and returns its path.
+#[pyfunction]
+fn render_chart(data: &SalesData, kind: &str, path: PathBuf) -> PyResult<PathBuf> {
+    if data.monthly.is_empty() {
+        return Err(PyValueError::new_err("The data has no sales rows"));
+    }
+    let labels = ChartLabels::default();
+    match kind {
+        "line" => create_line_chart(&path, &data.monthly, &labels),
+        "bar" => create_bar_chart(&path, &data.products, &labels),
+        "pie" => create_pie_chart(&path, &data.products, &labels),
+        _ => return Err(PyValueError::new_err(format!("Unknown chart kind: {} (expected line, bar, or pie)", kind))),
+    }
+    .map_err(value_error)?;
+    Ok(path)
+}
+
+#[pymodule]
+fn sales_chart(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<SalesData>()?;
+    m.add_function(wrap_pyfunction!(load_sales, m)?)?;
+    m.add_function(wrap_pyfunction!(render_chart, m)?)?;
+    Ok(())
+}
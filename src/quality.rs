@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{missing_months, DateKey, ParseReport};
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct AmountStats {
+    pub count: u64,
+    pub mean: f64,
+    m2: f64,
+}
+
+impl AmountStats {
+    pub fn push(&mut self, value: f64) {
+        self.count += 1;
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (value - self.mean);
+    }
+
+    // Chan et al. parallel combination, so per-thread stats from the rayon fold can be merged.
+    pub fn merge(self, other: Self) -> Self {
+        if self.count == 0 {
+            return other;
+        }
+        if other.count == 0 {
+            return self;
+        }
+        let count = self.count + other.count;
+        let delta = other.mean - self.mean;
+        AmountStats {
+            count,
+            mean: self.mean + delta * other.count as f64 / count as f64,
+            m2: self.m2 + other.m2 + delta * delta * self.count as f64 * other.count as f64 / count as f64,
+        }
+    }
+
+    pub fn std_dev(&self) -> f64 {
+        if self.count < 2 {
+            return 0.0;
+        }
+        (self.m2 / (self.count - 1) as f64).sqrt()
+    }
+
+    pub fn is_outlier(&self, value: f64, threshold: f64) -> bool {
+        let std_dev = self.std_dev();
+        std_dev > 0.0 && (value - self.mean).abs() > threshold * std_dev
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct QualityReport {
+    pub rows: u64,
+    pub malformed_rows: u64,
+    pub duplicates: u64,
+    pub outliers: u64,
+    pub months_present: u64,
+    pub months_missing: u64,
+}
+
+impl QualityReport {
+    pub fn new(report: &ParseReport, sales_by_month: &HashMap<DateKey, f64>) -> Self {
+        QualityReport {
+            rows: report.rows,
+            malformed_rows: report.errors.len() as u64,
+            duplicates: report.duplicates,
+            outliers: report.outliers(),
+            months_present: sales_by_month.len() as u64,
+            months_missing: missing_months(sales_by_month.keys().copied()).len() as u64,
+        }
+    }
+
+    fn share(&self, count: u64) -> f64 {
+        if self.rows == 0 {
+            return 0.0;
+        }
+        count as f64 / self.rows as f64
+    }
+
+    pub fn completeness(&self) -> f64 {
+        let expected = self.months_present + self.months_missing;
+        if expected == 0 {
+            return 0.0;
+        }
+        self.months_present as f64 / expected as f64
+    }
+
+    pub fn validity(&self) -> f64 {
+        1.0 - self.share(self.malformed_rows)
+    }
+
+    pub fn uniqueness(&self) -> f64 {
+        1.0 - self.share(self.duplicates)
+    }
+
+    pub fn plausibility(&self) -> f64 {
+        1.0 - self.share(self.outliers)
+    }
+
+    /// Overall score from 0 to 100, the mean of the four component ratios.
+    pub fn score(&self) -> f64 {
+        (self.completeness() + self.validity() + self.uniqueness() + self.plausibility()) / 4.0 * 100.0
+    }
+}
+
+impl fmt::Display for QualityReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Data quality score: {:.1}/100 ({} rows)", self.score(), self.rows)?;
+        writeln!(
+            f,
+            "  completeness  {:>6.1}%  ({} of {} months present)",
+            self.completeness() * 100.0,
+            self.months_present,
+            self.months_present + self.months_missing
+        )?;
+        writeln!(f, "  validity      {:>6.1}%  ({} malformed rows skipped)", self.validity() * 100.0, self.malformed_rows)?;
+        writeln!(f, "  uniqueness    {:>6.1}%  ({} duplicate rows)", self.uniqueness() * 100.0, self.duplicates)?;
+        write!(f, "  plausibility  {:>6.1}%  ({} outlier amounts)", self.plausibility() * 100.0, self.outliers)
+    }
+}
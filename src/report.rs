@@ -0,0 +1,747 @@
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use plotters::prelude::*;
+use serde::Serialize;
+
+use crate::charts::{bar_chart_areas, draw_line_chart, ChartLabels, MAX_BUMP};
+use crate::budget::BudgetVariance;
+use crate::compare::{Comparison, Delta};
+use crate::fx::currency_symbol;
+use crate::number::MoneyFormat;
+use crate::summary::{Aggregates, FunnelStage, ProductTotal, ProductTrend, RunSummary};
+#[cfg(feature = "templates")]
+use crate::summary::MonthTotal;
+use crate::translations::{CaptionValues, Translations};
+
+// Clicking a header sorts its table by that column, again to reverse. Mail clients drop
+// scripts, so there the tables simply stay in chart order.
+const SORT_SCRIPT: &str = r#"<script>
+document.querySelectorAll("table.sortable th").forEach((th) => {
+  th.style.cursor = "pointer";
+  th.addEventListener("click", () => {
+    const body = th.closest("table").tBodies[0];
+    const column = th.cellIndex;
+    const ascending = th.dataset.order !== "asc";
+    th.closest("tr").querySelectorAll("th").forEach((other) => delete other.dataset.order);
+    th.dataset.order = ascending ? "asc" : "desc";
+    const key = (row) => {
+      const cell = row.cells[column];
+      if (cell.dataset.value === undefined) return cell.textContent;
+      return cell.dataset.value === "" ? -Infinity : Number(cell.dataset.value);
+    };
+    const rows = [...body.rows].sort((a, b) => {
+      const [x, y] = [key(a), key(b)];
+      const order = typeof x === "number" ? (x > y) - (x < y) : x.localeCompare(y);
+      return ascending ? order : -order;
+    });
+    rows.forEach((row) => body.appendChild(row));
+  });
+});
+</script>
+"#;
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn number_cell(value: Option<f64>, text: String) -> String {
+    // An empty value (the first month has no change) sorts below every number.
+    let value = value.map_or(String::new(), |value| value.to_string());
+    format!("<td align=\"right\" data-value=\"{}\">{}</td>", value, text)
+}
+
+// Tables carry the id their chart's `aria-describedby` points at.
+fn table(id: &str, headers: &[&str], translations: &Translations, rows: Vec<String>) -> String {
+    let headers: String =
+        headers.iter().map(|header| format!("<th align=\"left\">{}</th>", escape_html(&translations.get(header)))).collect();
+    format!(
+        "<table id=\"{}\" class=\"sortable\" cellpadding=\"4\">\n<thead><tr>{}</tr></thead>\n<tbody>\n{}</tbody>\n</table>\n",
+        id,
+        headers,
+        rows.concat()
+    )
+}
+
+// Alt text for a chart of monthly totals: the months covered, how sales moved from the first
+// to the last, and the best and worst month.
+fn monthly_description(subject: &str, monthly: &[(String, f64)], money: &MoneyFormat) -> String {
+    let (Some(first), Some(last)) = (monthly.first(), monthly.last()) else {
+        return format!("{}, with no months", subject);
+    };
+    if monthly.len() == 1 {
+        return format!("{} in {}: {}.", subject, first.0, money.format(first.1));
+    }
+    let trend = if first.1 == 0.0 {
+        format!(", from {} to {}", money.format(first.1), money.format(last.1))
+    } else {
+        let change = (last.1 - first.1) / first.1.abs() * 100.0;
+        let direction = if change.abs() < 1.0 {
+            "roughly flat".to_string()
+        } else {
+            format!("{} {}", if change > 0.0 { "up" } else { "down" }, money.percent(change.abs(), false))
+        };
+        format!(", {} from {} to {}", direction, money.format(first.1), money.format(last.1))
+    };
+    let highest = monthly.iter().max_by(|a, b| a.1.total_cmp(&b.1)).unwrap_or(first);
+    let lowest = monthly.iter().min_by(|a, b| a.1.total_cmp(&b.1)).unwrap_or(first);
+    format!(
+        "{} from {} to {}{}. Highest in {} at {}, lowest in {} at {}.",
+        subject,
+        first.0,
+        last.0,
+        trend,
+        highest.0,
+        money.format(highest.1),
+        lowest.0,
+        money.format(lowest.1)
+    )
+}
+
+// Alt text for a chart of product totals: the leading products with their shares, and the last.
+fn product_description(subject: &str, products: &[ProductTotal], money: &MoneyFormat) -> String {
+    const NAMED: usize = 3;
+    let total: f64 = products.iter().map(|product| product.sales).sum();
+    let share = |sales: f64| if total == 0.0 { String::new() } else { format!(" ({})", money.percent(sales / total * 100.0, false)) };
+    let leading: Vec<String> = products
+        .iter()
+        .take(NAMED)
+        .map(|product| format!("{} {}{}", product.product, money.format(product.sales), share(product.sales)))
+        .collect();
+    let rest = match products.len() {
+        count if count > NAMED + 1 => {
+            let last = &products[count - 1];
+            format!(", and {} more down to {} at {}", count - NAMED, last.product, money.format(last.sales))
+        }
+        count if count == NAMED + 1 => {
+            let last = &products[NAMED];
+            format!(", and {} {}{}", last.product, money.format(last.sales), share(last.sales))
+        }
+        _ => String::new(),
+    };
+    let count = if products.len() == 1 { "1 product".to_string() } else { format!("{} products", products.len()) };
+    format!("{} across {}: {}{}.", subject, count, leading.join(", "), rest)
+}
+
+// Alt text for the budget chart: how many months made plan, and the totals against the budget.
+fn budget_description(budget: &[BudgetVariance], money: &MoneyFormat) -> String {
+    let on_plan = budget.iter().filter(|month| month.on_plan()).count();
+    let sales: f64 = budget.iter().map(|month| month.sales).sum();
+    let target: f64 = budget.iter().map(|month| month.target).sum();
+    format!(
+        "Line chart of monthly sales against budget: {} of {} budgeted months at or above plan, {} against a budget of {}.",
+        on_plan,
+        budget.len(),
+        money.format(sales),
+        money.format(target)
+    )
+}
+
+// Alt text for the variance chart: how many months fell either side of plan, and the extremes.
+fn variance_description(budget: &[BudgetVariance], money: &MoneyFormat) -> String {
+    let above = budget.iter().filter(|month| month.on_plan()).count();
+    let mut description = format!(
+        "Bar chart of each budgeted month's difference from its target: {} at or above plan, {} below.",
+        above,
+        budget.len() - above
+    );
+    if let Some(best) = budget.iter().filter(|month| month.on_plan()).max_by(|a, b| a.variance.total_cmp(&b.variance)) {
+        description.push_str(&format!(" Furthest above in {} by {}.", best.month, money.format(best.variance)));
+    }
+    if let Some(worst) = budget.iter().filter(|month| !month.on_plan()).min_by(|a, b| a.variance.total_cmp(&b.variance)) {
+        description.push_str(&format!(" Furthest below in {} by {}.", worst.month, money.format(-worst.variance)));
+    }
+    description
+}
+
+// Alt text for the bump chart: how many months each product it ranks was the best seller.
+fn bump_description(trends: &[ProductTrend]) -> String {
+    let trends = &trends[..trends.len().min(MAX_BUMP)];
+    let mut led = vec![0; trends.len()];
+    for month in 0..trends.first().map_or(0, |trend| trend.monthly.len()) {
+        let leader = (0..trends.len()).rev().max_by(|a, b| trends[*a].monthly[month].1.total_cmp(&trends[*b].monthly[month].1));
+        if let Some(leader) = leader {
+            led[leader] += 1;
+        }
+    }
+    let mut leaders: Vec<(usize, &str)> = led.into_iter().zip(trends.iter().map(|trend| trend.product.as_str())).filter(|(months, _)| *months > 0).collect();
+    leaders.sort_by_key(|(months, _)| std::cmp::Reverse(*months));
+    let leaders: Vec<String> = leaders
+        .iter()
+        .enumerate()
+        .map(|(i, (months, product))| match (i, months) {
+            (0, 1) => format!("{} led in 1 month", product),
+            (0, _) => format!("{} led in {} months", product, months),
+            _ => format!("{} in {}", product, months),
+        })
+        .collect();
+    format!("Bump chart of the rank of the {} best-selling products in each month: {}.", trends.len(), leaders.join(", "))
+}
+
+// Alt text for the funnel chart: each stage's sales and conversion from the one before.
+fn funnel_description(funnel: &[FunnelStage], money: &MoneyFormat) -> String {
+    let stages: Vec<String> = funnel
+        .iter()
+        .map(|stage| match stage.conversion_percent {
+            Some(conversion) => format!("{} {} ({} of the stage before)", stage.stage, money.format(stage.sales), money.percent(conversion, false)),
+            None => format!("{} {}", stage.stage, money.format(stage.sales)),
+        })
+        .collect();
+    format!("Funnel chart of sales by stage: {}.", stages.join(", "))
+}
+
+// Describes the charts `render_charts` and the inflation, budget, scenario, bump, streamgraph, stacked bar, facet, map, funnel, metric card, executive summary, and history options write, by file name.
+fn chart_description(name: &str, summary: &RunSummary, money: &MoneyFormat) -> String {
+    let aggregates = &summary.aggregates;
+    let monthly: Vec<(String, f64)> = aggregates.monthly.iter().map(|month| (month.month.clone(), month.sales)).collect();
+    match name {
+        "line_chart" => monthly_description("Line chart of monthly sales", &monthly, money),
+        "real_line_chart" => monthly_description("Line chart of nominal and inflation-adjusted monthly sales, nominal", &monthly, money),
+        "budget_chart" => budget_description(&summary.budget, money),
+        "variance_chart" => variance_description(&summary.budget, money),
+        "bar_chart" => product_description("Bar chart of sales by product", &aggregates.products, money),
+        "pie_chart" => product_description("Pie chart of each product's share of sales", &aggregates.products, money),
+        "facet_chart" => "Grid of line charts of monthly sales, one per value of the faceted column, on shared axes.".to_string(),
+        "bump_chart" => bump_description(&summary.product_trends),
+        "streamgraph_chart" => format!("Streamgraph of the monthly sales of {} products, stacked around a centered baseline.", summary.product_trends.len()),
+        "stacked_bar_chart" => format!("Stacked bar chart of each month's sales by product, {} products in all.", summary.product_trends.len()),
+        "scenario_chart" => "Line chart of monthly sales as they were, in red, against a what-if scenario, in blue.".to_string(),
+        "map_chart" => "Map of sales by region, each area shaded darker the more it sold.".to_string(),
+        "funnel_chart" => funnel_description(&summary.funnel, money),
+        "history_chart" => "Line chart of the total sales of every stored run of this input, in the order they ran.".to_string(),
+        "executive_summary" => monthly_description("Overview of metric cards, the top products, and a sparkline of monthly sales", &monthly, money),
+        "kpi_total_revenue" => format!("Metric card of the total revenue, {}.", money.format(aggregates.monthly.iter().map(|month| month.sales).sum())),
+        _ if name.starts_with("kpi_") => format!("Metric card of the {}.", name["kpi_".len()..].replace('_', " ")),
+        _ => name.replace('_', " "),
+    }
+}
+
+fn monthly_table(id: &str, monthly: impl IntoIterator<Item = (String, f64)>, labels: &ChartLabels) -> String {
+    let money = &labels.money;
+    let mut previous: Option<f64> = None;
+    let rows = monthly
+        .into_iter()
+        .map(|(month, sales)| {
+            let change = previous.filter(|previous| *previous != 0.0).map(|previous| (sales - previous) / previous * 100.0);
+            previous = Some(sales);
+            format!(
+                "<tr><td>{}</td>{}{}</tr>\n",
+                month,
+                number_cell(Some(sales), money.format(sales)),
+                number_cell(change, change.map_or(String::new(), |change| money.percent(change, true)))
+            )
+        })
+        .collect();
+    table(id, &["Month", "Sales", "Change"], &labels.translations, rows)
+}
+
+// Budgeted months with their targets; the variance is green at or above plan and red below it.
+fn budget_table(id: &str, budget: &[BudgetVariance], labels: &ChartLabels) -> String {
+    let money = &labels.money;
+    let rows = budget
+        .iter()
+        .map(|month| {
+            let percent = month.variance_percent.map_or(String::new(), |percent| format!(" ({})", money.percent(percent, true)));
+            let color = if month.on_plan() { "#009600" } else { "#c80000" };
+            format!(
+                "<tr><td>{}</td>{}{}<td align=\"right\" data-value=\"{}\" style=\"color: {}\">{}{}</td></tr>\n",
+                month.month,
+                number_cell(Some(month.sales), money.format(month.sales)),
+                number_cell(Some(month.target), money.format(month.target)),
+                month.variance,
+                color,
+                money.format(month.variance),
+                percent
+            )
+        })
+        .collect();
+    table(id, &["Month", "Sales", "Target", "Variance"], &labels.translations, rows)
+}
+
+fn funnel_table(id: &str, funnel: &[FunnelStage], labels: &ChartLabels) -> String {
+    let money = &labels.money;
+    let rows = funnel
+        .iter()
+        .map(|stage| {
+            format!(
+                "<tr><td>{}</td>{}{}</tr>\n",
+                escape_html(&stage.stage),
+                number_cell(Some(stage.sales), money.format(stage.sales)),
+                number_cell(stage.conversion_percent, stage.conversion_percent.map_or(String::new(), |conversion| money.percent(conversion, false)))
+            )
+        })
+        .collect();
+    table(id, &["Stage", "Sales", "Conversion"], &labels.translations, rows)
+}
+
+// Product names link to their drill-down sections when the report has them.
+// With `links`, the id prefix of the drill-down sections, each name links to its product's.
+fn product_table(id: &str, aggregates: &Aggregates, links: Option<&str>, labels: &ChartLabels) -> String {
+    let money = &labels.money;
+    let total: f64 = aggregates.products.iter().map(|product| product.sales).sum();
+    let rows = aggregates
+        .products
+        .iter()
+        .enumerate()
+        .map(|(i, product)| {
+            let share = (total != 0.0).then(|| product.sales / total * 100.0);
+            let name = escape_html(&product.product);
+            let name = match links {
+                Some(prefix) => format!("<a href=\"#{}product-{}\">{}</a>", prefix, i, name),
+                None => name,
+            };
+            format!(
+                "<tr><td>{}</td>{}{}</tr>\n",
+                name,
+                number_cell(Some(product.sales), money.format(product.sales)),
+                number_cell(share, share.map_or(String::new(), |share| money.percent(share, false)))
+            )
+        })
+        .collect();
+    table(id, &["Product", "Sales", "Share"], &labels.translations, rows)
+}
+
+// An image map over the bar chart, of `size`, taking each bar to its product's section.
+fn bar_links(aggregates: &Aggregates, prefix: &str, size: (u32, u32)) -> Result<String, Box<dyn Error>> {
+    let products: Vec<(String, f64)> = aggregates.products.iter().map(|product| (product.product.clone(), product.sales)).collect();
+    let mut html = format!("<map name=\"{}product-bars\">\n", prefix);
+    for (i, (area, (product, _))) in bar_chart_areas(&products, size)?.iter().zip(&products).enumerate() {
+        html.push_str(&format!(
+            "<area shape=\"rect\" coords=\"{},{},{},{}\" href=\"#{}product-{}\" alt=\"{}\" title=\"{}\">\n",
+            area.left,
+            area.top,
+            area.right,
+            area.bottom,
+            prefix,
+            i,
+            escape_html(product),
+            escape_html(product)
+        ));
+    }
+    html.push_str("</map>\n");
+    Ok(html)
+}
+
+fn trend_section(prefix: &str, i: usize, trend: &ProductTrend, labels: &ChartLabels) -> Result<String, Box<dyn Error>> {
+    let mut svg = String::new();
+    {
+        let root = SVGBackend::with_string(&mut svg, (800, 400)).into_drawing_area();
+        // The browser shapes and orders the SVG's text itself.
+        draw_line_chart(&root, &trend.monthly, &ChartLabels { visual_order: false, break_even: None, ..labels.clone() })?;
+    }
+    let total: f64 = trend.monthly.iter().map(|(_, sales)| sales).sum();
+    let monthly: Vec<(String, f64)> = trend.monthly.iter().map(|(month, sales)| (month.format("%Y-%m").to_string(), *sales)).collect();
+    let description = monthly_description(&format!("Line chart of monthly sales of {}", trend.product), &monthly, &labels.money);
+    let id = format!("{}product-{}-data", prefix, i);
+    Ok(format!(
+        "<h2 id=\"{}product-{}\">{}</h2>\n<p>{} {}. <a href=\"#{}bar_chart\">{}</a></p>\n<div role=\"img\" aria-label=\"{}\" aria-describedby=\"{}\">{}</div>\n{}",
+        prefix,
+        i,
+        escape_html(&trend.product),
+        escape_html(&labels.translations.get("Total sales")),
+        labels.money.format(total),
+        prefix,
+        escape_html(&labels.translations.get("Back to the bar chart")),
+        escape_html(&description),
+        id,
+        svg,
+        monthly_table(&id, monthly, labels)
+    ))
+}
+
+/// The charts among a run's artifacts, which also lists CSV reports and the dashboard, other
+/// than the charts of single products.
+pub fn charts(summary: &RunSummary) -> impl Iterator<Item = &Path> {
+    summary
+        .artifacts
+        .iter()
+        .filter(|path| path.extension().is_some_and(|ext| ext == "png" || ext == "svg") && !summary.product_charts.contains(path))
+        .map(|path| path.as_path())
+}
+
+// `labels` for one summary: amounts in its currency and captions filled in from its figures.
+fn summary_labels(summary: &RunSummary, labels: &ChartLabels) -> ChartLabels {
+    let mut labels = labels.clone();
+    if let Some(currency) = &summary.currency {
+        labels.money = MoneyFormat { symbol: currency_symbol(currency), ..labels.money };
+    }
+    let months = summary.aggregates.monthly.iter().map(|month| month.month.as_str());
+    labels.with_values(CaptionValues::new(months, labels.money.format(summary.total_sales), summary.rows))
+}
+
+// The summary's sentences on its totals, if it has them.
+fn narrative_paragraph(summary: &RunSummary) -> String {
+    if summary.narrative.is_empty() {
+        return String::new();
+    }
+    format!("<p>{}</p>\n", escape_html(&summary.narrative))
+}
+
+// The table of headline numbers. A run split by currency has no sales figures of its own.
+fn headline(summary: &RunSummary, labels: &ChartLabels) -> String {
+    let mut html = String::from("<table>\n");
+    let translations = &labels.translations;
+    let mut row = |label: &str, value: String| {
+        html.push_str(&format!("<tr><th align=\"left\">{}</th><td>{}</td></tr>\n", escape_html(&translations.get(label)), value))
+    };
+    let money = &labels.money;
+    if summary.currencies.is_empty() {
+        row("Total sales", money.format(summary.total_sales));
+    }
+    row("Months", summary.months.to_string());
+    row("Products", summary.products.to_string());
+    if let Some(latest) = &summary.latest_month {
+        let change = summary.latest_change.map_or(String::new(), |change| format!(" ({})", money.percent(change, true)));
+        row("Latest month", format!("{}: {}{}", latest.month, money.format(latest.sales), change));
+    }
+    row("Duplicate rows", summary.anomalies.duplicates.to_string());
+    row("Suspicious rows", summary.anomalies.suspicious_rows.to_string());
+    if !summary.budget.is_empty() {
+        let variance: f64 = summary.budget.iter().map(|month| month.variance).sum();
+        let target: f64 = summary.budget.iter().map(|month| month.target).sum();
+        let percent = if target == 0.0 { String::new() } else { format!(" ({})", money.percent(variance / target.abs() * 100.0, true)) };
+        row("Budget variance", format!("{}{}", money.format(variance), percent));
+    }
+    if let Some(changes) = &summary.run_changes {
+        row("Changed since", escape_html(&changes.previous_run));
+        let list = |items: &[String]| if items.is_empty() { "–".to_string() } else { escape_html(&items.join(", ")) };
+        row("New products", list(&changes.new_products));
+        row("Disappeared products", list(&changes.removed_products));
+        let months: Vec<String> = changes
+            .changed_months
+            .iter()
+            .map(|month| format!("{}: {} → {}", month.month, money.format(month.previous), money.format(month.current)))
+            .collect();
+        row("Changed months", list(&months));
+    }
+    if !summary.alerts.is_empty() {
+        row("Alerts", escape_html(&summary.alerts.join("; ")));
+    }
+    for (name, value) in &summary.metrics {
+        let value = value.as_str().map_or_else(|| value.to_string(), str::to_string);
+        html.push_str(&format!("<tr><th align=\"left\">{}</th><td>{}</td></tr>\n", escape_html(name), escape_html(&value)));
+    }
+    if let Some(holidays) = &summary.holidays {
+        for (caption, days, average) in [
+            ("Average day, holidays ({} days)", holidays.holiday_days, holidays.holiday_average),
+            ("Average day, other days ({} days)", holidays.other_days, holidays.other_average),
+        ] {
+            let label = escape_html(&translations.fill(caption, &days.to_string()));
+            html.push_str(&format!("<tr><th align=\"left\">{}</th><td>{}</td></tr>\n", label, money.format(average)));
+        }
+    }
+    html.push_str("</table>\n");
+    html
+}
+
+// One chart of a report: its image, linked to the per-product sections when there are any,
+// and the table of what it plots, empty for charts without one.
+#[derive(Serialize)]
+struct ReportChart {
+    name: String,
+    file: PathBuf,
+    src: String,
+    description: String,
+    image: String,
+    table: String,
+}
+
+// Every chart of `summary`. `prefix` keeps the element ids of one currency's charts apart from
+// another's.
+fn report_charts<F>(summary: &RunSummary, labels: &ChartLabels, prefix: &str, image_src: &mut F) -> Result<Vec<ReportChart>, Box<dyn Error>>
+where
+    F: FnMut(&Path) -> Result<String, Box<dyn Error>>,
+{
+    let money = &labels.money;
+    let drill_down = !summary.product_trends.is_empty();
+    let mut report_charts = Vec::new();
+    for chart in charts(summary) {
+        let name = chart.file_stem().unwrap_or_default().to_string_lossy();
+        // A currency's charts are named like `line_chart_EUR`.
+        let kind = summary.currency.as_ref().and_then(|currency| name.strip_suffix(&format!("_{}", currency))).unwrap_or(&name);
+        let src = image_src(chart)?;
+        let description = chart_description(kind, summary, money);
+        let alt = escape_html(&description);
+        // The inflation chart plots months too; the bar and pie charts plot products.
+        let id = format!("{}-data", name);
+        let data = if kind == "budget_chart" {
+            budget_table(&id, &summary.budget, labels)
+        } else if kind.ends_with("line_chart") {
+            let monthly = summary.aggregates.monthly.iter().map(|month| (month.month.clone(), month.sales));
+            monthly_table(&id, monthly, labels)
+        } else if kind == "bar_chart" || kind == "pie_chart" {
+            product_table(&id, &summary.aggregates, drill_down.then_some(prefix), labels)
+        } else if kind == "funnel_chart" {
+            funnel_table(&id, &summary.funnel, labels)
+        } else {
+            String::new()
+        };
+        // The variance chart plots the budget chart's table.
+        let described = if kind == "variance_chart" {
+            format!(" aria-describedby=\"{}\"", id.replacen("variance_chart", "budget_chart", 1))
+        } else if data.is_empty() {
+            String::new()
+        } else {
+            format!(" aria-describedby=\"{}\"", id)
+        };
+        let image = if kind == "bar_chart" && drill_down {
+            format!(
+                "{}<p id=\"{}bar_chart\"><img src=\"{}\" alt=\"{}\"{} usemap=\"#{}product-bars\"></p>\n",
+                bar_links(&summary.aggregates, prefix, labels.size)?,
+                prefix,
+                src,
+                alt,
+                described,
+                prefix
+            )
+        } else {
+            format!("<p><img src=\"{}\" alt=\"{}\"{}></p>\n", src, alt, described)
+        };
+        report_charts.push(ReportChart { name: name.to_string(), file: chart.to_path_buf(), src, description, image, table: data });
+    }
+    Ok(report_charts)
+}
+
+// The per-product drill-down sections of `summary`, if it has any.
+fn trend_sections(summary: &RunSummary, labels: &ChartLabels, prefix: &str) -> Result<String, Box<dyn Error>> {
+    summary.product_trends.iter().enumerate().map(|(i, trend)| trend_section(prefix, i, trend, labels)).collect()
+}
+
+// Every chart of `summary` with its table, then the per-product drill-down sections.
+fn chart_sections<F>(html: &mut String, summary: &RunSummary, labels: &ChartLabels, prefix: &str, image_src: &mut F) -> Result<(), Box<dyn Error>>
+where
+    F: FnMut(&Path) -> Result<String, Box<dyn Error>>,
+{
+    for chart in report_charts(summary, labels, prefix, image_src)? {
+        html.push_str(&chart.image);
+        html.push_str(&chart.table);
+    }
+    html.push_str(&trend_sections(summary, labels, prefix)?);
+    Ok(())
+}
+
+/// HTML report with the headline numbers and every chart, each followed by a sortable
+/// table of the totals it plots. When the summary has per-product trends, each bar of the bar
+/// chart links to a section with that product's monthly trend. A run split by currency gets a
+/// section per currency, with amounts in its symbol. `image_src` gives the `src` of a chart's
+/// `<img>`.
+pub fn html_report<F>(summary: &RunSummary, labels: &ChartLabels, mut image_src: F) -> Result<String, Box<dyn Error>>
+where
+    F: FnMut(&Path) -> Result<String, Box<dyn Error>>,
+{
+    let labels = &summary_labels(summary, labels);
+    let dir = if labels.rtl { " dir=\"rtl\"" } else { "" };
+    let mut html = format!("<html{}><body style=\"font-family: sans-serif\">\n<h1>{}</h1>\n", dir, escape_html(&labels.translations.get("Sales report")));
+    // A split run's sentences are in its currencies' sections.
+    if summary.currencies.is_empty() {
+        html.push_str(&narrative_paragraph(summary));
+    }
+    html.push_str(&headline(summary, labels));
+    // A split run's artifacts are its currencies' charts, shown in their sections.
+    if summary.currencies.is_empty() {
+        chart_sections(&mut html, summary, labels, "", &mut image_src)?;
+    }
+    for section in &summary.currencies {
+        let currency = section.currency.as_deref().unwrap_or_default();
+        let labels = &summary_labels(section, labels);
+        html.push_str(&format!("<h2 id=\"{}\">{}</h2>\n", escape_html(currency), escape_html(currency)));
+        html.push_str(&narrative_paragraph(section));
+        html.push_str(&headline(section, labels));
+        chart_sections(&mut html, section, labels, &format!("{}-", escape_html(currency)), &mut image_src)?;
+    }
+    html.push_str(SORT_SCRIPT);
+    html.push_str("</body></html>\n");
+    Ok(html)
+}
+
+// Each month's or product's totals in both datasets, the difference, and the change in percent.
+fn delta_table(id: &str, kind: &str, comparison: &Comparison, deltas: &[Delta], labels: &ChartLabels) -> String {
+    let money = &labels.money;
+    let rows = deltas
+        .iter()
+        .map(|delta| {
+            let change = delta.change();
+            format!(
+                "<tr><td>{}</td>{}{}{}{}</tr>\n",
+                escape_html(&delta.key),
+                number_cell(Some(delta.old), money.format(delta.old)),
+                number_cell(Some(delta.new), money.format(delta.new)),
+                number_cell(Some(delta.difference()), money.format(delta.difference())),
+                number_cell(change, change.map_or(String::new(), |change| money.percent(change, true)))
+            )
+        })
+        .collect();
+    let [old, new] = &comparison.names;
+    table(id, &[kind, old, new, "Difference", "Change"], &labels.translations, rows)
+}
+
+/// A page comparing two datasets: their total sales, then the comparison line and bar charts
+/// in `charts`, embedded, each followed by a sortable table of both datasets' totals with the
+/// difference.
+pub fn comparison_report(comparison: &Comparison, charts: &[PathBuf], labels: &ChartLabels) -> Result<String, Box<dyn Error>> {
+    let money = &labels.money;
+    let translations = &labels.translations;
+    let dir = if labels.rtl { " dir=\"rtl\"" } else { "" };
+    let mut html = format!("<html{}><body style=\"font-family: sans-serif\">\n<h1>{}</h1>\n<table>\n", dir, escape_html(&translations.get("Sales comparison")));
+    let totals = comparison.totals();
+    let change = totals.change().map_or(String::new(), |change| format!(" ({})", money.percent(change, true)));
+    for (label, value) in [
+        (format!("{} ({})", comparison.names[0], comparison.ranges[0]), money.format(totals.old)),
+        (format!("{} ({})", comparison.names[1], comparison.ranges[1]), money.format(totals.new)),
+        (translations.get("Difference").into_owned(), format!("{}{}", money.format(totals.difference()), change)),
+    ] {
+        html.push_str(&format!("<tr><th align=\"left\">{}</th><td>{}</td></tr>\n", escape_html(&label), value));
+    }
+    html.push_str("</table>\n");
+
+    let [old, new] = &comparison.names;
+    let monthly = |side: usize| -> Vec<(String, f64)> {
+        comparison.monthly[side].iter().map(|(month, sales)| (month.format("%Y-%m").to_string(), *sales)).collect()
+    };
+    let line = format!(
+        "{} {}",
+        monthly_description(&format!("Line chart of monthly sales of {}", old), &monthly(0), money),
+        monthly_description(&format!("Monthly sales of {}", new), &monthly(1), money)
+    );
+    let bar = format!("Bar chart of each product's sales in {} and {}, side by side.", old, new);
+    let sections = [(line, "Month", &comparison.months), (bar, "Product", &comparison.products)];
+    for (chart, (alt, kind, deltas)) in charts.iter().zip(sections) {
+        let png = fs::read(chart).map_err(|e| format!("Cannot read {}: {}", chart.display(), e))?;
+        let id = format!("{}-data", chart.file_stem().unwrap_or_default().to_string_lossy());
+        html.push_str(&format!(
+            "<p><img src=\"data:image/png;base64,{}\" alt=\"{}\" aria-describedby=\"{}\"></p>\n",
+            STANDARD.encode(png),
+            escape_html(&alt),
+            id
+        ));
+        html.push_str(&delta_table(&id, kind, comparison, deltas, labels));
+    }
+    html.push_str(SORT_SCRIPT);
+    html.push_str("</body></html>\n");
+    Ok(html)
+}
+
+pub fn write_comparison_report<P: AsRef<Path>>(path: P, comparison: &Comparison, charts: &[PathBuf], labels: &ChartLabels) -> Result<(), Box<dyn Error>> {
+    fs::write(path, comparison_report(comparison, charts, labels)?)?;
+    Ok(())
+}
+
+/// The report as a standalone page with the charts embedded, so it opens in any browser
+/// without the chart files next to it.
+pub fn standalone_html_report(summary: &RunSummary, labels: &ChartLabels) -> Result<String, Box<dyn Error>> {
+    html_report(summary, labels, embedded_image)
+}
+
+/// The media type of a chart file, `image/svg+xml` for an SVG image and `image/png` otherwise.
+pub fn image_type(chart: &Path) -> &'static str {
+    if chart.extension().is_some_and(|ext| ext == "svg") {
+        "image/svg+xml"
+    } else {
+        "image/png"
+    }
+}
+
+// A chart's image file as a `data:` URL.
+fn embedded_image(chart: &Path) -> Result<String, Box<dyn Error>> {
+    let image = fs::read(chart).map_err(|e| format!("Cannot read {}: {}", chart.display(), e))?;
+    Ok(format!("data:{};base64,{}", image_type(chart), STANDARD.encode(image)))
+}
+
+pub fn write_html_report<P: AsRef<Path>>(path: P, summary: &RunSummary, labels: &ChartLabels) -> Result<(), Box<dyn Error>> {
+    fs::write(path, standalone_html_report(summary, labels)?)?;
+    Ok(())
+}
+
+// One currency of a templated report, or the whole run.
+#[cfg(feature = "templates")]
+#[derive(Serialize)]
+struct TemplateSection<'a> {
+    currency: Option<&'a str>,
+    summary: &'a RunSummary,
+    monthly: &'a [MonthTotal],
+    products: &'a [ProductTotal],
+    headline: String,
+    charts: Vec<ReportChart>,
+    product_sections: String,
+}
+
+#[cfg(feature = "templates")]
+fn template_section<'a, F>(summary: &'a RunSummary, labels: &ChartLabels, prefix: &str, image_src: &mut F) -> Result<TemplateSection<'a>, Box<dyn Error>>
+where
+    F: FnMut(&Path) -> Result<String, Box<dyn Error>>,
+{
+    Ok(TemplateSection {
+        currency: summary.currency.as_deref(),
+        summary,
+        monthly: &summary.aggregates.monthly,
+        products: &summary.aggregates.products,
+        headline: headline(summary, labels),
+        charts: report_charts(summary, labels, prefix, image_src)?,
+        product_sections: trend_sections(summary, labels, prefix)?,
+    })
+}
+
+/// The report rendered from the Tera template at `template` instead of the built-in layout,
+/// for a report with a layout and commentary of its own, or in another format such as
+/// Markdown. Templates named `.html`, `.htm`, or `.xml` have their values escaped.
+///
+/// The template gets `title`, `rtl`, `summary` (the run summary as in the JSON), `monthly` and
+/// `products` in chart order, `headline` (the built-in table of headline numbers), `charts`
+/// (each with its `name`, `file`, `src`, `description`, and the built-in `image` and `table`
+/// HTML), `product_sections` (the per-product drill-down HTML), and `sort_script` (the script
+/// sorting the built-in tables). A run split by currency has these for each currency in
+/// `sections`, with its `currency`, and no charts of its own. The `money` filter formats an
+/// amount, in `currency="EUR"` if given, and the `percent` filter formats a change in percent.
+#[cfg(feature = "templates")]
+pub fn templated_report<F>(template: &Path, summary: &RunSummary, labels: &ChartLabels, mut image_src: F) -> Result<String, Box<dyn Error>>
+where
+    F: FnMut(&Path) -> Result<String, Box<dyn Error>>,
+{
+    use tera::{Context, Kwargs, State, Tera, TeraResult};
+
+    let source = fs::read_to_string(template).map_err(|e| format!("Cannot read report template {}: {}", template.display(), e))?;
+    let name = template.file_name().unwrap_or_default().to_string_lossy();
+    let labels = &summary_labels(summary, labels);
+    let mut tera = Tera::new();
+    let money = labels.money.clone();
+    tera.register_filter("money", move |amount: f64, kwargs: Kwargs, _: &State| -> TeraResult<String> {
+        Ok(match kwargs.get::<&str>("currency")? {
+            Some(currency) => MoneyFormat { symbol: currency_symbol(currency), ..money.clone() }.format(amount),
+            None => money.format(amount),
+        })
+    });
+    let money = labels.money.clone();
+    tera.register_filter("percent", move |change: f64, _: Kwargs, _: &State| money.percent(change, true));
+    tera.add_raw_template(&name, &source).map_err(|e| format!("Invalid report template {}: {}", template.display(), e))?;
+
+    let mut context = Context::new();
+    context.insert("title", &labels.translations.get("Sales report"));
+    context.insert("rtl", &labels.rtl);
+    context.insert("sort_script", SORT_SCRIPT);
+    // A split run's artifacts are its currencies' charts, in their sections.
+    let mut run = template_section(summary, labels, "", &mut image_src)?;
+    if !summary.currencies.is_empty() {
+        run.charts.clear();
+    }
+    context.extend(Context::from_serialize(&run)?);
+    let sections = summary
+        .currencies
+        .iter()
+        .map(|section| {
+            let prefix = format!("{}-", escape_html(section.currency.as_deref().unwrap_or_default()));
+            template_section(section, &summary_labels(section, labels), &prefix, &mut image_src)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    context.insert("sections", &sections);
+    tera.render(&name, &context).map_err(|e| format!("Cannot render report template {}: {}", template.display(), e).into())
+}
+
+/// `templated_report` with the charts embedded, like `standalone_html_report`.
+#[cfg(feature = "templates")]
+pub fn write_templated_report<P: AsRef<Path>>(path: P, template: &Path, summary: &RunSummary, labels: &ChartLabels) -> Result<(), Box<dyn Error>> {
+    fs::write(path, templated_report(template, summary, labels, embedded_image)?)?;
+    Ok(())
+}
@@ -0,0 +1,175 @@
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use chrono::{Datelike, Days, Months, NaiveDate, Weekday};
+use serde::Deserialize;
+
+/// Which of a month's days with the year-end weekday closes the fiscal year.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum YearEnd {
+    /// The one nearest the month's last day, which may fall early in the next month
+    #[default]
+    Nearest,
+    /// The month's last one
+    Last,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Config {
+    #[serde(default = "default_pattern")]
+    pattern: String,
+    #[serde(default = "default_end_weekday")]
+    end_weekday: String,
+    #[serde(default = "default_end_month")]
+    end_month: u32,
+    #[serde(default)]
+    end: YearEnd,
+}
+
+fn default_pattern() -> String {
+    "4-5-4".to_string()
+}
+
+fn default_end_weekday() -> String {
+    "Saturday".to_string()
+}
+
+fn default_end_month() -> u32 {
+    1
+}
+
+/// A 4-4-5 style retail calendar: fiscal years of 52 or 53 whole weeks ending on a fixed
+/// weekday, split into four quarters of three periods. The defaults are the NRF calendar,
+/// 4-5-4 weeks to the Saturday nearest the end of January.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RetailCalendar {
+    /// Weeks in the three periods of each quarter; the 53rd week of a long year joins the last period
+    pub weeks: [u32; 3],
+    pub end_weekday: Weekday,
+    pub end_month: u32,
+    pub end: YearEnd,
+}
+
+impl Default for RetailCalendar {
+    fn default() -> Self {
+        RetailCalendar { weeks: [4, 5, 4], end_weekday: Weekday::Sat, end_month: 1, end: YearEnd::Nearest }
+    }
+}
+
+impl RetailCalendar {
+    /// Reads a TOML file such as `pattern = "4-4-5"`, `end_weekday = "Sunday"`, `end_month = 12`,
+    /// `end = "last"`. Keys it leaves out take the NRF calendar's values.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path).map_err(|e| format!("Cannot read retail calendar {}: {}", path.display(), e))?;
+        let invalid = |reason: String| format!("Invalid retail calendar {}: {}", path.display(), reason);
+        let config: Config = toml::from_str(&contents).map_err(|e| invalid(e.to_string()))?;
+        let weeks: Vec<u32> = config.pattern.split('-').map(|weeks| weeks.trim().parse().unwrap_or(0)).collect();
+        let weeks: [u32; 3] = match weeks.as_slice() {
+            [a, b, c] if *a > 0 && *b > 0 && *c > 0 && a + b + c == 13 => [*a, *b, *c],
+            _ => return Err(invalid(format!("pattern \"{}\" is not three period lengths in weeks adding up to 13, such as 4-4-5", config.pattern)).into()),
+        };
+        let end_weekday = config.end_weekday.parse().map_err(|_| invalid(format!("\"{}\" is not a weekday", config.end_weekday)))?;
+        if !(1..=12).contains(&config.end_month) {
+            return Err(invalid(format!("end_month {} is not a month from 1 to 12", config.end_month)).into());
+        }
+        Ok(RetailCalendar { weeks, end_weekday, end_month: config.end_month, end: config.end })
+    }
+
+    // Last day of the fiscal year closing around the end of `end_month` in `year`.
+    fn year_end(&self, year: i32) -> NaiveDate {
+        let first = NaiveDate::from_ymd_opt(year, self.end_month, 1).expect("end_month is checked");
+        let last = first + Months::new(1) - Days::new(1);
+        let back = (7 + last.weekday().num_days_from_monday() - self.end_weekday.num_days_from_monday()) % 7;
+        match self.end {
+            YearEnd::Nearest if back > 3 => last + Days::new(u64::from(7 - back)),
+            _ => last - Days::new(u64::from(back)),
+        }
+    }
+
+    /// The fiscal period `date` falls in, as the first day of the month standing for it:
+    /// period 3 of fiscal 2024 is 2024-03-01. A fiscal year is named after the calendar year
+    /// most of it falls in, so the NRF year starting in February 2024 is 2024.
+    pub fn period_of(&self, date: NaiveDate) -> NaiveDate {
+        let year = (date.year() - 1..=date.year() + 1).find(|year| self.year_end(*year) >= date).unwrap_or(date.year() + 1);
+        let start = self.year_end(year - 1) + Days::new(1);
+        let week = (date - start).num_days() / 7;
+        let mut ends = 0;
+        let period = (0..12)
+            .find(|period| {
+                ends += i64::from(self.weeks[period % 3]);
+                week < ends
+            })
+            .unwrap_or(11);
+        let name = (start + Days::new(182)).year();
+        NaiveDate::from_ymd_opt(name, period as u32 + 1, 1).expect("periods are months 1 to 12")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn nrf_years_end_on_the_saturday_nearest_the_end_of_january() {
+        let nrf = RetailCalendar::default();
+        assert_eq!(nrf.year_end(2022), day(2022, 1, 29));
+        assert_eq!(nrf.year_end(2023), day(2023, 1, 28));
+        // Fiscal 2023 is a 53-week year, ending in early February 2024.
+        assert_eq!(nrf.year_end(2024), day(2024, 2, 3));
+        assert_eq!((nrf.year_end(2024) - nrf.year_end(2023)).num_days(), 53 * 7);
+        assert_eq!(nrf.year_end(2025), day(2025, 2, 1));
+    }
+
+    #[test]
+    fn last_year_ends_stay_in_their_month() {
+        let last = RetailCalendar { end: YearEnd::Last, ..RetailCalendar::default() };
+        assert_eq!(last.year_end(2023), day(2023, 1, 28));
+        assert_eq!(last.year_end(2024), day(2024, 1, 27));
+        assert_eq!(last.period_of(day(2024, 1, 28)), day(2024, 1, 1));
+        assert_eq!(RetailCalendar::default().period_of(day(2024, 1, 28)), day(2023, 12, 1));
+    }
+
+    #[test]
+    fn nrf_periods_follow_the_4_5_4_pattern() {
+        let nrf = RetailCalendar::default();
+        assert_eq!(nrf.period_of(day(2023, 1, 29)), day(2023, 1, 1));
+        assert_eq!(nrf.period_of(day(2023, 2, 25)), day(2023, 1, 1));
+        assert_eq!(nrf.period_of(day(2023, 2, 26)), day(2023, 2, 1));
+        assert_eq!(nrf.period_of(day(2023, 4, 1)), day(2023, 2, 1));
+        assert_eq!(nrf.period_of(day(2023, 4, 2)), day(2023, 3, 1));
+        assert_eq!(nrf.period_of(day(2023, 12, 30)), day(2023, 11, 1));
+        assert_eq!(nrf.period_of(day(2023, 12, 31)), day(2023, 12, 1));
+    }
+
+    #[test]
+    fn the_53rd_week_joins_the_last_period() {
+        let nrf = RetailCalendar::default();
+        assert_eq!(nrf.period_of(day(2024, 1, 27)), day(2023, 12, 1));
+        assert_eq!(nrf.period_of(day(2024, 1, 28)), day(2023, 12, 1));
+        assert_eq!(nrf.period_of(day(2024, 2, 3)), day(2023, 12, 1));
+        assert_eq!(nrf.period_of(day(2024, 2, 4)), day(2024, 1, 1));
+    }
+
+    #[test]
+    fn a_4_4_5_calendar_can_end_in_december() {
+        let calendar = RetailCalendar { weeks: [4, 4, 5], end_month: 12, ..RetailCalendar::default() };
+        assert_eq!(calendar.year_end(2022), day(2022, 12, 31));
+        assert_eq!(calendar.year_end(2023), day(2023, 12, 30));
+        assert_eq!(calendar.period_of(day(2023, 1, 1)), day(2023, 1, 1));
+        assert_eq!(calendar.period_of(day(2023, 2, 25)), day(2023, 2, 1));
+        assert_eq!(calendar.period_of(day(2023, 2, 26)), day(2023, 3, 1));
+        assert_eq!(calendar.period_of(day(2023, 4, 1)), day(2023, 3, 1));
+        assert_eq!(calendar.period_of(day(2023, 4, 2)), day(2023, 4, 1));
+        assert_eq!(calendar.period_of(day(2023, 12, 30)), day(2023, 12, 1));
+        // The year starting on the last day of December 2023 is mostly 2024.
+        assert_eq!(calendar.period_of(day(2023, 12, 31)), day(2024, 1, 1));
+    }
+}
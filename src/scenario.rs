@@ -0,0 +1,122 @@
+use std::error::Error;
+
+use chrono::NaiveDate;
+
+use crate::{date_to_key, key_to_date, parse_month, CubeCell, DateKey};
+
+/// One what-if change to the sales, such as `+10% on Product A from 2024-06`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Adjustment {
+    /// Change in percent, negative for a drop
+    pub percent: f64,
+    /// The product changed, or None for all of them
+    pub product: Option<String>,
+    /// First and last month changed, inclusive, or None for no bound
+    pub from: Option<DateKey>,
+    pub to: Option<DateKey>,
+}
+
+impl Adjustment {
+    /// Reads `<+N%|-N%> [on <product>] [from YYYY-MM] [to YYYY-MM]`.
+    pub fn parse(text: &str) -> Result<Self, Box<dyn Error>> {
+        let invalid = |reason: &str| format!("Invalid scenario \"{}\": {}", text, reason);
+        let mut rest = text.trim();
+        // A product may have "to" in its name, as in "Back to School", so only a month ends the text.
+        let month_after = |keyword: &str, rest: &mut &str| {
+            let (before, month) = rest.rsplit_once(&format!(" {} ", keyword))?;
+            let month = parse_month(month.trim().as_bytes()).ok()?;
+            *rest = before.trim_end();
+            Some(month)
+        };
+        let to = month_after("to", &mut rest);
+        let from = month_after("from", &mut rest);
+        let (change, product) = match rest.split_once(' ') {
+            Some((change, product)) => {
+                let product = product.trim_start().strip_prefix("on ").ok_or_else(|| invalid("the product goes after \"on\""))?;
+                (change, Some(product.trim().to_string()))
+            }
+            None => (rest, None),
+        };
+        let percent = change
+            .strip_suffix('%')
+            .filter(|number| number.starts_with(['+', '-']))
+            .and_then(|number| number.parse::<f64>().ok())
+            .filter(|percent| percent.is_finite())
+            .ok_or_else(|| invalid("needs a change such as +10% or -5% first"))?;
+        if let (Some(from), Some(to)) = (from, to) {
+            if to < from {
+                return Err(invalid("ends before it starts").into());
+            }
+        }
+        Ok(Adjustment { percent, product: product.filter(|product| !product.is_empty()), from, to })
+    }
+
+    fn applies(&self, product: &str, month: DateKey) -> bool {
+        self.product.as_ref().is_none_or(|changed| changed == product)
+            && self.from.is_none_or(|from| month >= from)
+            && self.to.is_none_or(|to| month <= to)
+    }
+}
+
+/// What-if adjustments applied together on top of the historical sales; two changing the same
+/// product and month compound.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Scenario {
+    pub adjustments: Vec<Adjustment>,
+}
+
+impl Scenario {
+    pub fn parse<S: AsRef<str>>(texts: &[S]) -> Result<Self, Box<dyn Error>> {
+        let adjustments = texts.iter().map(|text| Adjustment::parse(text.as_ref())).collect::<Result<_, _>>()?;
+        Ok(Scenario { adjustments })
+    }
+
+    /// The products the adjustments name that sell in none of `cube`'s cells.
+    pub fn unknown_products(&self, cube: &[CubeCell]) -> Vec<&str> {
+        self.adjustments
+            .iter()
+            .filter_map(|adjustment| adjustment.product.as_deref())
+            .filter(|product| !cube.iter().any(|cell| cell.product == *product))
+            .collect()
+    }
+
+    /// The months of `monthly_data` with every product's sales in `cube` changed as the
+    /// scenario has them.
+    pub fn apply(&self, monthly_data: &[(NaiveDate, f64)], cube: &[CubeCell]) -> Vec<(NaiveDate, f64)> {
+        let mut scenario: Vec<(NaiveDate, f64)> = monthly_data.to_vec();
+        for cell in cube {
+            let factor: f64 = self
+                .adjustments
+                .iter()
+                .filter(|adjustment| adjustment.applies(&cell.product, cell.month))
+                .map(|adjustment| 1.0 + adjustment.percent / 100.0)
+                .product();
+            if factor == 1.0 {
+                continue;
+            }
+            if let Some((_, sales)) = scenario.iter_mut().find(|(month, _)| date_to_key(month) == cell.month) {
+                *sales += cell.sales * (factor - 1.0);
+            }
+        }
+        scenario
+    }
+
+    /// Each adjustment written back out, as in `+10% on Product A from 2024-06`.
+    pub fn describe(&self) -> Vec<String> {
+        self.adjustments
+            .iter()
+            .map(|adjustment| {
+                let mut text = format!("{:+}%", adjustment.percent);
+                if let Some(product) = &adjustment.product {
+                    text.push_str(&format!(" on {}", product));
+                }
+                for (keyword, month) in [("from", adjustment.from), ("to", adjustment.to)] {
+                    if let Some(month) = month {
+                        text.push_str(&format!(" {} {}", keyword, key_to_date(month).format("%Y-%m")));
+                    }
+                }
+                text
+            })
+            .collect()
+    }
+}
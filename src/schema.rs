@@ -0,0 +1,500 @@
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime};
+use chrono_tz::Tz;
+use csv::{ByteRecord, StringRecord};
+use serde::Deserialize;
+
+use crate::date_order::{parse_numeric_date, DateOrder};
+use crate::fx::ExchangeRates;
+use crate::number::NumberFormat;
+use crate::retail::RetailCalendar;
+use crate::{date_to_key, key_to_date, parse_month, DateKey, MissingValuePolicy, MissingValues};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColumnType {
+    #[default]
+    String,
+    Date,
+    Number,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColumnRole {
+    Month,
+    Product,
+    Amount,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ColumnSpec {
+    pub name: String,
+    #[serde(rename = "type", default)]
+    pub column_type: ColumnType,
+    pub role: Option<ColumnRole>,
+    #[serde(default)]
+    pub formats: Vec<String>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    #[serde(default = "default_required")]
+    pub required: bool,
+}
+
+fn default_required() -> bool {
+    true
+}
+
+impl ColumnSpec {
+    fn new(name: &str, column_type: ColumnType) -> Self {
+        ColumnSpec {
+            name: name.to_string(),
+            column_type,
+            role: None,
+            formats: Vec::new(),
+            min: None,
+            max: None,
+            required: true,
+        }
+    }
+
+    pub fn optional(name: &str) -> Self {
+        ColumnSpec {
+            required: false,
+            ..ColumnSpec::new(name, ColumnType::String)
+        }
+    }
+
+    fn role(&self) -> Option<ColumnRole> {
+        self.role.or_else(|| match self.name.to_lowercase().as_str() {
+            "month" => Some(ColumnRole::Month),
+            "product" => Some(ColumnRole::Product),
+            "sales_amount" => Some(ColumnRole::Amount),
+            _ => None,
+        })
+    }
+
+    fn check_range(&self, value: f64) -> Result<(), String> {
+        // NaN compares false with any bound, so it is never inside one.
+        if (value.is_nan() && (self.min.is_some() || self.max.is_some())) || self.min.is_some_and(|min| value < min) || self.max.is_some_and(|max| value > max) {
+            return Err(format!(
+                "Value {} is outside the allowed range {}..{}",
+                value,
+                self.min.map_or(String::new(), |min| min.to_string()),
+                self.max.map_or(String::new(), |max| max.to_string()),
+            ));
+        }
+        Ok(())
+    }
+
+    fn check_value(&self, field: &[u8], number: &NumberFormat) -> Result<(), String> {
+        if field.is_empty() {
+            if self.required {
+                return Err("Missing value".into());
+            }
+            return Ok(());
+        }
+        match self.column_type {
+            ColumnType::String => Ok(()),
+            ColumnType::Number => self.check_range(number.parse(field)?),
+            ColumnType::Date => parse_date(field, &self.formats, None, Tz::UTC).map(|_| ()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Schema {
+    #[serde(default)]
+    pub allow_extra_columns: bool,
+    pub columns: Vec<ColumnSpec>,
+}
+
+impl Default for Schema {
+    fn default() -> Self {
+        Schema {
+            allow_extra_columns: false,
+            columns: vec![
+                ColumnSpec::new("month", ColumnType::Date),
+                ColumnSpec::new("product", ColumnType::String),
+                ColumnSpec::new("sales_amount", ColumnType::Number),
+            ],
+        }
+    }
+}
+
+impl Schema {
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let contents = fs::read_to_string(path.as_ref())?;
+        let schema: Schema = toml::from_str(&contents)
+            .map_err(|e| format!("Invalid schema file {}: {}", path.as_ref().display(), e))?;
+        schema.check()?;
+        Ok(schema)
+    }
+
+    fn check(&self) -> Result<(), Box<dyn Error>> {
+        for (role, column_type) in [
+            (ColumnRole::Month, ColumnType::Date),
+            (ColumnRole::Product, ColumnType::String),
+            (ColumnRole::Amount, ColumnType::Number),
+        ] {
+            let columns: Vec<&ColumnSpec> = self.columns.iter().filter(|c| c.role() == Some(role)).collect();
+            match columns.as_slice() {
+                [column] if !column.required => {
+                    return Err(format!("Schema column {} must be required", column.name).into())
+                }
+                [column] if role != ColumnRole::Product && column.column_type != column_type => {
+                    return Err(format!("Schema column {} must have type {:?}", column.name, column_type).into())
+                }
+                [_] => {}
+                [] => return Err(format!("Schema has no column with role {:?}", role).into()),
+                _ => return Err(format!("Schema has more than one column with role {:?}", role).into()),
+            }
+        }
+        Ok(())
+    }
+
+    pub fn resolve(&self, headers: &StringRecord) -> Result<Layout, Box<dyn Error>> {
+        // A schema built in code has not been checked the way one read from a file is.
+        self.check()?;
+        let position = |name: &str| headers.iter().position(|h| h.to_lowercase() == name.to_lowercase());
+
+        let mut resolved = Vec::new();
+        for column in &self.columns {
+            match position(&column.name) {
+                Some(index) => resolved.push((index, column.clone())),
+                None if column.required => return Err(format!("Missing column: {}", column.name).into()),
+                None => {}
+            }
+        }
+        if !self.allow_extra_columns {
+            if let Some(extra) = headers.iter().find(|h| !resolved.iter().any(|(_, c)| c.name.eq_ignore_ascii_case(h))) {
+                return Err(format!("Unexpected column: {}", extra).into());
+            }
+        }
+
+        let find = |role: ColumnRole| {
+            resolved.iter().find(|(_, c)| c.role() == Some(role)).cloned().ok_or_else(|| format!("Missing column with role {:?}", role))
+        };
+        let (month_index, month) = find(ColumnRole::Month)?;
+        let month_name = month.name;
+        let (product_index, _) = find(ColumnRole::Product)?;
+        let (sales_index, amount) = find(ColumnRole::Amount)?;
+        let checks = resolved.into_iter().filter(|(_, c)| c.role().is_none()).collect();
+
+        Ok(Layout {
+            width: headers.len(),
+            month_index,
+            product_index,
+            sales_index,
+            month_name,
+            month_formats: month.formats,
+            amount,
+            checks,
+            missing: MissingValues::default(),
+            key_indices: Vec::new(),
+            number: NumberFormat::default(),
+            date_order: None,
+            timezone: Tz::UTC,
+            calendar: None,
+            currency: None,
+            rates: ExchangeRates::default(),
+        })
+    }
+}
+
+pub struct Layout {
+    pub width: usize,
+    pub month_index: usize,
+    pub product_index: usize,
+    pub sales_index: usize,
+    month_name: String,
+    month_formats: Vec<String>,
+    amount: ColumnSpec,
+    checks: Vec<(usize, ColumnSpec)>,
+    missing: MissingValues,
+    key_indices: Vec<usize>,
+    number: NumberFormat,
+    date_order: Option<DateOrder>,
+    timezone: Tz,
+    calendar: Option<RetailCalendar>,
+    currency: Option<(usize, String)>,
+    rates: ExchangeRates,
+}
+
+/// Why a row was rejected, and which column caused it when the problem is a single field.
+#[derive(Clone, Debug)]
+pub struct FieldError {
+    pub column: Option<String>,
+    pub reason: String,
+}
+
+impl FieldError {
+    fn in_column(column: &str) -> impl FnOnce(String) -> FieldError + '_ {
+        move |reason| FieldError { column: Some(column.to_string()), reason }
+    }
+}
+
+pub struct ParsedRow {
+    pub values: Option<(DateKey, f64)>,
+    pub missing_amount: bool,
+    /// The row's day, when its date has one
+    pub day: Option<DateKey>,
+}
+
+impl Layout {
+    pub fn with_missing_values(mut self, missing: MissingValues) -> Self {
+        self.missing = missing;
+        self
+    }
+
+    pub fn with_number_format(mut self, number: NumberFormat) -> Self {
+        self.number = number;
+        self
+    }
+
+    pub fn with_date_order(mut self, date_order: Option<DateOrder>) -> Self {
+        self.date_order = date_order;
+        self
+    }
+
+    /// Zone whose local date decides the month of timestamps carrying an offset.
+    pub fn with_timezone(mut self, timezone: Tz) -> Self {
+        self.timezone = timezone;
+        self
+    }
+
+    /// Totals rows by the fiscal periods of `calendar` rather than by month.
+    pub fn with_calendar(mut self, calendar: Option<RetailCalendar>) -> Self {
+        self.calendar = calendar;
+        self
+    }
+
+    pub fn with_key_indices(mut self, key_indices: Vec<usize>) -> Self {
+        self.key_indices = key_indices;
+        self
+    }
+
+    pub fn key_indices(&self) -> &[usize] {
+        &self.key_indices
+    }
+
+    /// Converts amounts using the currency code in column `index`; rates are set separately
+    /// once the currencies and months present are known.
+    pub fn with_currency_column(mut self, index: usize, name: &str) -> Self {
+        self.currency = Some((index, name.to_string()));
+        self
+    }
+
+    pub fn with_exchange_rates(mut self, rates: ExchangeRates) -> Self {
+        self.rates = rates;
+        self
+    }
+
+    /// Currency and month of every record that needs converting, skipping unparseable ones.
+    pub fn currency_months(&self, records: &[ByteRecord]) -> BTreeSet<(String, DateKey)> {
+        let Some((index, _)) = self.currency else {
+            return BTreeSet::new();
+        };
+        records
+            .iter()
+            .filter(|record| record.len() == self.width)
+            .filter_map(|record| {
+                let month = parse_date(&record[self.month_index], &self.month_formats, self.date_order, self.timezone).ok()?;
+                Some((String::from_utf8_lossy(record[index].trim_ascii()).to_ascii_uppercase(), month))
+            })
+            .collect()
+    }
+
+    pub fn parse_row(&self, record: &ByteRecord) -> Result<ParsedRow, FieldError> {
+        if record.len() != self.width {
+            return Err(FieldError {
+                column: None,
+                reason: format!("Invalid column length in data row: expected {} fields, found {}", self.width, record.len()),
+            });
+        }
+
+        let field = &record[self.month_index];
+        let (date, has_day) =
+            parse_day(field, &self.month_formats, self.date_order, self.timezone).map_err(FieldError::in_column(&self.month_name))?;
+        // Exchange rates stay monthly; only the totals go by fiscal period.
+        let month = month_of(date);
+        let period = match &self.calendar {
+            Some(_) if !has_day => {
+                return Err(FieldError::in_column(&self.month_name)(format!(
+                    "\"{}\" has no day, which the retail calendar needs to place it in a period",
+                    String::from_utf8_lossy(field)
+                )))
+            }
+            Some(calendar) => date_to_key(&calendar.period_of(date)),
+            None => month,
+        };
+        let day = has_day.then(|| date_to_key(&date));
+        for (index, column) in &self.checks {
+            column.check_value(&record[*index], &self.number).map_err(FieldError::in_column(&column.name))?;
+        }
+
+        let field = &record[self.sales_index];
+        if self.missing.is_missing(field) {
+            let values = match self.missing.policy {
+                MissingValuePolicy::Error => {
+                    return Err(FieldError::in_column(&self.amount.name)(format!(
+                        "Missing value \"{}\"",
+                        String::from_utf8_lossy(field)
+                    )))
+                }
+                MissingValuePolicy::Skip => None,
+                MissingValuePolicy::Zero => Some((period, 0.0)),
+            };
+            return Ok(ParsedRow { values, missing_amount: true, day });
+        }
+
+        let sales = self
+            .number
+            .parse(field)
+            .and_then(|sales| self.amount.check_range(sales).map(|_| sales))
+            .map_err(FieldError::in_column(&self.amount.name))?;
+        let sales = match &self.currency {
+            Some((index, name)) => {
+                let code = String::from_utf8_lossy(record[*index].trim_ascii());
+                let rate = self.rates.rate(&code, month).ok_or_else(|| {
+                    FieldError::in_column(name)(format!(
+                        "No exchange rate from \"{}\" to {} for {}",
+                        code,
+                        self.rates.base(),
+                        key_to_date(month).format("%Y-%m")
+                    ))
+                })?;
+                sales * rate
+            }
+            None => sales,
+        };
+
+        Ok(ParsedRow { values: Some((period, sales)), missing_amount: false, day })
+    }
+}
+
+fn month_of(date: NaiveDate) -> DateKey {
+    date_to_key(&NaiveDate::from_ymd_opt(date.year(), date.month(), 1).unwrap())
+}
+
+// ISO 8601 dates and datetimes. One with an offset, such as `2024-01-31T23:30:00-05:00`, is
+// moved to `zone` first, so a late-evening sale lands in the month it was made in there; one
+// without is taken to be in `zone` already.
+fn parse_timestamp(text: &str, zone: Tz) -> Option<NaiveDate> {
+    const ZONED: &[&str] = &["%Y-%m-%dT%H:%M:%S%.f%z", "%Y-%m-%d %H:%M:%S%.f%z", "%Y-%m-%dT%H:%M%z", "%Y-%m-%d %H:%M%z"];
+    const LOCAL: &[&str] = &["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M"];
+    let text = text.trim();
+    let zoned = DateTime::parse_from_rfc3339(text).ok().or_else(|| ZONED.iter().find_map(|format| DateTime::parse_from_str(text, format).ok()));
+    if let Some(timestamp) = zoned {
+        return Some(timestamp.with_timezone(&zone).date_naive());
+    }
+    LOCAL
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+        .map(|timestamp| timestamp.date())
+        .or_else(|| NaiveDate::parse_from_str(text, "%Y-%m-%d").ok())
+}
+
+fn parse_date(field: &[u8], formats: &[String], order: Option<DateOrder>, zone: Tz) -> Result<DateKey, String> {
+    parse_day(field, formats, order, zone).map(|(date, _)| month_of(date))
+}
+
+// The date in `field` and whether it has a day. Formats without a day component are completed
+// with the first of the month, mirroring how the default `YYYY-MM` layout is parsed. Formats
+// with an offset (`%z`) are moved to `zone`.
+fn parse_day(field: &[u8], formats: &[String], order: Option<DateOrder>, zone: Tz) -> Result<(NaiveDate, bool), String> {
+    if formats.is_empty() {
+        if let Some(date) = order.and_then(|order| parse_numeric_date(field, order)) {
+            return Ok((date, true));
+        }
+        return parse_month(field).map(|month| (key_to_date(month), false)).or_else(|e| {
+            std::str::from_utf8(field).ok().and_then(|text| parse_timestamp(text, zone)).map(|date| (date, true)).ok_or(e)
+        });
+    }
+
+    let date_str = String::from_utf8_lossy(field);
+    let mut last_error = None;
+    for format in formats {
+        if format.contains("%z") || format.contains("%:z") || format.contains("%#z") {
+            match DateTime::parse_from_str(&date_str, format) {
+                Ok(timestamp) => return Ok((timestamp.with_timezone(&zone).date_naive(), true)),
+                Err(e) => last_error = Some(e),
+            }
+            continue;
+        }
+        let has_day = format.contains("%d") || format.contains("%e") || format.contains("%j");
+        let parsed = if has_day {
+            NaiveDate::parse_from_str(&date_str, format)
+        } else {
+            NaiveDate::parse_from_str(&format!("{}-01", date_str), &format!("{}-%d", format))
+        };
+        match parsed {
+            Ok(date) => return Ok((date, has_day)),
+            Err(e) => last_error = Some(e),
+        }
+    }
+
+    Err(format!(
+        "Invalid date format in \"{}\": {} (expected one of {})",
+        date_str,
+        last_error.unwrap(),
+        formats.join(", ")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(names: &[&str]) -> StringRecord {
+        StringRecord::from(names.to_vec())
+    }
+
+    #[test]
+    fn a_schema_without_an_amount_column_is_an_error() {
+        let schema = Schema { allow_extra_columns: true, columns: vec![ColumnSpec::new("month", ColumnType::Date), ColumnSpec::new("product", ColumnType::String)] };
+        let error = schema.resolve(&headers(&["month", "product", "sales_amount"])).err().unwrap().to_string();
+        assert_eq!(error, "Schema has no column with role Amount");
+    }
+
+    #[test]
+    fn the_default_schema_finds_its_columns_in_any_case() {
+        let layout = Schema::default().resolve(&headers(&["Product", "MONTH", "sales_amount"])).unwrap();
+        assert_eq!((layout.month_index, layout.product_index, layout.sales_index), (1, 0, 2));
+    }
+
+    fn day(year: i32, month: u32, day: u32) -> Option<NaiveDate> {
+        NaiveDate::from_ymd_opt(year, month, day)
+    }
+
+    #[test]
+    fn timestamps_with_an_offset_land_in_the_month_of_the_zone() {
+        let late_evening = "2024-01-31T23:30:00-05:00";
+        assert_eq!(parse_timestamp(late_evening, Tz::UTC), day(2024, 2, 1));
+        assert_eq!(parse_timestamp(late_evening, Tz::America__New_York), day(2024, 1, 31));
+        assert_eq!(parse_date(late_evening.as_bytes(), &[], None, Tz::UTC), Ok(month_of(day(2024, 2, 1).unwrap())));
+        assert_eq!(parse_date(late_evening.as_bytes(), &[], None, Tz::America__New_York), Ok(month_of(day(2024, 1, 31).unwrap())));
+    }
+
+    #[test]
+    fn timestamps_without_an_offset_stay_in_their_own_zone() {
+        for zone in [Tz::UTC, Tz::America__New_York, Tz::Asia__Tokyo] {
+            assert_eq!(parse_timestamp("2024-01-31T23:30:00", zone), day(2024, 1, 31));
+            assert_eq!(parse_timestamp("2024-01-31 23:30", zone), day(2024, 1, 31));
+            assert_eq!(parse_timestamp("2024-01-31", zone), day(2024, 1, 31));
+        }
+    }
+
+    #[test]
+    fn an_offset_format_is_moved_to_the_zone() {
+        let formats = ["%d/%m/%Y %H:%M %z".to_string()];
+        assert_eq!(parse_day(b"31/01/2024 23:30 -0500", &formats, None, Tz::UTC), Ok((day(2024, 2, 1).unwrap(), true)));
+        assert_eq!(parse_day(b"31/01/2024 23:30 -0500", &formats, None, Tz::America__New_York), Ok((day(2024, 1, 31).unwrap(), true)));
+    }
+}
@@ -0,0 +1,105 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use csv::{ByteRecord, StringRecord};
+use rhai::{CallFnOptions, Dynamic, Engine, Map, Scope, AST};
+use tracing::info;
+
+use crate::summary::RunSummary;
+
+/// A Rhai script of custom row transforms and metrics. Its top-level statements run once when
+/// it is loaded, so the constants it declares there are seen by its functions, and `print`
+/// logs. It defines either function or both:
+///
+/// - `fn transform(row)` gets each row as a map of column name to text, and returns it with
+///   fields changed, as in `row.sales_amount = parse_float(row.quantity) * parse_float(row.price); row`,
+///   or `()` to leave the row out. Columns the file does not have cannot be added.
+/// - `fn metrics(totals)` gets `#{total_sales, rows, monthly: [#{month, sales}], products:
+///   [#{product, sales}]}` once the rows are totalled, in chart order, and returns a map of
+///   metric names to values.
+pub struct Script {
+    path: PathBuf,
+    engine: Engine,
+    ast: AST,
+    scope: Scope<'static>,
+}
+
+impl fmt::Debug for Script {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Script").field("path", &self.path).finish_non_exhaustive()
+    }
+}
+
+impl Script {
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path).map_err(|e| format!("Cannot read script {}: {}", path.display(), e))?;
+        let mut engine = Engine::new();
+        engine.on_print(|text| info!("{}", text));
+        let ast = engine.compile(&source).map_err(|e| format!("Invalid script {}: {}", path.display(), e))?;
+        let mut scope = Scope::new();
+        engine.run_ast_with_scope(&mut scope, &ast).map_err(|e| format!("Script {} failed: {}", path.display(), e))?;
+        let script = Script { path: path.to_path_buf(), engine, ast, scope };
+        if !script.defines("transform") && !script.defines("metrics") {
+            return Err(format!("Script {} defines neither fn transform(row) nor fn metrics(totals)", path.display()).into());
+        }
+        Ok(script)
+    }
+
+    fn defines(&self, name: &str) -> bool {
+        self.ast.iter_functions().any(|function| function.name == name && function.params.len() == 1)
+    }
+
+    pub fn transforms(&self) -> bool {
+        self.defines("transform")
+    }
+
+    fn call(&self, name: &str, argument: Dynamic) -> Result<Dynamic, String> {
+        // The top-level statements already ran; each call starts from the scope they left.
+        let mut scope = self.scope.clone();
+        self.engine
+            .call_fn_with_options(CallFnOptions::new().eval_ast(false), &mut scope, &self.ast, name, (argument,))
+            .map_err(|e| format!("{} in script {} failed: {}", name, self.path.display(), e))
+    }
+
+    /// `record` as `transform` returns it, or None for a row it leaves out. Fields past the
+    /// headers are left as they are.
+    pub fn transform(&self, headers: &StringRecord, record: &ByteRecord) -> Result<Option<ByteRecord>, String> {
+        let row: Map = headers.iter().zip(record).map(|(name, field)| (name.into(), String::from_utf8_lossy(field).into_owned().into())).collect();
+        let result = self.call("transform", row.into())?;
+        if result.is_unit() {
+            return Ok(None);
+        }
+        let returned = result.type_name();
+        let row = result.try_cast::<Map>().ok_or_else(|| format!("transform in script {} returned {} instead of the row or ()", self.path.display(), returned))?;
+        if let Some(unknown) = row.keys().find(|key| !headers.iter().any(|name| name == key.as_str())) {
+            return Err(format!("transform in script {} returned a column {} the file does not have", self.path.display(), unknown));
+        }
+        let mut changed = ByteRecord::new();
+        for (i, field) in record.iter().enumerate() {
+            match headers.get(i).and_then(|name| row.get(name)) {
+                Some(value) => changed.push_field(value.to_string().as_bytes()),
+                None => changed.push_field(field),
+            }
+        }
+        changed.set_position(record.position().cloned());
+        Ok(Some(changed))
+    }
+
+    /// The metrics `metrics` returns for the totals of `summary`, by name; none without a
+    /// `metrics` function.
+    pub fn metrics(&self, summary: &RunSummary) -> Result<BTreeMap<String, serde_json::Value>, Box<dyn Error>> {
+        if !self.defines("metrics") {
+            return Ok(BTreeMap::new());
+        }
+        let mut totals: Map = rhai::serde::to_dynamic(&summary.aggregates)?.cast();
+        totals.insert("total_sales".into(), summary.total_sales.into());
+        totals.insert("rows".into(), (summary.rows as i64).into());
+        let result = self.call("metrics", totals.into())?;
+        rhai::serde::from_dynamic(&result)
+            .map_err(|_| format!("metrics in script {} returned {} instead of a map of names to values", self.path.display(), result.type_name()).into())
+    }
+}
@@ -0,0 +1,489 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+use tiny_http::{Header, Method, Request, Response, Server};
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+use chrono::{DateTime, Months, NaiveDate};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::{error, info};
+
+use crate::charts::{create_bar_chart, create_line_chart, create_pie_chart, render_charts, ChartLabels};
+use crate::summary::product_trends;
+use crate::{ParseReport, PlotData};
+
+const UPLOAD_FORM: &str = r#"<!DOCTYPE html>
+<html>
+<head><meta charset="utf-8"><title>Sales charts</title></head>
+<body>
+<h1>Sales charts</h1>
+<form method="post" action="/charts" enctype="multipart/form-data">
+<p><input type="file" name="file" accept=".csv,text/csv" required></p>
+<p><button type="submit">Create charts</button></p>
+</form>
+<p>The charts come back as a zip file with line_chart.png, bar_chart.png, and pie_chart.png.</p>
+<p>API: <a href="/aggregates/monthly">/aggregates/monthly</a>, <a href="/aggregates/products">/aggregates/products</a>,
+<a href="/charts/line.png">/charts/line.png</a>, <a href="/charts/bar.png">/charts/bar.png</a>,
+<a href="/charts/pie.png">/charts/pie.png</a>. POST a CSV to any of them to use it instead of the server's file.</p>
+<p>Grafana: add a JSON (SimpleJSON) datasource with this server's <code>/grafana</code> as its URL.</p>
+</body>
+</html>
+"#;
+
+/// Runs the command-line pipeline on an uploaded file.
+pub type Analyze<'a> = dyn Fn(&Path) -> Result<(PlotData, ParseReport), Box<dyn Error>> + 'a;
+
+const ROUTES: &[&str] = &[
+    "/",
+    "/charts",
+    "/charts/line.png",
+    "/charts/bar.png",
+    "/charts/pie.png",
+    "/aggregates/monthly",
+    "/aggregates/products",
+    "/metrics",
+    "/grafana",
+    "/grafana/",
+    "/grafana/search",
+    "/grafana/query",
+    "/grafana/annotations",
+];
+const LATENCY_BUCKETS: &[f64] = &[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+#[derive(Default)]
+struct Histogram {
+    buckets: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, seconds: f64) {
+        self.buckets.resize(LATENCY_BUCKETS.len(), 0);
+        for (bucket, &bound) in self.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if seconds <= bound {
+                *bucket += 1;
+            }
+        }
+        self.sum += seconds;
+        self.count += 1;
+    }
+}
+
+/// Counters exposed on `/metrics` in the Prometheus text format.
+#[derive(Default)]
+struct Metrics {
+    rows_processed: u64,
+    parse_errors: u64,
+    failed_runs: u64,
+    charts_rendered: u64,
+    requests: BTreeMap<(&'static str, u16), u64>,
+    latency: BTreeMap<&'static str, Histogram>,
+}
+
+impl Metrics {
+    fn record_run(&mut self, result: &Result<(PlotData, ParseReport), Box<dyn Error>>) {
+        match result {
+            Ok((_, report)) => {
+                self.rows_processed += report.rows;
+                self.parse_errors += report.errors.len() as u64;
+            }
+            Err(_) => self.failed_runs += 1,
+        }
+    }
+
+    // Unknown paths share one label so scanners cannot blow up the series count.
+    fn record_request(&mut self, path: &str, status: u16, seconds: f64) {
+        let route = ROUTES.iter().find(|&&route| route == path).copied().unwrap_or("other");
+        *self.requests.entry((route, status)).or_insert(0) += 1;
+        self.latency.entry(route).or_default().observe(seconds);
+    }
+
+    fn render(&self) -> String {
+        let mut out = String::new();
+        let mut counter = |name: &str, help: &str, value: u64| {
+            out.push_str(&format!("# HELP {name} {help}\n# TYPE {name} counter\n{name} {value}\n"));
+        };
+        counter("sales_chart_rows_processed_total", "CSV data rows read.", self.rows_processed);
+        counter("sales_chart_parse_errors_total", "Rows skipped as malformed in lenient mode.", self.parse_errors);
+        counter("sales_chart_failed_runs_total", "Pipeline runs that ended in an error.", self.failed_runs);
+        counter("sales_chart_charts_rendered_total", "Chart images rendered.", self.charts_rendered);
+
+        out.push_str("# HELP sales_chart_http_requests_total HTTP requests answered.\n");
+        out.push_str("# TYPE sales_chart_http_requests_total counter\n");
+        for ((route, status), count) in &self.requests {
+            out.push_str(&format!("sales_chart_http_requests_total{{route=\"{route}\",status=\"{status}\"}} {count}\n"));
+        }
+
+        out.push_str("# HELP sales_chart_http_request_duration_seconds Time spent answering HTTP requests.\n");
+        out.push_str("# TYPE sales_chart_http_request_duration_seconds histogram\n");
+        for (route, histogram) in &self.latency {
+            let name = "sales_chart_http_request_duration_seconds";
+            for (count, bound) in histogram.buckets.iter().zip(LATENCY_BUCKETS) {
+                out.push_str(&format!("{name}_bucket{{route=\"{route}\",le=\"{bound}\"}} {count}\n"));
+            }
+            out.push_str(&format!("{name}_bucket{{route=\"{route}\",le=\"+Inf\"}} {}\n", histogram.count));
+            out.push_str(&format!("{name}_sum{{route=\"{route}\"}} {}\n", histogram.sum));
+            out.push_str(&format!("{name}_count{{route=\"{route}\"}} {}\n", histogram.count));
+        }
+        out
+    }
+}
+
+static NEXT_JOB: AtomicU64 = AtomicU64::new(0);
+
+// One scratch directory per request, removed again once the response is built.
+pub(crate) struct Job {
+    pub(crate) dir: PathBuf,
+}
+
+impl Job {
+    pub(crate) fn new() -> std::io::Result<Self> {
+        let id = NEXT_JOB.fetch_add(1, Ordering::Relaxed);
+        let dir = std::env::temp_dir().join(format!("sales_chart-{}-{}", std::process::id(), id));
+        fs::create_dir_all(&dir)?;
+        Ok(Job { dir })
+    }
+}
+
+impl Drop for Job {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+fn header<'a>(request: &'a Request, name: &str) -> Option<&'a str> {
+    request
+        .headers()
+        .iter()
+        .find(|h| h.field.as_str().as_str().eq_ignore_ascii_case(name))
+        .map(|h| h.value.as_str())
+}
+
+// Browser forms send multipart/form-data; pull out the first part that carries a file.
+fn multipart_file<'a>(body: &'a [u8], boundary: &str) -> Option<&'a [u8]> {
+    let delimiter = format!("--{}", boundary);
+    let mut rest = &body[find(body, delimiter.as_bytes())? + delimiter.len()..];
+    while let Some(end) = find(rest, delimiter.as_bytes()) {
+        let part = &rest[..end];
+        rest = &rest[end + delimiter.len()..];
+        let headers_end = find(part, b"\r\n\r\n")?;
+        let headers = String::from_utf8_lossy(&part[..headers_end]);
+        if headers.contains("filename=") {
+            let content = &part[headers_end + 4..];
+            return Some(content.strip_suffix(b"\r\n").unwrap_or(content));
+        }
+    }
+    None
+}
+
+// A request the client got wrong, answered with `status`; any other error is the server's
+// own and answers 500.
+#[derive(Debug)]
+struct RequestError {
+    status: u16,
+    message: String,
+}
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl Error for RequestError {}
+
+fn bad_request(message: impl fmt::Display) -> Box<dyn Error> {
+    Box::new(RequestError { status: 400, message: message.to_string() })
+}
+
+// The request body, refused unread when it says it is longer than `max_upload` bytes, and
+// read no further than that when it does not say.
+fn body(request: &mut Request, max_upload: u64) -> Result<Vec<u8>, Box<dyn Error>> {
+    let too_large = || Box::new(RequestError { status: 413, message: format!("The request is larger than the {} bytes the server accepts", max_upload) });
+    if request.body_length().is_some_and(|length| length as u64 > max_upload) {
+        return Err(too_large());
+    }
+    let mut body = Vec::new();
+    request.as_reader().take(max_upload + 1).read_to_end(&mut body)?;
+    if body.len() as u64 > max_upload {
+        return Err(too_large());
+    }
+    Ok(body)
+}
+
+/// The uploaded CSV: the file part of a form post, or the raw request body otherwise.
+fn upload(request: &mut Request, max_upload: u64) -> Result<Vec<u8>, Box<dyn Error>> {
+    let boundary = header(request, "Content-Type")
+        .filter(|value| value.starts_with("multipart/form-data"))
+        .and_then(|value| value.split("boundary=").nth(1))
+        .map(|boundary| boundary.trim_matches('"').to_string());
+
+    let body = body(request, max_upload)?;
+    match boundary {
+        Some(boundary) => Ok(multipart_file(&body, &boundary).ok_or_else(|| bad_request("No file in the upload"))?.to_vec()),
+        None if body.is_empty() => Err(bad_request("Empty upload, send a CSV file")),
+        None => Ok(body),
+    }
+}
+
+#[derive(Serialize)]
+struct MonthlyTotal {
+    month: String,
+    sales: f64,
+}
+
+#[derive(Serialize)]
+struct ProductTotal {
+    product: String,
+    sales: f64,
+}
+
+// The body of a Grafana `search` request, which filters the series by name.
+#[derive(Default, Deserialize)]
+struct GrafanaSearch {
+    #[serde(default)]
+    target: String,
+}
+
+// The body of a Grafana `query` request: the dashboard's time range and the series asked for.
+#[derive(Deserialize)]
+struct GrafanaQuery {
+    range: Option<GrafanaRange>,
+    targets: Vec<GrafanaTarget>,
+}
+
+#[derive(Deserialize)]
+struct GrafanaRange {
+    from: String,
+    to: String,
+}
+
+#[derive(Deserialize)]
+struct GrafanaTarget {
+    #[serde(default)]
+    target: String,
+    /// `timeserie` or `table`
+    #[serde(rename = "type", default)]
+    kind: Option<String>,
+}
+
+type Reply = Response<Cursor<Vec<u8>>>;
+
+fn content_type(value: &str) -> Header {
+    Header::from_bytes("Content-Type", value).expect("valid header")
+}
+
+fn json<T: Serialize>(value: &T) -> Result<Reply, Box<dyn Error>> {
+    Ok(Response::from_data(serde_json::to_vec(value)?).with_header(content_type("application/json")))
+}
+
+fn chart_archive(job: &Job, (monthly_data, product_data): &PlotData) -> Result<Reply, Box<dyn Error>> {
+    let charts = render_charts(&job.dir, monthly_data, product_data, &ChartLabels::default())?;
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    for chart in charts {
+        let name = chart.file_name().unwrap_or_default().to_string_lossy();
+        zip.start_file(name, SimpleFileOptions::default())?;
+        zip.write_all(&fs::read(&chart)?)?;
+    }
+    Ok(Response::from_data(zip.finish()?.into_inner())
+        .with_header(content_type("application/zip"))
+        .with_header(
+            Header::from_bytes("Content-Disposition", "attachment; filename=\"sales_charts.zip\"").expect("valid header"),
+        ))
+}
+
+fn chart_image(job: &Job, kind: &str, (monthly_data, product_data): &PlotData) -> Result<Reply, Box<dyn Error>> {
+    let path = job.dir.join(format!("{}_chart.png", kind));
+    let labels = ChartLabels::default();
+    match kind {
+        "line" => create_line_chart(&path, monthly_data, &labels)?,
+        "bar" => create_bar_chart(&path, product_data, &labels)?,
+        "pie" => create_pie_chart(&path, product_data, &labels)?,
+        _ => return Err(format!("Unknown chart kind: {} (expected line, bar, or pie)", kind).into()),
+    }
+    Ok(Response::from_data(fs::read(&path)?).with_header(content_type("image/png")))
+}
+
+// The start of `month` in milliseconds since the epoch, as Grafana times its points.
+fn month_millis(month: &NaiveDate) -> i64 {
+    month.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc().timestamp_millis()
+}
+
+// The months of `monthly` overlapping `range`, or all of them without one.
+fn months_in(monthly: &[(NaiveDate, f64)], range: Option<&GrafanaRange>) -> Result<Vec<(NaiveDate, f64)>, Box<dyn Error>> {
+    let Some(range) = range else {
+        return Ok(monthly.to_vec());
+    };
+    let time = |text: &str| DateTime::parse_from_rfc3339(text).map(|time| time.timestamp_millis()).map_err(|e| bad_request(format!("Invalid Grafana time range {}: {}", text, e)));
+    let (from, to) = (time(&range.from)?, time(&range.to)?);
+    Ok(monthly.iter().filter(|(month, _)| month_millis(month) <= to && month_millis(&(*month + Months::new(1))) > from).copied().collect())
+}
+
+// Grafana's SimpleJSON datasource protocol on the server's input file. `/grafana/` answers
+// the connection test, `search` lists the series, `query` returns the monthly `total` or a
+// `product:<name>`'s monthly sales as time series, or as tables of time and sales when the
+// panel asks for a table, and the `products` totals as a table; there are no annotations.
+fn grafana(request: &mut Request, endpoint: &str, input: &Path, analyze: &Analyze<'_>, metrics: &mut Metrics, max_upload: u64) -> Result<Reply, Box<dyn Error>> {
+    match endpoint {
+        "" | "/" => return Ok(Response::from_string("OK\n")),
+        "/annotations" => return json(&Vec::<Value>::new()),
+        _ => {}
+    }
+    let body = body(request, max_upload)?;
+    let result = analyze(input);
+    metrics.record_run(&result);
+    let (data, report) = result?;
+    let trends = product_trends(&data, &report.cube);
+    if endpoint == "/search" {
+        let search: GrafanaSearch = serde_json::from_slice(&body).unwrap_or_default();
+        let filter = search.target.to_lowercase();
+        let names = ["total".to_string(), "products".to_string()].into_iter().chain(trends.iter().map(|trend| format!("product:{}", trend.product)));
+        return json(&names.filter(|name| name.to_lowercase().contains(&filter)).collect::<Vec<_>>());
+    }
+    let query: GrafanaQuery = serde_json::from_slice(&body).map_err(|e| bad_request(format!("Invalid Grafana query: {}", e)))?;
+    let mut replies = Vec::new();
+    for target in query.targets.iter().filter(|target| !target.target.is_empty()) {
+        if target.target == "products" {
+            let rows: Vec<Value> = data.1.iter().map(|(product, sales)| json!([product, sales])).collect();
+            replies.push(json!({"type": "table", "columns": [{"text": "Product", "type": "string"}, {"text": "Sales", "type": "number"}], "rows": rows}));
+            continue;
+        }
+        let monthly = match target.target.strip_prefix("product:") {
+            None if target.target == "total" => &data.0,
+            Some(product) => &trends.iter().find(|trend| trend.product == product).ok_or_else(|| bad_request(format!("No product {} in {}", product, input.display())))?.monthly,
+            None => return Err(bad_request(format!("Unknown Grafana target {}, expected total, products, or product:<name>", target.target))),
+        };
+        let months = months_in(monthly, query.range.as_ref())?;
+        if target.kind.as_deref() == Some("table") {
+            let rows: Vec<Value> = months.iter().map(|(month, sales)| json!([month_millis(month), sales])).collect();
+            replies.push(json!({"type": "table", "columns": [{"text": "Time", "type": "time"}, {"text": "Sales", "type": "number"}], "rows": rows}));
+        } else {
+            let points: Vec<Value> = months.iter().map(|(month, sales)| json!([sales, month_millis(month)])).collect();
+            replies.push(json!({"target": target.target, "datapoints": points}));
+        }
+    }
+    json(&replies)
+}
+
+// GET requests chart the server's own input file; POST requests chart the uploaded CSV, whose
+// problems are the client's to fix.
+fn respond(
+    request: &mut Request,
+    path: &str,
+    input: &Path,
+    analyze: &Analyze<'_>,
+    metrics: &mut Metrics,
+    max_upload: u64,
+) -> Result<Option<Reply>, Box<dyn Error>> {
+    if !ROUTES.contains(&path) {
+        return Ok(None);
+    }
+    if let Some(endpoint) = path.strip_prefix("/grafana") {
+        return grafana(request, endpoint, input, analyze, metrics, max_upload).map(Some);
+    }
+    let job = Job::new()?;
+    let posted = *request.method() == Method::Post;
+    let result = match request.method() {
+        Method::Get if path == "/" => {
+            return Ok(Some(Response::from_string(UPLOAD_FORM).with_header(content_type("text/html; charset=utf-8"))))
+        }
+        Method::Get if path == "/metrics" => {
+            let text = metrics.render();
+            return Ok(Some(Response::from_string(text).with_header(content_type("text/plain; version=0.0.4"))));
+        }
+        Method::Get => analyze(input),
+        Method::Post => {
+            let upload_path = job.dir.join("upload.csv");
+            fs::write(&upload_path, upload(request, max_upload)?)?;
+            analyze(&upload_path).map_err(bad_request)
+        }
+        _ => return Ok(None),
+    };
+    metrics.record_run(&result);
+    let (data, _) = result?;
+    if data.0.is_empty() {
+        return Err(if posted { bad_request("The data has no sales rows") } else { "The data has no sales rows".into() });
+    }
+
+    let reply = match path {
+        "/charts" => {
+            metrics.charts_rendered += 3;
+            chart_archive(&job, &data)?
+        }
+        "/aggregates/monthly" => json(
+            &data
+                .0
+                .iter()
+                .map(|(month, sales)| MonthlyTotal { month: month.format("%Y-%m").to_string(), sales: *sales })
+                .collect::<Vec<_>>(),
+        )?,
+        "/aggregates/products" => json(
+            &data
+                .1
+                .iter()
+                .map(|(product, sales)| ProductTotal { product: product.clone(), sales: *sales })
+                .collect::<Vec<_>>(),
+        )?,
+        _ => match path.strip_prefix("/charts/").and_then(|name| name.strip_suffix(".png")) {
+            Some(kind) => {
+                let image = chart_image(&job, kind, &data)?;
+                metrics.charts_rendered += 1;
+                image
+            }
+            None => return Ok(None),
+        },
+    };
+    Ok(Some(reply))
+}
+
+fn handle(mut request: Request, input: &Path, analyze: &Analyze<'_>, metrics: &mut Metrics, max_upload: u64) -> std::io::Result<()> {
+    let started = Instant::now();
+    let path = request.url().split('?').next().unwrap_or_default().to_string();
+    let reply = match respond(&mut request, &path, input, analyze, metrics, max_upload) {
+        Ok(Some(reply)) => reply,
+        Ok(None) => Response::from_string("Not found\n").with_status_code(404),
+        Err(e) => {
+            let status = e.downcast_ref::<RequestError>().map_or(500, |e| e.status);
+            if status == 500 {
+                error!("Error answering {} {}: {}", request.method(), request.url(), e);
+            }
+            Response::from_string(format!("{}\n", e)).with_status_code(status)
+        }
+    };
+    let status = reply.status_code().0;
+    let result = request.respond(reply);
+    metrics.record_request(&path, status, started.elapsed().as_secs_f64());
+    result
+}
+
+/// Serves the upload form on `/`, a zip of charts on `/charts`, JSON totals on
+/// `/aggregates/{monthly,products}`, single images on `/charts/{line,bar,pie}.png`,
+/// Prometheus metrics on `/metrics`, and a Grafana datasource on `/grafana`. Request bodies
+/// longer than `max_upload` bytes are refused with 413, problems with a request answer 400,
+/// and the server's own errors 500.
+pub fn serve(port: u16, input: &Path, analyze: &Analyze<'_>, max_upload: u64) -> Result<(), Box<dyn Error>> {
+    let server = Server::http(("0.0.0.0", port)).map_err(|e| format!("Cannot listen on port {}: {}", port, e))?;
+    info!("Listening on http://localhost:{}, serving {}", port, input.display());
+    let mut metrics = Metrics::default();
+    for request in server.incoming_requests() {
+        let (method, url) = (request.method().clone(), request.url().to_string());
+        if let Err(e) = handle(request, input, analyze, &mut metrics, max_upload) {
+            error!("Error answering {} {}: {}", method, url, e);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,107 @@
+use std::error::Error;
+use std::fs;
+
+use serde::Deserialize;
+
+use crate::number::MoneyFormat;
+use crate::summary::{RunStatus, RunSummary};
+
+const API: &str = "https://slack.com/api";
+
+/// The message posted after a run: headline numbers, or the error for a failed run.
+pub fn headline(summary: &RunSummary, money: &MoneyFormat) -> String {
+    if summary.status == RunStatus::Failure {
+        return format!(
+            ":warning: Sales report for {} failed: {}",
+            summary.input.display(),
+            summary.error.as_deref().unwrap_or("unknown error")
+        );
+    }
+
+    let mut text = format!(
+        "*Sales report*: {} total across {} months and {} products",
+        money.format(summary.total_sales),
+        summary.months,
+        summary.products
+    );
+    if let Some(latest) = &summary.latest_month {
+        text.push_str(&format!("\nLatest month {}: {}", latest.month, money.format(latest.sales)));
+        if let Some(change) = summary.latest_change {
+            text.push_str(&format!(" ({} vs previous month)", money.percent(change, true)));
+        }
+    }
+    if !summary.narrative.is_empty() {
+        text.push_str(&format!("\n{}", summary.narrative));
+    }
+    for alert in &summary.alerts {
+        text.push_str(&format!("\n:rotating_light: {}", alert));
+    }
+    let anomalies = &summary.anomalies;
+    let flagged = anomalies.parse_errors + anomalies.duplicates + anomalies.suspicious_rows;
+    if flagged > 0 || !anomalies.missing_months.is_empty() {
+        text.push_str(&format!(
+            "\n{} malformed, {} duplicate, {} suspicious rows; {} months missing",
+            anomalies.parse_errors,
+            anomalies.duplicates,
+            anomalies.suspicious_rows,
+            anomalies.missing_months.len()
+        ));
+    }
+    text
+}
+
+pub fn post_message(webhook_url: &str, text: &str) -> Result<(), Box<dyn Error>> {
+    let body = serde_json::json!({ "text": text }).to_string();
+    ureq::post(webhook_url)
+        .header("Content-Type", "application/json")
+        .send(body)
+        .map_err(|e| format!("Slack webhook failed: {}", e))?;
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct ApiResponse {
+    ok: bool,
+    error: Option<String>,
+    upload_url: Option<String>,
+    file_id: Option<String>,
+}
+
+fn check(response: ApiResponse, method: &str) -> Result<ApiResponse, Box<dyn Error>> {
+    if !response.ok {
+        return Err(format!("Slack {} failed: {}", method, response.error.unwrap_or_default()).into());
+    }
+    Ok(response)
+}
+
+/// Uploads the charts to a channel with a bot token. Incoming webhooks can only post text,
+/// so files go through Slack's two-step external upload API.
+pub fn upload_charts(token: &str, channel: &str, summary: &RunSummary) -> Result<(), Box<dyn Error>> {
+    let auth = format!("Bearer {}", token);
+    let mut files = Vec::new();
+    for path in &summary.artifacts {
+        let contents = fs::read(path)?;
+        let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        let length = contents.len().to_string();
+
+        let mut response = ureq::post(format!("{}/files.getUploadURLExternal", API))
+            .header("Authorization", &auth)
+            .send_form([("filename", name.as_str()), ("length", length.as_str())])?;
+        let upload: ApiResponse = serde_json::from_str(&response.body_mut().read_to_string()?)?;
+        let upload = check(upload, "files.getUploadURLExternal")?;
+        let (Some(url), Some(id)) = (upload.upload_url, upload.file_id) else {
+            return Err("Slack files.getUploadURLExternal returned no upload URL".into());
+        };
+
+        ureq::post(&url).send(&contents[..]).map_err(|e| format!("Slack upload of {} failed: {}", name, e))?;
+        files.push(serde_json::json!({ "id": id, "title": name }));
+    }
+
+    let body = serde_json::json!({ "files": files, "channel_id": channel }).to_string();
+    let mut response = ureq::post(format!("{}/files.completeUploadExternal", API))
+        .header("Authorization", &auth)
+        .header("Content-Type", "application/json; charset=utf-8")
+        .send(body)?;
+    check(serde_json::from_str(&response.body_mut().read_to_string()?)?, "files.completeUploadExternal")?;
+    Ok(())
+}
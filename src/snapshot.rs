@@ -0,0 +1,122 @@
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::NaiveDate;
+use image::{Rgb, RgbImage};
+use plotters::coord::Shift;
+use plotters::prelude::*;
+
+use crate::charts::{draw_bar_chart, draw_line_chart, draw_pie_chart, ChartLabels, SIZE};
+use crate::image_diff::{self, ImageDiff};
+
+// Helpers for catching chart regressions in `cargo test`: render a chart into memory at the
+// size of the PNG files and compare it with a golden image checked into the repository.
+// Different machines rasterize fonts slightly differently, hence the perceptual tolerance.
+
+/// Setting this environment variable to `1` makes `assert_golden` rewrite the golden files
+/// instead of comparing against them.
+pub const UPDATE_VAR: &str = "SALES_CHART_UPDATE_SNAPSHOTS";
+
+/// How far a render may drift from its golden image and still pass.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tolerance {
+    /// Colour distance between 0 and 1, measured in YIQ space with luminance weighted most,
+    /// up to which two pixels count as the same
+    pub pixel: f64,
+    /// Percentage of pixels allowed to differ by more than `pixel`
+    pub changed_percent: f64,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Tolerance { pixel: 0.1, changed_percent: 0.5 }
+    }
+}
+
+impl Tolerance {
+    pub fn exact() -> Self {
+        Tolerance { pixel: 0.0, changed_percent: 0.0 }
+    }
+}
+
+/// Draws into an in-memory bitmap the size of the PNG charts.
+pub fn render<F>(draw: F) -> Result<RgbImage, Box<dyn Error>>
+where
+    F: FnOnce(&DrawingArea<BitMapBackend, Shift>) -> Result<(), Box<dyn Error>>,
+{
+    let mut buffer = vec![0u8; (SIZE.0 * SIZE.1 * 3) as usize];
+    draw(&BitMapBackend::with_buffer(&mut buffer, SIZE).into_drawing_area())?;
+    Ok(RgbImage::from_raw(SIZE.0, SIZE.1, buffer).expect("the buffer holds every pixel"))
+}
+
+pub fn line_chart(monthly_data: &[(NaiveDate, f64)]) -> Result<RgbImage, Box<dyn Error>> {
+    render(|root| draw_line_chart(root, monthly_data, &ChartLabels::default()))
+}
+
+pub fn bar_chart(product_data: &[(String, f64)]) -> Result<RgbImage, Box<dyn Error>> {
+    render(|root| draw_bar_chart(root, product_data, &ChartLabels::default()))
+}
+
+pub fn pie_chart(product_data: &[(String, f64)]) -> Result<RgbImage, Box<dyn Error>> {
+    render(|root| draw_pie_chart(root, product_data, &ChartLabels::default()))
+}
+
+// The YIQ distance pixelmatch uses, scaled so that black against white is 1.
+fn distance(Rgb(a): Rgb<u8>, Rgb(b): Rgb<u8>) -> f64 {
+    let [r, g, b] = [0, 1, 2].map(|i| a[i] as f64 - b[i] as f64);
+    let y = r * 0.29889531 + g * 0.58662247 + b * 0.11448223;
+    let i = r * 0.59597799 - g * 0.27417610 - b * 0.32180189;
+    let q = r * 0.21147017 - g * 0.52261711 + b * 0.31114694;
+    ((0.5053 * y * y + 0.299 * i * i + 0.1957 * q * q) / 35215.0).sqrt()
+}
+
+/// Compares `image` with the golden file and returns the difference when it is out of
+/// tolerance, along with the visual diff; `None` means the render matches.
+pub fn compare_golden(image: &RgbImage, golden: &Path, tolerance: Tolerance) -> Result<Option<(ImageDiff, RgbImage)>, Box<dyn Error>> {
+    let expected = image_diff::open(golden)?;
+    let (diff, visual) = image_diff::compare(&expected, image, |a, b| distance(a, b) <= tolerance.pixel);
+    Ok((diff.resized || diff.changed_percent() > tolerance.changed_percent).then_some((diff, visual)))
+}
+
+fn sibling(golden: &Path, suffix: &str) -> PathBuf {
+    let stem = golden.file_stem().unwrap_or_default().to_string_lossy();
+    golden.with_file_name(format!("{}.{}.png", stem, suffix))
+}
+
+/// Panics unless `image` matches the golden file within `tolerance`, leaving the render and a
+/// visual diff next to it as `<name>.actual.png` and `<name>.diff.png` to look at. A missing
+/// golden file also fails; run with `SALES_CHART_UPDATE_SNAPSHOTS=1` to (re)write them.
+pub fn assert_golden<P: AsRef<Path>>(image: &RgbImage, golden: P, tolerance: Tolerance) {
+    let golden = golden.as_ref();
+    let (actual, diff) = (sibling(golden, "actual"), sibling(golden, "diff"));
+    if std::env::var(UPDATE_VAR).is_ok_and(|value| value == "1") {
+        if let Some(dir) = golden.parent() {
+            fs::create_dir_all(dir).unwrap_or_else(|e| panic!("Cannot create {}: {}", dir.display(), e));
+        }
+        image.save(golden).unwrap_or_else(|e| panic!("Cannot write {}: {}", golden.display(), e));
+        return;
+    }
+    if !golden.exists() {
+        panic!("No golden image at {}; run with {}=1 to create it", golden.display(), UPDATE_VAR);
+    }
+    match compare_golden(image, golden, tolerance) {
+        Ok(None) => {
+            let _ = fs::remove_file(&actual);
+            let _ = fs::remove_file(&diff);
+        }
+        Ok(Some((difference, visual))) => {
+            let _ = image.save(&actual);
+            let _ = visual.save(&diff);
+            panic!(
+                "Chart differs from {}: {}; see {} and {}, or run with {}=1 to accept it",
+                golden.display(),
+                difference,
+                actual.display(),
+                diff.display(),
+                UPDATE_VAR
+            );
+        }
+        Err(e) => panic!("Cannot compare with {}: {}", golden.display(), e),
+    }
+}
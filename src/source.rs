@@ -0,0 +1,241 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+
+use csv::{ByteRecord, Position, StringRecord};
+use serde_json::{Map, Value};
+
+/// A format sales rows are read from, each row becoming the record a CSV file with a header row
+/// would have, so rows of any format are totalled the same way.
+pub trait SalesSource {
+    /// The names of the columns, as a CSV file's header row gives them.
+    fn headers(&mut self) -> csv::Result<StringRecord>;
+
+    /// Reads the next row into `record`, with its position in the input, and returns false once
+    /// there are no more. As with a CSV reader, an error on one row leaves the next one to read.
+    fn read_record(&mut self, record: &mut ByteRecord) -> csv::Result<bool>;
+}
+
+impl<R: Read> SalesSource for csv::Reader<R> {
+    fn headers(&mut self) -> csv::Result<StringRecord> {
+        csv::Reader::headers(self).cloned()
+    }
+
+    fn read_record(&mut self, record: &mut ByteRecord) -> csv::Result<bool> {
+        self.read_byte_record(record)
+    }
+}
+
+/// The rows of `source` to its end.
+pub fn records<S: SalesSource + ?Sized>(source: &mut S) -> impl Iterator<Item = csv::Result<ByteRecord>> + '_ {
+    std::iter::from_fn(move || {
+        let mut record = ByteRecord::new();
+        match source.read_record(&mut record) {
+            Ok(true) => Some(Ok(record)),
+            Ok(false) => None,
+            Err(e) => Some(Err(e)),
+        }
+    })
+}
+
+type Objects = Box<dyn Iterator<Item = csv::Result<(u64, Map<String, Value>)>> + Send>;
+
+/// Rows of sales as JSON objects, such as `{"month":"2023-01","product":"A","sales_amount":12.5}`.
+/// The columns are the keys of the first object: later objects missing one of them have it
+/// empty, and their other keys are ignored. Strings are read as their text, numbers and
+/// booleans as written, and `null` as an empty cell.
+pub struct JsonSource {
+    objects: Objects,
+    headers: Option<StringRecord>,
+    first: Option<(u64, Map<String, Value>)>,
+}
+
+fn invalid(reason: String) -> csv::Error {
+    io::Error::new(io::ErrorKind::InvalidData, reason).into()
+}
+
+fn object(value: Value, row: &str) -> csv::Result<Map<String, Value>> {
+    match value {
+        Value::Object(object) => Ok(object),
+        other => Err(invalid(format!("{} is not a JSON object: {}", row, other))),
+    }
+}
+
+impl JsonSource {
+    /// JSON Lines (NDJSON): an object on each line, numbered as lines of the input, with blank
+    /// lines skipped. Lines are read as they are needed, so a large input is never held whole.
+    pub fn lines<R: BufRead + Send + 'static>(reader: R) -> Self {
+        let objects = reader.lines().zip(1..).filter_map(|(text, line)| match text {
+            Ok(text) if text.trim().is_empty() => None,
+            Ok(text) => Some(
+                serde_json::from_str(&text)
+                    .map_err(|e| invalid(format!("Invalid JSON on line {}: {}", line, e)))
+                    .and_then(|value| object(value, &format!("Line {}", line)))
+                    .map(|object| (line, object)),
+            ),
+            Err(e) => Some(Err(e.into())),
+        });
+        JsonSource { objects: Box::new(objects), headers: None, first: None }
+    }
+
+    /// A JSON array of objects, numbered from 1 in their order in the array.
+    pub fn array<R: Read>(reader: R) -> Result<Self, Box<dyn Error>> {
+        let values: Vec<Value> = serde_json::from_reader(reader).map_err(|e| format!("Invalid JSON array of rows: {}", e))?;
+        let objects = values.into_iter().zip(1..).map(|(value, row)| object(value, &format!("Row {} of the array", row)).map(|object| (row, object)));
+        Ok(JsonSource { objects: Box::new(objects), headers: None, first: None })
+    }
+}
+
+// The text of a JSON value as a cell of a row.
+fn cell(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+impl SalesSource for JsonSource {
+    fn headers(&mut self) -> csv::Result<StringRecord> {
+        if let Some(headers) = &self.headers {
+            return Ok(headers.clone());
+        }
+        self.first = self.objects.next().transpose()?;
+        let headers: StringRecord = self.first.iter().flat_map(|(_, object)| object.keys()).collect();
+        self.headers = Some(headers.clone());
+        Ok(headers)
+    }
+
+    fn read_record(&mut self, record: &mut ByteRecord) -> csv::Result<bool> {
+        let headers = self.headers()?;
+        let Some((line, object)) = self.first.take().map(Ok).or_else(|| self.objects.next()).transpose()? else {
+            return Ok(false);
+        };
+        record.clear();
+        for name in &headers {
+            record.push_field(object.get(name).map(cell).unwrap_or_default().as_bytes());
+        }
+        let mut position = Position::new();
+        position.set_line(line).set_record(line);
+        record.set_position(Some(position));
+        Ok(true)
+    }
+}
+
+/// Rows of sales in a sheet of an Excel workbook: its first row that is not empty names the
+/// columns, and the rows after it are numbered as the sheet numbers them, skipping empty ones.
+/// Dates are read as `2023-01-31`, or with their time of day when they have one, and other
+/// cells as Excel shows them unformatted. The whole sheet is read when the source is opened.
+#[cfg(feature = "excel")]
+pub struct XlsxSource {
+    headers: StringRecord,
+    rows: std::vec::IntoIter<(u64, Vec<String>)>,
+}
+
+#[cfg(feature = "excel")]
+fn sheet_cell(cell: &calamine::Data) -> String {
+    match cell {
+        calamine::Data::DateTime(date) if date.is_datetime() => match date.as_datetime() {
+            Some(date) if date.time() == chrono::NaiveTime::MIN => date.format("%Y-%m-%d").to_string(),
+            Some(date) => date.format("%Y-%m-%dT%H:%M:%S").to_string(),
+            None => cell.to_string(),
+        },
+        other => other.to_string(),
+    }
+}
+
+#[cfg(feature = "excel")]
+impl XlsxSource {
+    /// The sheet named `sheet`, ignoring case, of the workbook at `path`, or its first sheet.
+    /// Besides .xlsx, workbooks may be .xlsm, .xlsb, .xls, or OpenDocument .ods files.
+    pub fn open(path: &Path, sheet: Option<&str>) -> Result<Self, Box<dyn Error>> {
+        use calamine::Reader;
+        let mut workbook = calamine::open_workbook_auto(path).map_err(|e| format!("Cannot read workbook {}: {}", path.display(), e))?;
+        let names = workbook.sheet_names();
+        let name = match sheet {
+            Some(sheet) => names
+                .iter()
+                .find(|name| name.eq_ignore_ascii_case(sheet))
+                .ok_or_else(|| format!("{} has no sheet \"{}\", only {}", path.display(), sheet, names.join(", ")))?,
+            None => names.first().ok_or_else(|| format!("{} has no sheets", path.display()))?,
+        };
+        let range = workbook.worksheet_range(name).map_err(|e| format!("Cannot read sheet {} of {}: {}", name, path.display(), e))?;
+        let first_line = range.start().map_or(0, |(row, _)| u64::from(row)) + 1;
+        let mut rows = range
+            .rows()
+            .zip(first_line..)
+            .map(|(cells, line)| (line, cells.iter().map(sheet_cell).collect::<Vec<_>>()))
+            .filter(|(_, cells)| cells.iter().any(|cell| !cell.is_empty()));
+        let headers = rows.next().map(|(_, cells)| cells.iter().map(|cell| cell.trim()).collect()).unwrap_or_default();
+        let rows: Vec<_> = rows.collect();
+        Ok(XlsxSource { headers, rows: rows.into_iter() })
+    }
+}
+
+#[cfg(feature = "excel")]
+impl SalesSource for XlsxSource {
+    fn headers(&mut self) -> csv::Result<StringRecord> {
+        Ok(self.headers.clone())
+    }
+
+    fn read_record(&mut self, record: &mut ByteRecord) -> csv::Result<bool> {
+        let Some((line, cells)) = self.rows.next() else {
+            return Ok(false);
+        };
+        record.clear();
+        for cell in &cells {
+            record.push_field(cell.as_bytes());
+        }
+        let mut position = Position::new();
+        position.set_line(line).set_record(line);
+        record.set_position(Some(position));
+        Ok(true)
+    }
+}
+
+/// How an input file is written.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InputFormat {
+    /// Comma-separated, with a header row
+    #[default]
+    Csv,
+    /// A JSON array of row objects
+    Json,
+    /// A JSON object on each line
+    JsonLines,
+    /// An Excel workbook, read from one of its sheets
+    Xlsx,
+}
+
+impl InputFormat {
+    /// The format of the file at `path` going by its extension: JSON for `.json`, JSON Lines for
+    /// `.ndjson` and `.jsonl`, a workbook for `.xlsx`, `.xlsm`, `.xlsb`, `.xls`, and `.ods`, and
+    /// CSV for any other.
+    pub fn of_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()).map(str::to_ascii_lowercase).as_deref() {
+            Some("json") => InputFormat::Json,
+            Some("ndjson" | "jsonl") => InputFormat::JsonLines,
+            Some("xlsx" | "xlsm" | "xlsb" | "xls" | "ods") => InputFormat::Xlsx,
+            _ => InputFormat::Csv,
+        }
+    }
+}
+
+/// The rows of the file at `path` in `format`; CSV rows may be of any length, and a workbook's
+/// are those of its first sheet.
+pub fn open_source(path: &Path, format: InputFormat) -> Result<Box<dyn SalesSource + Send>, Box<dyn Error>> {
+    if format == InputFormat::Xlsx {
+        #[cfg(feature = "excel")]
+        return Ok(Box::new(XlsxSource::open(path, None)?));
+        #[cfg(not(feature = "excel"))]
+        return Err("sales_chart was built without the `excel` feature".into());
+    }
+    let file = BufReader::new(File::open(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?);
+    Ok(match format {
+        InputFormat::Csv => Box::new(csv::ReaderBuilder::new().has_headers(true).flexible(true).from_reader(file)),
+        InputFormat::Json => Box::new(JsonSource::array(file)?),
+        InputFormat::JsonLines => Box::new(JsonSource::lines(file)),
+        InputFormat::Xlsx => unreachable!("workbooks are opened above"),
+    })
+}
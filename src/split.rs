@@ -0,0 +1,190 @@
+use std::error::Error;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+
+use csv::{ByteRecord, ReaderBuilder, StringRecord};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use tracing::{debug, debug_span};
+
+// About how many bytes of rows each thread parses at a time.
+const RANGE_BYTES: usize = 8 << 20;
+
+// Where the scan for row boundaries is within a field.
+#[derive(Clone, Copy)]
+enum Field {
+    Start,
+    Unquoted,
+    Quoted,
+    // A quote inside a quoted field, either closing it or the first of a doubled quote.
+    QuoteInQuoted,
+}
+
+/// The header and rows of the CSV file at `path`, read with rows of any length. The rows are
+/// parsed in ranges of a few megabytes on separate threads, and come back in file order with
+/// the line and byte they start at in the file, as a reader going through it would give them.
+pub fn read_records(path: &Path) -> Result<(StringRecord, Vec<ByteRecord>), Box<dyn Error>> {
+    let _span = debug_span!("split").entered();
+    let bytes = fs::read(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+    split_records(&bytes, RANGE_BYTES)
+}
+
+// The header and rows of `bytes`, parsed in ranges of about `size` bytes.
+fn split_records(bytes: &[u8], size: usize) -> Result<(StringRecord, Vec<ByteRecord>), Box<dyn Error>> {
+    let mut rdr = ReaderBuilder::new().has_headers(true).flexible(true).from_reader(bytes);
+    let headers = rdr.headers()?.clone();
+    let (start, first_line) = (rdr.position().byte() as usize, rdr.position().line());
+    let ranges = row_ranges(bytes, start, size);
+    debug!(bytes = bytes.len(), ranges = ranges.len(), "split rows");
+
+    let parse = |(range, lines_before): &(Range<usize>, u64)| -> csv::Result<Vec<ByteRecord>> {
+        let mut rdr = ReaderBuilder::new().has_headers(false).flexible(true).from_reader(&bytes[range.clone()]);
+        let mut records = Vec::new();
+        for result in rdr.byte_records() {
+            let mut record = result?;
+            if let Some(position) = record.position() {
+                let mut position = position.clone();
+                let (line, byte) = (position.line(), position.byte());
+                // The range's reader counts its lines from 1 and its bytes from 0.
+                position.set_line(first_line + lines_before + line - 1).set_byte(range.start as u64 + byte);
+                record.set_position(Some(position));
+            }
+            records.push(record);
+        }
+        Ok(records)
+    };
+    #[cfg(feature = "parallel")]
+    let chunks = ranges.par_iter().map(parse).collect::<csv::Result<Vec<_>>>()?;
+    #[cfg(not(feature = "parallel"))]
+    let chunks = ranges.iter().map(parse).collect::<csv::Result<Vec<_>>>()?;
+    Ok((headers, chunks.into_iter().flatten().collect()))
+}
+
+// Splits `bytes` from `start` into ranges of at least `size` bytes ending after a row's line
+// break, each with the line breaks before it. Like the csv reader, a quote only opens a field it
+// starts, and a line break inside a quoted field does not end the row. A `\r\n` is split after
+// its `\r`: the reader starts the next row at the `\n`, on the line before it, and the rows of
+// each range are numbered the way it numbers them.
+fn row_ranges(bytes: &[u8], start: usize, size: usize) -> Vec<(Range<usize>, u64)> {
+    let mut ranges = Vec::new();
+    let (mut from, mut lines, mut lines_before) = (start, 0, 0);
+    let mut field = Field::Start;
+    for (i, &byte) in bytes.iter().enumerate().skip(start) {
+        if byte == b'\n' {
+            lines += 1;
+        }
+        field = match (field, byte) {
+            (Field::Quoted, b'"') => Field::QuoteInQuoted,
+            (Field::Quoted, _) => Field::Quoted,
+            (Field::Start | Field::QuoteInQuoted, b'"') => Field::Quoted,
+            (_, b',') => Field::Start,
+            (_, b'\r' | b'\n') => {
+                let crlf = byte == b'\n' && i > 0 && bytes[i - 1] == b'\r';
+                if !crlf && i + 1 - from >= size {
+                    ranges.push((from..i + 1, lines_before));
+                    from = i + 1;
+                    lines_before = lines;
+                }
+                Field::Start
+            }
+            _ => Field::Unquoted,
+        };
+    }
+    if from < bytes.len() {
+        ranges.push((from..bytes.len(), lines_before));
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{process_records, process_sales_data, process_sales_file, ParseOptions, RowError};
+
+    // A quoted line break, doubled quotes, a quote inside an unquoted field, and an invalid row.
+    const SALES: &str = "month,product,sales_amount\n2023-01,\"Widget\nLarge\",10\n2023-01,\"Gadget \"\"Pro\"\"\n, boxed\",5\n2023-02,19\" Monitor,7.5\n2023-13,Widget,1\n2023-02,\"\"\"\",2\n";
+
+    // Each row's fields and the line and byte it starts at.
+    type Rows = Vec<(Vec<Vec<u8>>, u64, u64)>;
+
+    fn rows(records: &[ByteRecord]) -> Rows {
+        records.iter().map(|record| (record.iter().map(<[u8]>::to_vec).collect(), record.position().unwrap().line(), record.position().unwrap().byte())).collect()
+    }
+
+    // The rows as one reader going through the whole input gives them.
+    fn read_whole(bytes: &[u8]) -> (StringRecord, Rows) {
+        let mut rdr = ReaderBuilder::new().has_headers(true).flexible(true).from_reader(bytes);
+        let headers = rdr.headers().unwrap().clone();
+        let records: Vec<ByteRecord> = rdr.byte_records().map(Result::unwrap).collect();
+        (headers, rows(&records))
+    }
+
+    // Split at every possible size, so some range ends right before, inside, and after each
+    // quoted line break.
+    fn assert_splits_like_a_reader(csv: &str) {
+        let whole = read_whole(csv.as_bytes());
+        assert!(!whole.1.is_empty());
+        for size in 1..=csv.len() {
+            let (headers, records) = split_records(csv.as_bytes(), size).unwrap();
+            assert_eq!((headers, rows(&records)), whole, "ranges of {} bytes", size);
+        }
+    }
+
+    #[test]
+    fn quoted_fields_split_like_a_reader() {
+        assert_splits_like_a_reader(SALES);
+    }
+
+    #[test]
+    fn crlf_rows_split_like_a_reader() {
+        assert_splits_like_a_reader(&SALES.replace('\n', "\r\n"));
+    }
+
+    #[test]
+    fn a_last_row_without_a_line_break_is_read() {
+        assert_splits_like_a_reader(SALES.trim_end());
+        assert_splits_like_a_reader(SALES.replace('\n', "\r\n").trim_end());
+    }
+
+    #[test]
+    fn ranges_end_after_a_row() {
+        let start = SALES.find('\n').unwrap() + 1;
+        let ranges = row_ranges(SALES.as_bytes(), start, 8);
+        let ends: Vec<&str> = ranges.iter().map(|(range, _)| &SALES[range.clone()]).collect();
+        assert_eq!(ends, ["2023-01,\"Widget\nLarge\",10\n", "2023-01,\"Gadget \"\"Pro\"\"\n, boxed\",5\n", "2023-02,19\" Monitor,7.5\n", "2023-13,Widget,1\n", "2023-02,\"\"\"\",2\n"]);
+        let lines_before: Vec<u64> = ranges.iter().map(|(_, lines)| *lines).collect();
+        assert_eq!(lines_before, [0, 2, 4, 5, 6]);
+    }
+
+    fn error_lines(errors: &[RowError]) -> Vec<(u64, &str)> {
+        errors.iter().map(|error| (error.line, error.raw.as_str())).collect()
+    }
+
+    #[test]
+    fn rows_read_in_ranges_total_like_a_reader() {
+        let options = ParseOptions { lenient: true, ..ParseOptions::default() };
+        let (expected, expected_report) = process_sales_data(&mut csv::Reader::from_reader(SALES.as_bytes()), &options).unwrap();
+        assert_eq!(error_lines(&expected_report.errors), [(7, "2023-13,Widget,1")]);
+        for size in [1, 8, 40] {
+            let (headers, records) = split_records(SALES.as_bytes(), size).unwrap();
+            let (totals, report) = process_records(&headers, records.into_iter().map(Ok), &options).unwrap();
+            assert_eq!(totals, expected);
+            assert_eq!(error_lines(&report.errors), error_lines(&expected_report.errors));
+        }
+    }
+
+    #[test]
+    fn a_file_totals_like_a_reader() {
+        let path = std::env::temp_dir().join(format!("sales_chart-split-{}.csv", std::process::id()));
+        let csv = SALES.replace('\n', "\r\n");
+        fs::write(&path, &csv).unwrap();
+        let options = ParseOptions { lenient: true, ..ParseOptions::default() };
+        let (totals, report) = process_sales_file(&path, &options).unwrap();
+        let (expected, expected_report) = process_sales_data(&mut csv::Reader::from_reader(csv.as_bytes()), &options).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(totals, expected);
+        assert_eq!((report.rows, report.duplicates), (expected_report.rows, expected_report.duplicates));
+        assert_eq!(error_lines(&report.errors), error_lines(&expected_report.errors));
+    }
+}
@@ -0,0 +1,226 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::ops::RangeInclusive;
+
+use csv::{ByteRecord, Position, StringRecord};
+use serde::{Deserialize, Serialize};
+
+use crate::date_order::DateOrder;
+use crate::schema::Layout;
+use crate::quality::AmountStats;
+use crate::{DateKey, DuplicateTracker, ParseOptions, ParseReport, RowError, SalesTotals, Total};
+
+/// Totals kept up to date as sales events arrive one at a time, e.g. from a message queue.
+///
+/// An event is either a CSV row in the order of `headers` or a JSON object keyed by the
+/// column names, such as `{"month": "2024-03", "product": "Widget", "sales_amount": 12.5}`.
+/// Repeated events are only looked for with `ParseOptions::dedupe`, as the keys seen so far
+/// grow with every event.
+pub struct RunningTotals {
+    layout: Layout,
+    headers: StringRecord,
+    months: Option<RangeInclusive<DateKey>>,
+    product_filter: Option<String>,
+    tracker: Option<DuplicateTracker>,
+    sales_by_month: HashMap<DateKey, Total>,
+    sales_by_product: HashMap<String, Total>,
+    pub report: ParseReport,
+}
+
+/// Everything `RunningTotals` has added up so far, to carry on from with `RunningTotals::resume`.
+#[derive(Serialize, Deserialize)]
+pub struct TotalsState {
+    sales_by_month: HashMap<DateKey, Total>,
+    sales_by_product: HashMap<String, Total>,
+    rows: u64,
+    errors: Vec<RowError>,
+    missing_amounts: u64,
+    duplicates: u64,
+    amount_stats: AmountStats,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tracker: Option<DuplicateTracker>,
+}
+
+impl TotalsState {
+    pub fn rows(&self) -> u64 {
+        self.rows
+    }
+}
+
+impl RunningTotals {
+    pub fn new(options: &ParseOptions, headers: StringRecord) -> Result<Self, Box<dyn Error>> {
+        if options.currency.is_some() {
+            return Err("Currency conversion needs every month up front and is not supported for streamed rows".into());
+        }
+        if options.exact {
+            return Err("Exact totals are not supported for streamed rows".into());
+        }
+        #[cfg(feature = "scripting")]
+        if options.script.is_some() {
+            return Err("Scripts are not supported for streamed rows".into());
+        }
+        // There is no whole column to infer the day/month order from, so only an explicit
+        // order enables numeric dates like 03/04/2024.
+        let order = match options.date_order {
+            DateOrder::Auto => None,
+            order => Some(order),
+        };
+        let layout = options.layout(&headers)?.with_date_order(order);
+        Ok(RunningTotals {
+            layout,
+            headers,
+            months: options.months.clone(),
+            product_filter: options.product_filter.as_ref().map(|filter| filter.to_lowercase()),
+            tracker: options.dedupe.then(DuplicateTracker::default),
+            sales_by_month: HashMap::new(),
+            sales_by_product: HashMap::new(),
+            report: ParseReport::default(),
+        })
+    }
+
+    /// Totals picking up where `state` left off, for the same options and headers.
+    pub fn resume(options: &ParseOptions, headers: StringRecord, state: TotalsState) -> Result<Self, Box<dyn Error>> {
+        let mut totals = RunningTotals::new(options, headers)?;
+        totals.sales_by_month = state.sales_by_month;
+        totals.sales_by_product = state.sales_by_product;
+        totals.tracker = state.tracker;
+        totals.report.rows = state.rows;
+        totals.report.errors = state.errors;
+        totals.report.missing_amounts = state.missing_amounts;
+        totals.report.duplicates = state.duplicates;
+        totals.report.amount_stats = state.amount_stats;
+        Ok(totals)
+    }
+
+    pub fn state(&self) -> TotalsState {
+        TotalsState {
+            sales_by_month: self.sales_by_month.clone(),
+            sales_by_product: self.sales_by_product.clone(),
+            rows: self.report.rows,
+            errors: self.report.errors.clone(),
+            missing_amounts: self.report.missing_amounts,
+            duplicates: self.report.duplicates,
+            amount_stats: self.report.amount_stats,
+            tracker: self.tracker.clone(),
+        }
+    }
+
+    /// Adds one event; `line` (counted from 1, like a file line) identifies it in errors. A
+    /// rejected event leaves the totals untouched and is kept in the report as well as returned.
+    pub fn push(&mut self, payload: &[u8], line: u64) -> Result<(), RowError> {
+        let result = self.record(payload, line).and_then(|record| self.add(&record, line));
+        self.push_result(result)
+    }
+
+    /// Adds one row already read as a record, such as from a CSV reader, like `push`.
+    pub fn push_record(&mut self, record: &ByteRecord) -> Result<(), RowError> {
+        let line = record.position().map_or(0, |pos| pos.line());
+        let result = self.add(record, line);
+        self.push_result(result)
+    }
+
+    fn push_result(&mut self, result: Result<(), RowError>) -> Result<(), RowError> {
+        self.report.rows += 1;
+        if let Err(error) = &result {
+            self.report.errors.push(error.clone());
+        }
+        result
+    }
+
+    pub fn totals(&self) -> SalesTotals {
+        let months = self.sales_by_month.iter().map(|(month, total)| (*month, total.value())).collect();
+        let products = self.sales_by_product.iter().map(|(product, total)| (product.clone(), total.value())).collect();
+        (months, products)
+    }
+
+    /// Sales of all months added up so far.
+    pub fn total_sales(&self) -> f64 {
+        let mut months: Vec<_> = self.sales_by_month.iter().collect();
+        months.sort_unstable_by_key(|(month, _)| **month);
+        let mut total = Total::default();
+        for (_, sales) in months {
+            total += *sales;
+        }
+        total.value()
+    }
+
+    fn record(&self, payload: &[u8], line: u64) -> Result<ByteRecord, RowError> {
+        let invalid = |reason: String| RowError {
+            line,
+            column: None,
+            raw: String::from_utf8_lossy(payload).trim_end().to_string(),
+            reason,
+        };
+        let mut record = if payload.trim_ascii_start().starts_with(b"{") {
+            let event: serde_json::Map<String, serde_json::Value> =
+                serde_json::from_slice(payload).map_err(|e| invalid(format!("Invalid JSON event: {}", e)))?;
+            let field = |name: &str| match event.iter().find(|(key, _)| key.eq_ignore_ascii_case(name)) {
+                Some((_, serde_json::Value::String(value))) => value.clone(),
+                Some((_, serde_json::Value::Null)) | None => String::new(),
+                Some((_, value)) => value.to_string(),
+            };
+            ByteRecord::from(self.headers.iter().map(field).collect::<Vec<_>>())
+        } else {
+            let mut rdr = csv::ReaderBuilder::new().has_headers(false).flexible(true).from_reader(payload);
+            match rdr.byte_records().next() {
+                Some(Ok(record)) => record,
+                Some(Err(e)) => return Err(invalid(e.to_string())),
+                None => return Err(invalid("Empty event".to_string())),
+            }
+        };
+        let mut position = Position::new();
+        position.set_line(line);
+        record.set_position(Some(position));
+        Ok(record)
+    }
+
+    fn add(&mut self, record: &ByteRecord, line: u64) -> Result<(), RowError> {
+        if let Some(tracker) = &mut self.tracker {
+            if tracker.check(&self.layout, record, line).is_some() {
+                self.report.duplicates += 1;
+                return Ok(());
+            }
+        }
+
+        let row = self.layout.parse_row(record).map_err(|error| RowError::new(record, error))?;
+        self.report.missing_amounts += u64::from(row.missing_amount);
+        let Some((month, sales)) = row.values.filter(|(month, _)| self.months.as_ref().is_none_or(|m| m.contains(month))) else {
+            return Ok(());
+        };
+        let product = String::from_utf8_lossy(&record[self.layout.product_index]).into_owned();
+        if !self.product_filter.as_ref().is_none_or(|filter| product.to_lowercase().contains(filter)) {
+            return Ok(());
+        }
+        if !row.missing_amount {
+            self.report.amount_stats.push(sales);
+        }
+        let sales = Total::Float { sum: sales, compensation: 0.0 };
+        *self.sales_by_month.entry(month).or_default() += sales;
+        *self.sales_by_product.entry(product).or_default() += sales;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process_sales_data;
+
+    const HEADERS: [&str; 3] = ["month", "product", "sales_amount"];
+
+    #[test]
+    fn streamed_rows_add_up_like_a_file() {
+        let mut rows = vec!["2023-01,A,0.1"; 10];
+        rows.extend(["2023-02,B,1e16", "2023-02,B,1", "2023-02,B,1"]);
+        let mut totals = RunningTotals::new(&ParseOptions::default(), StringRecord::from(HEADERS.to_vec())).unwrap();
+        for (line, row) in rows.iter().enumerate() {
+            totals.push(row.as_bytes(), line as u64 + 2).unwrap();
+        }
+        let csv = format!("{}\n{}\n", HEADERS.join(","), rows.join("\n"));
+        let (file, _) = process_sales_data(&mut csv::Reader::from_reader(csv.as_bytes()), &ParseOptions::default()).unwrap();
+        assert_eq!(totals.totals(), file);
+        assert_eq!(totals.totals().1["A"], 1.0);
+        assert_eq!(totals.totals().1["B"], 1e16 + 2.0);
+        assert_eq!(totals.total_sales(), 1e16 + 3.0);
+    }
+}
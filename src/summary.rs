@@ -0,0 +1,368 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+use chrono::NaiveDate;
+use serde::Serialize;
+
+use crate::budget::BudgetVariance;
+use crate::holidays::HolidayAverages;
+use crate::timings::StageTiming;
+use crate::{date_to_key, missing_months, CubeCell, DateKey, ParseReport, PlotData};
+
+#[derive(Clone, Copy, Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RunStatus {
+    Success,
+    Failure,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Anomalies {
+    pub parse_errors: u64,
+    pub duplicates: u64,
+    pub suspicious_rows: u64,
+    pub missing_amounts: u64,
+    pub missing_months: Vec<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct MonthTotal {
+    pub month: String,
+    pub sales: f64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ProductTotal {
+    pub product: String,
+    pub sales: f64,
+}
+
+/// Monthly and per-product totals in chart order, as handed to the language bindings:
+/// `{"monthly": [{"month", "sales"}], "products": [{"product", "sales"}]}`.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Aggregates {
+    pub monthly: Vec<MonthTotal>,
+    pub products: Vec<ProductTotal>,
+}
+
+impl Aggregates {
+    pub fn new((monthly_data, product_data): &PlotData) -> Self {
+        Aggregates {
+            monthly: monthly_data
+                .iter()
+                .map(|(month, sales)| MonthTotal { month: month.format("%Y-%m").to_string(), sales: *sales })
+                .collect(),
+            products: product_data
+                .iter()
+                .map(|(product, sales)| ProductTotal { product: product.clone(), sales: *sales })
+                .collect(),
+        }
+    }
+}
+
+/// A month whose total differs from the previous run's, such as after a late correction.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct MonthChange {
+    pub month: String,
+    pub previous: f64,
+    /// Zero when the month is no longer in the data
+    pub current: f64,
+}
+
+/// How a run's totals differ from those of the previous run of the same input.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct RunChanges {
+    /// When the previous run ran
+    pub previous_run: String,
+    /// Products with sales now but not in the previous run, and the other way round
+    pub new_products: Vec<String>,
+    pub removed_products: Vec<String>,
+    /// The previous run's months whose totals changed by a cent or more
+    pub changed_months: Vec<MonthChange>,
+}
+
+impl RunChanges {
+    pub fn between(previous_run: &str, previous: &Aggregates, current: &Aggregates) -> Self {
+        let has = |aggregates: &Aggregates, product: &str| aggregates.products.iter().any(|total| total.product == product);
+        let changed_months = previous
+            .monthly
+            .iter()
+            .filter_map(|before| {
+                let now = current.monthly.iter().find(|total| total.month == before.month).map_or(0.0, |total| total.sales);
+                ((now - before.sales).abs() >= 0.005).then(|| MonthChange { month: before.month.clone(), previous: before.sales, current: now })
+            })
+            .collect();
+        RunChanges {
+            previous_run: previous_run.to_string(),
+            new_products: current.products.iter().filter(|total| !has(previous, &total.product)).map(|total| total.product.clone()).collect(),
+            removed_products: previous.products.iter().filter(|total| !has(current, &total.product)).map(|total| total.product.clone()).collect(),
+            changed_months,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.new_products.is_empty() && self.removed_products.is_empty() && self.changed_months.is_empty()
+    }
+}
+
+/// One product's sales in every month of the run, zero where it sold nothing.
+#[derive(Clone, Debug)]
+pub struct ProductTrend {
+    pub product: String,
+    pub monthly: Vec<(NaiveDate, f64)>,
+}
+
+/// Products in chart order; empty when the run did not collect the month by product cube.
+pub fn product_trends((monthly_data, product_data): &PlotData, cube: &[CubeCell]) -> Vec<ProductTrend> {
+    if cube.is_empty() {
+        return Vec::new();
+    }
+    let mut sales: HashMap<(&str, DateKey), f64> = HashMap::new();
+    for cell in cube {
+        *sales.entry((cell.product.as_str(), cell.month)).or_insert(0.0) += cell.sales;
+    }
+    product_data
+        .iter()
+        .map(|(product, _)| ProductTrend {
+            product: product.clone(),
+            monthly: monthly_data
+                .iter()
+                .map(|(month, _)| (*month, sales.get(&(product.as_str(), date_to_key(month))).copied().unwrap_or(0.0)))
+                .collect(),
+        })
+        .collect()
+}
+
+/// Monthly totals of one value of a column such as region, for a panel of a faceted chart.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Facet {
+    pub name: String,
+    pub monthly: Vec<(NaiveDate, f64)>,
+}
+
+/// The monthly totals of every value `facet` gives the cells of `cube`, on all the months of
+/// `monthly_data` so the panels share an axis, the best-selling first. Blank values are `(blank)`.
+pub fn facets<F>(monthly_data: &[(NaiveDate, f64)], cube: &[CubeCell], facet: F) -> Vec<Facet>
+where
+    F: Fn(&CubeCell) -> String,
+{
+    let mut sales: HashMap<String, HashMap<DateKey, f64>> = HashMap::new();
+    for cell in cube {
+        let name = facet(cell);
+        let name = if name.is_empty() { "(blank)".to_string() } else { name };
+        *sales.entry(name).or_default().entry(cell.month).or_insert(0.0) += cell.sales;
+    }
+    let mut facets: Vec<(f64, Facet)> = sales
+        .into_iter()
+        .map(|(name, months)| {
+            let monthly = monthly_data.iter().map(|(month, _)| (*month, months.get(&date_to_key(month)).copied().unwrap_or(0.0))).collect();
+            (months.values().sum(), Facet { name, monthly })
+        })
+        .collect();
+    facets.sort_by(|(a, a_facet), (b, b_facet)| b.total_cmp(a).then_with(|| a_facet.name.cmp(&b_facet.name)));
+    facets.into_iter().map(|(_, facet)| facet).collect()
+}
+
+/// One stage of a sales funnel with its sales.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct FunnelStage {
+    pub stage: String,
+    pub sales: f64,
+    /// The stage's sales in percent of the stage before it, or None for the first stage or after
+    /// one without sales
+    pub conversion_percent: Option<f64>,
+}
+
+/// The stages of `stages` in the order of `order`, then any it leaves out, the largest first.
+/// A stage `order` names without sales is in the funnel at zero.
+pub fn funnel(stages: &[(String, f64)], order: &[String]) -> Vec<FunnelStage> {
+    let sales = |stage: &str| stages.iter().filter(|(name, _)| name.eq_ignore_ascii_case(stage)).fold(0.0, |total, (_, sales)| total + sales);
+    let listed = order.iter().map(|stage| (stage.clone(), sales(stage)));
+    let rest = stages.iter().filter(|(name, _)| !order.iter().any(|stage| stage.eq_ignore_ascii_case(name))).cloned();
+    let mut previous: Option<f64> = None;
+    listed
+        .chain(rest)
+        .map(|(stage, sales)| {
+            let conversion_percent = previous.filter(|previous| *previous != 0.0).map(|previous| sales / previous * 100.0);
+            previous = Some(sales);
+            FunnelStage { stage, sales, conversion_percent }
+        })
+        .collect()
+}
+
+/// What a run produced, for anything that wants to react to it without scraping stdout.
+#[derive(Clone, Debug, Serialize)]
+pub struct RunSummary {
+    pub status: RunStatus,
+    pub input: PathBuf,
+    /// Other files read by the run, such as product aliases or a schema
+    pub config_files: Vec<PathBuf>,
+    pub artifacts: Vec<PathBuf>,
+    pub total_sales: f64,
+    pub months: usize,
+    pub products: usize,
+    pub rows: u64,
+    pub latest_month: Option<MonthTotal>,
+    /// Percentage change of the latest month against the one before it.
+    pub latest_change: Option<f64>,
+    pub anomalies: Anomalies,
+    pub error: Option<String>,
+    /// How long each stage took, when the caller collected `timings::StageTimings`
+    pub timings: Vec<StageTiming>,
+    /// The totals behind the charts, for the HTML report; left out of the JSON to keep
+    /// webhook payloads small.
+    #[serde(skip)]
+    pub aggregates: Aggregates,
+    /// Per-product monthly totals for the HTML report's drill-down sections, also left out.
+    #[serde(skip)]
+    pub product_trends: Vec<ProductTrend>,
+    /// Currency of every amount, for one currency of a run split by currency
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
+    /// A summary per currency when the data mixes currencies and none are converted; the
+    /// run's own sales figures are then left at zero rather than adding the currencies up.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub currencies: Vec<RunSummary>,
+    /// Average daily sales on holidays against other days, with a holiday calendar
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub holidays: Option<HolidayAverages>,
+    /// Each budgeted month's sales against its target, with a budget file
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub budget: Vec<BudgetVariance>,
+    /// The artifacts that chart a single product, which the HTML report leaves to its
+    /// drill-down sections
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub product_charts: Vec<PathBuf>,
+    /// Sales per stage of the funnel, with a stage column in the data
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub funnel: Vec<FunnelStage>,
+    /// What changed since the previous run of the input, with a run history
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub run_changes: Option<RunChanges>,
+    /// Custom metrics by name, from the `metrics` function of a script
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub metrics: BTreeMap<String, serde_json::Value>,
+    /// A few sentences on the totals, once the caller wrote them with `narrative::narrative`
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub narrative: String,
+    /// What the caller's `alert::AlertRule`s raised, one message each
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub alerts: Vec<String>,
+}
+
+impl RunSummary {
+    pub fn success(input: &Path, data: &PlotData, report: &ParseReport, artifacts: Vec<PathBuf>) -> Self {
+        let (monthly_data, product_data) = data;
+        let missing = missing_months(monthly_data.iter().map(|(month, _)| date_to_key(month)));
+        let latest = monthly_data.last().map(|(month, sales)| MonthTotal { month: month.format("%Y-%m").to_string(), sales: *sales });
+        let latest_change = match monthly_data.as_slice() {
+            [.., (_, previous), (_, last)] if *previous != 0.0 => Some((last - previous) / previous * 100.0),
+            _ => None,
+        };
+        RunSummary {
+            status: RunStatus::Success,
+            input: input.to_path_buf(),
+            config_files: Vec::new(),
+            artifacts,
+            total_sales: report.exact_total.unwrap_or_else(|| monthly_data.iter().map(|(_, sales)| sales).sum()),
+            months: monthly_data.len(),
+            products: product_data.len(),
+            rows: report.rows,
+            latest_month: latest,
+            latest_change,
+            anomalies: Anomalies {
+                parse_errors: report.errors.len() as u64,
+                duplicates: report.duplicates,
+                suspicious_rows: report.suspicious.len() as u64,
+                missing_amounts: report.missing_amounts,
+                missing_months: missing.iter().map(|month| month.format("%Y-%m").to_string()).collect(),
+            },
+            error: None,
+            timings: Vec::new(),
+            aggregates: Aggregates::new(data),
+            product_trends: product_trends(data, &report.cube),
+            currency: None,
+            currencies: Vec::new(),
+            holidays: None,
+            budget: Vec::new(),
+            product_charts: Vec::new(),
+            funnel: funnel(&report.stages, &[]),
+            run_changes: None,
+            metrics: BTreeMap::new(),
+            narrative: String::new(),
+            alerts: Vec::new(),
+        }
+    }
+
+    /// A run split into `currencies`, each with its own summary. Rows and anomalies add up
+    /// across them; months and products count each once.
+    pub fn by_currency(input: &Path, currencies: Vec<RunSummary>, artifacts: Vec<PathBuf>) -> Self {
+        let months: BTreeSet<&str> = currencies.iter().flat_map(|summary| summary.aggregates.monthly.iter().map(|month| month.month.as_str())).collect();
+        let products: BTreeSet<&str> =
+            currencies.iter().flat_map(|summary| summary.aggregates.products.iter().map(|product| product.product.as_str())).collect();
+        let sum = |count: fn(&RunSummary) -> u64| currencies.iter().map(count).sum();
+        RunSummary {
+            status: RunStatus::Success,
+            input: input.to_path_buf(),
+            config_files: Vec::new(),
+            artifacts,
+            total_sales: 0.0,
+            months: months.len(),
+            products: products.len(),
+            rows: sum(|summary| summary.rows),
+            latest_month: None,
+            latest_change: None,
+            anomalies: Anomalies {
+                parse_errors: sum(|summary| summary.anomalies.parse_errors),
+                duplicates: sum(|summary| summary.anomalies.duplicates),
+                suspicious_rows: sum(|summary| summary.anomalies.suspicious_rows),
+                missing_amounts: sum(|summary| summary.anomalies.missing_amounts),
+                missing_months: Vec::new(),
+            },
+            error: None,
+            timings: Vec::new(),
+            aggregates: Aggregates::default(),
+            product_trends: Vec::new(),
+            currency: None,
+            currencies,
+            holidays: None,
+            budget: Vec::new(),
+            product_charts: Vec::new(),
+            funnel: Vec::new(),
+            run_changes: None,
+            metrics: BTreeMap::new(),
+            narrative: String::new(),
+            alerts: Vec::new(),
+        }
+    }
+
+    pub fn failure(input: &Path, error: &str) -> Self {
+        RunSummary {
+            status: RunStatus::Failure,
+            input: input.to_path_buf(),
+            config_files: Vec::new(),
+            artifacts: Vec::new(),
+            total_sales: 0.0,
+            months: 0,
+            products: 0,
+            rows: 0,
+            latest_month: None,
+            latest_change: None,
+            anomalies: Anomalies::default(),
+            error: Some(error.to_string()),
+            timings: Vec::new(),
+            aggregates: Aggregates::default(),
+            product_trends: Vec::new(),
+            currency: None,
+            currencies: Vec::new(),
+            holidays: None,
+            budget: Vec::new(),
+            product_charts: Vec::new(),
+            funnel: Vec::new(),
+            run_changes: None,
+            metrics: BTreeMap::new(),
+            narrative: String::new(),
+            alerts: Vec::new(),
+        }
+    }
+}
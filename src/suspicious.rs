@@ -0,0 +1,127 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+
+use chrono::{Months, NaiveDate};
+use csv::ByteRecord;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::quality::AmountStats;
+use crate::{date_to_key, key_to_date, raw_row, DateKey};
+
+#[derive(Clone, Debug)]
+pub struct SuspicionRules {
+    pub std_devs: f64,
+    pub date_tolerance_months: u32,
+    pub flag_zero_amounts: bool,
+}
+
+impl Default for SuspicionRules {
+    fn default() -> Self {
+        SuspicionRules {
+            std_devs: 3.0,
+            date_tolerance_months: 12,
+            flag_zero_amounts: true,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Suspicion {
+    Outlier { std_devs: f64 },
+    DateOutOfRange { from: NaiveDate, to: NaiveDate },
+    ZeroAmount,
+}
+
+impl fmt::Display for Suspicion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Suspicion::Outlier { std_devs } => write!(f, "Amount is {:.1} standard deviations from the mean", std_devs),
+            Suspicion::DateOutOfRange { from, to } => write!(
+                f,
+                "Month is far outside the bulk of the data ({} .. {})",
+                from.format("%Y-%m"),
+                to.format("%Y-%m")
+            ),
+            Suspicion::ZeroAmount => write!(f, "Amount is zero"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SuspiciousRow {
+    pub line: u64,
+    pub raw: String,
+    pub reason: Suspicion,
+}
+
+// The 1st..99th percentile of row months, widened by the tolerance, so a handful of typos
+// like 2204-01 cannot stretch the range they are measured against.
+fn expected_month_range(values: &[(usize, DateKey, f64)], tolerance: u32) -> Option<(NaiveDate, NaiveDate)> {
+    let mut histogram: BTreeMap<DateKey, u64> = BTreeMap::new();
+    for &(_, month, _) in values {
+        *histogram.entry(month).or_insert(0) += 1;
+    }
+
+    let total = values.len() as f64;
+    let percentile = |p: f64| {
+        let mut cumulative = 0;
+        histogram.iter().find_map(|(&month, &count)| {
+            cumulative += count;
+            (cumulative as f64 >= total * p).then_some(month)
+        })
+    };
+    let from = key_to_date(percentile(0.01)?).checked_sub_months(Months::new(tolerance))?;
+    let to = key_to_date(percentile(0.99)?).checked_add_months(Months::new(tolerance))?;
+    Some((from, to))
+}
+
+pub fn flag_rows(
+    values: &[(usize, DateKey, f64)],
+    records: &[ByteRecord],
+    stats: &AmountStats,
+    rules: &SuspicionRules,
+) -> Vec<SuspiciousRow> {
+    let range = expected_month_range(values, rules.date_tolerance_months)
+        .map(|(from, to)| (from, to, date_to_key(&from), date_to_key(&to)));
+    let std_dev = stats.std_dev();
+
+    #[cfg(feature = "parallel")]
+    let values = values.par_iter();
+    #[cfg(not(feature = "parallel"))]
+    let values = values.iter();
+    let mut flagged: Vec<SuspiciousRow> = values
+        .filter_map(|&(index, month, sales)| {
+            let reason = if stats.is_outlier(sales, rules.std_devs) {
+                Suspicion::Outlier { std_devs: (sales - stats.mean).abs() / std_dev }
+            } else if let Some((from, to, _, _)) = range.filter(|&(_, _, lo, hi)| month < lo || month > hi) {
+                Suspicion::DateOutOfRange { from, to }
+            } else if rules.flag_zero_amounts && sales == 0.0 {
+                Suspicion::ZeroAmount
+            } else {
+                return None;
+            };
+
+            let record = &records[index];
+            Some(SuspiciousRow {
+                line: record.position().map_or(0, |pos| pos.line()),
+                raw: raw_row(record),
+                reason,
+            })
+        })
+        .collect();
+    flagged.sort_by_key(|row| row.line);
+    flagged
+}
+
+pub fn write_suspicious_report<P: AsRef<Path>>(path: P, rows: &[SuspiciousRow]) -> Result<(), Box<dyn Error>> {
+    let mut wtr = csv::Writer::from_path(path)?;
+    wtr.write_record(["line", "raw_row", "reason"])?;
+    for row in rows {
+        wtr.write_record([row.line.to_string().as_str(), &row.raw, &row.reason.to_string()])?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
@@ -0,0 +1,84 @@
+use std::fmt;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use serde::Serialize;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id};
+use tracing::Subscriber;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+/// Wall-clock time of one stage, named after its span and the spans around it, e.g.
+/// `run/analyze/load/ingest` or `run/render line`.
+#[derive(Clone, Debug, Serialize)]
+pub struct StageTiming {
+    pub stage: String,
+    pub seconds: f64,
+}
+
+static FINISHED: Mutex<Vec<StageTiming>> = Mutex::new(Vec::new());
+
+struct Started {
+    stage: String,
+    at: Instant,
+}
+
+// Charts are rendered by the same span, told apart by its `chart` field.
+#[derive(Default)]
+struct ChartField(Option<String>);
+
+impl Visit for ChartField {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "chart" {
+            self.0 = Some(value.to_string());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "chart" {
+            self.0 = Some(format!("{:?}", value));
+        }
+    }
+}
+
+/// A `tracing` layer timing every span from creation to close; `take_stage_timings` collects
+/// the results in the order the stages finished.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StageTimings;
+
+impl<S> Layer<S> for StageTimings
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut chart = ChartField::default();
+        attrs.record(&mut chart);
+        let name = match chart.0 {
+            Some(chart) => format!("{} {}", attrs.metadata().name(), chart),
+            None => attrs.metadata().name().to_string(),
+        };
+        let parent = span.parent().and_then(|parent| parent.extensions().get::<Started>().map(|started| started.stage.clone()));
+        let stage = parent.map_or(name.clone(), |parent| format!("{}/{}", parent, name));
+        span.extensions_mut().insert(Started { stage, at: Instant::now() });
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let timing = span.extensions().get::<Started>().map(|started| StageTiming { stage: started.stage.clone(), seconds: started.at.elapsed().as_secs_f64() });
+        if let Some(timing) = timing {
+            FINISHED.lock().unwrap_or_else(|e| e.into_inner()).push(timing);
+        }
+    }
+}
+
+/// Stages finished since the last call, when `StageTimings` is installed.
+pub fn take_stage_timings() -> Vec<StageTiming> {
+    std::mem::take(&mut *FINISHED.lock().unwrap_or_else(|e| e.into_inner()))
+}
@@ -0,0 +1,184 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use chrono::Local;
+
+/// Every caption of the charts and the HTML report, in English. A `{}` stands for a value
+/// filled in when the caption is drawn.
+pub const CAPTIONS: &[&str] = &[
+    "Monthly Sales Trend",
+    "Total Sales",
+    "Prior Year",
+    "Break-even",
+    "Nominal vs Real Monthly Sales",
+    "Nominal Sales",
+    "Real Sales ({} prices)",
+    "Actual vs Budget",
+    "Budget",
+    "Variance from Budget",
+    "Scenario vs Actual",
+    "Scenario",
+    "Sales by Product",
+    "Product Rank by Month",
+    "Product Mix by Month",
+    "Product Sales by Month",
+    "Other",
+    "Monthly Sales by {}",
+    "Monthly Sales of {}",
+    "Sales by Region",
+    "Sales Funnel",
+    "Total Revenue",
+    "Month over Month",
+    "Best Month",
+    "Top Product",
+    "Executive Summary",
+    "Top Products",
+    "Total Sales per Run",
+    "{} converted",
+    "Monthly Sales Comparison",
+    "Sales by Product Comparison",
+    "Sales report",
+    "Sales comparison",
+    "Difference",
+    "Total sales",
+    "Months",
+    "Products",
+    "Latest month",
+    "Duplicate rows",
+    "Suspicious rows",
+    "Budget variance",
+    "Changed since",
+    "New products",
+    "Disappeared products",
+    "Changed months",
+    "Alerts",
+    "Average day, holidays ({} days)",
+    "Average day, other days ({} days)",
+    "Month",
+    "Product",
+    "Sales",
+    "Change",
+    "Share",
+    "Target",
+    "Variance",
+    "Stage",
+    "Conversion",
+    "Back to the bar chart",
+];
+
+/// Placeholders a translation may use, such as `{date_range}`, filled in from the data charted.
+pub const VARIABLES: &[&str] = &["date_range", "total_sales", "row_count", "generated_at"];
+
+/// What the placeholders of `VARIABLES` stand for in one run.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CaptionValues {
+    /// First and last month charted, as in `2024-01 – 2024-09`
+    pub date_range: String,
+    pub total_sales: String,
+    pub row_count: String,
+    /// Local time of the run, as in `2024-10-01 09:30`
+    pub generated_at: String,
+}
+
+impl CaptionValues {
+    /// Values for `months`, the `%Y-%m` months charted in order, generated now.
+    pub fn new<'a>(months: impl IntoIterator<Item = &'a str>, total_sales: String, rows: u64) -> Self {
+        let mut months = months.into_iter();
+        let first = months.next();
+        let date_range = match (first, months.last()) {
+            (Some(first), Some(last)) if first != last => format!("{} – {}", first, last),
+            (Some(first), _) => first.to_string(),
+            (None, _) => String::new(),
+        };
+        CaptionValues { date_range, total_sales, row_count: rows.to_string(), generated_at: Local::now().format("%Y-%m-%d %H:%M").to_string() }
+    }
+
+    fn get(&self, variable: &str) -> Option<&str> {
+        match variable {
+            "date_range" => Some(&self.date_range),
+            "total_sales" => Some(&self.total_sales),
+            "row_count" => Some(&self.row_count),
+            "generated_at" => Some(&self.generated_at),
+            _ => None,
+        }
+    }
+}
+
+// The names between braces in `text`, such as `date_range` in `Sales {date_range}`; `{}` has none.
+fn placeholders(text: &str) -> impl Iterator<Item = &str> {
+    text.split('{').skip(1).filter_map(|rest| rest.split_once('}').map(|(name, _)| name)).filter(|name| !name.is_empty())
+}
+
+/// Captions in the reader's language, read from a TOML file of `"English caption" = "translation"`
+/// lines. Captions the file leaves out stay in English. Translations may use the placeholders
+/// of `VARIABLES`, which are filled in once `with_values` has the run's values.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Translations {
+    captions: BTreeMap<String, String>,
+    values: Option<CaptionValues>,
+}
+
+impl Translations {
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path).map_err(|e| format!("Cannot read translations {}: {}", path.display(), e))?;
+        let captions: BTreeMap<String, String> =
+            toml::from_str(&contents).map_err(|e| format!("Invalid translations file {}: {}", path.display(), e))?;
+        for (caption, translation) in &captions {
+            if !CAPTIONS.contains(&caption.as_str()) {
+                return Err(format!("{} translates \"{}\", which is not a caption sales_chart shows", path.display(), caption).into());
+            }
+            if caption.contains("{}") && !translation.contains("{}") {
+                return Err(format!("The translation of \"{}\" in {} must keep its {{}}", caption, path.display()).into());
+            }
+            if let Some(unknown) = placeholders(translation).find(|name| !VARIABLES.contains(name)) {
+                return Err(format!(
+                    "The translation of \"{}\" in {} uses {{{}}}, which is none of {{{}}}",
+                    caption,
+                    path.display(),
+                    unknown,
+                    VARIABLES.join("}, {")
+                )
+                .into());
+            }
+        }
+        Ok(Translations { captions, values: None })
+    }
+
+    /// The translations with their placeholders standing for `values`.
+    pub fn with_values(self, values: CaptionValues) -> Self {
+        Translations { values: Some(values), ..self }
+    }
+
+    /// The translations without the time of the run when none of them shows it, which is all
+    /// that changes between two runs on the same data otherwise.
+    pub fn timeless(&self) -> Self {
+        let shown = self.captions.values().any(|translation| placeholders(translation).any(|name| name == "generated_at"));
+        let values = self.values.clone().map(|values| if shown { values } else { CaptionValues { generated_at: String::new(), ..values } });
+        Translations { captions: self.captions.clone(), values }
+    }
+
+    /// The translation of `caption`, or `caption` itself without one, with its placeholders
+    /// filled in when there are values for them.
+    pub fn get<'a>(&'a self, caption: &'a str) -> Cow<'a, str> {
+        let translation = self.captions.get(caption).map_or(caption, String::as_str);
+        let Some(values) = self.values.as_ref().filter(|_| placeholders(translation).next().is_some()) else {
+            return Cow::Borrowed(translation);
+        };
+        let mut filled = translation.to_string();
+        for variable in VARIABLES {
+            if let Some(value) = values.get(variable) {
+                filled = filled.replace(&format!("{{{}}}", variable), value);
+            }
+        }
+        Cow::Owned(filled)
+    }
+
+    /// The translation of a caption with a `{}`, with `value` in its place.
+    pub fn fill(&self, caption: &str, value: &str) -> String {
+        self.get(caption).replacen("{}", value, 1)
+    }
+}
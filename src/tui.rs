@@ -0,0 +1,262 @@
+use std::error::Error;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+
+use chrono::NaiveDate;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Color, Style, Stylize};
+use ratatui::symbols::Marker;
+use ratatui::text::Line;
+use ratatui::widgets::{Axis, Bar, BarChart, BarGroup, Block, Chart, Dataset, GraphType, Paragraph, Row, Table, Tabs};
+use ratatui::{DefaultTerminal, Frame};
+
+use crate::number::MoneyFormat;
+use crate::summary::RunSummary;
+use crate::{ParseReport, PlotData};
+
+/// The rows the dashboard asks to have aggregated.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Filter {
+    /// Text the product name must contain, ignoring case; empty for every product
+    pub product: String,
+    /// `None` for every month
+    pub months: Option<RangeInclusive<NaiveDate>>,
+}
+
+const VIEWS: [&str; 3] = ["Trend", "Products", "Metrics"];
+const HELP: &str = " Tab/1-3 view  / filter products  [ ] first month  , . last month  r reset  q quit ";
+
+struct Dashboard {
+    input: PathBuf,
+    months: Vec<NaiveDate>,
+    view: usize,
+    start: usize,
+    end: usize,
+    product: String,
+    editing: bool,
+    data: PlotData,
+    summary: RunSummary,
+    error: Option<String>,
+    money: MoneyFormat,
+}
+
+impl Dashboard {
+    fn filter(&self) -> Filter {
+        let everything = self.start == 0 && self.end == self.months.len() - 1;
+        Filter {
+            product: self.product.clone(),
+            months: (!everything).then(|| self.months[self.start]..=self.months[self.end]),
+        }
+    }
+
+    /// Applies one key press; returns false when the dashboard should close.
+    fn handle(&mut self, code: KeyCode) -> bool {
+        let last = self.months.len() - 1;
+        if self.editing {
+            match code {
+                KeyCode::Char(c) => self.product.push(c),
+                KeyCode::Backspace => {
+                    self.product.pop();
+                }
+                KeyCode::Enter => self.editing = false,
+                KeyCode::Esc => {
+                    self.product.clear();
+                    self.editing = false;
+                }
+                _ => {}
+            }
+            return true;
+        }
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Tab | KeyCode::Right => self.view = (self.view + 1) % VIEWS.len(),
+            KeyCode::BackTab | KeyCode::Left => self.view = (self.view + VIEWS.len() - 1) % VIEWS.len(),
+            KeyCode::Char(c @ '1'..='3') => self.view = c as usize - '1' as usize,
+            KeyCode::Char('/') => self.editing = true,
+            KeyCode::Char('[') => self.start = self.start.saturating_sub(1),
+            KeyCode::Char(']') => self.start = (self.start + 1).min(self.end),
+            KeyCode::Char(',') => self.end = self.end.saturating_sub(1).max(self.start),
+            KeyCode::Char('.') => self.end = (self.end + 1).min(last),
+            KeyCode::Char('r') => {
+                self.product.clear();
+                self.start = 0;
+                self.end = last;
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn render(&self, frame: &mut Frame) {
+        let [tabs, body, status] =
+            Layout::vertical([Constraint::Length(3), Constraint::Min(0), Constraint::Length(3)]).areas(frame.area());
+        let title = format!(" sales_chart - {} ", self.input.display());
+        frame.render_widget(
+            Tabs::new(VIEWS).select(self.view).block(Block::bordered().title(title)).highlight_style(Style::new().yellow().bold()),
+            tabs,
+        );
+
+        if self.data.0.is_empty() {
+            frame.render_widget(Paragraph::new("No sales match the filter").block(Block::bordered()), body);
+        } else {
+            match self.view {
+                0 => self.render_trend(frame, body),
+                1 => self.render_products(frame, body),
+                _ => self.render_metrics(frame, body),
+            }
+        }
+
+        let products = match (self.product.as_str(), self.editing) {
+            (product, true) => format!("{}_", product),
+            ("", false) => "all".to_string(),
+            (product, false) => format!("\"{}\"", product),
+        };
+        let mut line = Line::from(format!(
+            "Products: {}   Months: {} to {}",
+            products,
+            self.months[self.start].format("%Y-%m"),
+            self.months[self.end].format("%Y-%m")
+        ));
+        if let Some(error) = &self.error {
+            line.push_span(format!("   {}", error).red());
+        }
+        frame.render_widget(Paragraph::new(line).block(Block::bordered().title(HELP)), status);
+    }
+
+    fn render_trend(&self, frame: &mut Frame, area: Rect) {
+        let monthly_data = &self.data.0;
+        let points: Vec<(f64, f64)> = monthly_data.iter().enumerate().map(|(i, (_, sales))| (i as f64, *sales)).collect();
+        let max = points.iter().map(|(_, sales)| *sales).fold(0f64, f64::max);
+        let month = |i: usize| monthly_data[i].0.format("%Y-%m").to_string();
+        let last = monthly_data.len() - 1;
+        let dataset = Dataset::default()
+            .name("Total Sales")
+            .marker(Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::new().red())
+            .data(&points);
+        let chart = Chart::new(vec![dataset])
+            .block(Block::bordered().title(" Monthly Sales Trend "))
+            .x_axis(Axis::default().bounds([0.0, last.max(1) as f64]).labels([month(0), month(last / 2), month(last)]))
+            .y_axis(Axis::default().bounds([0.0, max]).labels(["0".to_string(), format!("{:.0}", max / 2.0), format!("{:.0}", max)]));
+        frame.render_widget(chart, area);
+    }
+
+    fn render_products(&self, frame: &mut Frame, area: Rect) {
+        let bars: Vec<Bar> = self
+            .data
+            .1
+            .iter()
+            .map(|(product, sales)| {
+                Bar::default().label(Line::from(product.as_str())).value(sales.max(0.0) as u64).text_value(self.money.format(*sales))
+            })
+            .collect();
+        let chart = BarChart::default()
+            .block(Block::bordered().title(" Sales by Product "))
+            .direction(Direction::Horizontal)
+            .bar_width(1)
+            .bar_gap(0)
+            .bar_style(Style::new().fg(Color::Blue))
+            .data(BarGroup::default().bars(&bars));
+        frame.render_widget(chart, area);
+    }
+
+    fn render_metrics(&self, frame: &mut Frame, area: Rect) {
+        let (summary, money) = (&self.summary, &self.money);
+        let (best_month, best_sales) = self.data.0.iter().fold(self.data.0[0], |best, month| if month.1 > best.1 { *month } else { best });
+        let latest = match (&summary.latest_month, summary.latest_change) {
+            (Some(month), Some(change)) => {
+                format!("{} {} ({} on the month before)", month.month, money.format(month.sales), money.percent(change, true))
+            }
+            (Some(month), None) => format!("{} {}", month.month, money.format(month.sales)),
+            (None, _) => "-".to_string(),
+        };
+        let anomalies = &summary.anomalies;
+        let missing_months = match anomalies.missing_months.as_slice() {
+            [] => "none".to_string(),
+            months => months.join(", "),
+        };
+        let rows = [
+            ("Total sales", money.format(summary.total_sales)),
+            ("Average per month", money.format(summary.total_sales / summary.months as f64)),
+            ("Best month", format!("{} {}", best_month.format("%Y-%m"), money.format(best_sales))),
+            ("Latest month", latest),
+            ("Months", summary.months.to_string()),
+            ("Products", summary.products.to_string()),
+            ("Rows read", summary.rows.to_string()),
+            ("Malformed rows", anomalies.parse_errors.to_string()),
+            ("Duplicate rows", anomalies.duplicates.to_string()),
+            ("Suspicious rows", anomalies.suspicious_rows.to_string()),
+            ("Missing amounts", anomalies.missing_amounts.to_string()),
+            ("Months without sales", missing_months),
+        ];
+        let table = Table::new(rows.map(|(name, value)| Row::new([name.to_string(), value])), [Constraint::Length(22), Constraint::Min(0)])
+            .block(Block::bordered().title(" Metrics "));
+        frame.render_widget(table, area);
+    }
+
+    fn event_loop<F>(&mut self, terminal: &mut DefaultTerminal, load: &mut F) -> Result<(), Box<dyn Error>>
+    where
+        F: FnMut(&Filter) -> Result<(PlotData, ParseReport), Box<dyn Error>>,
+    {
+        loop {
+            terminal.draw(|frame| self.render(frame))?;
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+                return Ok(());
+            }
+
+            let before = self.filter();
+            if !self.handle(key.code) {
+                return Ok(());
+            }
+            let filter = self.filter();
+            if filter != before {
+                match load(&filter) {
+                    Ok((data, report)) => {
+                        self.summary = RunSummary::success(&self.input, &data, &report, Vec::new());
+                        self.data = data;
+                        self.error = None;
+                    }
+                    Err(e) => self.error = Some(e.to_string()),
+                }
+            }
+        }
+    }
+}
+
+/// Runs the dashboard on the terminal until `q` is pressed. It opens on `data`, and `load`
+/// re-aggregates the input whenever the product filter or the month range changes.
+pub fn run<F>(input: &Path, data: PlotData, report: &ParseReport, money: MoneyFormat, mut load: F) -> Result<(), Box<dyn Error>>
+where
+    F: FnMut(&Filter) -> Result<(PlotData, ParseReport), Box<dyn Error>>,
+{
+    let months: Vec<NaiveDate> = data.0.iter().map(|(month, _)| *month).collect();
+    if months.is_empty() {
+        return Err("The data has no sales rows".into());
+    }
+    let mut dashboard = Dashboard {
+        input: input.to_path_buf(),
+        end: months.len() - 1,
+        months,
+        view: 0,
+        start: 0,
+        product: String::new(),
+        editing: false,
+        summary: RunSummary::success(input, &data, report, Vec::new()),
+        data,
+        error: None,
+        money,
+    };
+
+    let mut terminal = ratatui::try_init()?;
+    let result = dashboard.event_loop(&mut terminal, &mut load);
+    ratatui::restore();
+    result
+}
@@ -0,0 +1,279 @@
+use std::error::Error;
+use std::fmt;
+use std::path::PathBuf;
+
+/// The `user@host:port/dir` part of an SFTP or FTP URL.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Remote {
+    pub user: Option<String>,
+    pub host: String,
+    pub port: Option<u16>,
+    pub dir: String,
+}
+
+impl Remote {
+    fn parse(rest: &str, url: &str) -> Result<Self, String> {
+        let (authority, dir) = rest.split_once('/').unwrap_or((rest, ""));
+        let (user, host_port) = match authority.rsplit_once('@') {
+            Some((user, host_port)) => (Some(user.to_string()), host_port),
+            None => (None, authority),
+        };
+        let (host, port) = match host_port.rsplit_once(':') {
+            Some((host, port)) => (host, Some(port.parse().map_err(|_| format!("Invalid port in {}", url))?)),
+            None => (host_port, None),
+        };
+        if host.is_empty() {
+            return Err(format!("Missing host in {}", url));
+        }
+        Ok(Remote { user, host: host.to_string(), port, dir: dir.trim_end_matches('/').to_string() })
+    }
+
+    fn display(&self, scheme: &str, name: &str) -> String {
+        let user = self.user.as_ref().map_or(String::new(), |user| format!("{}@", user));
+        let port = self.port.map_or(String::new(), |port| format!(":{}", port));
+        let dir = if self.dir.is_empty() { String::new() } else { format!("{}/", self.dir) };
+        format!("{}://{}{}{}/{}{}", scheme, user, self.host, port, dir, name)
+    }
+}
+
+/// Where `--upload` sends the generated files.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Destination {
+    /// `s3://bucket/prefix/`, also for S3-compatible stores via `AWS_ENDPOINT_URL`
+    S3 { bucket: String, prefix: String },
+    /// `sftp://user@host:port/dir/`
+    Sftp(Remote),
+    /// `ftp://user@host:port/dir/`, for partner portals that only take file drops
+    Ftp(Remote),
+}
+
+impl Destination {
+    pub fn parse(url: &str) -> Result<Self, String> {
+        if let Some(rest) = url.strip_prefix("s3://") {
+            let (bucket, prefix) = rest.split_once('/').unwrap_or((rest, ""));
+            if bucket.is_empty() {
+                return Err(format!("Missing bucket in {}", url));
+            }
+            let mut prefix = prefix.to_string();
+            if !prefix.is_empty() && !prefix.ends_with('/') {
+                prefix.push('/');
+            }
+            return Ok(Destination::S3 { bucket: bucket.to_string(), prefix });
+        }
+        if let Some(rest) = url.strip_prefix("sftp://") {
+            return Ok(Destination::Sftp(Remote::parse(rest, url)?));
+        }
+        if let Some(rest) = url.strip_prefix("ftp://") {
+            return Ok(Destination::Ftp(Remote::parse(rest, url)?));
+        }
+        Err(format!(
+            "Unsupported upload destination: {} (expected s3://bucket/prefix/, sftp://user@host/dir/, or ftp://user@host/dir/)",
+            url
+        ))
+    }
+
+    pub fn upload(&self, files: &[PathBuf]) -> Result<Vec<String>, Box<dyn Error>> {
+        match self {
+            #[cfg(feature = "s3")]
+            Destination::S3 { bucket, prefix } => s3_store::upload(bucket, prefix, files),
+            #[cfg(feature = "sftp")]
+            Destination::Sftp(remote) => sftp::upload(remote, files),
+            #[cfg(feature = "ftp")]
+            Destination::Ftp(remote) => ftp::upload(remote, files),
+            #[allow(unreachable_patterns)]
+            _ => {
+                let _ = files;
+                Err(format!("sales_chart was built without the `{}` feature", self.feature()).into())
+            }
+        }
+    }
+
+    /// The cargo feature that provides this destination.
+    pub fn feature(&self) -> &'static str {
+        match self {
+            Destination::S3 { .. } => "s3",
+            Destination::Sftp(_) => "sftp",
+            Destination::Ftp(_) => "ftp",
+        }
+    }
+}
+
+impl fmt::Display for Destination {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Destination::S3 { bucket, prefix } => write!(f, "s3://{}/{}", bucket, prefix),
+            Destination::Sftp(remote) => write!(f, "{}", remote.display("sftp", "")),
+            Destination::Ftp(remote) => write!(f, "{}", remote.display("ftp", "")),
+        }
+    }
+}
+
+#[cfg(feature = "s3")]
+mod s3_store {
+    use std::error::Error;
+    use std::fs;
+    use std::path::PathBuf;
+
+    use s3::creds::Credentials;
+    use s3::{Bucket, Region};
+
+    // Region from AWS_REGION / AWS_DEFAULT_REGION; AWS_ENDPOINT_URL switches to a custom
+    // endpoint with path-style addressing, which is what MinIO and most S3 clones expect.
+    fn region() -> Result<(Region, bool), Box<dyn Error>> {
+        let name = std::env::var("AWS_REGION")
+            .or_else(|_| std::env::var("AWS_DEFAULT_REGION"))
+            .unwrap_or_else(|_| "us-east-1".to_string());
+        match std::env::var("AWS_ENDPOINT_URL") {
+            Ok(endpoint) => Ok((Region::Custom { region: name, endpoint }, true)),
+            Err(_) => Ok((name.parse()?, false)),
+        }
+    }
+
+    fn content_type(path: &std::path::Path) -> &'static str {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("png") => "image/png",
+            Some("svg") => "image/svg+xml",
+            Some("html") => "text/html; charset=utf-8",
+            Some("csv") => "text/csv",
+            Some("json") => "application/json",
+            _ => "application/octet-stream",
+        }
+    }
+
+    /// Uploads with the standard credential chain: environment, shared profile, web identity,
+    /// then instance metadata.
+    pub fn upload(bucket: &str, prefix: &str, files: &[PathBuf]) -> Result<Vec<String>, Box<dyn Error>> {
+        let (region, path_style) = region()?;
+        let credentials = Credentials::default().map_err(|e| format!("No AWS credentials found: {}", e))?;
+        let mut target = Bucket::new(bucket, region, credentials)?;
+        if path_style {
+            target = target.with_path_style();
+        }
+
+        let mut uploaded = Vec::new();
+        for file in files {
+            let name = file.file_name().unwrap_or_default().to_string_lossy();
+            let key = format!("{}{}", prefix, name);
+            target
+                .put_object_with_content_type(&key, &fs::read(file)?, content_type(file))
+                .map_err(|e| format!("Uploading {} to s3://{}/{} failed: {}", file.display(), bucket, key, e))?;
+            uploaded.push(format!("s3://{}/{}", bucket, key));
+        }
+        Ok(uploaded)
+    }
+}
+
+#[cfg(feature = "sftp")]
+mod sftp {
+    use std::error::Error;
+    use std::fs;
+    use std::io::Write;
+    use std::net::TcpStream;
+    use std::path::{Path, PathBuf};
+
+    use ssh2::{CheckResult, KnownHostFileKind, Session};
+
+    use super::Remote;
+
+    fn home() -> PathBuf {
+        std::env::var_os("HOME").map(PathBuf::from).unwrap_or_default()
+    }
+
+    // Refuse hosts that are not in known_hosts rather than silently trusting a new key.
+    fn verify_host_key(session: &Session, remote: &Remote, port: u16) -> Result<(), Box<dyn Error>> {
+        let (key, _) = session.host_key().ok_or("SFTP server sent no host key")?;
+        let mut known_hosts = session.known_hosts()?;
+        let path = home().join(".ssh/known_hosts");
+        if path.exists() {
+            known_hosts.read_file(&path, KnownHostFileKind::OpenSSH)?;
+        }
+        match known_hosts.check_port(&remote.host, port, key) {
+            CheckResult::Match => Ok(()),
+            CheckResult::Mismatch => Err(format!("Host key for {} does not match ~/.ssh/known_hosts", remote.host).into()),
+            _ => Err(format!("Host {} is not in ~/.ssh/known_hosts; connect once with ssh to add it", remote.host).into()),
+        }
+    }
+
+    // ssh-agent first, then SFTP_PASSWORD, then the usual private key files (or SFTP_KEY).
+    fn authenticate(session: &Session, user: &str) -> Result<(), Box<dyn Error>> {
+        if session.userauth_agent(user).is_ok() {
+            return Ok(());
+        }
+        if let Ok(password) = std::env::var("SFTP_PASSWORD") {
+            session.userauth_password(user, &password)?;
+            return Ok(());
+        }
+        let keys = match std::env::var_os("SFTP_KEY") {
+            Some(key) => vec![PathBuf::from(key)],
+            None => vec![home().join(".ssh/id_ed25519"), home().join(".ssh/id_rsa")],
+        };
+        for key in keys.iter().filter(|key| key.exists()) {
+            if session.userauth_pubkey_file(user, None, key, None).is_ok() {
+                return Ok(());
+            }
+        }
+        Err(format!("SFTP authentication as {} failed (tried ssh-agent, SFTP_PASSWORD, and key files)", user).into())
+    }
+
+    pub fn upload(remote: &Remote, files: &[PathBuf]) -> Result<Vec<String>, Box<dyn Error>> {
+        let port = remote.port.unwrap_or(22);
+        let user = match &remote.user {
+            Some(user) => user.clone(),
+            None => std::env::var("USER").map_err(|_| "No user in the sftp:// URL and USER is not set")?,
+        };
+
+        let mut session = Session::new()?;
+        let stream = TcpStream::connect((remote.host.as_str(), port))
+            .map_err(|e| format!("Cannot connect to {}:{}: {}", remote.host, port, e))?;
+        session.set_tcp_stream(stream);
+        session.handshake()?;
+        verify_host_key(&session, remote, port)?;
+        authenticate(&session, &user)?;
+
+        let sftp = session.sftp()?;
+        let mut uploaded = Vec::new();
+        for file in files {
+            let name = file.file_name().unwrap_or_default().to_string_lossy();
+            let target = Path::new(&remote.dir).join(name.as_ref());
+            let mut handle = sftp.create(&target).map_err(|e| format!("Cannot create {}: {}", target.display(), e))?;
+            handle.write_all(&fs::read(file)?)?;
+            uploaded.push(remote.display("sftp", &name));
+        }
+        Ok(uploaded)
+    }
+}
+
+#[cfg(feature = "ftp")]
+mod ftp {
+    use std::error::Error;
+    use std::fs::File;
+    use std::path::PathBuf;
+
+    use suppaftp::types::FileType;
+    use suppaftp::FtpStream;
+
+    use super::Remote;
+
+    /// Logs in as the URL's user with FTP_PASSWORD, or anonymously without a user.
+    pub fn upload(remote: &Remote, files: &[PathBuf]) -> Result<Vec<String>, Box<dyn Error>> {
+        let port = remote.port.unwrap_or(21);
+        let mut ftp = FtpStream::connect((remote.host.as_str(), port))
+            .map_err(|e| format!("Cannot connect to {}:{}: {}", remote.host, port, e))?;
+        let user = remote.user.as_deref().unwrap_or("anonymous");
+        let password = std::env::var("FTP_PASSWORD").unwrap_or_default();
+        ftp.login(user, password.as_str())?;
+        ftp.transfer_type(FileType::Binary)?;
+        if !remote.dir.is_empty() {
+            ftp.cwd(&remote.dir)?;
+        }
+
+        let mut uploaded = Vec::new();
+        for file in files {
+            let name = file.file_name().unwrap_or_default().to_string_lossy();
+            ftp.put_file(name.as_ref(), &mut File::open(file)?)?;
+            uploaded.push(remote.display("ftp", &name));
+        }
+        ftp.quit()?;
+        Ok(uploaded)
+    }
+}
@@ -0,0 +1,132 @@
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fmt;
+
+use chrono::NaiveDate;
+use csv::ByteRecord;
+
+use crate::date_order::resolve_date_order;
+use crate::source::{records, SalesSource};
+use crate::{key_to_date, missing_months, DuplicateTracker, ParseOptions, RowError};
+
+const MAX_LISTED: usize = 20;
+
+#[derive(Clone, Debug, Default)]
+pub struct ValidationReport {
+    pub rows: u64,
+    pub header_error: Option<String>,
+    pub row_errors: Vec<RowError>,
+    pub duplicates: Vec<(u64, u64)>,
+    pub first_month: Option<NaiveDate>,
+    pub last_month: Option<NaiveDate>,
+    pub missing_months: Vec<NaiveDate>,
+}
+
+impl ValidationReport {
+    pub fn problem_count(&self) -> usize {
+        usize::from(self.header_error.is_some()) + self.row_errors.len() + self.duplicates.len() + self.missing_months.len()
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.problem_count() == 0
+    }
+}
+
+fn write_listed<T>(
+    f: &mut fmt::Formatter<'_>,
+    items: &[T],
+    line: impl Fn(&mut fmt::Formatter<'_>, &T) -> fmt::Result,
+) -> fmt::Result {
+    for item in items.iter().take(MAX_LISTED) {
+        line(f, item)?;
+    }
+    if items.len() > MAX_LISTED {
+        writeln!(f, "    ... and {} more", items.len() - MAX_LISTED)?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.header_error {
+            Some(error) => writeln!(f, "header:          FAILED ({})", error)?,
+            None => writeln!(f, "header:          ok")?,
+        }
+        writeln!(f, "rows checked:    {}", self.rows)?;
+        if let (Some(first), Some(last)) = (self.first_month, self.last_month) {
+            writeln!(f, "months covered:  {} .. {}", first.format("%Y-%m"), last.format("%Y-%m"))?;
+        }
+
+        writeln!(f, "invalid rows:    {}", self.row_errors.len())?;
+        write_listed(f, &self.row_errors, |f, e| writeln!(f, "    {}", e))?;
+
+        writeln!(f, "duplicate rows:  {}", self.duplicates.len())?;
+        write_listed(f, &self.duplicates, |f, (line, first)| {
+            writeln!(f, "    line {} duplicates line {}", line, first)
+        })?;
+
+        writeln!(f, "missing months:  {}", self.missing_months.len())?;
+        write_listed(f, &self.missing_months, |f, month| writeln!(f, "    {}", month.format("%Y-%m")))?;
+
+        if self.is_ok() {
+            write!(f, "result:          OK")
+        } else {
+            write!(f, "result:          FAILED ({} problems)", self.problem_count())
+        }
+    }
+}
+
+pub fn validate_sales_data<S: SalesSource + ?Sized>(
+    source: &mut S,
+    options: &ParseOptions,
+) -> Result<ValidationReport, Box<dyn Error>> {
+    let mut report = ValidationReport::default();
+
+    let layout = match options.layout(&source.headers()?) {
+        Ok(layout) => layout,
+        Err(e) => {
+            report.header_error = Some(e.to_string());
+            return Ok(report);
+        }
+    };
+
+    let records: Vec<ByteRecord> = records(source).collect::<Result<_, _>>()?;
+    let order = match resolve_date_order(options.date_order, records.iter().filter_map(|r| r.get(layout.month_index))) {
+        Ok(order) => order,
+        Err(e) => {
+            report.header_error = Some(e.to_string());
+            return Ok(report);
+        }
+    };
+    let layout = layout.with_date_order(order);
+
+    let mut tracker = DuplicateTracker::default();
+    let mut months = BTreeSet::new();
+
+    for record in records {
+        let line = record.position().map_or(0, |pos| pos.line());
+        report.rows += 1;
+
+        match layout.parse_row(&record) {
+            Ok(row) => {
+                if let Some((month, _)) = row.values {
+                    months.insert(month);
+                }
+            }
+            Err(error) => {
+                report.row_errors.push(RowError::new(&record, error));
+                continue;
+            }
+        }
+
+        if let Some(first) = tracker.check(&layout, &record, line) {
+            report.duplicates.push((line, first));
+        }
+    }
+
+    report.first_month = months.first().map(|&key| key_to_date(key));
+    report.last_month = months.last().map(|&key| key_to_date(key));
+    report.missing_months = missing_months(months);
+
+    Ok(report)
+}
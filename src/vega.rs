@@ -0,0 +1,390 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::NaiveDate;
+use serde_json::{json, Value};
+
+use crate::charts::{facets_per_row, product_layers, ChartLabels, MAX_BUMP};
+use crate::number::MoneyFormat;
+use crate::summary::{Facet, FunnelStage, ProductTrend};
+
+/// Extension of the spec files, after the chart's name.
+pub const EXTENSION: &str = "vl.json";
+
+const SCHEMA: &str = "https://vega.github.io/schema/vega-lite/v5.json";
+
+// The colors of the PNG charts.
+const RED: &str = "#ff0000";
+const BLUE: &str = "#0000ff";
+const GREY: &str = "#808080";
+const PRIOR_YEAR: &str = "#969696";
+const BREAK_EVEN: &str = "#800080";
+const ON_PLAN: &str = "#009600";
+const BELOW_PLAN: &str = "#c80000";
+
+// One line of a monthly chart: its legend, months, color, and whether it is dashed.
+type Line<'a> = (String, &'a [(NaiveDate, f64)], &'static str, bool);
+
+// `labels` amounts with the decimal places `chart_decimals` gives `chart`, if any.
+fn money(labels: &ChartLabels, chart: &str) -> MoneyFormat {
+    match labels.chart_decimals.iter().rev().find(|(name, _)| name == chart) {
+        Some(&(_, decimals)) => MoneyFormat { decimals, ..labels.money.clone() },
+        None => labels.money.clone(),
+    }
+}
+
+fn month_label(month: &NaiveDate, labels: &ChartLabels) -> String {
+    labels.locale.map_or_else(|| month.format("%Y-%m").to_string(), |locale| locale.month_label(*month))
+}
+
+// The spec of one chart, `body` giving its data, marks, and encodings, fit into `size` padding
+// included like the PNG charts.
+fn spec(caption: String, size: (u32, u32), body: Value) -> Value {
+    let mut spec = json!({
+        "$schema": SCHEMA,
+        "title": caption,
+        "width": size.0,
+        "height": size.1,
+        "autosize": {"type": "fit", "contains": "padding"},
+    });
+    if let (Some(spec), Value::Object(body)) = (spec.as_object_mut(), body) {
+        spec.extend(body);
+    }
+    spec
+}
+
+// The month axis, in the order the months come in rather than sorted by their labels.
+fn month_axis(labels: &ChartLabels) -> Value {
+    json!({"field": "month", "type": "ordinal", "sort": null, "title": labels.translations.get("Month")})
+}
+
+fn sales_axis(labels: &ChartLabels) -> Value {
+    json!({"field": "sales", "type": "quantitative", "title": labels.translations.get("Sales")})
+}
+
+// Lines of monthly sales with a legend, each in its own color.
+fn monthly_lines(caption: String, lines: &[Line], labels: &ChartLabels, chart: &str) -> Value {
+    let money = &money(labels, chart);
+    let values: Vec<Value> = lines
+        .iter()
+        .flat_map(|(series, monthly, _, dashed)| {
+            monthly.iter().map(move |(month, sales)| json!({"series": series, "month": month_label(month, labels), "sales": sales, "amount": money.format(*sales), "dashed": dashed}))
+        })
+        .collect();
+    let names: Vec<&str> = lines.iter().map(|(series, ..)| series.as_str()).collect();
+    let colors: Vec<&str> = lines.iter().map(|(_, _, color, _)| *color).collect();
+    spec(
+        caption,
+        labels.size,
+        json!({
+            "data": {"values": values},
+            "mark": {"type": "line", "point": true},
+            "encoding": {
+                "x": month_axis(labels),
+                "y": sales_axis(labels),
+                "color": {"field": "series", "type": "nominal", "title": null, "scale": {"domain": names, "range": colors}},
+                "strokeDash": {"field": "dashed", "type": "nominal", "legend": null, "scale": {"domain": [false, true], "range": [[1, 0], [6, 4]]}},
+                "tooltip": [{"field": "series", "title": null}, {"field": "month", "title": labels.translations.get("Month")}, {"field": "amount", "title": labels.translations.get("Sales")}],
+            },
+        }),
+    )
+}
+
+/// The monthly trend of `render_charts`' line chart, with the break-even level and the prior
+/// year when `labels` asks for them.
+pub fn line_chart(monthly_data: &[(NaiveDate, f64)], labels: &ChartLabels) -> Value {
+    let translations = &labels.translations;
+    let level: Vec<(NaiveDate, f64)> = labels.break_even.map_or(Vec::new(), |level| monthly_data.iter().map(|(month, _)| (*month, level)).collect());
+    let prior = if labels.prior_year { crate::charts::prior_year(monthly_data) } else { Vec::new() };
+    let mut lines: Vec<Line> = vec![(translations.get("Total Sales").to_string(), monthly_data, RED, false)];
+    if !level.is_empty() {
+        lines.push((translations.get("Break-even").to_string(), &level, BREAK_EVEN, false));
+    }
+    if !prior.is_empty() {
+        lines.push((translations.get("Prior Year").to_string(), &prior, PRIOR_YEAR, true));
+    }
+    monthly_lines(translations.get("Monthly Sales Trend").to_string(), &lines, labels, "line")
+}
+
+/// One product's monthly trend, as `--per-product` charts it.
+pub fn product_chart(product: &str, monthly_data: &[(NaiveDate, f64)], labels: &ChartLabels) -> Value {
+    let translations = &labels.translations;
+    let lines = [(translations.get("Total Sales").to_string(), monthly_data, RED, false)];
+    monthly_lines(translations.fill("Monthly Sales of {}", product), &lines, labels, "product")
+}
+
+/// Actual monthly sales against their targets.
+pub fn budget_chart(monthly_data: &[(NaiveDate, f64)], targets: &[(NaiveDate, f64)], labels: &ChartLabels) -> Value {
+    let translations = &labels.translations;
+    let lines = [(translations.get("Total Sales").to_string(), monthly_data, BLUE, false), (translations.get("Budget").to_string(), targets, GREY, false)];
+    monthly_lines(translations.get("Actual vs Budget").to_string(), &lines, labels, "budget")
+}
+
+/// The monthly totals as they were and as a what-if scenario has them.
+pub fn scenario_chart(monthly_data: &[(NaiveDate, f64)], scenario: &[(NaiveDate, f64)], labels: &ChartLabels) -> Value {
+    let translations = &labels.translations;
+    let lines = [(translations.get("Total Sales").to_string(), monthly_data, RED, false), (translations.get("Scenario").to_string(), scenario, BLUE, false)];
+    monthly_lines(translations.get("Scenario vs Actual").to_string(), &lines, labels, "scenario")
+}
+
+/// Nominal monthly sales against the same in prices of `base`.
+pub fn inflation_chart(nominal: &[(NaiveDate, f64)], real: &[(NaiveDate, f64)], base: NaiveDate, labels: &ChartLabels) -> Value {
+    let translations = &labels.translations;
+    let lines = [
+        (translations.get("Nominal Sales").to_string(), nominal, RED, false),
+        (translations.fill("Real Sales ({} prices)", &month_label(&base, labels)), real, BLUE, false),
+    ];
+    monthly_lines(translations.get("Nominal vs Real Monthly Sales").to_string(), &lines, labels, "inflation")
+}
+
+/// A bar per product in chart order.
+pub fn bar_chart(product_data: &[(String, f64)], labels: &ChartLabels) -> Value {
+    let money = money(labels, "bar");
+    let values: Vec<Value> = product_data.iter().map(|(product, sales)| json!({"product": product, "sales": sales, "amount": money.format(*sales)})).collect();
+    let product = labels.translations.get("Product");
+    spec(
+        labels.translations.get("Sales by Product").to_string(),
+        labels.size,
+        json!({
+            "data": {"values": values},
+            "mark": "bar",
+            "encoding": {
+                "x": {"field": "product", "type": "nominal", "sort": null, "title": product},
+                "y": sales_axis(labels),
+                "color": {"field": "product", "type": "nominal", "sort": null, "legend": null},
+                "tooltip": [{"field": "product", "title": product}, {"field": "amount", "title": labels.translations.get("Sales")}],
+            },
+        }),
+    )
+}
+
+/// Each product's share of the sales as a slice.
+pub fn pie_chart(product_data: &[(String, f64)], labels: &ChartLabels) -> Value {
+    let money = money(labels, "pie");
+    let total: f64 = product_data.iter().map(|(_, sales)| sales).sum();
+    let values: Vec<Value> = product_data
+        .iter()
+        .map(|(product, sales)| json!({"product": product, "sales": sales, "amount": money.format(*sales), "share": money.percent(sales / total * 100.0, false)}))
+        .collect();
+    let translations = &labels.translations;
+    spec(
+        translations.get("Sales by Product").to_string(),
+        labels.size,
+        json!({
+            "data": {"values": values},
+            "mark": "arc",
+            "encoding": {
+                "theta": {"field": "sales", "type": "quantitative", "stack": true},
+                "color": {"field": "product", "type": "nominal", "sort": null, "title": translations.get("Product")},
+                "order": {"field": "sales", "type": "quantitative", "sort": "descending"},
+                "tooltip": [
+                    {"field": "product", "title": translations.get("Product")},
+                    {"field": "amount", "title": translations.get("Sales")},
+                    {"field": "share", "title": translations.get("Share")},
+                ],
+            },
+        }),
+    )
+}
+
+/// Each month's difference from its target as a bar, green above plan and red below.
+pub fn variance_chart(variance: &[(NaiveDate, f64)], labels: &ChartLabels) -> Value {
+    let money = money(labels, "variance");
+    let values: Vec<Value> = variance
+        .iter()
+        .map(|(month, difference)| json!({"month": month_label(month, labels), "sales": difference, "amount": money.format(*difference), "on_plan": *difference >= 0.0}))
+        .collect();
+    let translations = &labels.translations;
+    spec(
+        translations.get("Variance from Budget").to_string(),
+        labels.size,
+        json!({
+            "data": {"values": values},
+            "mark": "bar",
+            "encoding": {
+                "x": month_axis(labels),
+                "y": {"field": "sales", "type": "quantitative", "title": translations.get("Variance")},
+                "color": {"field": "on_plan", "type": "nominal", "legend": null, "scale": {"domain": [true, false], "range": [ON_PLAN, BELOW_PLAN]}},
+                "tooltip": [{"field": "month", "title": translations.get("Month")}, {"field": "amount", "title": translations.get("Variance")}],
+            },
+        }),
+    )
+}
+
+/// The products' monthly sales stacked around a center line, the best-selling `MAX_STREAMS`
+/// each in a layer and the rest as one.
+pub fn streamgraph(trends: &[ProductTrend], labels: &ChartLabels) -> Value {
+    let values = layer_values(trends, labels, &money(labels, "streamgraph"));
+    let translations = &labels.translations;
+    spec(
+        translations.get("Product Mix by Month").to_string(),
+        labels.size,
+        json!({
+            "data": {"values": values},
+            "mark": "area",
+            "encoding": {
+                "x": month_axis(labels),
+                "y": {"field": "sales", "type": "quantitative", "stack": "center", "axis": null},
+                "color": {"field": "product", "type": "nominal", "sort": null, "title": translations.get("Product")},
+                "order": {"field": "layer", "type": "quantitative"},
+                "tooltip": [{"field": "product", "title": translations.get("Product")}, {"field": "month", "title": translations.get("Month")}, {"field": "amount", "title": translations.get("Sales")}],
+            },
+        }),
+    )
+}
+
+/// Each month's sales as a bar of the products' sales stacked from the best-selling at the
+/// bottom, the best-selling `MAX_STREAMS` each in a layer and the rest as one.
+pub fn stacked_bar_chart(trends: &[ProductTrend], labels: &ChartLabels) -> Value {
+    let values = layer_values(trends, labels, &money(labels, "stacked_bar"));
+    let translations = &labels.translations;
+    spec(
+        translations.get("Product Sales by Month").to_string(),
+        labels.size,
+        json!({
+            "data": {"values": values},
+            "mark": "bar",
+            "encoding": {
+                "x": month_axis(labels),
+                "y": {"field": "sales", "type": "quantitative", "stack": "zero", "title": translations.get("Sales")},
+                "color": {"field": "product", "type": "nominal", "sort": null, "title": translations.get("Product")},
+                "order": {"field": "layer", "type": "quantitative"},
+                "tooltip": [{"field": "product", "title": translations.get("Product")}, {"field": "month", "title": translations.get("Month")}, {"field": "amount", "title": translations.get("Sales")}],
+            },
+        }),
+    )
+}
+
+// A value for each month of each layer `product_layers` makes of `trends`, numbered from the
+// best-selling product's.
+fn layer_values(trends: &[ProductTrend], labels: &ChartLabels, money: &MoneyFormat) -> Vec<Value> {
+    product_layers(trends)
+        .into_iter()
+        .enumerate()
+        .flat_map(|(layer, (product, monthly))| {
+            let product = product.map_or_else(|| labels.translations.get("Other").to_string(), str::to_string);
+            monthly.into_iter().map(move |(month, sales)| json!({"product": product, "layer": layer, "month": month_label(&month, labels), "sales": sales, "amount": money.format(sales)}))
+        })
+        .collect()
+}
+
+/// Each of the best-selling `MAX_BUMP` products' rank among them by sales in every month,
+/// rank 1 at the top.
+pub fn bump_chart(trends: &[ProductTrend], labels: &ChartLabels) -> Value {
+    let money = money(labels, "bump");
+    let trends = &trends[..trends.len().min(MAX_BUMP)];
+    let months = trends.first().map_or(0, |trend| trend.monthly.len());
+    let mut values = Vec::new();
+    for i in 0..months {
+        let mut ranked: Vec<&ProductTrend> = trends.iter().collect();
+        ranked.sort_by(|a, b| b.monthly[i].1.total_cmp(&a.monthly[i].1));
+        for (rank, trend) in ranked.iter().enumerate() {
+            let (month, sales) = trend.monthly[i];
+            values.push(json!({"product": trend.product, "month": month_label(&month, labels), "rank": rank + 1, "amount": money.format(sales)}));
+        }
+    }
+    let translations = &labels.translations;
+    spec(
+        translations.get("Product Rank by Month").to_string(),
+        labels.size,
+        json!({
+            "data": {"values": values},
+            "mark": {"type": "line", "point": true},
+            "encoding": {
+                "x": month_axis(labels),
+                "y": {"field": "rank", "type": "ordinal", "title": null},
+                "color": {"field": "product", "type": "nominal", "sort": null, "title": translations.get("Product")},
+                "tooltip": [{"field": "product", "title": translations.get("Product")}, {"field": "month", "title": translations.get("Month")}, {"field": "amount", "title": translations.get("Sales")}],
+            },
+        }),
+    )
+}
+
+/// A panel of monthly sales per value of the column `by`.
+pub fn facet_chart(facets: &[Facet], by: &str, labels: &ChartLabels) -> Value {
+    let money = money(labels, "facet");
+    let values: Vec<Value> = facets
+        .iter()
+        .flat_map(|facet| facet.monthly.iter().map(|(month, sales)| json!({"facet": facet.name, "month": month_label(month, labels), "sales": sales, "amount": money.format(*sales)})))
+        .collect();
+    let translations = &labels.translations;
+    let mut spec = spec(
+        translations.fill("Monthly Sales by {}", by),
+        labels.size,
+        json!({
+            "data": {"values": values},
+            "columns": facets_per_row(facets.len()),
+            "facet": {"field": "facet", "type": "nominal", "sort": null, "title": null},
+            "spec": {
+                "mark": {"type": "line", "color": RED},
+                "encoding": {
+                    "x": month_axis(labels),
+                    "y": sales_axis(labels),
+                    "tooltip": [{"field": "month", "title": translations.get("Month")}, {"field": "amount", "title": translations.get("Sales")}],
+                },
+            },
+        }),
+    );
+    // A faceted spec sizes its panels rather than the whole chart, and cannot fit them in.
+    if let Some(spec) = spec.as_object_mut() {
+        for key in ["width", "height", "autosize"] {
+            spec.remove(key);
+        }
+    }
+    spec
+}
+
+/// The funnel's stages from the top as bars, each with its conversion from the stage before.
+pub fn funnel_chart(stages: &[FunnelStage], labels: &ChartLabels) -> Value {
+    let money = money(labels, "funnel");
+    let values: Vec<Value> = stages
+        .iter()
+        .map(|stage| {
+            let conversion = stage.conversion_percent.map_or(String::new(), |percent| money.percent(percent, false));
+            json!({"stage": stage.stage, "sales": stage.sales, "amount": money.format(stage.sales), "conversion": conversion})
+        })
+        .collect();
+    let translations = &labels.translations;
+    spec(
+        translations.get("Sales Funnel").to_string(),
+        labels.size,
+        json!({
+            "data": {"values": values},
+            "mark": "bar",
+            "encoding": {
+                "y": {"field": "stage", "type": "nominal", "sort": null, "title": translations.get("Stage")},
+                "x": sales_axis(labels),
+                "color": {"field": "stage", "type": "nominal", "sort": null, "legend": null},
+                "tooltip": [
+                    {"field": "stage", "title": translations.get("Stage")},
+                    {"field": "amount", "title": translations.get("Sales")},
+                    {"field": "conversion", "title": translations.get("Conversion")},
+                ],
+            },
+        }),
+    )
+}
+
+pub fn write_spec(path: &Path, spec: &Value) -> Result<(), Box<dyn Error>> {
+    let file = File::create(path).map_err(|e| format!("Cannot create {}: {}", path.display(), e))?;
+    let mut writer = BufWriter::new(file);
+    serde_json::to_writer_pretty(&mut writer, spec)?;
+    writer.write_all(b"\n")?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// The line, bar, and pie charts of `render_charts` as Vega-Lite specs with their data inlined,
+/// written to `output_dir` as `line_chart.vl.json` and so on.
+pub fn write_charts(output_dir: &Path, monthly_data: &[(NaiveDate, f64)], product_data: &[(String, f64)], labels: &ChartLabels) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let specs = [("line_chart", line_chart(monthly_data, labels)), ("bar_chart", bar_chart(product_data, labels)), ("pie_chart", pie_chart(product_data, labels))];
+    let mut paths = Vec::new();
+    for (name, spec) in specs {
+        let path = output_dir.join(format!("{}.{}", name, EXTENSION));
+        write_spec(&path, &spec)?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
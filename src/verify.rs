@@ -0,0 +1,57 @@
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Debug)]
+pub enum Difference {
+    /// The golden directory has no file of that name
+    Missing { golden: PathBuf },
+    /// Both files exist but their bytes differ
+    Changed { golden: PathBuf, offset: usize },
+}
+
+#[derive(Clone, Debug)]
+pub struct Mismatch {
+    pub output: PathBuf,
+    pub difference: Difference,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.difference {
+            Difference::Missing { golden } => {
+                write!(f, "{}: no previous output at {}", self.output.display(), golden.display())
+            }
+            Difference::Changed { golden, offset } => write!(
+                f,
+                "{}: differs from {} starting at byte {}",
+                self.output.display(),
+                golden.display(),
+                offset
+            ),
+        }
+    }
+}
+
+/// Compares each output byte for byte with the file of the same name in `golden_dir`.
+pub fn compare_outputs(outputs: &[PathBuf], golden_dir: &Path) -> Result<Vec<Mismatch>, Box<dyn Error>> {
+    let mut mismatches = Vec::new();
+    for output in outputs {
+        let golden = golden_dir.join(output.file_name().ok_or_else(|| format!("Not a file: {}", output.display()))?);
+        let difference = match fs::read(&golden) {
+            Err(_) => Some(Difference::Missing { golden }),
+            Ok(expected) => {
+                let actual = fs::read(output)?;
+                let offset = actual.iter().zip(&expected).position(|(a, b)| a != b);
+                offset
+                    .or_else(|| (actual.len() != expected.len()).then(|| actual.len().min(expected.len())))
+                    .map(|offset| Difference::Changed { golden, offset })
+            }
+        };
+        if let Some(difference) = difference {
+            mismatches.push(Mismatch { output: output.clone(), difference });
+        }
+    }
+    Ok(mismatches)
+}
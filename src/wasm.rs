@@ -0,0 +1,48 @@
+use plotters::prelude::*;
+use wasm_bindgen::prelude::*;
+
+use crate::charts::{draw_bar_chart, draw_line_chart, draw_pie_chart, ChartLabels};
+use crate::summary::Aggregates;
+use crate::{prepare_data_for_plotting, process_sales_data, ParseOptions, PlotData};
+
+// Browser entry points. The CSV arrives as text (e.g. from a file input) and charts go back as
+// SVG markup; without the `parallel` feature, or where threads are unavailable, the work runs on
+// the calling thread.
+
+fn analyze(csv: &str, lenient: bool) -> Result<PlotData, JsError> {
+    let mut rdr = csv::ReaderBuilder::new().has_headers(true).flexible(true).from_reader(csv.as_bytes());
+    let options = ParseOptions { lenient, ..ParseOptions::default() };
+    let ((sales_by_month, sales_by_product), _) =
+        process_sales_data(&mut rdr, &options).map_err(|e| JsError::new(&e.to_string()))?;
+    Ok(prepare_data_for_plotting(sales_by_month, sales_by_product))
+}
+
+/// Monthly and per-product totals as JSON: `{"monthly": [{"month", "sales"}], "products": [{"product", "sales"}]}`.
+#[wasm_bindgen]
+pub fn aggregate(csv: &str, lenient: bool) -> Result<String, JsError> {
+    let plot_data = analyze(csv, lenient)?;
+    serde_json::to_string(&Aggregates::new(&plot_data)).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Draws the `line`, `bar`, or `pie` chart and returns it as an SVG document.
+#[wasm_bindgen(js_name = renderChart)]
+pub fn render_chart(csv: &str, kind: &str, width: u32, height: u32, lenient: bool) -> Result<String, JsError> {
+    let (monthly_data, product_data) = analyze(csv, lenient)?;
+    if monthly_data.is_empty() {
+        return Err(JsError::new("The data has no sales rows"));
+    }
+
+    let mut svg = String::new();
+    {
+        let root = SVGBackend::with_string(&mut svg, (width, height)).into_drawing_area();
+        let labels = ChartLabels { visual_order: false, ..ChartLabels::default() };
+        match kind {
+            "line" => draw_line_chart(&root, &monthly_data, &labels),
+            "bar" => draw_bar_chart(&root, &product_data, &labels),
+            "pie" => draw_pie_chart(&root, &product_data, &labels),
+            _ => return Err(JsError::new(&format!("Unknown chart kind: {} (expected line, bar, or pie)", kind))),
+        }
+        .map_err(|e| JsError::new(&e.to_string()))?;
+    }
+    Ok(svg)
+}
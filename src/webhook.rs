@@ -0,0 +1,13 @@
+use std::error::Error;
+
+use crate::summary::RunSummary;
+
+/// POSTs the run summary as JSON; any non-2xx answer counts as a failed delivery.
+pub fn post_summary(url: &str, summary: &RunSummary) -> Result<(), Box<dyn Error>> {
+    let body = serde_json::to_string(summary)?;
+    ureq::post(url)
+        .header("Content-Type", "application/json")
+        .send(body)
+        .map_err(|e| format!("Webhook {} failed: {}", url, e))?;
+    Ok(())
+}
@@ -0,0 +1,27 @@
+#![cfg(feature = "bitmap")]
+
+use chrono::NaiveDate;
+use sales_chart::snapshot::{assert_golden, bar_chart, line_chart, pie_chart, Tolerance};
+
+fn monthly() -> Vec<(NaiveDate, f64)> {
+    [120.0, 150.0, 90.0, 210.0, 180.0, 240.0].iter().enumerate().map(|(i, sales)| (NaiveDate::from_ymd_opt(2024, i as u32 + 1, 1).unwrap(), *sales)).collect()
+}
+
+fn products() -> Vec<(String, f64)> {
+    [("Product A", 420.0), ("Product B", 310.0), ("Product C", 160.0), ("Product D", 100.0)].iter().map(|(product, sales)| (product.to_string(), *sales)).collect()
+}
+
+#[test]
+fn line_chart_matches_golden() {
+    assert_golden(&line_chart(&monthly()).unwrap(), "tests/golden/line_chart.png", Tolerance::default());
+}
+
+#[test]
+fn bar_chart_matches_golden() {
+    assert_golden(&bar_chart(&products()).unwrap(), "tests/golden/bar_chart.png", Tolerance::default());
+}
+
+#[test]
+fn pie_chart_matches_golden() {
+    assert_golden(&pie_chart(&products()).unwrap(), "tests/golden/pie_chart.png", Tolerance::default());
+}